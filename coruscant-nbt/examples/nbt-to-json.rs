@@ -1,2 +1,37 @@
-fn main() {}
-//todo
+// Converts NBT data into JSON through `coruscant_nbt::Value`.
+//
+// NBT records the type of every number while JSON does not, so this direction
+// always works, but the types are lost on the way: `1b` and `1L` both become
+// `1`. Arrays become JSON arrays of numbers. For a lossless text form of NBT,
+// use the string transcript or SNBT instead; the `nbt` tool prints all three.
+
+fn main() {
+    use coruscant_nbt::Value;
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct School {
+        name: &'static str,
+        #[serde(rename = "985")]
+        is_985: bool,
+    }
+
+    #[derive(Serialize)]
+    struct Person {
+        nick: &'static str,
+        school: School,
+    }
+
+    let person = Person {
+        nick: "luojia65",
+        school: School {
+            name: "hust",
+            is_985: true,
+        },
+    };
+    let nbt = coruscant_nbt::to_vec(("person", &person)).expect("serialize NBT");
+
+    let value: Value = coruscant_nbt::from_slice(&nbt).expect("deserialize NBT");
+    let json = serde_json::to_string_pretty(&value).expect("serialize JSON");
+    println!("{}", json);
+}
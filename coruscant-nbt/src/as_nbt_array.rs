@@ -59,3 +59,8 @@ where
 pub struct __WrapAsArray<T>(pub T);
 
 pub(crate) const TOKEN_ARRAY: &'static str = "$coruscant_nbt::private::__WrapAsArray";
+
+// Arrays with a known element type, so that empty arrays keep their type.
+pub(crate) const TOKEN_BYTE_ARRAY: &str = "$coruscant_nbt::private::ByteArray";
+pub(crate) const TOKEN_INT_ARRAY: &str = "$coruscant_nbt::private::IntArray";
+pub(crate) const TOKEN_LONG_ARRAY: &str = "$coruscant_nbt::private::LongArray";
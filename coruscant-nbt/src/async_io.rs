@@ -0,0 +1,407 @@
+//! Read and write NBT on Tokio's asynchronous streams.
+//!
+//! Serde itself is synchronous, so these functions do not decode on the
+//! stream. Reading walks the stream just far enough to take exactly one NBT
+//! document off it, leaving any following bytes (the next packet) in place,
+//! and then decodes that document with the same `SliceRead` based
+//! deserializer as `from_slice`. Writing serializes into memory first and
+//! then writes the bytes out asynchronously.
+//!
+//! Readers take an `AsyncBufRead`, like `tokio::io::BufReader`, so that they
+//! can look at the bytes that arrived and consume only those of the document
+//! or compressed stream.
+
+use crate::de::Deserializer;
+use crate::error::{Error, Result};
+use crate::read::DocumentEnd;
+use crate::root;
+use serde::{de, Serialize};
+use std::io;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+#[cfg(any(feature = "gzip", feature = "zlib"))]
+use crate::Compression;
+#[cfg(feature = "zlib")]
+use flate2::{Decompress, FlushDecompress, Status};
+#[cfg(feature = "gzip")]
+use {flate2::bufread::GzDecoder, std::io::Read};
+
+/// Deserialize an instance of type `T` from an asynchronous stream of NBT.
+///
+/// Exactly one NBT document is read off the stream; bytes after it are left
+/// in the stream's buffer.
+///
+/// # Example
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let mut stream = Vec::new();
+/// coruscant_nbt::to_async_writer(&mut stream, ("", &vec![1i8, 2, 3])).await.unwrap();
+/// stream.extend_from_slice(b"next packet");
+///
+/// let mut stream = &stream[..];
+/// let list: Vec<i8> = coruscant_nbt::from_async_reader(&mut stream).await.unwrap();
+/// assert_eq!(list, [1, 2, 3]);
+/// assert_eq!(stream, b"next packet");
+/// # }
+/// ```
+pub async fn from_async_reader<R, T>(read: &mut R) -> Result<T>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+    T: de::DeserializeOwned,
+{
+    let document = read_document(read).await?;
+    T::deserialize(&mut Deserializer::slice(&document))
+}
+
+/// Deserialize an instance of type `T` from a GZip compressed asynchronous
+/// stream of NBT.
+#[cfg(feature = "gzip")]
+pub async fn from_async_gzip_reader<R, T>(read: &mut R) -> Result<T>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+    T: de::DeserializeOwned,
+{
+    let document = read_gzip(read).await?;
+    T::deserialize(&mut Deserializer::slice(&document))
+}
+
+/// Deserialize an instance of type `T` from a Zlib compressed asynchronous
+/// stream of NBT.
+#[cfg(feature = "zlib")]
+pub async fn from_async_zlib_reader<R, T>(read: &mut R) -> Result<T>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+    T: de::DeserializeOwned,
+{
+    let mut decompress = Decompress::new(true);
+    let document = inflate(read, &mut decompress).await?;
+    T::deserialize(&mut Deserializer::slice(&document))
+}
+
+/// Serialize the given data structure with root name as NBT into an
+/// asynchronous stream.
+///
+/// The optional NBT root name is wrapped into variable `root`, see
+/// `root::Root` for detailed descriptions.
+pub async fn to_async_writer<'k, 'v, W, T, R>(writer: &mut W, root: R) -> Result<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+    T: 'v + Serialize + ?Sized,
+    R: Into<root::Root<'k, 'v, T>>,
+{
+    let bytes = crate::to_vec(root)?;
+    write_all(writer, &bytes).await
+}
+
+/// Serialize the given data structure with root name as NBT, compress it by
+/// GZip, then write into an asynchronous stream.
+#[cfg(feature = "gzip")]
+pub async fn to_async_gzip_writer<'k, 'v, W, T, R>(
+    writer: &mut W,
+    root: R,
+    compression: Compression,
+) -> Result<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+    T: 'v + Serialize + ?Sized,
+    R: Into<root::Root<'k, 'v, T>>,
+{
+    let mut bytes = Vec::new();
+    crate::to_gzip_writer(&mut bytes, root, compression)?;
+    write_all(writer, &bytes).await
+}
+
+/// Serialize the given data structure with root name as NBT, compress it by
+/// Zlib, then write into an asynchronous stream.
+#[cfg(feature = "zlib")]
+pub async fn to_async_zlib_writer<'k, 'v, W, T, R>(
+    writer: &mut W,
+    root: R,
+    compression: Compression,
+) -> Result<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+    T: 'v + Serialize + ?Sized,
+    R: Into<root::Root<'k, 'v, T>>,
+{
+    let mut bytes = Vec::new();
+    crate::to_zlib_writer(&mut bytes, root, compression)?;
+    write_all(writer, &bytes).await
+}
+
+async fn write_all<W>(writer: &mut W, bytes: &[u8]) -> Result<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    writer.write_all(bytes).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Copy the raw bytes of one NBT document off the stream.
+///
+/// Only lengths and type ids are looked at; everything else is checked when
+/// the document is deserialized.
+async fn read_document<R>(read: &mut R) -> Result<Vec<u8>>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    let mut document = Vec::with_capacity(128);
+    let mut end = DocumentEnd::new();
+    loop {
+        let input = read.fill_buf().await?;
+        if input.is_empty() {
+            let eof = io::Error::from(io::ErrorKind::UnexpectedEof);
+            return Err(Error::io_at(eof, document.len()));
+        }
+        let (start, len) = (document.len(), input.len());
+        document.extend_from_slice(input);
+        match end.walk(&document)? {
+            Some(end) => {
+                read.consume(end - start);
+                document.truncate(end);
+                return Ok(document);
+            }
+            None => read.consume(len),
+        }
+    }
+}
+
+/// Inflate one zlib stream, consuming no bytes after its end.
+#[cfg(feature = "zlib")]
+async fn inflate<R>(read: &mut R, decompress: &mut Decompress) -> Result<Vec<u8>>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    let mut ans = Vec::with_capacity(1024);
+    loop {
+        let input = read.fill_buf().await?;
+        if input.is_empty() {
+            let eof = io::Error::from(io::ErrorKind::UnexpectedEof);
+            return Err(Error::io(eof));
+        }
+        if ans.capacity() - ans.len() < input.len() * 2 {
+            ans.reserve(input.len() * 4);
+        }
+        let total_in = decompress.total_in();
+        let status = decompress
+            .decompress_vec(input, &mut ans, FlushDecompress::None)
+            .map_err(|e| Error::io(e.into()))?;
+        let consumed = (decompress.total_in() - total_in) as usize;
+        read.consume(consumed);
+        if let Status::StreamEnd = status {
+            return Ok(ans);
+        }
+    }
+}
+
+/// Decompress one gzip member, consuming no bytes after its end.
+///
+/// flate2's decoder takes the bytes that arrived so far, and picks up where it
+/// stopped when they run out.
+#[cfg(feature = "gzip")]
+async fn read_gzip<R>(read: &mut R) -> Result<Vec<u8>>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    let mut decoder = GzDecoder::new(Arrived::default());
+    let mut ans = Vec::with_capacity(1024);
+    loop {
+        let input = read.fill_buf().await?;
+        if input.is_empty() {
+            let eof = io::Error::from(io::ErrorKind::UnexpectedEof);
+            return Err(Error::io(eof));
+        }
+        decoder.get_mut().refill(input);
+        let result = decoder.read_to_end(&mut ans);
+        read.consume(decoder.get_mut().consumed);
+        match result {
+            Ok(_) => return Ok(ans),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => return Err(Error::io(e)),
+        }
+    }
+}
+
+/// The bytes of an asynchronous stream that arrived so far, as a `BufRead`
+/// that would block once they are used up.
+#[cfg(feature = "gzip")]
+#[derive(Default)]
+struct Arrived {
+    bytes: Vec<u8>,
+    consumed: usize,
+}
+
+#[cfg(feature = "gzip")]
+impl Arrived {
+    fn refill(&mut self, bytes: &[u8]) {
+        self.bytes.clear();
+        self.bytes.extend_from_slice(bytes);
+        self.consumed = 0;
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl io::Read for Arrived {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = io::Read::read(&mut io::BufRead::fill_buf(self)?, buf)?;
+        io::BufRead::consume(self, n);
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl io::BufRead for Arrived {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match &self.bytes[self.consumed..] {
+            [] => Err(io::ErrorKind::WouldBlock.into()),
+            rest => Ok(rest),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.consumed += amt;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Map, Value};
+    use tokio::io::{AsyncReadExt, BufReader};
+
+    fn document() -> Value {
+        let snbt = r#"{name:"level",list:[[1b],[],[2b,3b]],longs:[L;1L,2L],
+            compounds:[{a:1s},{}],bytes:[B;1b,2b,3b],text:"",pi:3.14d}"#;
+        crate::snbt::parse(snbt).unwrap()
+    }
+
+    // Reads in pieces of `capacity` bytes, so documents span many refills.
+    fn stream(bytes: &[u8], capacity: usize) -> BufReader<&[u8]> {
+        BufReader::with_capacity(capacity, bytes)
+    }
+
+    async fn rest(stream: &mut BufReader<&[u8]>) -> Vec<u8> {
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).await.unwrap();
+        rest
+    }
+
+    #[tokio::test]
+    async fn documents_leave_the_following_bytes() {
+        let mut bytes = Vec::new();
+        crate::to_async_writer(&mut bytes, ("root", &document()))
+            .await
+            .unwrap();
+        crate::to_async_writer(&mut bytes, &Value::Int(7))
+            .await
+            .unwrap();
+        bytes.extend_from_slice(b"next packet");
+        for capacity in [1, 3, 8192] {
+            let mut stream = stream(&bytes, capacity);
+            let value: Value = crate::from_async_reader(&mut stream).await.unwrap();
+            assert_eq!(value, document());
+            let value: i32 = crate::from_async_reader(&mut stream).await.unwrap();
+            assert_eq!(value, 7);
+            assert_eq!(rest(&mut stream).await, b"next packet");
+        }
+    }
+
+    #[tokio::test]
+    async fn documents_match_the_sync_reader() {
+        let mut big = Map::new();
+        big.insert("ints".to_string(), Value::IntArray((0..10000).collect()));
+        big.insert("text".to_string(), Value::String("é".repeat(10000)));
+        let bytes = crate::to_vec(&Value::Compound(big)).unwrap();
+        let expected: Value = crate::from_slice(&bytes).unwrap();
+        let value: Value = crate::from_async_reader(&mut stream(&bytes, 1000))
+            .await
+            .unwrap();
+        assert_eq!(value, expected);
+    }
+
+    #[tokio::test]
+    async fn broken_documents_are_refused() {
+        let bytes = crate::to_vec(&document()).unwrap();
+        let cut = &bytes[..bytes.len() - 1];
+        let read = crate::from_async_reader::<_, Value>(&mut stream(cut, 4)).await;
+        assert!(read.is_err());
+
+        // a list of 1 element typed `TAG_End`, then an unknown type id
+        for bytes in [
+            &[9, 0, 0, 0, 0, 0, 0, 1][..],
+            &[10, 0, 0, 13, 0, 0][..],
+            &[0][..],
+        ] {
+            let read = crate::from_async_reader::<_, Value>(&mut stream(bytes, 4)).await;
+            assert!(read.is_err(), "{:?}", bytes);
+        }
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn gzip_members_leave_the_following_bytes() {
+        use flate2::{write::GzEncoder, GzBuilder};
+        use std::io::Write;
+
+        let nbt = crate::to_vec(&document()).unwrap();
+        let mut bytes = Vec::new();
+        crate::to_async_gzip_writer(&mut bytes, &document(), Default::default())
+            .await
+            .unwrap();
+        // a header with every optional field
+        let builder = GzBuilder::new().extra(vec![1, 2]).filename("level.dat");
+        let mut encoder: GzEncoder<Vec<u8>> =
+            builder.comment("hi").write(Vec::new(), Default::default());
+        encoder.write_all(&nbt).unwrap();
+        bytes.extend(encoder.finish().unwrap());
+        bytes.extend_from_slice(b"next packet");
+        for capacity in [1, 7, 8192] {
+            let mut stream = stream(&bytes, capacity);
+            for _ in 0..2 {
+                let value: Value = crate::from_async_gzip_reader(&mut stream).await.unwrap();
+                assert_eq!(value, document());
+            }
+            assert_eq!(rest(&mut stream).await, b"next packet");
+        }
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn corrupt_gzip_members_are_refused() {
+        let mut bytes = Vec::new();
+        crate::to_async_gzip_writer(&mut bytes, &document(), Default::default())
+            .await
+            .unwrap();
+        let len = bytes.len();
+        bytes[len - 8] ^= 1; // the checksum
+        let read = crate::from_async_gzip_reader::<_, Value>(&mut stream(&bytes, 16)).await;
+        assert!(read.is_err());
+        let cut = &bytes[..len - 1];
+        let read = crate::from_async_gzip_reader::<_, Value>(&mut stream(cut, 16)).await;
+        assert!(read.is_err());
+        let read = crate::from_async_gzip_reader::<_, Value>(&mut stream(b"not gzip", 16)).await;
+        assert!(read.is_err());
+    }
+
+    #[cfg(feature = "zlib")]
+    #[tokio::test]
+    async fn zlib_streams_leave_the_following_bytes() {
+        let mut bytes = Vec::new();
+        for _ in 0..2 {
+            crate::to_async_zlib_writer(&mut bytes, &document(), Default::default())
+                .await
+                .unwrap();
+        }
+        bytes.extend_from_slice(b"next packet");
+        for capacity in [1, 7, 8192] {
+            let mut stream = stream(&bytes, capacity);
+            for _ in 0..2 {
+                let value: Value = crate::from_async_zlib_reader(&mut stream).await.unwrap();
+                assert_eq!(value, document());
+            }
+            assert_eq!(rest(&mut stream).await, b"next packet");
+        }
+    }
+}
@@ -0,0 +1,893 @@
+//! `nbt`, a command-line tool to inspect and edit NBT files.
+//!
+//! Built with the `cli` feature. Run `nbt help` for usage. Input compression
+//! is detected automatically; a file name of `-` stands for stdin or stdout.
+
+use coruscant_nbt::path::{NbtPath, PathNode};
+use coruscant_nbt::read::{self, Read as _};
+use coruscant_nbt::ser::{
+    BinaryFormatter, Formatter, LittleEndianFormatter, SnbtFormatter, TranscriptFormatter,
+};
+use coruscant_nbt::{snbt, transcode, Deserializer, Map, Serializer, Value};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+const USAGE: &str = "\
+usage: nbt [--endian big|little] <command> [args]
+
+commands:
+  dump <file> [--format transcript|snbt|json]
+      print the whole file
+  get <file> <path> [--format snbt|transcript|json]
+      print the value at an NBT path, like `Data.Player.Inventory[0]`
+  set <file> <path> <snbt> [-o <out>]
+      put an SNBT value at an NBT path, creating missing compounds
+  remove <file> <path> [-o <out>]
+      remove the value at an NBT path
+  convert <in> <out> [--compression none|gzip|zlib] [--to-endian big|little]
+      re-encode a file, keeping its content tag by tag
+  diff <a> <b>
+      print differences between two files; exits with 1 if there are any
+  validate <file>...
+      check that files are well-formed NBT; exits with 1 if any is not
+
+--endian sets the byte order of input files, which is big endian for Java
+Edition and little endian for Bedrock Edition. Files are written back with
+the compression and byte order they were read with, unless converted.
+`set` and `remove` edit files in place, so all other tags keep their order
+and types.";
+
+type Result<T> = core::result::Result<T, Box<dyn Error>>;
+
+fn main() {
+    match run(std::env::args().skip(1).collect()) {
+        Ok(code) => process::exit(code),
+        Err(err) => {
+            eprintln!("nbt: {}", err);
+            process::exit(2);
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> Result<i32> {
+    let mut args = Args::new(args);
+    let endian = match args.option("--endian") {
+        Some(endian) => Endian::parse(&endian)?,
+        None => Endian::Big,
+    };
+    let command = match args.next() {
+        Some(command) => command,
+        None => {
+            eprintln!("{}", USAGE);
+            return Ok(2);
+        }
+    };
+    match command.as_str() {
+        "dump" => {
+            let format = Format::parse(args.option("--format"), Format::Transcript)?;
+            let file = NbtFile::open(&args.required("file")?, endian)?;
+            args.finish()?;
+            file.dump(format)?;
+        }
+        "get" => {
+            let format = Format::parse(args.option("--format"), Format::Snbt)?;
+            let file = NbtFile::open(&args.required("file")?, endian)?;
+            let path: NbtPath = args.required("path")?.parse()?;
+            args.finish()?;
+            let (_, root) = file.value()?;
+            let value = path.get(&root).ok_or("no value at this NBT path")?;
+            print_value(&path.to_string(), value, format)?;
+        }
+        "set" | "remove" => {
+            let output = args.option("-o");
+            let name = args.required("file")?;
+            let mut file = NbtFile::open(&name, endian)?;
+            let path: NbtPath = args.required("path")?.parse()?;
+            let value = match command.as_str() {
+                "set" => Some(snbt::parse(&args.required("snbt")?)?),
+                _ => None,
+            };
+            args.finish()?;
+            file.validate_tags()?;
+            match value {
+                Some(value) => file.set(&path, &value)?,
+                None => file.remove(&path)?,
+            }
+            let output = output.unwrap_or(name);
+            write_output(&output, &file.compression.compress(&file.bytes)?)?;
+        }
+        "convert" => {
+            let compression = match args.option("--compression") {
+                Some(compression) => Some(Compression::parse(&compression)?),
+                None => None,
+            };
+            let to_endian = match args.option("--to-endian") {
+                Some(endian) => Some(Endian::parse(&endian)?),
+                None => None,
+            };
+            let file = NbtFile::open(&args.required("in")?, endian)?;
+            let output = args.required("out")?;
+            args.finish()?;
+            let to_endian = to_endian.unwrap_or(file.endian);
+            let mut bytes = Vec::new();
+            let rest = match to_endian {
+                Endian::Big => file.transcode(&mut bytes, BinaryFormatter)?,
+                Endian::Little => file.transcode(&mut bytes, LittleEndianFormatter)?,
+            };
+            if rest != 0 {
+                return Err(format!("{} trailing bytes after NBT data", rest).into());
+            }
+            let compression = compression.unwrap_or(file.compression);
+            write_output(&output, &compression.compress(&bytes)?)?;
+        }
+        "diff" => {
+            let a = NbtFile::open(&args.required("a")?, endian)?.value()?;
+            let b = NbtFile::open(&args.required("b")?, endian)?.value()?;
+            args.finish()?;
+            let mut lines = Vec::new();
+            if a.0 != b.0 {
+                lines.push(format!("~ <root name>: {:?} -> {:?}", a.0, b.0));
+            }
+            diff(&mut Vec::new(), &a.1, &b.1, &mut lines);
+            for line in &lines {
+                println!("{}", line);
+            }
+            return Ok(if lines.is_empty() { 0 } else { 1 });
+        }
+        "validate" => {
+            let names = args.rest();
+            if names.is_empty() {
+                return Err("missing argument <file>".into());
+            }
+            let mut code = 0;
+            for name in names {
+                match NbtFile::open(&name, endian).and_then(|file| file.validate()) {
+                    Ok(()) => println!("{}: ok", name),
+                    Err(err) => {
+                        println!("{}: {}", name, err);
+                        code = 1;
+                    }
+                }
+            }
+            return Ok(code);
+        }
+        "help" | "--help" | "-h" => println!("{}", USAGE),
+        other => return Err(format!("unknown command `{}`; try `nbt help`", other).into()),
+    }
+    Ok(0)
+}
+
+/// Command-line arguments, with options taken out by name.
+struct Args {
+    args: Vec<String>,
+}
+
+impl Args {
+    fn new(args: Vec<String>) -> Self {
+        Args { args }
+    }
+
+    fn option(&mut self, name: &str) -> Option<String> {
+        let n = self.args.iter().position(|arg| arg == name)?;
+        if n + 1 >= self.args.len() {
+            return None;
+        }
+        self.args.remove(n);
+        Some(self.args.remove(n))
+    }
+
+    fn next(&mut self) -> Option<String> {
+        if self.args.is_empty() {
+            return None;
+        }
+        Some(self.args.remove(0))
+    }
+
+    fn required(&mut self, name: &str) -> Result<String> {
+        self.next()
+            .ok_or_else(|| format!("missing argument <{}>", name).into())
+    }
+
+    fn rest(&mut self) -> Vec<String> {
+        core::mem::take(&mut self.args)
+    }
+
+    fn finish(&self) -> Result<()> {
+        match self.args.first() {
+            Some(arg) => Err(format!("unexpected argument `{}`", arg).into()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Transcript,
+    Snbt,
+    Json,
+}
+
+impl Format {
+    fn parse(s: Option<String>, default: Format) -> Result<Self> {
+        match s.as_deref() {
+            None => Ok(default),
+            Some("transcript") => Ok(Format::Transcript),
+            Some("snbt") => Ok(Format::Snbt),
+            Some("json") => Ok(Format::Json),
+            Some(other) => Err(format!("unknown format `{}`", other).into()),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Compression {
+    None,
+    Gzip,
+    Zlib,
+}
+
+impl Compression {
+    fn parse(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(Compression::None),
+            "gzip" => Ok(Compression::Gzip),
+            "zlib" => Ok(Compression::Zlib),
+            other => Err(format!("unknown compression `{}`", other).into()),
+        }
+    }
+
+    fn detect(bytes: &[u8]) -> Self {
+        match bytes {
+            [0x1f, 0x8b, ..] => Compression::Gzip,
+            // zlib header: deflate method, and a checksum of the first two bytes
+            [a, b, ..] if a & 0x0f == 8 && (u16::from(*a) << 8 | u16::from(*b)) % 31 == 0 => {
+                Compression::Zlib
+            }
+            _ => Compression::None,
+        }
+    }
+
+    fn decompress(self, bytes: Vec<u8>) -> Result<Vec<u8>> {
+        let mut ans = Vec::new();
+        match self {
+            Compression::None => return Ok(bytes),
+            Compression::Gzip => GzDecoder::new(&bytes[..]).read_to_end(&mut ans)?,
+            Compression::Zlib => ZlibDecoder::new(&bytes[..]).read_to_end(&mut ans)?,
+        };
+        Ok(ans)
+    }
+
+    fn compress(self, bytes: &[u8]) -> Result<Vec<u8>> {
+        let level = coruscant_nbt::Compression::default();
+        let ans = match self {
+            Compression::None => bytes.to_vec(),
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), level);
+                encoder.write_all(bytes)?;
+                encoder.finish()?
+            }
+            Compression::Zlib => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), level);
+                encoder.write_all(bytes)?;
+                encoder.finish()?
+            }
+        };
+        Ok(ans)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Endian {
+    Big,
+    Little,
+}
+
+impl Endian {
+    fn parse(s: &str) -> Result<Self> {
+        match s {
+            "big" => Ok(Endian::Big),
+            "little" => Ok(Endian::Little),
+            other => Err(format!("unknown byte order `{}`", other).into()),
+        }
+    }
+
+    fn int_bytes(self, n: i32) -> [u8; 4] {
+        match self {
+            Endian::Big => n.to_be_bytes(),
+            Endian::Little => n.to_le_bytes(),
+        }
+    }
+
+    /// Write `value` as one named tag: its type id, name and payload.
+    fn tag_bytes(self, name: &str, value: &Value) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.write_value(&mut bytes, name, value)?;
+        Ok(bytes)
+    }
+
+    fn write_value<W: Write>(self, writer: W, root_name: &str, value: &Value) -> Result<()> {
+        match self {
+            Endian::Big => {
+                value.serialize(&mut Serializer::binary(writer, root_name))?;
+            }
+            Endian::Little => {
+                let mut ser = Serializer::with_formatter(writer, LittleEndianFormatter, root_name);
+                value.serialize(&mut ser)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A decompressed NBT file, with how it was stored.
+struct NbtFile {
+    bytes: Vec<u8>,
+    compression: Compression,
+    endian: Endian,
+}
+
+impl NbtFile {
+    fn open(name: &str, endian: Endian) -> Result<Self> {
+        let mut bytes = Vec::new();
+        if name == "-" {
+            io::stdin().read_to_end(&mut bytes)?;
+        } else {
+            bytes = fs::read(name).map_err(|err| format!("{}: {}", name, err))?;
+        }
+        let compression = Compression::detect(&bytes);
+        let bytes = compression.decompress(bytes)?;
+        Ok(NbtFile {
+            bytes,
+            compression,
+            endian,
+        })
+    }
+
+    /// Read the root name and value.
+    fn value(&self) -> Result<(String, Value)> {
+        let (root_name, value) = match self.endian {
+            Endian::Big => {
+                let mut read = read::IoRead::new(&self.bytes[..]);
+                read.read_type_id()?;
+                let name = read.read_name()?.into_owned();
+                let value = Value::deserialize(&mut Deserializer::slice(&self.bytes))?;
+                (name, value)
+            }
+            Endian::Little => {
+                let mut read = read::IoRead::little_endian(&self.bytes[..]);
+                read.read_type_id()?;
+                let name = read.read_name()?.into_owned();
+                let read = read::IoRead::little_endian(&self.bytes[..]);
+                let value = Value::deserialize(&mut Deserializer::new(read))?;
+                (name, value)
+            }
+        };
+        Ok((root_name, value))
+    }
+
+    /// Transcode the file into `writer`, returning the bytes left over.
+    fn transcode<W, F>(&self, writer: W, formatter: F) -> Result<usize>
+    where
+        W: Write,
+        F: Formatter,
+    {
+        let mut rest = &self.bytes[..];
+        match self.endian {
+            Endian::Big => transcode::transcode(read::IoRead::new(&mut rest), writer, formatter)?,
+            Endian::Little => {
+                let read = read::IoRead::little_endian(&mut rest);
+                transcode::transcode(read, writer, formatter)?
+            }
+        }
+        Ok(rest.len())
+    }
+
+    fn dump(&self, format: Format) -> Result<()> {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        match format {
+            Format::Transcript => self.transcode(&mut stdout, TranscriptFormatter::new())?,
+            Format::Snbt => self.transcode(&mut stdout, SnbtFormatter::new())?,
+            Format::Json => {
+                serde_json::to_writer_pretty(&mut stdout, &self.value()?.1)?;
+                0
+            }
+        };
+        writeln!(stdout)?;
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self.validate_tags()? {
+            0 => Ok(()),
+            n => Err(format!("{} trailing bytes after NBT data", n).into()),
+        }
+    }
+
+    /// Check the tags of the document, returning the bytes left over.
+    fn validate_tags(&self) -> Result<usize> {
+        self.transcode(io::sink(), BinaryFormatter)
+    }
+
+    /// Find where `path` points in the document.
+    fn locate(&self, path: &NbtPath) -> Result<Slot> {
+        match self.endian {
+            Endian::Big => locate(read::IoRead::new(&self.bytes[..]), path.nodes()),
+            Endian::Little => locate(read::IoRead::little_endian(&self.bytes[..]), path.nodes()),
+        }
+    }
+
+    /// Put `value` where `path` points, rewriting only the bytes of that tag.
+    fn set(&mut self, path: &NbtPath, value: &Value) -> Result<()> {
+        match self.locate(path)? {
+            Slot::Entry { key, start, end } => {
+                let tag = self.endian.tag_bytes(&key, value)?;
+                self.bytes.splice(start..end, tag);
+            }
+            Slot::Missing { keys, end } => {
+                // missing compounds along the path are created, like `/data modify` does
+                let mut value = value.clone();
+                for key in keys[1..].iter().rev() {
+                    let mut map = Map::new();
+                    map.insert(key.clone(), value);
+                    value = Value::Compound(map);
+                }
+                let tag = self.endian.tag_bytes(&keys[0], &value)?;
+                self.bytes.splice(end..end, tag);
+            }
+            Slot::Element {
+                head,
+                type_id,
+                len,
+                start,
+                end,
+            } => {
+                let tag = self.endian.tag_bytes("", value)?;
+                if tag[0] != type_id {
+                    if len != 1 {
+                        return Err("value is of another type than the list elements".into());
+                    }
+                    self.bytes[head] = tag[0];
+                }
+                // an unnamed tag is its type id, a zero name length, then the payload
+                self.bytes.splice(start..end, tag[3..].iter().copied());
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the value `path` points to, rewriting only the bytes around it.
+    fn remove(&mut self, path: &NbtPath) -> Result<()> {
+        match self.locate(path)? {
+            Slot::Entry { start, end, .. } => {
+                self.bytes.drain(start..end);
+            }
+            Slot::Missing { .. } => return Err("no value at this NBT path".into()),
+            Slot::Element {
+                head,
+                len,
+                start,
+                end,
+                ..
+            } => {
+                self.bytes.drain(start..end);
+                let len = self.endian.int_bytes(len - 1);
+                self.bytes[head + 1..head + 5].copy_from_slice(&len);
+            }
+        }
+        Ok(())
+    }
+}
+
+const TAG_END: u8 = 0;
+const TAG_BYTE: u8 = 1;
+const TAG_SHORT: u8 = 2;
+const TAG_INT: u8 = 3;
+const TAG_LONG: u8 = 4;
+const TAG_FLOAT: u8 = 5;
+const TAG_DOUBLE: u8 = 6;
+const TAG_BYTE_ARRAY: u8 = 7;
+const TAG_STRING: u8 = 8;
+const TAG_LIST: u8 = 9;
+const TAG_COMPOUND: u8 = 10;
+const TAG_INT_ARRAY: u8 = 11;
+const TAG_LONG_ARRAY: u8 = 12;
+
+/// Where an NBT path points in a binary document, as byte offsets.
+enum Slot {
+    /// A compound entry, from its type id to the end of its payload.
+    Entry {
+        key: String,
+        start: usize,
+        end: usize,
+    },
+    /// Keys missing from a compound whose end tag is at `end`.
+    Missing { keys: Vec<String>, end: usize },
+    /// The payload of a list element, in the list whose head is at `head`.
+    Element {
+        head: usize,
+        type_id: u8,
+        len: i32,
+        start: usize,
+        end: usize,
+    },
+}
+
+fn locate<'de, R: read::Read<'de>>(mut read: R, nodes: &[PathNode]) -> Result<Slot> {
+    let not_found = || -> Box<dyn Error> { "no value at this NBT path".into() };
+    let mut type_id = read.read_type_id()?;
+    read.read_name()?;
+    for (n, node) in nodes.iter().enumerate() {
+        let last = n + 1 == nodes.len();
+        match (node, type_id) {
+            (PathNode::Key(key), TAG_COMPOUND) => loop {
+                let start = read.index();
+                let entry_type_id = read.read_type_id()?;
+                if entry_type_id == TAG_END {
+                    let mut keys = Vec::new();
+                    for node in &nodes[n..] {
+                        match node {
+                            PathNode::Key(key) => keys.push(key.clone()),
+                            PathNode::Index(_) => return Err(not_found()),
+                        }
+                    }
+                    return Ok(Slot::Missing { keys, end: start });
+                }
+                let name = read.read_name()?;
+                if name != key.as_str() {
+                    skip(&mut read, entry_type_id)?;
+                    continue;
+                }
+                if last {
+                    skip(&mut read, entry_type_id)?;
+                    let key = key.clone();
+                    let end = read.index();
+                    return Ok(Slot::Entry { key, start, end });
+                }
+                type_id = entry_type_id;
+                break;
+            },
+            (PathNode::Index(index), TAG_LIST) => {
+                let head = read.index();
+                let elem_type_id = read.read_type_id()?;
+                let len = read.read_array_length()?;
+                let index = if *index < 0 { len + index } else { *index };
+                if index < 0 || index >= len {
+                    return Err(not_found());
+                }
+                for _ in 0..index {
+                    skip(&mut read, elem_type_id)?;
+                }
+                if last {
+                    let start = read.index();
+                    skip(&mut read, elem_type_id)?;
+                    return Ok(Slot::Element {
+                        head,
+                        type_id: elem_type_id,
+                        len,
+                        start,
+                        end: read.index(),
+                    });
+                }
+                type_id = elem_type_id;
+            }
+            _ => return Err(not_found()),
+        }
+    }
+    Err(not_found())
+}
+
+/// Read past the payload of a tag.
+fn skip<'de, R: read::Read<'de>>(read: &mut R, type_id: u8) -> Result<()> {
+    match type_id {
+        TAG_BYTE => {
+            read.read_byte_inner()?;
+        }
+        TAG_SHORT => {
+            read.read_short_inner()?;
+        }
+        TAG_INT => {
+            read.read_int_inner()?;
+        }
+        TAG_LONG => {
+            read.read_long_inner()?;
+        }
+        TAG_FLOAT => {
+            read.read_float_inner()?;
+        }
+        TAG_DOUBLE => {
+            read.read_double_inner()?;
+        }
+        TAG_STRING => {
+            read.read_string_inner()?;
+        }
+        TAG_BYTE_ARRAY | TAG_INT_ARRAY | TAG_LONG_ARRAY => {
+            let len = read.read_array_length()?;
+            let elem_type_id = match type_id {
+                TAG_BYTE_ARRAY => TAG_BYTE,
+                TAG_INT_ARRAY => TAG_INT,
+                _ => TAG_LONG,
+            };
+            for _ in 0..len {
+                skip(read, elem_type_id)?;
+            }
+        }
+        TAG_LIST => {
+            let elem_type_id = read.read_type_id()?;
+            for _ in 0..read.read_array_length()? {
+                skip(read, elem_type_id)?;
+            }
+        }
+        TAG_COMPOUND => loop {
+            let type_id = read.read_type_id()?;
+            if type_id == TAG_END {
+                break;
+            }
+            read.read_name()?;
+            skip(read, type_id)?;
+        },
+        other => return Err(format!("invalid tag type {}", other).into()),
+    }
+    Ok(())
+}
+
+fn print_value(name: &str, value: &Value, format: Format) -> Result<()> {
+    let out = match format {
+        Format::Transcript => coruscant_nbt::to_string_transcript((name, value))?,
+        Format::Snbt => coruscant_nbt::to_string_snbt(value)?,
+        Format::Json => serde_json::to_string_pretty(value)?,
+    };
+    println!("{}", out);
+    Ok(())
+}
+
+fn write_output(name: &str, bytes: &[u8]) -> Result<()> {
+    if name == "-" {
+        io::stdout().write_all(bytes)?;
+        return Ok(());
+    }
+    // write aside and rename, so that a failure never leaves half a file
+    let temp = format!("{}.nbt-tmp", name);
+    fs::write(&temp, bytes)?;
+    fs::rename(&temp, name)?;
+    Ok(())
+}
+
+fn diff(path: &mut Vec<PathNode>, a: &Value, b: &Value, lines: &mut Vec<String>) {
+    let show = |path: &[PathNode]| match path {
+        [] => "<root>".to_string(),
+        path => NbtPath::new(path.to_vec()).to_string(),
+    };
+    match (a, b) {
+        (Value::Compound(a), Value::Compound(b)) => {
+            for (key, old) in a {
+                path.push(PathNode::Key(key.clone()));
+                match b.get(key) {
+                    Some(new) => diff(path, old, new, lines),
+                    None => lines.push(format!("- {}: {}", show(path), snbt_of(old))),
+                }
+                path.pop();
+            }
+            for (key, new) in b {
+                if !a.contains_key(key) {
+                    path.push(PathNode::Key(key.clone()));
+                    lines.push(format!("+ {}: {}", show(path), snbt_of(new)));
+                    path.pop();
+                }
+            }
+        }
+        (Value::List(a), Value::List(b)) if a.len() == b.len() => {
+            for (i, (old, new)) in a.iter().zip(b).enumerate() {
+                path.push(PathNode::Index(i as i32));
+                diff(path, old, new, lines);
+                path.pop();
+            }
+        }
+        (a, b) if !same(a, b) => {
+            let (old, new) = (snbt_of(a), snbt_of(b));
+            lines.push(format!("~ {}: {} -> {}", show(path), old, new));
+        }
+        _ => {}
+    }
+}
+
+// Values compare equal, with NaNs equal to themselves.
+fn same(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+        (Value::Double(a), Value::Double(b)) => a.to_bits() == b.to_bits(),
+        (Value::List(a), Value::List(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b))
+        }
+        (Value::Compound(a), Value::Compound(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| matches!(b.get(key), Some(b) if same(a, b)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+fn snbt_of(value: &Value) -> String {
+    match coruscant_nbt::to_string_snbt(value) {
+        Ok(snbt) => snbt,
+        // SNBT cannot write NaN or infinite floats
+        Err(_) => format!("{:?}", value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(type_id: u8, name: &str, payload: &[u8]) -> Vec<u8> {
+        let mut tag = vec![type_id];
+        tag.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        tag.extend_from_slice(name.as_bytes());
+        tag.extend_from_slice(payload);
+        tag
+    }
+
+    fn compound(entries: &[Vec<u8>]) -> Vec<u8> {
+        let mut payload = entries.concat();
+        payload.push(TAG_END);
+        payload
+    }
+
+    fn int_list(values: &[i32]) -> Vec<u8> {
+        let mut payload = vec![TAG_INT];
+        payload.extend_from_slice(&(values.len() as i32).to_be_bytes());
+        for value in values {
+            payload.extend_from_slice(&value.to_be_bytes());
+        }
+        payload
+    }
+
+    // `z` before `a`, and an empty list of strings, which `Value` cannot keep.
+    fn entries() -> Vec<Vec<u8>> {
+        vec![
+            named(TAG_INT, "z", &1i32.to_be_bytes()),
+            named(TAG_LIST, "a", &[TAG_STRING, 0, 0, 0, 0]),
+            named(TAG_LIST, "l", &int_list(&[1, 2, 3])),
+        ]
+    }
+
+    fn file(entries: &[Vec<u8>]) -> NbtFile {
+        NbtFile {
+            bytes: named(TAG_COMPOUND, "", &compound(entries)),
+            compression: Compression::None,
+            endian: Endian::Big,
+        }
+    }
+
+    fn set(file: &mut NbtFile, path: &str, snbt: &str) -> Result<()> {
+        file.set(&path.parse()?, &snbt::parse(snbt)?)
+    }
+
+    #[test]
+    fn set_keeps_other_tags() {
+        let mut nbt = file(&entries());
+        set(&mut nbt, "z", "2s").unwrap();
+        let mut expected = entries();
+        expected[0] = named(TAG_SHORT, "z", &2i16.to_be_bytes());
+        assert_eq!(nbt.bytes, file(&expected).bytes);
+    }
+
+    #[test]
+    fn set_creates_missing_compounds() {
+        let mut nbt = file(&entries());
+        set(&mut nbt, "b.c", "1b").unwrap();
+        let mut expected = entries();
+        let inner = compound(&[named(TAG_BYTE, "c", &[1])]);
+        expected.push(named(TAG_COMPOUND, "b", &inner));
+        assert_eq!(nbt.bytes, file(&expected).bytes);
+        assert!(set(&mut nbt, "x[0]", "1b").is_err());
+    }
+
+    #[test]
+    fn set_replaces_list_elements() {
+        let mut nbt = file(&entries());
+        set(&mut nbt, "l[-1]", "7").unwrap();
+        let mut expected = entries();
+        expected[2] = named(TAG_LIST, "l", &int_list(&[1, 2, 7]));
+        assert_eq!(nbt.bytes, file(&expected).bytes);
+
+        assert!(set(&mut nbt, "l[0]", "7b").is_err());
+        assert!(set(&mut nbt, "l[3]", "7").is_err());
+        assert!(set(&mut nbt, "a[0]", "\"s\"").is_err());
+    }
+
+    #[test]
+    fn set_retypes_single_element_lists() {
+        let mut nbt = file(&[named(TAG_LIST, "l", &int_list(&[1]))]);
+        set(&mut nbt, "l[0]", "1b").unwrap();
+        let expected = [named(TAG_LIST, "l", &[TAG_BYTE, 0, 0, 0, 1, 1])];
+        assert_eq!(nbt.bytes, file(&expected).bytes);
+    }
+
+    #[test]
+    fn remove_keeps_other_tags() {
+        let mut nbt = file(&entries());
+        nbt.remove(&"l[1]".parse().unwrap()).unwrap();
+        nbt.remove(&"z".parse().unwrap()).unwrap();
+        let mut expected = entries();
+        expected[2] = named(TAG_LIST, "l", &int_list(&[1, 3]));
+        expected.remove(0);
+        assert_eq!(nbt.bytes, file(&expected).bytes);
+
+        assert!(nbt.remove(&"z".parse().unwrap()).is_err());
+        assert!(nbt.remove(&"l[2]".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn little_endian_lists_are_edited() {
+        let mut nbt = NbtFile {
+            bytes: vec![
+                TAG_COMPOUND,
+                0,
+                0,
+                TAG_LIST,
+                1,
+                0,
+                b'l',
+                TAG_BYTE,
+                2,
+                0,
+                0,
+                0,
+                5,
+                6,
+                TAG_END,
+            ],
+            compression: Compression::None,
+            endian: Endian::Little,
+        };
+        nbt.remove(&"l[0]".parse().unwrap()).unwrap();
+        let expected = [
+            TAG_COMPOUND,
+            0,
+            0,
+            TAG_LIST,
+            1,
+            0,
+            b'l',
+            TAG_BYTE,
+            1,
+            0,
+            0,
+            0,
+            6,
+            TAG_END,
+        ];
+        assert_eq!(nbt.bytes, expected);
+    }
+
+    #[test]
+    fn diff_shows_nan() {
+        let a = snbt::parse("{f:1f,g:[1d]}").unwrap();
+        let mut b = a.clone();
+        "f".parse::<NbtPath>()
+            .unwrap()
+            .set(&mut b, Value::Float(f32::NAN))
+            .unwrap();
+        "g".parse::<NbtPath>()
+            .unwrap()
+            .set(&mut b, Value::List(vec![Value::Double(f64::INFINITY)]))
+            .unwrap();
+        let mut lines = Vec::new();
+        diff(&mut Vec::new(), &a, &b, &mut lines);
+        assert_eq!(
+            lines,
+            ["~ f: 1.0f -> Float(NaN)", "~ g[0]: 1.0d -> Double(inf)"]
+        );
+
+        lines.clear();
+        diff(&mut Vec::new(), &b, &b, &mut lines);
+        assert!(lines.is_empty());
+    }
+}
@@ -3,6 +3,7 @@
 use crate::consts;
 use crate::error::{Error, Result};
 use crate::read;
+use crate::value;
 use serde::de;
 use std::borrow::Cow;
 use std::io;
@@ -62,6 +63,17 @@ pub struct Deserializer<R> {
     read: R,
 }
 
+impl<'de, R> Deserializer<R>
+where
+    R: read::Read<'de>,
+{
+    /// Create a NBT deserializer from any NBT source, for example a
+    /// little endian `read::IoRead`.
+    pub fn new(read: R) -> Self {
+        Deserializer { read }
+    }
+}
+
 impl<R> Deserializer<read::IoRead<R>>
 where
     R: io::Read,
@@ -111,9 +123,24 @@ where
         Ok(value)
     }
 
+    #[inline]
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if name != value::TOKEN_VALUE {
+            return self.deserialize_any(visitor);
+        }
+        let type_id = self.read.read_type_id()?;
+        let root_name = self.read.read_name()?;
+        let value = proc_deserialize_nbt_value(visitor, type_id, self)?;
+        drop(root_name);
+        Ok(value)
+    }
+
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        bytes byte_buf option unit unit_struct seq tuple
         tuple_struct struct map enum identifier ignored_any
     }
 }
@@ -169,8 +196,8 @@ where
 
 struct ListOrArrayAccess<'a, R> {
     type_id: u8,
-    cur_len: i16,
-    total_len: i16,
+    cur_len: i32,
+    total_len: i32,
     outer: &'a mut Deserializer<R>,
 }
 
@@ -180,7 +207,7 @@ where
 {
     fn list(outer: &'a mut Deserializer<R>) -> Result<Self> {
         let type_id = outer.read.read_type_id()?;
-        let total_len = outer.read.read_length()?;
+        let total_len = outer.read.read_array_length()?;
         Ok(Self {
             type_id,
             cur_len: 0,
@@ -190,7 +217,7 @@ where
     }
 
     fn byte_array(outer: &'a mut Deserializer<R>) -> Result<Self> {
-        let total_len = outer.read.read_length()?;
+        let total_len = outer.read.read_array_length()?;
         Ok(Self {
             type_id: consts::TYPE_ID_BYTE,
             cur_len: 0,
@@ -200,7 +227,7 @@ where
     }
 
     fn int_array(outer: &'a mut Deserializer<R>) -> Result<Self> {
-        let total_len = outer.read.read_length()?;
+        let total_len = outer.read.read_array_length()?;
         Ok(Self {
             type_id: consts::TYPE_ID_INT,
             cur_len: 0,
@@ -210,7 +237,7 @@ where
     }
 
     fn long_array(outer: &'a mut Deserializer<R>) -> Result<Self> {
-        let total_len = outer.read.read_length()?;
+        let total_len = outer.read.read_array_length()?;
         Ok(Self {
             type_id: consts::TYPE_ID_LONG,
             cur_len: 0,
@@ -286,6 +313,13 @@ where
     where
         V: de::Visitor<'de>,
     {
+        // strings end up here when they are ignored or deserialized untyped
+        if self.type_id == consts::TYPE_ID_STRING {
+            return match self.outer.read.read_string_inner()? {
+                Cow::Borrowed(borrowed) => visitor.visit_borrowed_str(borrowed),
+                Cow::Owned(owned) => visitor.visit_string(owned),
+            };
+        }
         proc_deserialize_value(visitor, self.type_id, self.outer)
     }
 
@@ -339,9 +373,29 @@ where
         visitor.visit_string(owned)
     }
 
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        // NBT has no null value; a present tag is always `Some`
+        visitor.visit_some(self)
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if name != value::TOKEN_VALUE {
+            return self.deserialize_any(visitor);
+        }
+        proc_deserialize_nbt_value(visitor, self.type_id, self.outer)
+    }
+
     forward_to_deserialize_any! {
         i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char
-        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        bytes byte_buf unit unit_struct seq tuple
         tuple_struct map struct enum identifier ignored_any
     }
 }
@@ -372,3 +426,37 @@ where
         invalid => Err(Error::invalid_id_at(invalid, outer.read.index())),
     }
 }
+
+// Deserialize into a `coruscant_nbt::Value`, which unlike other types needs to
+// know whether a sequence is a list or an array.
+#[inline]
+fn proc_deserialize_nbt_value<'de, 'a, R, V>(
+    visitor: V,
+    type_id: u8,
+    outer: &'a mut Deserializer<R>,
+) -> Result<V::Value>
+where
+    R: read::Read<'de>,
+    V: de::Visitor<'de>,
+{
+    use crate::as_nbt_array::{TOKEN_BYTE_ARRAY, TOKEN_INT_ARRAY, TOKEN_LONG_ARRAY};
+    match type_id {
+        consts::TYPE_ID_BYTE_ARRAY => visitor.visit_map(value::ArrayAccess::new(
+            TOKEN_BYTE_ARRAY,
+            ListOrArrayAccess::byte_array(outer)?,
+        )),
+        consts::TYPE_ID_INT_ARRAY => visitor.visit_map(value::ArrayAccess::new(
+            TOKEN_INT_ARRAY,
+            ListOrArrayAccess::int_array(outer)?,
+        )),
+        consts::TYPE_ID_LONG_ARRAY => visitor.visit_map(value::ArrayAccess::new(
+            TOKEN_LONG_ARRAY,
+            ListOrArrayAccess::long_array(outer)?,
+        )),
+        consts::TYPE_ID_STRING => match outer.read.read_string_inner()? {
+            Cow::Borrowed(borrowed) => visitor.visit_borrowed_str(borrowed),
+            Cow::Owned(owned) => visitor.visit_string(owned),
+        },
+        _ => proc_deserialize_value(visitor, type_id, outer),
+    }
+}
@@ -35,8 +35,16 @@ pub(crate) enum ErrorCode {
     InvalidUtf8String,
     TypeIdMismatch(u8, u8),
     TypeIdInvalid(u8),
-    InvalidLength(i16),
+    InvalidLength(i32),
     SliceUnexpectedEof,
+    TranscriptUnexpectedEof,
+    InvalidTranscriptLine,
+    InvalidTranscriptNumber,
+    TrailingCharacters,
+    SnbtUnexpectedEof,
+    InvalidSnbtSyntax,
+    InvalidNbtPath,
+    NbtPathNotFound,
 }
 
 impl Error {
@@ -74,7 +82,7 @@ impl Error {
         Self::from_inner(code, index)
     }
 
-    pub(crate) fn invalid_len_at(invalid: i16, index: usize) -> Self {
+    pub(crate) fn invalid_len_at(invalid: i32, index: usize) -> Self {
         let code = ErrorCode::InvalidLength(invalid);
         Self::from_inner(code, index)
     }
@@ -84,6 +92,11 @@ impl Error {
         Self::from_inner(code, 0)
     }
 
+    #[cfg(feature = "async")]
+    pub(crate) fn is_slice_eof(&self) -> bool {
+        matches!(self.err.code, ErrorCode::SliceUnexpectedEof)
+    }
+
     #[inline]
     fn from_inner(code: ErrorCode, index: usize) -> Self {
         Error {
@@ -154,12 +167,22 @@ impl fmt::Display for ErrorCode {
                 invalid, invalid
             )),
             ErrorCode::InvalidLength(invalid) => f.write_fmt(format_args!(
-                "invalid length {} (0x{:04X}); length must be positive for NBT",
+                "invalid length {} (0x{:X}); length must be positive for NBT",
                 invalid, invalid
             )),
             ErrorCode::SliceUnexpectedEof => {
                 f.write_str("unexpected EOF when reading NBT source slice")
             }
+            ErrorCode::TranscriptUnexpectedEof => {
+                f.write_str("unexpected EOF when reading NBT transcript")
+            }
+            ErrorCode::InvalidTranscriptLine => f.write_str("invalid NBT transcript line"),
+            ErrorCode::InvalidTranscriptNumber => f.write_str("invalid number in NBT transcript"),
+            ErrorCode::TrailingCharacters => f.write_str("trailing characters after NBT value"),
+            ErrorCode::SnbtUnexpectedEof => f.write_str("unexpected EOF when reading SNBT"),
+            ErrorCode::InvalidSnbtSyntax => f.write_str("invalid SNBT syntax"),
+            ErrorCode::InvalidNbtPath => f.write_str("invalid NBT path"),
+            ErrorCode::NbtPathNotFound => f.write_str("NBT path does not match any value"),
         }
     }
 }
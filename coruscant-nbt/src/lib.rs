@@ -11,7 +11,7 @@ pub use error::{Error, Result};
 pub use root::Root;
 
 #[doc(inline)]
-pub use ser::{to_string_transcript, to_vec, to_writer, Serializer};
+pub use ser::{to_string_snbt, to_string_transcript, to_vec, to_writer, Serializer};
 #[cfg(feature = "gzip")]
 #[doc(inline)]
 pub use {de::from_gzip_reader, ser::to_gzip_writer};
@@ -19,11 +19,25 @@ pub use {de::from_gzip_reader, ser::to_gzip_writer};
 #[doc(inline)]
 pub use {de::from_zlib_reader, ser::to_zlib_writer};
 
+#[cfg(feature = "async")]
+#[doc(inline)]
+pub use async_io::{from_async_reader, to_async_writer};
+#[cfg(all(feature = "async", feature = "gzip"))]
+#[doc(inline)]
+pub use async_io::{from_async_gzip_reader, to_async_gzip_writer};
+#[cfg(all(feature = "async", feature = "zlib"))]
+#[doc(inline)]
+pub use async_io::{from_async_zlib_reader, to_async_zlib_writer};
+
 #[doc(inline)]
 pub use de::{from_reader, from_slice, Deserializer};
+#[doc(inline)]
+pub use snbt::from_str_snbt;
+#[doc(inline)]
+pub use transcript::from_str_transcript;
 
 #[doc(inline)]
-pub use value::{to_value, Value};
+pub use value::{from_value, to_value, Value};
 
 #[doc(inline)]
 pub use map::Map;
@@ -34,12 +48,18 @@ pub use as_nbt_array::serialize as as_nbt_array;
 #[macro_use]
 mod macros;
 mod consts;
+#[cfg(feature = "async")]
+pub mod async_io;
 pub mod de;
 pub mod error;
 pub mod map;
-mod read;
+pub mod path;
+pub mod read;
 pub mod root;
 pub mod ser;
+pub mod snbt;
+pub mod transcode;
+pub mod transcript;
 pub mod value;
 
 mod as_nbt_array;
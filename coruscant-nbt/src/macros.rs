@@ -17,9 +17,9 @@ macro_rules! return_expr_for_serialized_types_method {
     };
     ($expr:expr, $func:ident($($arg:ty),*), where: $where:path) => {
         #[inline]
-        fn $func<__T: ?Sized>(self, $(_: $arg,)*)
-                              -> ::core::result::Result<Self::Ok, Self::Error>
-            where __T: $where
+        fn $func<__T>(self, $(_: $arg,)*)
+                      -> ::core::result::Result<Self::Ok, Self::Error>
+            where __T: ?Sized + $where
         {
             $expr
         }
@@ -0,0 +1,338 @@
+//! NBT paths, the syntax `/data get` uses to point into NBT data.
+//!
+//! A path is a chain of compound keys and list indices, such as
+//! `Inventory[0].tag.display."custom name"`. Keys are separated by `.` and
+//! may be quoted with `"` or `'` when they contain special characters;
+//! indices are written in brackets and count from the end when negative.
+//!
+//! Only plain keys and list indices are supported; compound filters like
+//! `Items[{Slot:0b}]` are not.
+
+use crate::error::{Error, ErrorCode, Result};
+use crate::map::Map;
+use crate::value::Value;
+use core::fmt;
+use core::str::FromStr;
+
+/// A parsed NBT path.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_nbt::{path::NbtPath, Value};
+///
+/// let mut root = coruscant_nbt::snbt::parse("{Inventory:[{id:\"minecraft:stone\"}]}").unwrap();
+/// let path: NbtPath = "Inventory[0].id".parse().unwrap();
+/// assert_eq!(path.get(&root), Some(&Value::String("minecraft:stone".to_string())));
+///
+/// path.set(&mut root, Value::String("minecraft:dirt".to_string())).unwrap();
+/// assert!(path.remove(&mut root).is_some());
+/// assert_eq!(path.get(&root), None);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NbtPath {
+    nodes: Vec<PathNode>,
+}
+
+/// One step of an `NbtPath`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathNode {
+    /// A key into a compound.
+    Key(String),
+    /// An index into a list; negative indices count from the end.
+    Index(i32),
+}
+
+impl NbtPath {
+    /// Build a path from its nodes.
+    #[inline]
+    pub fn new(nodes: Vec<PathNode>) -> Self {
+        NbtPath { nodes }
+    }
+
+    #[inline]
+    pub fn nodes(&self) -> &[PathNode] {
+        &self.nodes
+    }
+
+    /// Find the value this path points to under `root`.
+    pub fn get<'v>(&self, root: &'v Value) -> Option<&'v Value> {
+        self.nodes
+            .iter()
+            .try_fold(root, |value, node| node.get(value))
+    }
+
+    /// Find the value this path points to under `root` for modification.
+    pub fn get_mut<'v>(&self, root: &'v mut Value) -> Option<&'v mut Value> {
+        self.nodes
+            .iter()
+            .try_fold(root, |value, node| node.get_mut(value))
+    }
+
+    /// Put `value` where this path points to, returning the value replaced.
+    ///
+    /// Missing compounds along the path are created, like `/data modify` does;
+    /// list elements must exist already.
+    pub fn set(&self, root: &mut Value, value: Value) -> Result<Option<Value>> {
+        let (last, parents) = self.nodes.split_last().ok_or_else(not_found)?;
+        let mut current = root;
+        for node in parents {
+            current = match (node, current) {
+                (PathNode::Key(key), Value::Compound(map)) => {
+                    if !map.contains_key(key) {
+                        map.insert(key.clone(), Value::Compound(Map::new()));
+                    }
+                    map.get_mut(key).expect("inserted above")
+                }
+                (node, current) => node.get_mut(current).ok_or_else(not_found)?,
+            };
+        }
+        match (last, current) {
+            (PathNode::Key(key), Value::Compound(map)) => Ok(map.insert(key.clone(), value)),
+            (node, current) => {
+                let slot = node.get_mut(current).ok_or_else(not_found)?;
+                Ok(Some(core::mem::replace(slot, value)))
+            }
+        }
+    }
+
+    /// Remove the value this path points to, if any.
+    pub fn remove(&self, root: &mut Value) -> Option<Value> {
+        let (last, parents) = self.nodes.split_last()?;
+        let parent = parents
+            .iter()
+            .try_fold(root, |value, node| node.get_mut(value))?;
+        match (last, parent) {
+            (PathNode::Key(key), Value::Compound(map)) => map.remove(key),
+            (PathNode::Index(index), Value::List(list)) => {
+                let index = resolve_index(*index, list.len())?;
+                Some(list.remove(index))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl PathNode {
+    fn get<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        match (self, value) {
+            (PathNode::Key(key), Value::Compound(map)) => map.get(key),
+            (PathNode::Index(index), Value::List(list)) => {
+                list.get(resolve_index(*index, list.len())?)
+            }
+            _ => None,
+        }
+    }
+
+    fn get_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        match (self, value) {
+            (PathNode::Key(key), Value::Compound(map)) => map.get_mut(key),
+            (PathNode::Index(index), Value::List(list)) => {
+                let index = resolve_index(*index, list.len())?;
+                list.get_mut(index)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn resolve_index(index: i32, len: usize) -> Option<usize> {
+    let index = if index < 0 {
+        len.checked_sub(index.unsigned_abs() as usize)?
+    } else {
+        index as usize
+    };
+    if index < len {
+        Some(index)
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn not_found() -> Error {
+    Error::syntax(ErrorCode::NbtPathNotFound, 0)
+}
+
+#[inline]
+fn is_bare_key_char(c: char) -> bool {
+    !matches!(c, ' ' | '.' | '[' | ']' | '{' | '}' | '"' | '\'')
+}
+
+impl FromStr for NbtPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |index| Error::syntax(ErrorCode::InvalidNbtPath, index);
+        let mut nodes = Vec::new();
+        let mut index = 0;
+        while index < s.len() {
+            let rest = &s[index..];
+            if let Some(inner) = rest.strip_prefix('[') {
+                let end = inner.find(']').ok_or_else(|| invalid(index))?;
+                let n = inner[..end].trim().parse().map_err(|_| invalid(index))?;
+                nodes.push(PathNode::Index(n));
+                index += end + 2;
+                continue;
+            }
+            let rest = match rest.strip_prefix('.') {
+                Some(rest) if !nodes.is_empty() => {
+                    index += 1;
+                    rest
+                }
+                Some(_) => return Err(invalid(index)),
+                None if nodes.is_empty() => rest,
+                None => return Err(invalid(index)),
+            };
+            match rest.chars().next() {
+                Some(quote @ '"') | Some(quote @ '\'') => {
+                    let mut key = String::new();
+                    let mut chars = rest.char_indices().skip(1);
+                    let mut end = None;
+                    while let Some((n, c)) = chars.next() {
+                        match c {
+                            '\\' => match chars.next() {
+                                Some((_, c)) => key.push(c),
+                                None => break,
+                            },
+                            c if c == quote => {
+                                end = Some(n + 1);
+                                break;
+                            }
+                            c => key.push(c),
+                        }
+                    }
+                    index += end.ok_or_else(|| invalid(index))?;
+                    nodes.push(PathNode::Key(key));
+                }
+                _ => {
+                    let len = rest.find(|c| !is_bare_key_char(c)).unwrap_or(rest.len());
+                    if len == 0 {
+                        return Err(invalid(index));
+                    }
+                    nodes.push(PathNode::Key(rest[..len].to_owned()));
+                    index += len;
+                }
+            }
+        }
+        if nodes.is_empty() {
+            return Err(invalid(0));
+        }
+        Ok(NbtPath { nodes })
+    }
+}
+
+impl fmt::Display for NbtPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, node) in self.nodes.iter().enumerate() {
+            match node {
+                PathNode::Key(key) => {
+                    if i != 0 {
+                        f.write_str(".")?;
+                    }
+                    if !key.is_empty() && key.chars().all(is_bare_key_char) {
+                        f.write_str(key)?;
+                    } else {
+                        f.write_str("\"")?;
+                        for c in key.chars() {
+                            if c == '"' || c == '\\' {
+                                f.write_str("\\")?;
+                            }
+                            write!(f, "{}", c)?;
+                        }
+                        f.write_str("\"")?;
+                    }
+                }
+                PathNode::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NbtPath, PathNode};
+    use crate::snbt::parse;
+    use crate::value::Value;
+
+    fn path(s: &str) -> NbtPath {
+        s.parse().unwrap()
+    }
+
+    fn key(key: &str) -> PathNode {
+        PathNode::Key(key.to_string())
+    }
+
+    #[test]
+    fn paths_are_parsed() {
+        let nodes = vec![key("Inventory"), PathNode::Index(0), key("tag"), key("a b")];
+        assert_eq!(path("Inventory[0].tag.\"a b\""), NbtPath::new(nodes));
+        assert_eq!(
+            path("[ -1 ][2]").nodes(),
+            &[PathNode::Index(-1), PathNode::Index(2)]
+        );
+        assert_eq!(path(r#"'it\'s'.x"#).nodes(), &[key("it's"), key("x")]);
+        assert_eq!(path("\"\"").nodes(), &[key("")]);
+    }
+
+    #[test]
+    fn invalid_paths_are_refused() {
+        for s in [
+            "",
+            ".a",
+            "a..b",
+            "a.",
+            "a[",
+            "a[x]",
+            "a[0]b",
+            "\"a",
+            "a b",
+            "Items[{Slot:0b}]",
+        ] {
+            assert!(s.parse::<NbtPath>().is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn paths_are_displayed_as_parsed() {
+        for s in ["a.b[0][-1].c", "\"a b\".\"\"", "\"q\\\"\\\\\"[3]", "[0].a"] {
+            assert_eq!(path(s).to_string(), s);
+            assert_eq!(path(&path(s).to_string()), path(s));
+        }
+    }
+
+    #[test]
+    fn get_follows_keys_and_indices() {
+        let root = parse("{a:{b:[1,2,3]},c:5b}").unwrap();
+        assert_eq!(path("a.b[0]").get(&root), Some(&Value::Int(1)));
+        assert_eq!(path("a.b[-1]").get(&root), Some(&Value::Int(3)));
+        assert_eq!(path("c").get(&root), Some(&Value::Byte(5)));
+        for s in ["a.b[3]", "a.b[-4]", "a.x", "c.d", "c[0]", "a[0]"] {
+            assert_eq!(path(s).get(&root), None, "{}", s);
+        }
+    }
+
+    #[test]
+    fn set_creates_compounds_but_not_list_elements() {
+        let mut root = parse("{a:[1,2]}").unwrap();
+        assert_eq!(path("b.c.d").set(&mut root, Value::Byte(1)).unwrap(), None);
+        let old = path("a[-2]").set(&mut root, Value::Int(7)).unwrap();
+        assert_eq!(old, Some(Value::Int(1)));
+        assert_eq!(root, parse("{a:[7,2],b:{c:{d:1b}}}").unwrap());
+
+        assert!(path("a[2]").set(&mut root, Value::Int(0)).is_err());
+        assert!(path("x[0].y").set(&mut root, Value::Int(0)).is_err());
+        assert!(path("a[0].y").set(&mut root, Value::Int(0)).is_err());
+    }
+
+    #[test]
+    fn remove_takes_out_entries_and_elements() {
+        let mut root = parse("{a:[1,2,3],b:{c:1b}}").unwrap();
+        assert_eq!(path("a[-1]").remove(&mut root), Some(Value::Int(3)));
+        assert_eq!(path("b.c").remove(&mut root), Some(Value::Byte(1)));
+        assert_eq!(path("b.c").remove(&mut root), None);
+        assert_eq!(path("a[5]").remove(&mut root), None);
+        assert_eq!(root, parse("{a:[1,2],b:{}}").unwrap());
+    }
+}
@@ -1,10 +1,19 @@
+//! Sources of NBT binary data, as used by the deserializer and the transcoder.
+
+#[cfg(feature = "async")]
+use crate::consts;
 use crate::error::{Error, Result};
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt};
 use core::convert::TryInto;
+use core::marker::PhantomData;
 use core::mem::size_of;
 use std::borrow::Cow;
 use std::io;
 
+/// Reads the building blocks of NBT binary data from some source.
+///
+/// Implemented by `IoRead` for any `io::Read` and by `SliceRead` for byte
+/// slices, which can borrow strings from the input.
 pub trait Read<'de> {
     fn index(&self) -> usize;
 
@@ -12,7 +21,9 @@ pub trait Read<'de> {
 
     fn read_name(&mut self) -> Result<Cow<'de, str>>;
 
-    fn read_length(&mut self) -> Result<i16>;
+    /// Read the length of a name or a string, which is an unsigned 16-bit
+    /// integer.
+    fn read_length(&mut self) -> Result<u16>;
 
     fn read_byte_inner(&mut self) -> Result<i8>;
 
@@ -27,13 +38,30 @@ pub trait Read<'de> {
     fn read_double_inner(&mut self) -> Result<f64>;
 
     fn read_string_inner(&mut self) -> Result<Cow<'de, str>>;
+
+    /// Read the length of a list or an array, which unlike names and strings
+    /// is a 32-bit integer.
+    #[inline]
+    fn read_array_length(&mut self) -> Result<i32> {
+        let len = self.read_int_inner()?;
+        if len < 0 {
+            return Err(Error::invalid_len_at(len, self.index()));
+        }
+        Ok(len)
+    }
 }
 
-pub struct IoRead<R> {
+/// NBT source from an `io::Read`.
+///
+/// Numbers are read in big endian as Java Edition does, unless the byte order
+/// `O` is changed to `LittleEndian` for Bedrock Edition data.
+pub struct IoRead<R, O = BigEndian> {
     inner: R,
     index: usize,
+    byte_order: PhantomData<O>,
 }
 
+/// NBT source from a byte slice.
 pub struct SliceRead<'a> {
     inner: &'a [u8],
     original_inner: &'a [u8],
@@ -50,17 +78,34 @@ where
         IoRead {
             inner: read,
             index: 0,
+            byte_order: PhantomData,
         }
     }
+}
 
+impl<R> IoRead<R, LittleEndian>
+where
+    R: io::Read,
+{
+    pub fn little_endian(read: R) -> Self {
+        IoRead {
+            inner: read,
+            index: 0,
+            byte_order: PhantomData,
+        }
+    }
+}
+
+impl<R, O> IoRead<R, O> {
     pub fn into_inner(self) -> R {
         self.inner
     }
 }
 
-impl<'de, R> Read<'de> for IoRead<R>
+impl<'de, R, O> Read<'de> for IoRead<R, O>
 where
     R: io::Read,
+    O: ByteOrder,
 {
     fn index(&self) -> usize {
         self.index
@@ -79,8 +124,8 @@ where
         self.read_string_inner()
     }
 
-    fn read_length(&mut self) -> Result<i16> {
-        self.read_short_inner()
+    fn read_length(&mut self) -> Result<u16> {
+        Ok(self.read_short_inner()? as u16)
     }
 
     fn read_byte_inner(&mut self) -> Result<i8> {
@@ -95,7 +140,7 @@ where
     fn read_short_inner(&mut self) -> Result<i16> {
         let value = self
             .inner
-            .read_i16::<BigEndian>()
+            .read_i16::<O>()
             .map_err(|e| Error::io_at(e, self.index))?;
         self.index += size_of::<i16>();
         Ok(value)
@@ -104,7 +149,7 @@ where
     fn read_int_inner(&mut self) -> Result<i32> {
         let value = self
             .inner
-            .read_i32::<BigEndian>()
+            .read_i32::<O>()
             .map_err(|e| Error::io_at(e, self.index))?;
         self.index += size_of::<i32>();
         Ok(value)
@@ -113,7 +158,7 @@ where
     fn read_long_inner(&mut self) -> Result<i64> {
         let value = self
             .inner
-            .read_i64::<BigEndian>()
+            .read_i64::<O>()
             .map_err(|e| Error::io_at(e, self.index))?;
         self.index += size_of::<i64>();
         Ok(value)
@@ -122,7 +167,7 @@ where
     fn read_float_inner(&mut self) -> Result<f32> {
         let value = self
             .inner
-            .read_f32::<BigEndian>()
+            .read_f32::<O>()
             .map_err(|e| Error::io_at(e, self.index))?;
         self.index += size_of::<f32>();
         Ok(value)
@@ -131,18 +176,14 @@ where
     fn read_double_inner(&mut self) -> Result<f64> {
         let value = self
             .inner
-            .read_f64::<BigEndian>()
+            .read_f64::<O>()
             .map_err(|e| Error::io_at(e, self.index))?;
         self.index += size_of::<f64>();
         Ok(value)
     }
 
     fn read_string_inner(&mut self) -> Result<Cow<'de, str>> {
-        let len = self.read_length()?;
-        if len < 0 {
-            return Err(Error::invalid_len_at(len, self.index));
-        }
-        let len = len as usize;
+        let len = usize::from(self.read_length()?);
         let mut buf = vec![0; len];
         self.inner
             .read_exact(&mut buf)
@@ -169,9 +210,28 @@ impl<'a> SliceRead<'a> {
         self.original_inner
     }
 
+    /// A source reading `slice` from `index` on, so that errors report
+    /// positions in the whole slice.
+    #[cfg(feature = "async")]
+    pub(crate) fn at(slice: &'a [u8], index: usize) -> Self {
+        SliceRead {
+            inner: &slice[index..],
+            original_inner: slice,
+            index,
+        }
+    }
+
+    #[cfg(feature = "async")]
+    fn skip(&mut self, len: usize) -> Result<()> {
+        self.check_eof(len)?;
+        self.inner = &self.inner[len..];
+        self.index += len;
+        Ok(())
+    }
+
     #[inline]
     fn check_eof(&self, remain_at_least: usize) -> Result<()> {
-        if self.index > self.original_inner.len() - remain_at_least {
+        if self.inner.len() < remain_at_least {
             return Err(Error::slice_eof());
         }
         Ok(())
@@ -200,8 +260,8 @@ impl<'a> Read<'a> for SliceRead<'a> {
         self.read_string_inner()
     }
 
-    fn read_length(&mut self) -> Result<i16> {
-        self.read_short_inner()
+    fn read_length(&mut self) -> Result<u16> {
+        Ok(self.read_short_inner()? as u16)
     }
 
     fn read_byte_inner(&mut self) -> Result<i8> {
@@ -260,11 +320,8 @@ impl<'a> Read<'a> for SliceRead<'a> {
     }
 
     fn read_string_inner(&mut self) -> Result<Cow<'a, str>> {
-        let len = self.read_length()?;
-        if len < 0 {
-            return Err(Error::invalid_len_at(len, self.index));
-        }
-        let len = len as usize;
+        let len = usize::from(self.read_length()?);
+        self.check_eof(len)?;
         let (bytes, rest) = self.inner.split_at(len);
         let borrowed = std::str::from_utf8(bytes).map_err(|_| Error::utf8_at(self.index))?;
         self.index += len;
@@ -272,3 +329,117 @@ impl<'a> Read<'a> for SliceRead<'a> {
         Ok(Cow::Borrowed(borrowed))
     }
 }
+
+/// Finds where a binary NBT document ends in bytes that arrive in pieces, so
+/// that asynchronous readers take no bytes past the document off a stream.
+///
+/// Each step reads a whole tag header or payload through `SliceRead`; a step
+/// cut short by the end of the bytes is taken again once more bytes arrived.
+#[cfg(feature = "async")]
+pub(crate) struct DocumentEnd {
+    started: bool,
+    next: Option<u8>,
+    frames: Vec<Frame>,
+    index: usize,
+}
+
+/// What is left to read of an open compound or list.
+#[cfg(feature = "async")]
+enum Frame {
+    Compound,
+    List { type_id: u8, remaining: i32 },
+}
+
+#[cfg(feature = "async")]
+impl DocumentEnd {
+    pub(crate) fn new() -> Self {
+        DocumentEnd {
+            started: false,
+            next: None,
+            frames: Vec::new(),
+            index: 0,
+        }
+    }
+
+    /// Walk on through `bytes`, the start of a document and everything that
+    /// arrived of it so far. Returns the length of the document once `bytes`
+    /// reach its end.
+    pub(crate) fn walk(&mut self, bytes: &[u8]) -> Result<Option<usize>> {
+        loop {
+            match self.step(bytes) {
+                Ok(true) => return Ok(Some(self.index)),
+                Ok(false) => {}
+                Err(err) if err.is_slice_eof() => return Ok(None),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    // Read one tag header or payload; true once the document is complete.
+    fn step(&mut self, bytes: &[u8]) -> Result<bool> {
+        let mut read = SliceRead::at(bytes, self.index);
+        if !self.started {
+            let type_id = read.read_type_id()?;
+            if type_id == consts::TYPE_ID_END {
+                return Err(Error::invalid_id_at(type_id, read.index()));
+            }
+            read.read_name()?;
+            self.started = true;
+            self.next = Some(type_id);
+        } else if let Some(type_id) = self.next {
+            let frame = match type_id {
+                consts::TYPE_ID_BYTE => read.read_byte_inner().map(|_| None)?,
+                consts::TYPE_ID_SHORT => read.read_short_inner().map(|_| None)?,
+                consts::TYPE_ID_INT => read.read_int_inner().map(|_| None)?,
+                consts::TYPE_ID_LONG => read.read_long_inner().map(|_| None)?,
+                consts::TYPE_ID_FLOAT => read.read_float_inner().map(|_| None)?,
+                consts::TYPE_ID_DOUBLE => read.read_double_inner().map(|_| None)?,
+                consts::TYPE_ID_STRING => read.read_string_inner().map(|_| None)?,
+                consts::TYPE_ID_BYTE_ARRAY => array(&mut read, 1)?,
+                consts::TYPE_ID_INT_ARRAY => array(&mut read, 4)?,
+                consts::TYPE_ID_LONG_ARRAY => array(&mut read, 8)?,
+                consts::TYPE_ID_LIST => {
+                    let type_id = read.read_type_id()?;
+                    let remaining = read.read_array_length()?;
+                    if type_id == consts::TYPE_ID_END && remaining != 0 {
+                        return Err(Error::invalid_id_at(type_id, read.index()));
+                    }
+                    Some(Frame::List { type_id, remaining })
+                }
+                consts::TYPE_ID_COMPOUND => Some(Frame::Compound),
+                _ => return Err(Error::invalid_id_at(type_id, read.index())),
+            };
+            self.frames.extend(frame);
+            self.next = None;
+        } else {
+            match self.frames.last_mut() {
+                None => return Ok(true),
+                Some(Frame::List { remaining: 0, .. }) => {
+                    self.frames.pop();
+                }
+                Some(Frame::List { type_id, remaining }) => {
+                    *remaining -= 1;
+                    self.next = Some(*type_id);
+                }
+                Some(Frame::Compound) => match read.read_type_id()? {
+                    consts::TYPE_ID_END => {
+                        self.frames.pop();
+                    }
+                    type_id => {
+                        read.read_name()?;
+                        self.next = Some(type_id);
+                    }
+                },
+            }
+        }
+        self.index = read.index();
+        Ok(false)
+    }
+}
+
+#[cfg(feature = "async")]
+fn array(read: &mut SliceRead, element_size: usize) -> Result<Option<Frame>> {
+    let len = read.read_array_length()?;
+    read.skip(len as usize * element_size)?;
+    Ok(None)
+}
@@ -7,7 +7,7 @@ use crate::{
     error::{Error, ErrorCode, Result},
     root, Compression,
 };
-use byteorder::{BigEndian, WriteBytesExt}; // <- SPICY mojang
+use byteorder::{BigEndian, LittleEndian, WriteBytesExt}; // <- SPICY mojang
 use serde::ser::{self, Impossible, Serialize};
 use std::borrow::Cow;
 
@@ -136,6 +136,28 @@ where
     Ok(unsafe { String::from_utf8_unchecked(ser.into_inner()) })
 }
 
+/// Serialize the given data structure as compact SNBT into a `String`.
+///
+/// SNBT is the text format of NBT used in Minecraft commands. The root is
+/// passed like for `to_string_transcript`, but SNBT has no place for a root
+/// name, so the name is left out.
+///
+/// # Errors
+///
+/// Serialization can fail for various reasons, for example `T` contains a map
+/// with non-string keys, or a float that is NaN or infinite.
+pub fn to_string_snbt<'k, 'v, T, R>(root: R) -> Result<String>
+where
+    T: 'v + Serialize + ?Sized,
+    R: Into<root::Root<'k, 'v, T>>,
+{
+    let root::Root { value, .. } = root.into();
+    let writer = Vec::with_capacity(128);
+    let mut ser = Serializer::with_formatter(writer, SnbtFormatter::new(), "");
+    value.serialize(&mut ser)?;
+    Ok(String::from_utf8(ser.into_inner()).expect("SNBT strings are valid UTF-8"))
+}
+
 /// A structure that serializes Rust values into JSON.
 pub struct Serializer<'a, W, F> {
     writer: W,
@@ -201,6 +223,18 @@ fn sequence_size_unknown() -> Error {
     Error::syntax(ErrorCode::SequenceSizeUnknown, 0)
 }
 
+// Array element type of private newtype structs, see `as_nbt_array`.
+#[inline]
+fn array_type_hint(name: &'static str) -> Option<u8> {
+    use crate::as_nbt_array::{TOKEN_BYTE_ARRAY, TOKEN_INT_ARRAY, TOKEN_LONG_ARRAY};
+    match name {
+        TOKEN_BYTE_ARRAY => Some(consts::TYPE_ID_BYTE_ARRAY),
+        TOKEN_INT_ARRAY => Some(consts::TYPE_ID_INT_ARRAY),
+        TOKEN_LONG_ARRAY => Some(consts::TYPE_ID_LONG_ARRAY),
+        _ => None,
+    }
+}
+
 impl<'a, 'b: 'a, W, F> ser::Serializer for &'a mut Serializer<'b, W, F>
 where
     W: io::Write,
@@ -330,10 +364,7 @@ where
     }
 
     #[inline]
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<()>
-    where
-        T: ser::Serialize,
-    {
+    fn serialize_some<T: ?Sized + ser::Serialize>(self, value: &T) -> Result<()> {
         value.serialize(self)
     }
 
@@ -360,28 +391,31 @@ where
     }
 
     #[inline]
-    fn serialize_newtype_struct<T: ?Sized>(self, name: &'static str, value: &T) -> Result<()>
-    where
-        T: ser::Serialize,
-    {
-        if name == crate::as_nbt_array::TOKEN_ARRAY {
-            value.serialize(ArraySerializer { ser: self })?;
-            return Ok(());
-        }
-        value.serialize(self)
+    fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        let type_hint = match array_type_hint(name) {
+            Some(type_id) => Some(type_id),
+            None if name == crate::as_nbt_array::TOKEN_ARRAY => None,
+            None => return value.serialize(self),
+        };
+        value.serialize(ArraySerializer {
+            type_hint,
+            inner: false,
+            ser: self,
+        })
     }
 
     #[inline]
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T: ?Sized + ser::Serialize>(
         self,
         _name: &'static str,
         _variant_index: u32,
         _variant: &'static str,
         _value: &T,
-    ) -> Result<()>
-    where
-        T: ser::Serialize,
-    {
+    ) -> Result<()> {
         unimplemented!()
     }
 
@@ -399,6 +433,7 @@ where
         Ok(SerializeList {
             type_id: None,
             len,
+            inner: false,
             ser: self,
         })
     }
@@ -571,10 +606,11 @@ where
     }
 
     #[inline]
-    fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<()>
-    where
-        T: ser::Serialize,
-    {
+    fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
         value.serialize(self)
     }
 }
@@ -583,6 +619,7 @@ where
 pub struct SerializeList<'a, 'b, W, F> {
     type_id: Option<u8>,
     len: usize,
+    inner: bool, // an element of another list, thus without a name
     ser: &'a mut Serializer<'b, W, F>,
 }
 
@@ -595,10 +632,7 @@ where
     type Error = Error;
 
     #[inline]
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
-    where
-        T: serde::Serialize,
-    {
+    fn serialize_element<T: ?Sized + serde::Serialize>(&mut self, value: &T) -> Result<()> {
         if let Some(type_id) = self.type_id {
             value.serialize(ListInnerSerializer {
                 type_id,
@@ -607,6 +641,7 @@ where
         } else {
             let type_id = value.serialize(ListHeadSerializer {
                 len: self.len,
+                inner: self.inner,
                 ser: self.ser,
             })?;
             self.type_id = Some(type_id);
@@ -619,8 +654,7 @@ where
 
     #[inline]
     fn end(self) -> Result<()> {
-        self.ser.formatter.close_list(&mut self.ser.writer)?;
-        Ok(())
+        self.end_list()
     }
 }
 
@@ -633,10 +667,7 @@ where
     type Error = Error;
 
     #[inline]
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
-    where
-        T: serde::Serialize,
-    {
+    fn serialize_element<T: ?Sized + serde::Serialize>(&mut self, value: &T) -> Result<()> {
         if let Some(type_id) = self.type_id {
             value.serialize(ListInnerSerializer {
                 type_id,
@@ -645,6 +676,7 @@ where
         } else {
             let type_id = value.serialize(ListHeadSerializer {
                 len: self.len,
+                inner: self.inner,
                 ser: self.ser,
             })?;
             self.type_id = Some(type_id);
@@ -657,6 +689,26 @@ where
 
     #[inline]
     fn end(self) -> Result<()> {
+        self.end_list()
+    }
+}
+
+impl<W, F> SerializeList<'_, '_, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    #[inline]
+    fn end_list(self) -> Result<()> {
+        // no element has written the list head; an empty list is a list of ends
+        if self.type_id.is_none() {
+            ListHeadSerializer {
+                len: 0,
+                inner: self.inner,
+                ser: self.ser,
+            }
+            .serialize_head(consts::TYPE_ID_END)?;
+        }
         self.ser.formatter.close_list(&mut self.ser.writer)?;
         Ok(())
     }
@@ -664,6 +716,7 @@ where
 
 struct ListHeadSerializer<'a, 'b, W, F> {
     len: usize,
+    inner: bool,
     ser: &'a mut Serializer<'b, W, F>,
 }
 
@@ -674,13 +727,19 @@ where
 {
     #[inline]
     fn serialize_head(&mut self, type_id: u8) -> Result<u8> {
-        self.ser.formatter.write_list_tag(
-            &mut self.ser.writer,
-            type_id,
-            self.len as i16,
-            self.ser.next_name.len() as i16,
-            self.ser.next_name.as_bytes(),
-        )?;
+        if self.inner {
+            self.ser
+                .formatter
+                .write_list_inner(&mut self.ser.writer, type_id, self.len as i32)?;
+        } else {
+            self.ser.formatter.write_list_tag(
+                &mut self.ser.writer,
+                type_id,
+                self.len as i32,
+                self.ser.next_name.len() as i16,
+                self.ser.next_name.as_bytes(),
+            )?;
+        }
         Ok(type_id)
     }
 }
@@ -693,8 +752,8 @@ where
     type Ok = u8;
     type Error = Error;
 
-    type SerializeSeq = Impossible<Self::Ok, Self::Error>;
-    type SerializeTuple = Impossible<Self::Ok, Self::Error>;
+    type SerializeSeq = NoOp;
+    type SerializeTuple = NoOp;
     type SerializeTupleStruct = Impossible<Self::Ok, Self::Error>;
     type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
     type SerializeMap = NoOp;
@@ -704,8 +763,8 @@ where
     return_expr_for_serialized_types! {
         Err(unsupported_list_inner_type());
         u8 u16 u32 u64 bytes
-        newtype_variant unit unit_struct seq
-        tuple tuple_struct tuple_variant struct_variant
+        newtype_variant unit unit_struct
+        tuple_struct tuple_variant struct_variant
     }
 
     #[inline]
@@ -768,13 +827,27 @@ where
     }
 
     #[inline]
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok>
-    where
-        T: ser::Serialize,
-    {
+    fn serialize_some<T: ?Sized + ser::Serialize>(self, value: &T) -> Result<Self::Ok> {
         value.serialize(self)
     }
 
+    #[inline]
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        if let Some(len) = len {
+            self.serialize_tuple(len)
+        } else {
+            Err(sequence_size_unknown())
+        }
+    }
+
+    #[inline]
+    fn serialize_tuple(mut self, _len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_head(consts::TYPE_ID_LIST)?;
+        Ok(NoOp {
+            type_id: consts::TYPE_ID_LIST,
+        })
+    }
+
     #[inline]
     fn serialize_map(mut self, _len: Option<usize>) -> Result<Self::SerializeMap> {
         self.serialize_head(consts::TYPE_ID_COMPOUND)?;
@@ -806,11 +879,15 @@ where
     }
 
     #[inline]
-    fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
-    where
-        T: ser::Serialize,
-    {
-        value.serialize(self)
+    fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(
+        mut self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok> {
+        match array_type_hint(name) {
+            Some(type_id) => self.serialize_head(type_id),
+            None => value.serialize(self),
+        }
     }
 }
 
@@ -851,8 +928,8 @@ where
     type Ok = ();
     type Error = Error;
 
-    type SerializeSeq = Impossible<(), Error>;
-    type SerializeTuple = Impossible<(), Error>;
+    type SerializeSeq = SerializeList<'a, 'b, W, F>;
+    type SerializeTuple = SerializeList<'a, 'b, W, F>;
     type SerializeTupleStruct = Impossible<(), Error>;
     type SerializeTupleVariant = Impossible<(), Error>;
     type SerializeMap = SerializeCompound<'a, 'b, W, F>;
@@ -862,8 +939,8 @@ where
     return_expr_for_serialized_types! {
         Err(unsupported_list_inner_type());
         u8 u16 u32 u64 bytes
-        newtype_variant unit unit_struct seq
-        tuple tuple_struct tuple_variant struct_variant
+        newtype_variant unit unit_struct
+        tuple_struct tuple_variant struct_variant
     }
 
     #[inline]
@@ -957,13 +1034,30 @@ where
     }
 
     #[inline]
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<()>
-    where
-        T: ser::Serialize,
-    {
+    fn serialize_some<T: ?Sized + ser::Serialize>(self, value: &T) -> Result<()> {
         value.serialize(self)
     }
 
+    #[inline]
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        if let Some(len) = len {
+            self.serialize_tuple(len)
+        } else {
+            Err(sequence_size_unknown())
+        }
+    }
+
+    #[inline]
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.verify_type(consts::TYPE_ID_LIST)?;
+        Ok(SerializeList {
+            type_id: None,
+            len,
+            inner: true,
+            ser: self.ser,
+        })
+    }
+
     #[inline]
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
         self.verify_type(consts::TYPE_ID_COMPOUND)?;
@@ -993,15 +1087,28 @@ where
     }
 
     #[inline]
-    fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<()>
-    where
-        T: ser::Serialize,
-    {
-        value.serialize(self)
+    fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        match array_type_hint(name) {
+            Some(type_id) => {
+                self.verify_type(type_id)?;
+                value.serialize(ArraySerializer {
+                    type_hint: Some(type_id),
+                    inner: true,
+                    ser: self.ser,
+                })
+            }
+            None => value.serialize(self),
+        }
     }
 }
 
 struct ArraySerializer<'a, 'b, W, F> {
+    type_hint: Option<u8>,
+    inner: bool, // only arrays with a type hint could be inner
     ser: &'a mut Serializer<'b, W, F>,
 }
 
@@ -1035,15 +1142,28 @@ where
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
-        if let Some(len) = len {
-            Ok(SerializeArray {
-                len,
-                ser: self.ser,
-                type_id: None,
-            })
-        } else {
-            Err(sequence_size_unknown())
+        let len = len.ok_or_else(sequence_size_unknown)?;
+        // with a known type, the head is written at once so empty arrays keep it
+        if let Some(type_id) = self.type_hint {
+            if self.inner {
+                self.ser
+                    .formatter
+                    .write_array_inner(&mut self.ser.writer, type_id, len as i32)?;
+            } else {
+                self.ser.formatter.write_array_head(
+                    &mut self.ser.writer,
+                    type_id,
+                    len as i32,
+                    self.ser.next_name.len() as i16,
+                    self.ser.next_name.as_bytes(),
+                )?;
+            }
         }
+        Ok(SerializeArray {
+            type_id: self.type_hint,
+            len,
+            ser: self.ser,
+        })
     }
 }
 
@@ -1062,10 +1182,7 @@ where
     type Error = Error;
 
     #[inline]
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
-    where
-        T: serde::Serialize,
-    {
+    fn serialize_element<T: ?Sized + serde::Serialize>(&mut self, value: &T) -> Result<()> {
         if let Some(type_id) = self.type_id {
             value.serialize(ArrayInnerSerializer {
                 type_id,
@@ -1106,7 +1223,7 @@ where
         self.ser.formatter.write_array_head(
             &mut self.ser.writer,
             type_id,
-            self.len as i16,
+            self.len as i32,
             self.ser.next_name.len() as i16,
             self.ser.next_name.as_bytes(),
         )?;
@@ -1156,10 +1273,11 @@ where
     }
 
     #[inline]
-    fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
-    where
-        T: ser::Serialize,
-    {
+    fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok> {
         value.serialize(self)
     }
 }
@@ -1244,10 +1362,11 @@ where
     }
 
     #[inline]
-    fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<()>
-    where
-        T: ser::Serialize,
-    {
+    fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
         value.serialize(self)
     }
 }
@@ -1261,10 +1380,22 @@ impl ser::SerializeSeq for NoOp {
     type Error = Error;
 
     #[inline]
-    fn serialize_element<T: ?Sized>(&mut self, _value: &T) -> Result<()>
-    where
-        T: serde::Serialize,
-    {
+    fn serialize_element<T: ?Sized + serde::Serialize>(&mut self, _value: &T) -> Result<()> {
+        Ok(())
+    }
+
+    #[inline]
+    fn end(self) -> Result<u8> {
+        Ok(self.type_id)
+    }
+}
+
+impl ser::SerializeTuple for NoOp {
+    type Ok = u8;
+    type Error = Error;
+
+    #[inline]
+    fn serialize_element<T: ?Sized + serde::Serialize>(&mut self, _value: &T) -> Result<()> {
         Ok(())
     }
 
@@ -1279,10 +1410,11 @@ impl ser::SerializeStruct for NoOp {
     type Error = Error;
 
     #[inline]
-    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, _value: &T) -> Result<()>
-    where
-        T: serde::Serialize,
-    {
+    fn serialize_field<T: ?Sized + serde::Serialize>(
+        &mut self,
+        _key: &'static str,
+        _value: &T,
+    ) -> Result<()> {
         Ok(())
     }
 
@@ -1297,18 +1429,12 @@ impl ser::SerializeMap for NoOp {
     type Error = Error;
 
     #[inline]
-    fn serialize_key<T: ?Sized>(&mut self, _key: &T) -> Result<()>
-    where
-        T: serde::Serialize,
-    {
+    fn serialize_key<T: ?Sized + serde::Serialize>(&mut self, _key: &T) -> Result<()> {
         Ok(())
     }
 
     #[inline]
-    fn serialize_value<T: ?Sized>(&mut self, _value: &T) -> Result<()>
-    where
-        T: serde::Serialize,
-    {
+    fn serialize_value<T: ?Sized + serde::Serialize>(&mut self, _value: &T) -> Result<()> {
         Ok(())
     }
 
@@ -1318,41 +1444,36 @@ impl ser::SerializeMap for NoOp {
     }
 }
 
+/// Writes the tags of NBT data, in binary NBT or a text format.
+///
+/// The default methods write big endian binary NBT. Since 0.2 list and array
+/// lengths are `i32`, see the changelog.
 pub trait Formatter {
     #[inline]
-    fn write_compound_tag<W: ?Sized>(
+    fn write_compound_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         name_len: i16,
         name_bytes: &[u8],
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         w.write_u8(consts::TYPE_ID_COMPOUND)?;
         w.write_i16::<BigEndian>(name_len)?;
         w.write_all(name_bytes)
     }
 
     #[inline]
-    fn write_end_tag<W: ?Sized>(&mut self, w: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    fn write_end_tag<W: ?Sized + io::Write>(&mut self, w: &mut W) -> io::Result<()> {
         w.write_u8(consts::TYPE_ID_END)
     }
 
     #[inline]
-    fn write_byte_tag<W: ?Sized>(
+    fn write_byte_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         name_len: i16,
         name_bytes: &[u8],
         value: i8,
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         w.write_u8(consts::TYPE_ID_BYTE)?;
         w.write_i16::<BigEndian>(name_len)?;
         w.write_all(name_bytes)?;
@@ -1360,16 +1481,13 @@ pub trait Formatter {
     }
 
     #[inline]
-    fn write_short_tag<W: ?Sized>(
+    fn write_short_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         name_len: i16,
         name_bytes: &[u8],
         value: i16,
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         w.write_u8(consts::TYPE_ID_SHORT)?;
         w.write_i16::<BigEndian>(name_len)?;
         w.write_all(name_bytes)?;
@@ -1377,16 +1495,13 @@ pub trait Formatter {
     }
 
     #[inline]
-    fn write_int_tag<W: ?Sized>(
+    fn write_int_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         name_len: i16,
         name_bytes: &[u8],
         value: i32,
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         w.write_u8(consts::TYPE_ID_INT)?;
         w.write_i16::<BigEndian>(name_len)?;
         w.write_all(name_bytes)?;
@@ -1394,16 +1509,13 @@ pub trait Formatter {
     }
 
     #[inline]
-    fn write_long_tag<W: ?Sized>(
+    fn write_long_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         name_len: i16,
         name_bytes: &[u8],
         value: i64,
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         w.write_u8(consts::TYPE_ID_LONG)?;
         w.write_i16::<BigEndian>(name_len)?;
         w.write_all(name_bytes)?;
@@ -1411,16 +1523,13 @@ pub trait Formatter {
     }
 
     #[inline]
-    fn write_float_tag<W: ?Sized>(
+    fn write_float_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         name_len: i16,
         name_bytes: &[u8],
         value: f32,
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         w.write_u8(consts::TYPE_ID_FLOAT)?;
         w.write_i16::<BigEndian>(name_len)?;
         w.write_all(name_bytes)?;
@@ -1428,16 +1537,13 @@ pub trait Formatter {
     }
 
     #[inline]
-    fn write_double_tag<W: ?Sized>(
+    fn write_double_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         name_len: i16,
         name_bytes: &[u8],
         value: f64,
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         w.write_u8(consts::TYPE_ID_DOUBLE)?;
         w.write_i16::<BigEndian>(name_len)?;
         w.write_all(name_bytes)?;
@@ -1445,17 +1551,14 @@ pub trait Formatter {
     }
 
     #[inline]
-    fn write_string_tag<W: ?Sized>(
+    fn write_string_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         name_len: i16,
         name_bytes: &[u8],
         string_len: i16,
         string_bytes: &[u8],
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         w.write_u8(consts::TYPE_ID_STRING)?;
         w.write_i16::<BigEndian>(name_len)?;
         w.write_all(name_bytes)?;
@@ -1463,66 +1566,83 @@ pub trait Formatter {
         w.write_all(string_bytes)
     }
 
+    /// Write the head of a named list tag.
+    ///
+    /// `len` is 32 bits, like in the NBT format; it was an `i16` before
+    /// 0.2, which truncated lists longer than 32767 elements.
     #[inline]
-    fn write_list_tag<W: ?Sized>(
+    fn write_list_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         type_id: u8,
-        len: i16,
+        len: i32,
         name_len: i16,
         name_bytes: &[u8],
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         w.write_u8(consts::TYPE_ID_LIST)?;
         w.write_i16::<BigEndian>(name_len)?;
         w.write_all(name_bytes)?;
         w.write_u8(type_id)?;
-        w.write_i16::<BigEndian>(len)
+        w.write_i32::<BigEndian>(len)
     }
 
+    /// Begin a list as an element of another list.
     #[inline]
-    fn close_list<W: ?Sized>(&mut self, w: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    fn write_list_inner<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        type_id: u8,
+        len: i32,
+    ) -> io::Result<()> {
+        w.write_u8(type_id)?;
+        w.write_i32::<BigEndian>(len)
+    }
+
+    #[inline]
+    fn close_list<W: ?Sized + io::Write>(&mut self, w: &mut W) -> io::Result<()> {
         let _ = w;
         Ok(())
     }
 
+    /// Write the head of a named array tag.
+    ///
+    /// `len` is 32 bits, like in the NBT format; it was an `i16` before
+    /// 0.2, which truncated arrays longer than 32767 elements.
     #[inline]
-    fn write_array_head<W: ?Sized>(
+    fn write_array_head<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         type_id: u8,
-        len: i16,
+        len: i32,
         name_len: i16,
         name_bytes: &[u8],
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         w.write_u8(type_id)?;
         w.write_i16::<BigEndian>(name_len)?;
         w.write_all(name_bytes)?;
-        w.write_i16::<BigEndian>(len)
+        w.write_i32::<BigEndian>(len)
     }
 
+    /// Begin an array as an element of a list.
     #[inline]
-    fn close_array<W: ?Sized>(&mut self, w: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    fn write_array_inner<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        type_id: u8,
+        len: i32,
+    ) -> io::Result<()> {
+        let _ = type_id;
+        w.write_i32::<BigEndian>(len)
+    }
+
+    #[inline]
+    fn close_array<W: ?Sized + io::Write>(&mut self, w: &mut W) -> io::Result<()> {
         let _ = w;
         Ok(())
     }
 
     #[inline]
-    fn write_compound_inner<W: ?Sized>(&mut self, w: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    fn write_compound_inner<W: ?Sized + io::Write>(&mut self, w: &mut W) -> io::Result<()> {
         let _ = w;
         Ok(())
     }
@@ -1576,15 +1696,12 @@ pub trait Formatter {
     }
 
     #[inline]
-    fn write_string_inner<W: ?Sized>(
+    fn write_string_inner<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         string_len: i16,
         string_bytes: &[u8],
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         w.write_i16::<BigEndian>(string_len)?;
         w.write_all(string_bytes)
     }
@@ -1594,43 +1711,261 @@ pub struct BinaryFormatter;
 
 impl Formatter for BinaryFormatter {}
 
-pub struct TranscriptFormatter<'a> {
-    current_indent: usize,
-    indent: &'a [u8],
-}
+/// Writes binary NBT in little endian, as Bedrock Edition stores it on disk.
+pub struct LittleEndianFormatter;
 
-impl<'a> TranscriptFormatter<'a> {
+impl Formatter for LittleEndianFormatter {
     #[inline]
-    pub fn new() -> Self {
-        Default::default()
+    fn write_compound_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_len: i16,
+        name_bytes: &[u8],
+    ) -> io::Result<()> {
+        w.write_u8(consts::TYPE_ID_COMPOUND)?;
+        w.write_i16::<LittleEndian>(name_len)?;
+        w.write_all(name_bytes)
     }
 
     #[inline]
-    pub fn with_indent(indent: &'a [u8]) -> Self {
-        TranscriptFormatter {
-            current_indent: 0,
-            indent,
-        }
+    fn write_byte_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_len: i16,
+        name_bytes: &[u8],
+        value: i8,
+    ) -> io::Result<()> {
+        w.write_u8(consts::TYPE_ID_BYTE)?;
+        w.write_i16::<LittleEndian>(name_len)?;
+        w.write_all(name_bytes)?;
+        w.write_i8(value)
     }
-}
 
-impl Default for TranscriptFormatter<'_> {
     #[inline]
-    fn default() -> Self {
-        Self::with_indent(b"  ")
-    }
-}
-
-impl Formatter for TranscriptFormatter<'_> {
-    fn write_compound_tag<W: ?Sized>(
+    fn write_short_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         name_len: i16,
         name_bytes: &[u8],
-    ) -> io::Result<()>
-    where
-        W: io::Write,
+        value: i16,
+    ) -> io::Result<()> {
+        w.write_u8(consts::TYPE_ID_SHORT)?;
+        w.write_i16::<LittleEndian>(name_len)?;
+        w.write_all(name_bytes)?;
+        w.write_i16::<LittleEndian>(value)
+    }
+
+    #[inline]
+    fn write_int_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_len: i16,
+        name_bytes: &[u8],
+        value: i32,
+    ) -> io::Result<()> {
+        w.write_u8(consts::TYPE_ID_INT)?;
+        w.write_i16::<LittleEndian>(name_len)?;
+        w.write_all(name_bytes)?;
+        w.write_i32::<LittleEndian>(value)
+    }
+
+    #[inline]
+    fn write_long_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_len: i16,
+        name_bytes: &[u8],
+        value: i64,
+    ) -> io::Result<()> {
+        w.write_u8(consts::TYPE_ID_LONG)?;
+        w.write_i16::<LittleEndian>(name_len)?;
+        w.write_all(name_bytes)?;
+        w.write_i64::<LittleEndian>(value)
+    }
+
+    #[inline]
+    fn write_float_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_len: i16,
+        name_bytes: &[u8],
+        value: f32,
+    ) -> io::Result<()> {
+        w.write_u8(consts::TYPE_ID_FLOAT)?;
+        w.write_i16::<LittleEndian>(name_len)?;
+        w.write_all(name_bytes)?;
+        w.write_f32::<LittleEndian>(value)
+    }
+
+    #[inline]
+    fn write_double_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_len: i16,
+        name_bytes: &[u8],
+        value: f64,
+    ) -> io::Result<()> {
+        w.write_u8(consts::TYPE_ID_DOUBLE)?;
+        w.write_i16::<LittleEndian>(name_len)?;
+        w.write_all(name_bytes)?;
+        w.write_f64::<LittleEndian>(value)
+    }
+
+    #[inline]
+    fn write_string_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_len: i16,
+        name_bytes: &[u8],
+        string_len: i16,
+        string_bytes: &[u8],
+    ) -> io::Result<()> {
+        w.write_u8(consts::TYPE_ID_STRING)?;
+        w.write_i16::<LittleEndian>(name_len)?;
+        w.write_all(name_bytes)?;
+        w.write_i16::<LittleEndian>(string_len)?;
+        w.write_all(string_bytes)
+    }
+
+    #[inline]
+    fn write_list_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        type_id: u8,
+        len: i32,
+        name_len: i16,
+        name_bytes: &[u8],
+    ) -> io::Result<()> {
+        w.write_u8(consts::TYPE_ID_LIST)?;
+        w.write_i16::<LittleEndian>(name_len)?;
+        w.write_all(name_bytes)?;
+        w.write_u8(type_id)?;
+        w.write_i32::<LittleEndian>(len)
+    }
+
+    #[inline]
+    fn write_list_inner<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        type_id: u8,
+        len: i32,
+    ) -> io::Result<()> {
+        w.write_u8(type_id)?;
+        w.write_i32::<LittleEndian>(len)
+    }
+
+    #[inline]
+    fn write_array_head<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        type_id: u8,
+        len: i32,
+        name_len: i16,
+        name_bytes: &[u8],
+    ) -> io::Result<()> {
+        w.write_u8(type_id)?;
+        w.write_i16::<LittleEndian>(name_len)?;
+        w.write_all(name_bytes)?;
+        w.write_i32::<LittleEndian>(len)
+    }
+
+    #[inline]
+    fn write_array_inner<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        type_id: u8,
+        len: i32,
+    ) -> io::Result<()> {
+        let _ = type_id;
+        w.write_i32::<LittleEndian>(len)
+    }
+
+    #[inline]
+    fn write_short_inner<W>(&mut self, w: &mut W, value: i16) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        w.write_i16::<LittleEndian>(value)
+    }
+
+    #[inline]
+    fn write_int_inner<W>(&mut self, w: &mut W, value: i32) -> io::Result<()>
+    where
+        W: io::Write,
     {
+        w.write_i32::<LittleEndian>(value)
+    }
+
+    #[inline]
+    fn write_long_inner<W>(&mut self, w: &mut W, value: i64) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        w.write_i64::<LittleEndian>(value)
+    }
+
+    #[inline]
+    fn write_float_inner<W>(&mut self, w: &mut W, value: f32) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        w.write_f32::<LittleEndian>(value)
+    }
+
+    #[inline]
+    fn write_double_inner<W>(&mut self, w: &mut W, value: f64) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        w.write_f64::<LittleEndian>(value)
+    }
+
+    #[inline]
+    fn write_string_inner<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        string_len: i16,
+        string_bytes: &[u8],
+    ) -> io::Result<()> {
+        w.write_i16::<LittleEndian>(string_len)?;
+        w.write_all(string_bytes)
+    }
+}
+
+pub struct TranscriptFormatter<'a> {
+    current_indent: usize,
+    indent: &'a [u8],
+}
+
+impl<'a> TranscriptFormatter<'a> {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    #[inline]
+    pub fn with_indent(indent: &'a [u8]) -> Self {
+        TranscriptFormatter {
+            current_indent: 0,
+            indent,
+        }
+    }
+}
+
+impl Default for TranscriptFormatter<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self::with_indent(b"  ")
+    }
+}
+
+impl Formatter for TranscriptFormatter<'_> {
+    fn write_compound_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_len: i16,
+        name_bytes: &[u8],
+    ) -> io::Result<()> {
         let _ = name_len;
         let name = String::from_utf8_lossy(name_bytes);
         indent(w, self.current_indent, self.indent)?;
@@ -1639,10 +1974,7 @@ impl Formatter for TranscriptFormatter<'_> {
         Ok(())
     }
 
-    fn write_end_tag<W: ?Sized>(&mut self, w: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    fn write_end_tag<W: ?Sized + io::Write>(&mut self, w: &mut W) -> io::Result<()> {
         self.current_indent -= 1;
         indent(w, self.current_indent, self.indent)?;
         write!(w, "EndCompound")?;
@@ -1652,16 +1984,13 @@ impl Formatter for TranscriptFormatter<'_> {
         Ok(())
     }
 
-    fn write_byte_tag<W: ?Sized>(
+    fn write_byte_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         name_len: i16,
         name_bytes: &[u8],
         value: i8,
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         let _ = name_len;
         let name = String::from_utf8_lossy(name_bytes);
         indent(w, self.current_indent, self.indent)?;
@@ -1672,16 +2001,13 @@ impl Formatter for TranscriptFormatter<'_> {
         Ok(())
     }
 
-    fn write_short_tag<W: ?Sized>(
+    fn write_short_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         name_len: i16,
         name_bytes: &[u8],
         value: i16,
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         let _ = name_len;
         let name = String::from_utf8_lossy(name_bytes);
         indent(w, self.current_indent, self.indent)?;
@@ -1692,16 +2018,13 @@ impl Formatter for TranscriptFormatter<'_> {
         Ok(())
     }
 
-    fn write_int_tag<W: ?Sized>(
+    fn write_int_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         name_len: i16,
         name_bytes: &[u8],
         value: i32,
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         let _ = name_len;
         let name = String::from_utf8_lossy(name_bytes);
         indent(w, self.current_indent, self.indent)?;
@@ -1712,16 +2035,13 @@ impl Formatter for TranscriptFormatter<'_> {
         Ok(())
     }
 
-    fn write_long_tag<W: ?Sized>(
+    fn write_long_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         name_len: i16,
         name_bytes: &[u8],
         value: i64,
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         let _ = name_len;
         let name = String::from_utf8_lossy(name_bytes);
         indent(w, self.current_indent, self.indent)?;
@@ -1732,16 +2052,13 @@ impl Formatter for TranscriptFormatter<'_> {
         Ok(())
     }
 
-    fn write_float_tag<W: ?Sized>(
+    fn write_float_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         name_len: i16,
         name_bytes: &[u8],
         value: f32,
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         let _ = name_len;
         let name = String::from_utf8_lossy(name_bytes);
         indent(w, self.current_indent, self.indent)?;
@@ -1752,16 +2069,13 @@ impl Formatter for TranscriptFormatter<'_> {
         Ok(())
     }
 
-    fn write_double_tag<W: ?Sized>(
+    fn write_double_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         name_len: i16,
         name_bytes: &[u8],
         value: f64,
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         let _ = name_len;
         let name = String::from_utf8_lossy(name_bytes);
         indent(w, self.current_indent, self.indent)?;
@@ -1772,17 +2086,14 @@ impl Formatter for TranscriptFormatter<'_> {
         Ok(())
     }
 
-    fn write_string_tag<W: ?Sized>(
+    fn write_string_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         name_len: i16,
         name_bytes: &[u8],
         string_len: i16,
         string_bytes: &[u8],
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         let _ = (name_len, string_len);
         let name = String::from_utf8_lossy(name_bytes);
         let string = String::from_utf8_lossy(string_bytes);
@@ -1794,17 +2105,14 @@ impl Formatter for TranscriptFormatter<'_> {
         Ok(())
     }
 
-    fn write_list_tag<W: ?Sized>(
+    fn write_list_tag<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         type_id: u8,
-        len: i16,
+        len: i32,
         name_len: i16,
         name_bytes: &[u8],
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         let _ = name_len;
         let name = String::from_utf8_lossy(name_bytes);
         indent(w, self.current_indent, self.indent)?;
@@ -1813,10 +2121,19 @@ impl Formatter for TranscriptFormatter<'_> {
         Ok(())
     }
 
-    fn close_list<W: ?Sized>(&mut self, w: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    fn write_list_inner<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        type_id: u8,
+        len: i32,
+    ) -> io::Result<()> {
+        indent(w, self.current_indent, self.indent)?;
+        writeln!(w, "List: [{}; {}]", type_id, len)?;
+        self.current_indent += 1;
+        Ok(())
+    }
+
+    fn close_list<W: ?Sized + io::Write>(&mut self, w: &mut W) -> io::Result<()> {
         self.current_indent -= 1;
         indent(w, self.current_indent, self.indent)?;
         write!(w, "EndList")?;
@@ -1827,25 +2144,17 @@ impl Formatter for TranscriptFormatter<'_> {
     }
 
     #[inline]
-    fn write_array_head<W: ?Sized>(
+    fn write_array_head<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         type_id: u8,
-        len: i16,
+        len: i32,
         name_len: i16,
         name_bytes: &[u8],
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         let _ = name_len;
         let name = String::from_utf8_lossy(name_bytes);
-        let (array_type, inner_type) = match type_id {
-            consts::TYPE_ID_BYTE_ARRAY => ("ByteArray", "Byte"),
-            consts::TYPE_ID_INT_ARRAY => ("IntArray", "Int"),
-            consts::TYPE_ID_LONG_ARRAY => ("LongArray", "Long"),
-            _ => panic!("wrong type_id parameter"),
-        };
+        let (array_type, inner_type) = array_type_names(type_id);
         indent(w, self.current_indent, self.indent)?;
         writeln!(w, "{} '{}': [{}; {}]", array_type, name, inner_type, len)?;
         self.current_indent += 1;
@@ -1853,10 +2162,21 @@ impl Formatter for TranscriptFormatter<'_> {
     }
 
     #[inline]
-    fn close_array<W: ?Sized>(&mut self, w: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    fn write_array_inner<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        type_id: u8,
+        len: i32,
+    ) -> io::Result<()> {
+        let (array_type, inner_type) = array_type_names(type_id);
+        indent(w, self.current_indent, self.indent)?;
+        writeln!(w, "{}: [{}; {}]", array_type, inner_type, len)?;
+        self.current_indent += 1;
+        Ok(())
+    }
+
+    #[inline]
+    fn close_array<W: ?Sized + io::Write>(&mut self, w: &mut W) -> io::Result<()> {
         self.current_indent -= 1;
         indent(w, self.current_indent, self.indent)?;
         write!(w, "EndArray")?;
@@ -1866,10 +2186,7 @@ impl Formatter for TranscriptFormatter<'_> {
         Ok(())
     }
 
-    fn write_compound_inner<W: ?Sized>(&mut self, w: &mut W) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    fn write_compound_inner<W: ?Sized + io::Write>(&mut self, w: &mut W) -> io::Result<()> {
         indent(w, self.current_indent, self.indent)?;
         writeln!(w, "Compound")?;
         self.current_indent += 1;
@@ -1930,15 +2247,12 @@ impl Formatter for TranscriptFormatter<'_> {
         Ok(())
     }
 
-    fn write_string_inner<W: ?Sized>(
+    fn write_string_inner<W: ?Sized + io::Write>(
         &mut self,
         w: &mut W,
         string_len: i16,
         string_bytes: &[u8],
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-    {
+    ) -> io::Result<()> {
         let _ = string_len;
         let string = String::from_utf8_lossy(string_bytes);
         indent(w, self.current_indent, self.indent)?;
@@ -1947,10 +2261,339 @@ impl Formatter for TranscriptFormatter<'_> {
     }
 }
 
-fn indent<W: ?Sized>(w: &mut W, n: usize, s: &[u8]) -> io::Result<()>
-where
-    W: io::Write,
-{
+/// Writes compact SNBT, the text format of NBT used in Minecraft commands,
+/// like `{name:"Steve",Pos:[0.5d,64.0d,0.5d]}`.
+///
+/// SNBT has no place for the root name, so it is left out.
+#[derive(Default)]
+pub struct SnbtFormatter {
+    // whether each open compound, list or array has got any element yet
+    has_elements: Vec<bool>,
+}
+
+impl SnbtFormatter {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Write the separator before a value, plus its key if it is named.
+    fn begin_value<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_bytes: Option<&[u8]>,
+    ) -> io::Result<()> {
+        match self.has_elements.last_mut() {
+            Some(has_elements) => {
+                if *has_elements {
+                    w.write_all(b",")?;
+                }
+                *has_elements = true;
+            }
+            None => return Ok(()), // root name is not written
+        }
+        if let Some(name_bytes) = name_bytes {
+            let name = String::from_utf8_lossy(name_bytes);
+            if !name.is_empty() && name.bytes().all(is_snbt_bare_char) {
+                w.write_all(name.as_bytes())?;
+            } else {
+                write_snbt_quoted(w, &name)?;
+            }
+            w.write_all(b":")?;
+        }
+        Ok(())
+    }
+
+    fn open<W: ?Sized + io::Write>(&mut self, w: &mut W, s: &[u8]) -> io::Result<()> {
+        self.has_elements.push(false);
+        w.write_all(s)
+    }
+
+    fn close<W: ?Sized + io::Write>(&mut self, w: &mut W, s: &[u8]) -> io::Result<()> {
+        self.has_elements.pop();
+        w.write_all(s)
+    }
+}
+
+impl Formatter for SnbtFormatter {
+    fn write_compound_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_len: i16,
+        name_bytes: &[u8],
+    ) -> io::Result<()> {
+        let _ = name_len;
+        self.begin_value(w, Some(name_bytes))?;
+        self.open(w, b"{")
+    }
+
+    fn write_end_tag<W: ?Sized + io::Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.close(w, b"}")
+    }
+
+    fn write_byte_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_len: i16,
+        name_bytes: &[u8],
+        value: i8,
+    ) -> io::Result<()> {
+        let _ = name_len;
+        self.begin_value(w, Some(name_bytes))?;
+        write!(w, "{}b", value)
+    }
+
+    fn write_short_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_len: i16,
+        name_bytes: &[u8],
+        value: i16,
+    ) -> io::Result<()> {
+        let _ = name_len;
+        self.begin_value(w, Some(name_bytes))?;
+        write!(w, "{}s", value)
+    }
+
+    fn write_int_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_len: i16,
+        name_bytes: &[u8],
+        value: i32,
+    ) -> io::Result<()> {
+        let _ = name_len;
+        self.begin_value(w, Some(name_bytes))?;
+        write!(w, "{}", value)
+    }
+
+    fn write_long_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_len: i16,
+        name_bytes: &[u8],
+        value: i64,
+    ) -> io::Result<()> {
+        let _ = name_len;
+        self.begin_value(w, Some(name_bytes))?;
+        write!(w, "{}L", value)
+    }
+
+    fn write_float_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_len: i16,
+        name_bytes: &[u8],
+        value: f32,
+    ) -> io::Result<()> {
+        let _ = name_len;
+        check_snbt_finite(value.is_finite())?;
+        self.begin_value(w, Some(name_bytes))?;
+        write!(w, "{:?}f", value)
+    }
+
+    fn write_double_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_len: i16,
+        name_bytes: &[u8],
+        value: f64,
+    ) -> io::Result<()> {
+        let _ = name_len;
+        check_snbt_finite(value.is_finite())?;
+        self.begin_value(w, Some(name_bytes))?;
+        write!(w, "{:?}d", value)
+    }
+
+    fn write_string_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        name_len: i16,
+        name_bytes: &[u8],
+        string_len: i16,
+        string_bytes: &[u8],
+    ) -> io::Result<()> {
+        let _ = (name_len, string_len);
+        self.begin_value(w, Some(name_bytes))?;
+        write_snbt_quoted(w, &String::from_utf8_lossy(string_bytes))
+    }
+
+    fn write_list_tag<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        type_id: u8,
+        len: i32,
+        name_len: i16,
+        name_bytes: &[u8],
+    ) -> io::Result<()> {
+        let _ = (type_id, len, name_len);
+        self.begin_value(w, Some(name_bytes))?;
+        self.open(w, b"[")
+    }
+
+    fn write_list_inner<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        type_id: u8,
+        len: i32,
+    ) -> io::Result<()> {
+        let _ = (type_id, len);
+        self.begin_value(w, None)?;
+        self.open(w, b"[")
+    }
+
+    fn close_list<W: ?Sized + io::Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.close(w, b"]")
+    }
+
+    fn write_array_head<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        type_id: u8,
+        len: i32,
+        name_len: i16,
+        name_bytes: &[u8],
+    ) -> io::Result<()> {
+        let _ = (len, name_len);
+        self.begin_value(w, Some(name_bytes))?;
+        self.open(w, snbt_array_prefix(type_id))
+    }
+
+    fn write_array_inner<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        type_id: u8,
+        len: i32,
+    ) -> io::Result<()> {
+        let _ = len;
+        self.begin_value(w, None)?;
+        self.open(w, snbt_array_prefix(type_id))
+    }
+
+    fn close_array<W: ?Sized + io::Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.close(w, b"]")
+    }
+
+    fn write_compound_inner<W: ?Sized + io::Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.begin_value(w, None)?;
+        self.open(w, b"{")
+    }
+
+    fn write_byte_inner<W>(&mut self, w: &mut W, value: i8) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        self.begin_value(w, None)?;
+        write!(w, "{}b", value)
+    }
+
+    fn write_short_inner<W>(&mut self, w: &mut W, value: i16) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        self.begin_value(w, None)?;
+        write!(w, "{}s", value)
+    }
+
+    fn write_int_inner<W>(&mut self, w: &mut W, value: i32) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        self.begin_value(w, None)?;
+        write!(w, "{}", value)
+    }
+
+    fn write_long_inner<W>(&mut self, w: &mut W, value: i64) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        self.begin_value(w, None)?;
+        write!(w, "{}L", value)
+    }
+
+    fn write_float_inner<W>(&mut self, w: &mut W, value: f32) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        check_snbt_finite(value.is_finite())?;
+        self.begin_value(w, None)?;
+        write!(w, "{:?}f", value)
+    }
+
+    fn write_double_inner<W>(&mut self, w: &mut W, value: f64) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        check_snbt_finite(value.is_finite())?;
+        self.begin_value(w, None)?;
+        write!(w, "{:?}d", value)
+    }
+
+    fn write_string_inner<W: ?Sized + io::Write>(
+        &mut self,
+        w: &mut W,
+        string_len: i16,
+        string_bytes: &[u8],
+    ) -> io::Result<()> {
+        let _ = string_len;
+        self.begin_value(w, None)?;
+        write_snbt_quoted(w, &String::from_utf8_lossy(string_bytes))
+    }
+}
+
+#[inline]
+fn is_snbt_bare_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.' | b'+')
+}
+
+// Quote like vanilla: double quotes, unless the string only contains those.
+fn write_snbt_quoted<W: ?Sized + io::Write>(w: &mut W, s: &str) -> io::Result<()> {
+    let quote = if s.contains('"') && !s.contains('\'') {
+        '\''
+    } else {
+        '"'
+    };
+    let mut buf = String::with_capacity(s.len() + 2);
+    buf.push(quote);
+    for c in s.chars() {
+        if c == quote || c == '\\' {
+            buf.push('\\');
+        }
+        buf.push(c);
+    }
+    buf.push(quote);
+    w.write_all(buf.as_bytes())
+}
+
+// SNBT has no literal for NaN or the infinities; the `NaNf` a plain
+// formatting would give reads back as a string.
+fn check_snbt_finite(finite: bool) -> io::Result<()> {
+    if finite {
+        return Ok(());
+    }
+    let msg = "NaN and infinite floats cannot be written as SNBT";
+    Err(io::Error::new(io::ErrorKind::InvalidInput, msg))
+}
+
+fn snbt_array_prefix(type_id: u8) -> &'static [u8] {
+    match type_id {
+        consts::TYPE_ID_BYTE_ARRAY => b"[B;",
+        consts::TYPE_ID_INT_ARRAY => b"[I;",
+        consts::TYPE_ID_LONG_ARRAY => b"[L;",
+        _ => panic!("wrong type_id parameter"),
+    }
+}
+
+fn array_type_names(type_id: u8) -> (&'static str, &'static str) {
+    match type_id {
+        consts::TYPE_ID_BYTE_ARRAY => ("ByteArray", "Byte"),
+        consts::TYPE_ID_INT_ARRAY => ("IntArray", "Int"),
+        consts::TYPE_ID_LONG_ARRAY => ("LongArray", "Long"),
+        _ => panic!("wrong type_id parameter"),
+    }
+}
+
+fn indent<W: ?Sized + io::Write>(w: &mut W, n: usize, s: &[u8]) -> io::Result<()> {
     for _ in 0..n {
         w.write_all(s)?;
     }
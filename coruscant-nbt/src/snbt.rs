@@ -0,0 +1,420 @@
+//! Read SNBT, the string NBT format used in Minecraft commands.
+//!
+//! This accepts what `/data` and `/give` accept, for example
+//! `{Count:1b,id:"minecraft:stone",tag:{display:{Lore:['"hi"']}}}`:
+//!
+//! - numbers take a type suffix `b`, `s`, `L`, `f` or `d` in either case; an
+//!   unsuffixed integer is an Int, an unsuffixed decimal is a Double;
+//! - `true` and `false` are the Bytes 1 and 0;
+//! - strings are quoted with `"` or `'` and escape with `\`, or are left bare
+//!   when they only consist of `0-9A-Za-z_-.+`;
+//! - arrays are written as `[B;1b,2b]`, `[I;1,2]` and `[L;1L,2L]`;
+//! - all elements of a list must have the same type.
+//!
+//! Use `SnbtFormatter` or `to_string_snbt` to write SNBT.
+
+use crate::consts;
+use crate::error::{Error, ErrorCode, Result};
+use crate::map::Map;
+use crate::value::{from_value, Value};
+use serde::de;
+
+/// Deserialize an instance of type `T` from an SNBT string.
+///
+/// # Example
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Item {
+///     id: String,
+///     #[serde(rename = "Count")]
+///     count: i8,
+/// }
+///
+/// let item: Item = coruscant_nbt::from_str_snbt(r#"{id:"minecraft:stone",Count:64b}"#)
+///     .expect("parse SNBT");
+/// assert_eq!(item.count, 64);
+/// ```
+pub fn from_str_snbt<T>(s: &str) -> Result<T>
+where
+    T: de::DeserializeOwned,
+{
+    from_value(parse(s)?)
+}
+
+/// Parse an SNBT string into an NBT value.
+pub fn parse(s: &str) -> Result<Value> {
+    let mut parser = Parser { input: s, index: 0 };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.index < parser.input.len() {
+        return Err(Error::syntax(ErrorCode::TrailingCharacters, parser.index));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    index: usize,
+}
+
+impl<'a> Parser<'a> {
+    #[inline]
+    fn invalid(&self) -> Error {
+        Error::syntax(ErrorCode::InvalidSnbtSyntax, self.index)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.index..];
+        self.index += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Result<char> {
+        self.skip_whitespace();
+        self.input[self.index..]
+            .chars()
+            .next()
+            .ok_or_else(|| Error::syntax(ErrorCode::SnbtUnexpectedEof, self.index))
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.peek()? != c {
+            return Err(self.invalid());
+        }
+        self.index += c.len_utf8();
+        Ok(())
+    }
+
+    fn parse_value(&mut self) -> Result<Value> {
+        match self.peek()? {
+            '{' => self.parse_compound(),
+            '[' => self.parse_list_or_array(),
+            '"' | '\'' => Ok(Value::String(self.parse_quoted()?)),
+            _ => {
+                let bare = self.parse_bare()?;
+                Ok(bare_value(bare).unwrap_or_else(|| Value::String(bare.to_owned())))
+            }
+        }
+    }
+
+    fn parse_key(&mut self) -> Result<String> {
+        match self.peek()? {
+            '"' | '\'' => self.parse_quoted(),
+            _ => self.parse_bare().map(str::to_owned),
+        }
+    }
+
+    fn parse_bare(&mut self) -> Result<&'a str> {
+        let rest = &self.input[self.index..];
+        let len = rest.find(|c: char| !is_bare_char(c)).unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.invalid());
+        }
+        self.index += len;
+        Ok(&rest[..len])
+    }
+
+    fn parse_quoted(&mut self) -> Result<String> {
+        let quote = self.peek()?;
+        self.index += 1;
+        let mut ans = String::new();
+        let mut chars = self.input[self.index..].char_indices();
+        while let Some((n, c)) = chars.next() {
+            if c == quote {
+                self.index += n + 1;
+                return Ok(ans);
+            }
+            if c == '\\' {
+                match chars.next() {
+                    Some((_, c)) if c == quote || c == '\\' => ans.push(c),
+                    Some((m, _)) => {
+                        self.index += m;
+                        return Err(self.invalid());
+                    }
+                    None => break,
+                }
+            } else {
+                ans.push(c);
+            }
+        }
+        self.index = self.input.len();
+        Err(Error::syntax(ErrorCode::SnbtUnexpectedEof, self.index))
+    }
+
+    fn parse_compound(&mut self) -> Result<Value> {
+        self.expect('{')?;
+        let mut map = Map::new();
+        if self.peek()? == '}' {
+            self.index += 1;
+            return Ok(Value::Compound(map));
+        }
+        loop {
+            let key = self.parse_key()?;
+            self.expect(':')?;
+            let value = self.parse_value()?;
+            map.insert(key, value);
+            match self.peek()? {
+                ',' => self.index += 1,
+                '}' => {
+                    self.index += 1;
+                    return Ok(Value::Compound(map));
+                }
+                _ => return Err(self.invalid()),
+            }
+        }
+    }
+
+    fn parse_list_or_array(&mut self) -> Result<Value> {
+        self.expect('[')?;
+        let rest = &self.input[self.index..];
+        let array_type_id = match rest.as_bytes() {
+            [b'B', b';', ..] => Some(consts::TYPE_ID_BYTE_ARRAY),
+            [b'I', b';', ..] => Some(consts::TYPE_ID_INT_ARRAY),
+            [b'L', b';', ..] => Some(consts::TYPE_ID_LONG_ARRAY),
+            _ => None,
+        };
+        if let Some(type_id) = array_type_id {
+            self.index += 2;
+            return self.parse_array_body(type_id);
+        }
+        let mut list = Vec::new();
+        let mut type_id = None;
+        if self.peek()? == ']' {
+            self.index += 1;
+            return Ok(Value::List(list));
+        }
+        loop {
+            let index = self.index;
+            let value = self.parse_value()?;
+            let found = type_id_of(&value);
+            if *type_id.get_or_insert(found) != found {
+                return Err(Error::syntax(ErrorCode::ListDifferentType, index));
+            }
+            list.push(value);
+            match self.peek()? {
+                ',' => self.index += 1,
+                ']' => {
+                    self.index += 1;
+                    return Ok(Value::List(list));
+                }
+                _ => return Err(self.invalid()),
+            }
+        }
+    }
+
+    fn parse_array_body(&mut self, type_id: u8) -> Result<Value> {
+        let mut ans = match type_id {
+            consts::TYPE_ID_BYTE_ARRAY => Value::ByteArray(Vec::new()),
+            consts::TYPE_ID_INT_ARRAY => Value::IntArray(Vec::new()),
+            _ => Value::LongArray(Vec::new()),
+        };
+        if self.peek()? == ']' {
+            self.index += 1;
+            return Ok(ans);
+        }
+        loop {
+            let index = self.index;
+            let value = self.parse_value()?;
+            match (&mut ans, value) {
+                (Value::ByteArray(v), Value::Byte(e)) => v.push(e),
+                (Value::IntArray(v), Value::Int(e)) => v.push(e),
+                (Value::LongArray(v), Value::Long(e)) => v.push(e),
+                _ => return Err(Error::syntax(ErrorCode::ArrayDifferentType, index)),
+            }
+            match self.peek()? {
+                ',' => self.index += 1,
+                ']' => {
+                    self.index += 1;
+                    return Ok(ans);
+                }
+                _ => return Err(self.invalid()),
+            }
+        }
+    }
+}
+
+#[inline]
+fn is_bare_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+// Interpret a bare word as a number or boolean, if it looks like one.
+fn bare_value(s: &str) -> Option<Value> {
+    match s {
+        "true" => return Some(Value::Byte(1)),
+        "false" => return Some(Value::Byte(0)),
+        _ => {}
+    }
+    let (body, suffix) = match s.char_indices().last() {
+        Some((n, c)) if c.is_ascii_alphabetic() => (&s[..n], Some(c.to_ascii_lowercase())),
+        _ => (s, None),
+    };
+    let is_integer = is_integer(body);
+    let value = match suffix {
+        Some('b') if is_integer => Value::Byte(body.parse().ok()?),
+        Some('s') if is_integer => Value::Short(body.parse().ok()?),
+        Some('l') if is_integer => Value::Long(body.parse().ok()?),
+        Some('f') if is_decimal(body) => Value::Float(body.parse().ok()?),
+        Some('d') if is_decimal(body) => Value::Double(body.parse().ok()?),
+        None if is_integer => Value::Int(body.parse().ok()?),
+        None if is_decimal(body) && body.contains('.') => Value::Double(body.parse().ok()?),
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn is_integer(s: &str) -> bool {
+    let digits = s.strip_prefix(['+', '-']).unwrap_or(s);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+// `[-+]?(?:[0-9]+[.]?|[0-9]*[.][0-9]+)(?:e[-+]?[0-9]+)?`, as vanilla accepts
+fn is_decimal(s: &str) -> bool {
+    let s = s.strip_prefix(['+', '-']).unwrap_or(s);
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(n) => (&s[..n], Some(&s[n + 1..])),
+        None => (s, None),
+    };
+    let mut parts = mantissa.splitn(2, '.');
+    let int_part = parts.next().unwrap_or("");
+    let frac_part = parts.next();
+    let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let frac_part = frac_part.unwrap_or("");
+    let mantissa_ok = all_digits(int_part)
+        && all_digits(frac_part)
+        && !(int_part.is_empty() && frac_part.is_empty());
+    let exponent_ok = match exponent {
+        Some(e) => {
+            let e = e.strip_prefix(['+', '-']).unwrap_or(e);
+            !e.is_empty() && all_digits(e)
+        }
+        None => true,
+    };
+    mantissa_ok && exponent_ok
+}
+
+fn type_id_of(value: &Value) -> u8 {
+    match value {
+        Value::Byte(_) => consts::TYPE_ID_BYTE,
+        Value::Short(_) => consts::TYPE_ID_SHORT,
+        Value::Int(_) => consts::TYPE_ID_INT,
+        Value::Long(_) => consts::TYPE_ID_LONG,
+        Value::Float(_) => consts::TYPE_ID_FLOAT,
+        Value::Double(_) => consts::TYPE_ID_DOUBLE,
+        Value::ByteArray(_) => consts::TYPE_ID_BYTE_ARRAY,
+        Value::String(_) => consts::TYPE_ID_STRING,
+        Value::List(_) => consts::TYPE_ID_LIST,
+        Value::Compound(_) => consts::TYPE_ID_COMPOUND,
+        Value::IntArray(_) => consts::TYPE_ID_INT_ARRAY,
+        Value::LongArray(_) => consts::TYPE_ID_LONG_ARRAY,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::map::Map;
+    use crate::value::Value;
+
+    fn compound(entries: Vec<(&str, Value)>) -> Value {
+        let mut map = Map::new();
+        for (key, value) in entries {
+            map.insert(key.to_string(), value);
+        }
+        Value::Compound(map)
+    }
+
+    #[test]
+    fn numbers_take_their_suffix() {
+        let cases = [
+            ("1b", Value::Byte(1)),
+            ("-2S", Value::Short(-2)),
+            ("3", Value::Int(3)),
+            ("+4l", Value::Long(4)),
+            ("1.5f", Value::Float(1.5)),
+            ("2F", Value::Float(2.0)),
+            ("1.5", Value::Double(1.5)),
+            ("1e3d", Value::Double(1000.0)),
+            (".5", Value::Double(0.5)),
+            ("true", Value::Byte(1)),
+            ("false", Value::Byte(0)),
+        ];
+        for (snbt, value) in cases.iter() {
+            assert_eq!(&parse(snbt).unwrap(), value, "{}", snbt);
+        }
+    }
+
+    #[test]
+    fn words_that_are_not_numbers_are_strings() {
+        for word in ["1e3", "stone", "128b", "1.5b", "a.b", "-"] {
+            let value = parse(&format!("{{k:{}}}", word)).unwrap();
+            let expected = compound(vec![("k", Value::String(word.to_string()))]);
+            assert_eq!(value, expected, "{}", word);
+        }
+    }
+
+    #[test]
+    fn strings_are_quoted_and_escaped() {
+        let value = parse(r#"['a"b', "c\"d\\", '']"#).unwrap();
+        let strings = ["a\"b", "c\"d\\", ""];
+        let strings = strings.iter().map(|s| Value::String(s.to_string()));
+        assert_eq!(value, Value::List(strings.collect()));
+    }
+
+    #[test]
+    fn compounds_lists_and_arrays() {
+        let value = parse(r#"{ "quoted key" : [ {}, {a:[]} ], b:[B;1b,2b], i:[I;], l:[L;-1L] }"#);
+        let expected = compound(vec![
+            (
+                "quoted key",
+                Value::List(vec![
+                    compound(vec![]),
+                    compound(vec![("a", Value::List(vec![]))]),
+                ]),
+            ),
+            ("b", Value::ByteArray(vec![1, 2])),
+            ("i", Value::IntArray(vec![])),
+            ("l", Value::LongArray(vec![-1])),
+        ]);
+        assert_eq!(value.unwrap(), expected);
+    }
+
+    #[test]
+    fn invalid_snbt_is_refused() {
+        let cases = [
+            "",
+            "{",
+            "{a:1,}",
+            "{a 1}",
+            "[1,2b]",
+            "[I;1,2b]",
+            "[B;1]",
+            "\"unterminated",
+            "{a:1} trailing",
+            "[1,,2]",
+        ];
+        for snbt in cases.iter() {
+            assert!(parse(snbt).is_err(), "{:?}", snbt);
+        }
+    }
+
+    #[test]
+    fn written_snbt_is_read_back() {
+        let value = compound(vec![
+            ("byte", Value::Byte(-1)),
+            ("long", Value::Long(i64::MIN)),
+            ("float", Value::Float(0.1)),
+            ("double", Value::Double(-2.5e-10)),
+            ("string", Value::String("it's \"quoted\"".to_string())),
+            (
+                "list",
+                Value::List(vec![Value::List(vec![Value::Short(1)])]),
+            ),
+            ("longs", Value::LongArray(vec![1, 2])),
+        ]);
+        let snbt = crate::to_string_snbt(&value).unwrap();
+        assert_eq!(parse(&snbt).unwrap(), value, "{}", snbt);
+    }
+}
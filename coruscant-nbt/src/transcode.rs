@@ -0,0 +1,281 @@
+//! Transcode binary NBT into any `Formatter` without building a `Value`.
+//!
+//! Transcoding walks the input tag by tag and hands each one to the formatter,
+//! so the exact tag types, list element types and the root name survive the
+//! conversion. This is what a dump tool wants; going through `Value` or a
+//! serde type would lose empty list types and reorder compound keys.
+
+use crate::consts;
+use crate::error::{Error, ErrorCode, Result};
+use crate::read::{self, Read};
+use crate::ser::{BinaryFormatter, Formatter, SnbtFormatter, TranscriptFormatter};
+use core::convert::TryFrom;
+use std::io;
+
+/// Transcode one binary NBT document from `read` into `writer`, formatting it
+/// with `formatter`.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_nbt::{read::SliceRead, ser::SnbtFormatter, transcode};
+///
+/// let nbt = coruscant_nbt::to_vec(("level", &vec![1i8, 2])).unwrap();
+/// let mut snbt = Vec::new();
+/// transcode::transcode(SliceRead::new(&nbt), &mut snbt, SnbtFormatter::new()).unwrap();
+/// assert_eq!(snbt, b"[1b,2b]");
+/// ```
+pub fn transcode<'de, R, W, F>(mut read: R, mut writer: W, mut formatter: F) -> Result<()>
+where
+    R: Read<'de>,
+    W: io::Write,
+    F: Formatter,
+{
+    let type_id = read.read_type_id()?;
+    if type_id == consts::TYPE_ID_END {
+        return Err(Error::invalid_id_at(type_id, read.index()));
+    }
+    let name = read.read_name()?;
+    let mut transcoder = Transcoder {
+        read: &mut read,
+        writer: &mut writer,
+        formatter: &mut formatter,
+    };
+    transcoder.transcode_named(type_id, &name)
+}
+
+/// Transcode a binary NBT document from an IO stream into an NBT string
+/// transcript.
+pub fn to_string_transcript<R>(read: R) -> Result<String>
+where
+    R: io::Read,
+{
+    let mut ans = Vec::new();
+    transcode(
+        read::IoRead::new(read),
+        &mut ans,
+        TranscriptFormatter::new(),
+    )?;
+    Ok(String::from_utf8(ans).expect("transcripts are valid UTF-8"))
+}
+
+/// Transcode a binary NBT document from an IO stream into compact SNBT, the
+/// text format used by Minecraft commands.
+pub fn to_string_snbt<R>(read: R) -> Result<String>
+where
+    R: io::Read,
+{
+    let mut ans = Vec::new();
+    transcode(read::IoRead::new(read), &mut ans, SnbtFormatter::new())?;
+    Ok(String::from_utf8(ans).expect("SNBT strings are valid UTF-8"))
+}
+
+/// Copy a binary NBT document from an IO stream into `writer` tag by tag,
+/// for example to change its compression.
+pub fn to_writer<R, W>(read: R, writer: W) -> Result<()>
+where
+    R: io::Read,
+    W: io::Write,
+{
+    transcode(read::IoRead::new(read), writer, BinaryFormatter)
+}
+
+struct Transcoder<'a, R, W, F> {
+    read: &'a mut R,
+    writer: &'a mut W,
+    formatter: &'a mut F,
+}
+
+impl<'de, R, W, F> Transcoder<'_, R, W, F>
+where
+    R: Read<'de>,
+    W: io::Write,
+    F: Formatter,
+{
+    fn transcode_named(&mut self, type_id: u8, name: &str) -> Result<()> {
+        let (name_len, name_bytes) = (string_len(name, self.read.index())?, name.as_bytes());
+        let (w, f) = (&mut *self.writer, &mut *self.formatter);
+        match type_id {
+            consts::TYPE_ID_BYTE => {
+                let value = self.read.read_byte_inner()?;
+                f.write_byte_tag(w, name_len, name_bytes, value)?
+            }
+            consts::TYPE_ID_SHORT => {
+                let value = self.read.read_short_inner()?;
+                f.write_short_tag(w, name_len, name_bytes, value)?
+            }
+            consts::TYPE_ID_INT => {
+                let value = self.read.read_int_inner()?;
+                f.write_int_tag(w, name_len, name_bytes, value)?
+            }
+            consts::TYPE_ID_LONG => {
+                let value = self.read.read_long_inner()?;
+                f.write_long_tag(w, name_len, name_bytes, value)?
+            }
+            consts::TYPE_ID_FLOAT => {
+                let value = self.read.read_float_inner()?;
+                f.write_float_tag(w, name_len, name_bytes, value)?
+            }
+            consts::TYPE_ID_DOUBLE => {
+                let value = self.read.read_double_inner()?;
+                f.write_double_tag(w, name_len, name_bytes, value)?
+            }
+            consts::TYPE_ID_STRING => {
+                let value = self.read.read_string_inner()?;
+                let (len, bytes) = (string_len(&value, self.read.index())?, value.as_bytes());
+                f.write_string_tag(w, name_len, name_bytes, len, bytes)?
+            }
+            consts::TYPE_ID_LIST => {
+                let (elem_type_id, len) = read_list_head(self.read)?;
+                f.write_list_tag(w, elem_type_id, len, name_len, name_bytes)?;
+                self.list_body(elem_type_id, len)?
+            }
+            consts::TYPE_ID_COMPOUND => {
+                f.write_compound_tag(w, name_len, name_bytes)?;
+                self.compound_body()?
+            }
+            consts::TYPE_ID_BYTE_ARRAY | consts::TYPE_ID_INT_ARRAY | consts::TYPE_ID_LONG_ARRAY => {
+                let len = self.read.read_array_length()?;
+                f.write_array_head(w, type_id, len, name_len, name_bytes)?;
+                self.array_body(type_id, len)?
+            }
+            _ => return Err(Error::invalid_id_at(type_id, self.read.index())),
+        }
+        Ok(())
+    }
+
+    fn transcode_inner(&mut self, type_id: u8) -> Result<()> {
+        let (w, f) = (&mut *self.writer, &mut *self.formatter);
+        match type_id {
+            consts::TYPE_ID_BYTE => f.write_byte_inner(w, self.read.read_byte_inner()?)?,
+            consts::TYPE_ID_SHORT => f.write_short_inner(w, self.read.read_short_inner()?)?,
+            consts::TYPE_ID_INT => f.write_int_inner(w, self.read.read_int_inner()?)?,
+            consts::TYPE_ID_LONG => f.write_long_inner(w, self.read.read_long_inner()?)?,
+            consts::TYPE_ID_FLOAT => f.write_float_inner(w, self.read.read_float_inner()?)?,
+            consts::TYPE_ID_DOUBLE => f.write_double_inner(w, self.read.read_double_inner()?)?,
+            consts::TYPE_ID_STRING => {
+                let value = self.read.read_string_inner()?;
+                let len = string_len(&value, self.read.index())?;
+                f.write_string_inner(w, len, value.as_bytes())?
+            }
+            consts::TYPE_ID_LIST => {
+                let (elem_type_id, len) = read_list_head(self.read)?;
+                f.write_list_inner(w, elem_type_id, len)?;
+                self.list_body(elem_type_id, len)?
+            }
+            consts::TYPE_ID_COMPOUND => {
+                f.write_compound_inner(w)?;
+                self.compound_body()?
+            }
+            consts::TYPE_ID_BYTE_ARRAY | consts::TYPE_ID_INT_ARRAY | consts::TYPE_ID_LONG_ARRAY => {
+                let len = self.read.read_array_length()?;
+                f.write_array_inner(w, type_id, len)?;
+                self.array_body(type_id, len)?
+            }
+            _ => return Err(Error::invalid_id_at(type_id, self.read.index())),
+        }
+        Ok(())
+    }
+
+    fn list_body(&mut self, elem_type_id: u8, len: i32) -> Result<()> {
+        for _ in 0..len {
+            self.transcode_inner(elem_type_id)?;
+        }
+        self.formatter.close_list(self.writer)?;
+        Ok(())
+    }
+
+    fn compound_body(&mut self) -> Result<()> {
+        loop {
+            let type_id = self.read.read_type_id()?;
+            if type_id == consts::TYPE_ID_END {
+                break;
+            }
+            let name = self.read.read_name()?;
+            self.transcode_named(type_id, &name)?;
+        }
+        self.formatter.write_end_tag(self.writer)?;
+        Ok(())
+    }
+
+    fn array_body(&mut self, type_id: u8, len: i32) -> Result<()> {
+        let (w, f) = (&mut *self.writer, &mut *self.formatter);
+        for _ in 0..len {
+            match type_id {
+                consts::TYPE_ID_BYTE_ARRAY => {
+                    f.write_byte_inner(w, self.read.read_byte_inner()?)?
+                }
+                consts::TYPE_ID_INT_ARRAY => f.write_int_inner(w, self.read.read_int_inner()?)?,
+                _ => f.write_long_inner(w, self.read.read_long_inner()?)?,
+            }
+        }
+        f.close_array(w)?;
+        Ok(())
+    }
+}
+
+// Lengths of names and strings are unsigned 16 bit in NBT; `Formatter` takes
+// them as the `i16` of the same bits.
+fn string_len(s: &str, index: usize) -> Result<i16> {
+    match u16::try_from(s.len()) {
+        Ok(len) => Ok(len as i16),
+        Err(_) => Err(Error::syntax(ErrorCode::InvalidStringLength, index)),
+    }
+}
+
+fn read_list_head<'de, R: Read<'de>>(read: &mut R) -> Result<(u8, i32)> {
+    let elem_type_id = read.read_type_id()?;
+    let len = read.read_array_length()?;
+    // only empty lists may be typed with `TAG_End`
+    if elem_type_id == consts::TYPE_ID_END && len != 0 {
+        return Err(Error::invalid_id_at(elem_type_id, read.index()));
+    }
+    Ok((elem_type_id, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{string_len, transcode};
+    use crate::read::SliceRead;
+    use crate::ser::{BinaryFormatter, SnbtFormatter};
+
+    // A root compound holding a string tag named `s` of `len` bytes of `a`.
+    fn document_with_string(len: usize) -> Vec<u8> {
+        let mut nbt = vec![10, 0, 0, 8, 0, 1, b's'];
+        nbt.extend_from_slice(&(len as u16).to_be_bytes());
+        nbt.resize(nbt.len() + len, b'a');
+        nbt.push(0);
+        nbt
+    }
+
+    #[test]
+    fn long_strings_keep_unsigned_lengths() {
+        for len in [0, 32767, 32768, 40000, 65535] {
+            let nbt = document_with_string(len);
+            let mut out = Vec::new();
+            transcode(SliceRead::new(&nbt), &mut out, BinaryFormatter).unwrap();
+            assert_eq!(out, nbt, "string of {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn long_strings_in_lists_keep_unsigned_lengths() {
+        let mut nbt = vec![9, 0, 0, 8, 0, 0, 0, 1];
+        nbt.extend_from_slice(&40000u16.to_be_bytes());
+        nbt.resize(nbt.len() + 40000, b'b');
+        let mut out = Vec::new();
+        transcode(SliceRead::new(&nbt), &mut out, BinaryFormatter).unwrap();
+        assert_eq!(out, nbt);
+
+        let mut snbt = Vec::new();
+        transcode(SliceRead::new(&nbt), &mut snbt, SnbtFormatter::new()).unwrap();
+        assert_eq!(snbt.len(), 40000 + 4);
+    }
+
+    #[test]
+    fn string_lengths_above_u16_are_refused() {
+        assert_eq!(string_len("", 0).unwrap(), 0);
+        assert_eq!(string_len(&"a".repeat(65535), 0).unwrap() as u16, 65535);
+        assert!(string_len(&"a".repeat(65536), 0).is_err());
+    }
+}
@@ -0,0 +1,430 @@
+//! Read NBT string transcripts back into NBT values.
+//!
+//! A transcript is the human readable dump written by `TranscriptFormatter`,
+//! for example through `to_string_transcript`:
+//!
+//! ```text
+//! Compound 'person'
+//!   String 'nick' luojia65
+//!   List 'scores': [3; 2]
+//!     Int 90
+//!     Int 85
+//!   EndList
+//!   List 'matrix': [9; 1]
+//!     List: [1; 2]
+//!       Byte 0
+//!       Byte 1
+//!     EndList
+//!   EndList
+//! EndCompound
+//! ```
+//!
+//! Indentation is ignored when parsing, so hand edited transcripts need not be
+//! aligned. Transcripts do not escape anything: a string value always runs to
+//! the end of its line, and a tag name may not contain `' ` (a quote followed
+//! by a space).
+
+use crate::consts;
+use crate::error::{Error, ErrorCode, Result};
+use crate::map::Map;
+use crate::value::{from_value, Value};
+use core::str::FromStr;
+use serde::de;
+
+/// Deserialize an instance of type `T` from an NBT string transcript.
+///
+/// # Example
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct Person {
+///     nick: String,
+///     scores: Vec<i32>,
+/// }
+///
+/// let person = Person {
+///     nick: "luojia65".to_string(),
+///     scores: vec![90, 85],
+/// };
+/// let transcript = coruscant_nbt::to_string_transcript(("person", &person))
+///     .expect("generate string transcript");
+///
+/// let back: Person = coruscant_nbt::from_str_transcript(&transcript)
+///     .expect("parse string transcript");
+/// assert_eq!(person, back);
+/// ```
+pub fn from_str_transcript<T>(s: &str) -> Result<T>
+where
+    T: de::DeserializeOwned,
+{
+    let (_root_name, value) = parse(s)?;
+    from_value(value)
+}
+
+/// Parse an NBT string transcript into its root name and value.
+///
+/// Pass the result as `(name.as_str(), &value)` into any to-function to
+/// convert the transcript back to binary NBT.
+pub fn parse(s: &str) -> Result<(String, Value)> {
+    let mut parser = Parser { input: s, index: 0 };
+    let line = parser.next_line()?;
+    let ans = parser.parse_named(line)?;
+    parser.end()?;
+    Ok(ans)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    index: usize,
+}
+
+/// One transcript line with its indentation removed.
+#[derive(Clone, Copy)]
+struct Line<'a> {
+    text: &'a str,
+    index: usize,
+}
+
+impl<'a> Line<'a> {
+    #[inline]
+    fn invalid(&self) -> Error {
+        Error::syntax(ErrorCode::InvalidTranscriptLine, self.index)
+    }
+
+    #[inline]
+    fn number<T: FromStr>(&self, s: &str) -> Result<T> {
+        s.trim()
+            .parse()
+            .map_err(|_| Error::syntax(ErrorCode::InvalidTranscriptNumber, self.index))
+    }
+
+    /// Split `Kind rest` into the tag kind and the rest of the line.
+    #[inline]
+    fn split_kind(&self) -> (&'a str, &'a str) {
+        match self.text.find(' ') {
+            Some(n) => (&self.text[..n], &self.text[n + 1..]),
+            None => (self.text, ""),
+        }
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn next_line(&mut self) -> Result<Line<'a>> {
+        loop {
+            if self.index >= self.input.len() {
+                return Err(Error::syntax(
+                    ErrorCode::TranscriptUnexpectedEof,
+                    self.index,
+                ));
+            }
+            let rest = &self.input[self.index..];
+            let (raw, consumed) = match rest.find('\n') {
+                Some(n) => (&rest[..n], n + 1),
+                None => (rest, rest.len()),
+            };
+            let raw = raw.strip_suffix('\r').unwrap_or(raw);
+            let text = raw.trim_start();
+            let index = self.index + (raw.len() - text.len());
+            self.index += consumed;
+            if !text.is_empty() {
+                return Ok(Line { text, index });
+            }
+        }
+    }
+
+    fn expect_line(&mut self, expected: &str) -> Result<()> {
+        let line = self.next_line()?;
+        if line.text.trim_end() != expected {
+            return Err(line.invalid());
+        }
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<()> {
+        let rest = &self.input[self.index..];
+        match rest.find(|c: char| !c.is_whitespace()) {
+            Some(n) => Err(Error::syntax(ErrorCode::TrailingCharacters, self.index + n)),
+            None => Ok(()),
+        }
+    }
+
+    /// Parse a named tag, as found at the root or inside compounds.
+    fn parse_named(&mut self, line: Line<'a>) -> Result<(String, Value)> {
+        let (kind, rest) = line.split_kind();
+        let type_id = type_id_of(kind).ok_or_else(|| line.invalid())?;
+        if !rest.starts_with('\'') {
+            return Err(line.invalid());
+        }
+        if type_id == consts::TYPE_ID_STRING {
+            // the string value may contain quotes, but the name may not be
+            // followed by a space
+            let (name, value) = match rest[1..].find("' ") {
+                Some(n) => (&rest[1..n + 1], &rest[n + 3..]),
+                None if rest.len() > 1 && rest.ends_with('\'') => (&rest[1..rest.len() - 1], ""),
+                None => return Err(line.invalid()),
+            };
+            return Ok((name.to_owned(), Value::String(value.to_owned())));
+        }
+        let end = rest
+            .rfind('\'')
+            .filter(|&n| n > 0)
+            .ok_or_else(|| line.invalid())?;
+        let name = rest[1..end].to_owned();
+        let after = &rest[end + 1..];
+        let value = match type_id {
+            consts::TYPE_ID_COMPOUND if after.trim_end().is_empty() => {
+                Value::Compound(self.parse_compound_body()?)
+            }
+            consts::TYPE_ID_LIST => {
+                let (elem, len) = parse_head(line, after)?;
+                let elem_type_id = line.number(elem)?;
+                self.parse_list_body(elem_type_id, len)?
+            }
+            consts::TYPE_ID_BYTE_ARRAY | consts::TYPE_ID_INT_ARRAY | consts::TYPE_ID_LONG_ARRAY => {
+                let (elem, len) = parse_head(line, after)?;
+                if type_id_of(elem) != Some(array_inner_type_id(type_id)) {
+                    return Err(line.invalid());
+                }
+                self.parse_array_body(type_id, len)?
+            }
+            _ if after.starts_with(' ') => parse_number(line, type_id, after)?,
+            _ => return Err(line.invalid()),
+        };
+        Ok((name, value))
+    }
+
+    /// Parse an unnamed element of a list, whose type is declared by the list.
+    fn parse_inner(&mut self, line: Line<'a>, type_id: u8) -> Result<Value> {
+        let (kind, rest) = line.split_kind();
+        // nested lists and arrays are written as `List: [elem; len]`
+        let (kind, head) = match kind.strip_suffix(':') {
+            Some(kind) => (kind, &line.text[kind.len()..]),
+            None => (kind, ""),
+        };
+        let found = type_id_of(kind).ok_or_else(|| line.invalid())?;
+        if found != type_id {
+            return Err(Error::mismatch_at(found, type_id, line.index));
+        }
+        match type_id {
+            consts::TYPE_ID_STRING => Ok(Value::String(rest.to_owned())),
+            consts::TYPE_ID_COMPOUND if rest.trim_end().is_empty() => {
+                Ok(Value::Compound(self.parse_compound_body()?))
+            }
+            consts::TYPE_ID_COMPOUND => Err(line.invalid()),
+            consts::TYPE_ID_LIST => {
+                let (elem, len) = parse_head(line, head)?;
+                let elem_type_id = line.number(elem)?;
+                self.parse_list_body(elem_type_id, len)
+            }
+            consts::TYPE_ID_BYTE_ARRAY | consts::TYPE_ID_INT_ARRAY | consts::TYPE_ID_LONG_ARRAY => {
+                let (elem, len) = parse_head(line, head)?;
+                if type_id_of(elem) != Some(array_inner_type_id(type_id)) {
+                    return Err(line.invalid());
+                }
+                self.parse_array_body(type_id, len)
+            }
+            _ => parse_number(line, type_id, rest),
+        }
+    }
+
+    fn parse_compound_body(&mut self) -> Result<Map<String, Value>> {
+        let mut map = Map::new();
+        loop {
+            let line = self.next_line()?;
+            if line.text.trim_end() == "EndCompound" {
+                return Ok(map);
+            }
+            let (name, value) = self.parse_named(line)?;
+            map.insert(name, value);
+        }
+    }
+
+    fn parse_list_body(&mut self, type_id: u8, len: usize) -> Result<Value> {
+        let mut list = Vec::with_capacity(len);
+        for _ in 0..len {
+            let line = self.next_line()?;
+            list.push(self.parse_inner(line, type_id)?);
+        }
+        self.expect_line("EndList")?;
+        Ok(Value::List(list))
+    }
+
+    fn parse_array_body(&mut self, type_id: u8, len: usize) -> Result<Value> {
+        let value = match type_id {
+            consts::TYPE_ID_BYTE_ARRAY => Value::ByteArray(self.parse_array_elements("Byte", len)?),
+            consts::TYPE_ID_INT_ARRAY => Value::IntArray(self.parse_array_elements("Int", len)?),
+            _ => Value::LongArray(self.parse_array_elements("Long", len)?),
+        };
+        self.expect_line("EndArray")?;
+        Ok(value)
+    }
+
+    fn parse_array_elements<T: FromStr>(&mut self, kind: &str, len: usize) -> Result<Vec<T>> {
+        let mut elements = Vec::with_capacity(len);
+        for _ in 0..len {
+            let line = self.next_line()?;
+            match line.split_kind() {
+                (found, rest) if found == kind => elements.push(line.number(rest)?),
+                _ => return Err(line.invalid()),
+            }
+        }
+        Ok(elements)
+    }
+}
+
+/// Parse the `: [elem; len]` head of a list or an array.
+fn parse_head<'a>(line: Line<'a>, after: &'a str) -> Result<(&'a str, usize)> {
+    let inner = after
+        .trim_end()
+        .strip_prefix(": [")
+        .and_then(|s| s.strip_suffix(']'))
+        .ok_or_else(|| line.invalid())?;
+    let mut parts = inner.splitn(2, ';');
+    let elem = parts.next().unwrap_or("").trim();
+    let len: i32 = line.number(parts.next().ok_or_else(|| line.invalid())?)?;
+    if len < 0 {
+        return Err(Error::invalid_len_at(len, line.index));
+    }
+    Ok((elem, len as usize))
+}
+
+fn parse_number(line: Line, type_id: u8, s: &str) -> Result<Value> {
+    let value = match type_id {
+        consts::TYPE_ID_BYTE => Value::Byte(line.number(s)?),
+        consts::TYPE_ID_SHORT => Value::Short(line.number(s)?),
+        consts::TYPE_ID_INT => Value::Int(line.number(s)?),
+        consts::TYPE_ID_LONG => Value::Long(line.number(s)?),
+        consts::TYPE_ID_FLOAT => Value::Float(line.number(s)?),
+        consts::TYPE_ID_DOUBLE => Value::Double(line.number(s)?),
+        _ => return Err(line.invalid()),
+    };
+    Ok(value)
+}
+
+fn type_id_of(kind: &str) -> Option<u8> {
+    let type_id = match kind {
+        "Byte" => consts::TYPE_ID_BYTE,
+        "Short" => consts::TYPE_ID_SHORT,
+        "Int" => consts::TYPE_ID_INT,
+        "Long" => consts::TYPE_ID_LONG,
+        "Float" => consts::TYPE_ID_FLOAT,
+        "Double" => consts::TYPE_ID_DOUBLE,
+        "ByteArray" => consts::TYPE_ID_BYTE_ARRAY,
+        "String" => consts::TYPE_ID_STRING,
+        "List" => consts::TYPE_ID_LIST,
+        "Compound" => consts::TYPE_ID_COMPOUND,
+        "IntArray" => consts::TYPE_ID_INT_ARRAY,
+        "LongArray" => consts::TYPE_ID_LONG_ARRAY,
+        _ => return None,
+    };
+    Some(type_id)
+}
+
+#[inline]
+fn array_inner_type_id(type_id: u8) -> u8 {
+    match type_id {
+        consts::TYPE_ID_BYTE_ARRAY => consts::TYPE_ID_BYTE,
+        consts::TYPE_ID_INT_ARRAY => consts::TYPE_ID_INT,
+        _ => consts::TYPE_ID_LONG,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::map::Map;
+    use crate::value::Value;
+
+    fn round_trip(name: &str, value: Value) {
+        let transcript = crate::to_string_transcript((name, &value)).unwrap();
+        let (read_name, read) = parse(&transcript).unwrap();
+        assert_eq!(read_name, name, "{}", transcript);
+        assert_eq!(read, value, "{}", transcript);
+    }
+
+    fn compound(fields: Vec<(&str, Value)>) -> Value {
+        let mut map = Map::new();
+        for (key, value) in fields {
+            map.insert(key.to_owned(), value);
+        }
+        Value::Compound(map)
+    }
+
+    #[test]
+    fn nested_lists() {
+        let inner = |v: Vec<i8>| Value::List(v.into_iter().map(Value::Byte).collect());
+        let matrix = Value::List(vec![inner(vec![0, 1]), inner(vec![]), inner(vec![2])]);
+        let deeper = Value::List(vec![Value::List(vec![matrix.clone()])]);
+        let compounds = Value::List(vec![compound(vec![("list", matrix.clone())])]);
+        round_trip(
+            "root",
+            compound(vec![
+                ("matrix", matrix),
+                ("deeper", deeper),
+                ("compounds", compounds),
+            ]),
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        let arrays = Value::List(vec![Value::IntArray(vec![1, -2]), Value::IntArray(vec![])]);
+        round_trip(
+            "root",
+            compound(vec![
+                ("bytes", Value::ByteArray(vec![-1, 0, 1])),
+                ("ints", Value::IntArray(vec![i32::MIN, i32::MAX])),
+                ("longs", Value::LongArray(vec![i64::MIN, 0])),
+                ("empty", Value::LongArray(vec![])),
+                ("nested", arrays),
+                (
+                    "bytes_in_list",
+                    Value::List(vec![Value::ByteArray(vec![3])]),
+                ),
+            ]),
+        );
+    }
+
+    #[test]
+    fn long_lists() {
+        let list = Value::List((0..40000).map(Value::Int).collect());
+        round_trip("root", compound(vec![("list", list)]));
+    }
+
+    #[test]
+    fn empty_strings() {
+        let strings = Value::List(vec![
+            Value::String(String::new()),
+            Value::String(" padded ".to_owned()),
+        ]);
+        round_trip(
+            "",
+            compound(vec![
+                ("empty", Value::String(String::new())),
+                ("", Value::String("no name".to_owned())),
+                ("strings", strings),
+            ]),
+        );
+        // editors may strip the space after the name of an empty string
+        let (_, value) = parse("Compound ''\nString 'empty'\nEndCompound").unwrap();
+        assert_eq!(
+            value,
+            compound(vec![("empty", Value::String(String::new()))])
+        );
+    }
+
+    #[test]
+    fn quoted_names() {
+        round_trip(
+            "it's",
+            compound(vec![
+                ("don't", Value::Int(1)),
+                ("'quoted'", Value::List(vec![Value::Byte(2)])),
+                ("a'b", Value::String("c' d".to_owned())),
+                ("with space", compound(vec![("x'", Value::Long(3))])),
+            ]),
+        );
+    }
+}
@@ -1,6 +1,12 @@
 //! The Value enum, a loosely typed way of representing any valid NBT value.
+mod de;
+mod merge;
 mod ser;
 
+pub(crate) use de::{ArrayAccess, TOKEN_VALUE};
+pub use de::from_value;
+pub use merge::MergeStrategy;
+
 use crate::error::Result;
 use crate::map::Map;
 use core::fmt;
@@ -46,9 +52,13 @@ impl fmt::Debug for Value {
 
 /// Convert a `T` into `coruscant_nbt::Value` which is an enum that can represent
 /// any valid NBT data.
+///
+/// The value goes through the binary serializer, so that lists, arrays and
+/// the other NBT specific choices come out exactly as they would be written.
 pub fn to_value<T>(value: T) -> Result<Value>
 where
     T: serde::Serialize,
 {
-    value.serialize(ser::Serializer)
+    let bytes = crate::to_vec(&value)?;
+    crate::from_slice(&bytes)
 }
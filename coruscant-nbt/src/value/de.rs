@@ -0,0 +1,329 @@
+use super::Value;
+use crate::as_nbt_array::{TOKEN_BYTE_ARRAY, TOKEN_INT_ARRAY, TOKEN_LONG_ARRAY};
+use crate::error::{Error, Result};
+use crate::map::Map;
+use core::fmt;
+use serde::de::value::{
+    MapAccessDeserializer, MapDeserializer, SeqAccessDeserializer, SeqDeserializer,
+};
+use serde::de::{self, IntoDeserializer, Unexpected};
+use serde::forward_to_deserialize_any;
+
+// `Value` asks NBT deserializers for this newtype struct, so that they can tell
+// arrays apart from lists of numbers. See `ArrayAccess`.
+pub(crate) const TOKEN_VALUE: &str = "$coruscant_nbt::private::Value";
+
+impl<'de> de::Deserialize<'de> for Value {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(TOKEN_VALUE, ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> de::Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any valid NBT value")
+    }
+
+    #[inline]
+    fn visit_bool<E>(self, value: bool) -> core::result::Result<Value, E> {
+        Ok(Value::Byte(value as i8))
+    }
+
+    #[inline]
+    fn visit_i8<E>(self, value: i8) -> core::result::Result<Value, E> {
+        Ok(Value::Byte(value))
+    }
+
+    #[inline]
+    fn visit_i16<E>(self, value: i16) -> core::result::Result<Value, E> {
+        Ok(Value::Short(value))
+    }
+
+    #[inline]
+    fn visit_i32<E>(self, value: i32) -> core::result::Result<Value, E> {
+        Ok(Value::Int(value))
+    }
+
+    #[inline]
+    fn visit_i64<E>(self, value: i64) -> core::result::Result<Value, E> {
+        Ok(Value::Long(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> core::result::Result<Value, E>
+    where
+        E: de::Error,
+    {
+        if value > i64::MAX as u64 {
+            return Err(E::invalid_value(Unexpected::Unsigned(value), &self));
+        }
+        Ok(Value::Long(value as i64))
+    }
+
+    #[inline]
+    fn visit_f32<E>(self, value: f32) -> core::result::Result<Value, E> {
+        Ok(Value::Float(value))
+    }
+
+    #[inline]
+    fn visit_f64<E>(self, value: f64) -> core::result::Result<Value, E> {
+        Ok(Value::Double(value))
+    }
+
+    #[inline]
+    fn visit_str<E>(self, value: &str) -> core::result::Result<Value, E> {
+        Ok(Value::String(String::from(value)))
+    }
+
+    #[inline]
+    fn visit_string<E>(self, value: String) -> core::result::Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    #[inline]
+    fn visit_some<D>(self, deserializer: D) -> core::result::Result<Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        de::Deserialize::deserialize(deserializer)
+    }
+
+    #[inline]
+    fn visit_newtype_struct<D>(self, deserializer: D) -> core::result::Result<Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> core::result::Result<Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut vec = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(elem) = seq.next_element()? {
+            vec.push(elem);
+        }
+        Ok(Value::List(vec))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> core::result::Result<Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let mut ans = Map::new();
+        let first_key: Option<String> = map.next_key()?;
+        match first_key.as_deref() {
+            Some(TOKEN_BYTE_ARRAY) => return Ok(Value::ByteArray(map.next_value()?)),
+            Some(TOKEN_INT_ARRAY) => return Ok(Value::IntArray(map.next_value()?)),
+            Some(TOKEN_LONG_ARRAY) => return Ok(Value::LongArray(map.next_value()?)),
+            Some(_) => {}
+            None => return Ok(Value::Compound(ans)),
+        }
+        let mut key = first_key;
+        while let Some(k) = key {
+            ans.insert(k, map.next_value()?);
+            key = map.next_key()?;
+        }
+        Ok(Value::Compound(ans))
+    }
+}
+
+/// Presents an NBT array to `ValueVisitor` as a single-entry map, whose key
+/// names the array type and whose value is the sequence of elements.
+pub(crate) struct ArrayAccess<A> {
+    token: Option<&'static str>,
+    seq: Option<A>,
+}
+
+impl<A> ArrayAccess<A> {
+    #[inline]
+    pub(crate) fn new(token: &'static str, seq: A) -> Self {
+        ArrayAccess {
+            token: Some(token),
+            seq: Some(seq),
+        }
+    }
+}
+
+impl<'de, A> de::MapAccess<'de> for ArrayAccess<A>
+where
+    A: de::SeqAccess<'de, Error = Error>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.token.take() {
+            Some(token) => seed.deserialize(token.into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let seq = self
+            .seq
+            .take()
+            .expect("call next_value_seed before next_key_seed");
+        seed.deserialize(SeqAccessDeserializer::new(seq))
+    }
+}
+
+/// Interpret a `coruscant_nbt::Value` as an instance of type `T`.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_nbt::{from_value, Map, Value};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Book {
+///     title: String,
+///     generation: i32,
+/// }
+///
+/// let mut map = Map::new();
+/// map.insert("title".to_string(), Value::String("hello".to_string()));
+/// map.insert("generation".to_string(), Value::Int(0));
+///
+/// let book: Book = from_value(Value::Compound(map)).expect("deserialize book");
+/// assert_eq!(book.title, "hello");
+/// ```
+pub fn from_value<T>(value: Value) -> Result<T>
+where
+    T: de::DeserializeOwned,
+{
+    T::deserialize(value)
+}
+
+impl Value {
+    fn unexpected(&self) -> Unexpected<'_> {
+        match *self {
+            Value::Byte(v) => Unexpected::Signed(v as i64),
+            Value::Short(v) => Unexpected::Signed(v as i64),
+            Value::Int(v) => Unexpected::Signed(v as i64),
+            Value::Long(v) => Unexpected::Signed(v),
+            Value::Float(v) => Unexpected::Float(v as f64),
+            Value::Double(v) => Unexpected::Float(v),
+            Value::String(ref s) => Unexpected::Str(s),
+            Value::Compound(_) => Unexpected::Map,
+            Value::ByteArray(_) | Value::List(_) | Value::IntArray(_) | Value::LongArray(_) => {
+                Unexpected::Seq
+            }
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Self;
+
+    #[inline]
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Value::Byte(v) => visitor.visit_i8(v),
+            Value::Short(v) => visitor.visit_i16(v),
+            Value::Int(v) => visitor.visit_i32(v),
+            Value::Long(v) => visitor.visit_i64(v),
+            Value::Float(v) => visitor.visit_f32(v),
+            Value::Double(v) => visitor.visit_f64(v),
+            Value::ByteArray(v) => visitor.visit_seq(SeqDeserializer::new(v.into_iter())),
+            Value::String(v) => visitor.visit_string(v),
+            Value::List(v) => visitor.visit_seq(SeqDeserializer::new(v.into_iter())),
+            Value::Compound(v) => visitor.visit_map(MapDeserializer::new(v.into_iter())),
+            Value::IntArray(v) => visitor.visit_seq(SeqDeserializer::new(v.into_iter())),
+            Value::LongArray(v) => visitor.visit_seq(SeqDeserializer::new(v.into_iter())),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Value::Byte(0) => visitor.visit_bool(false),
+            Value::Byte(1) => visitor.visit_bool(true),
+            other => Err(de::Error::invalid_type(other.unexpected(), &visitor)),
+        }
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        // NBT has no null value; a present value is always `Some`
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if name != TOKEN_VALUE {
+            return visitor.visit_newtype_struct(self);
+        }
+        match self {
+            Value::ByteArray(v) => visitor.visit_map(ArrayAccess::new(
+                TOKEN_BYTE_ARRAY,
+                SeqDeserializer::new(v.into_iter()),
+            )),
+            Value::IntArray(v) => visitor.visit_map(ArrayAccess::new(
+                TOKEN_INT_ARRAY,
+                SeqDeserializer::new(v.into_iter()),
+            )),
+            Value::LongArray(v) => visitor.visit_map(ArrayAccess::new(
+                TOKEN_LONG_ARRAY,
+                SeqDeserializer::new(v.into_iter()),
+            )),
+            other => other.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Value::String(v) => visitor.visit_enum(v.into_deserializer()),
+            Value::Compound(v) => visitor.visit_enum(MapAccessDeserializer::new(
+                MapDeserializer::new(v.into_iter()),
+            )),
+            other => Err(de::Error::invalid_type(other.unexpected(), &visitor)),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
@@ -0,0 +1,192 @@
+use super::Value;
+use core::mem;
+
+/// How `Value::merge_with` combines two values found under the same key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum MergeStrategy {
+    /// Merge compounds recursively and replace everything else, lists included.
+    ///
+    /// This is what vanilla `/data merge` does.
+    #[default]
+    Replace,
+    /// Like `Replace`, but append the elements of a list onto an existing list
+    /// instead of replacing it.
+    ///
+    /// NBT lists hold a single element type, so a list whose elements are of
+    /// another type than the existing ones still replaces the existing list.
+    AppendLists,
+}
+
+impl Value {
+    /// Merge `other` into this value with the semantics of `/data merge`.
+    ///
+    /// Compounds are merged key by key recursively; any other value, lists
+    /// included, replaces what was there before. Keys missing in `other` are
+    /// left untouched.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use coruscant_nbt::{Map, Value};
+    ///
+    /// let mut display = Map::new();
+    /// display.insert("Name".to_string(), Value::String("Sword".to_string()));
+    /// let mut base = Map::new();
+    /// base.insert("Damage".to_string(), Value::Int(0));
+    /// base.insert("display".to_string(), Value::Compound(display));
+    /// let mut base = Value::Compound(base);
+    ///
+    /// let mut lore = Map::new();
+    /// lore.insert("Lore".to_string(), Value::List(vec![]));
+    /// let mut patch = Map::new();
+    /// patch.insert("Damage".to_string(), Value::Int(5));
+    /// patch.insert("display".to_string(), Value::Compound(lore));
+    ///
+    /// base.merge(Value::Compound(patch));
+    ///
+    /// if let Value::Compound(map) = &base {
+    ///     assert_eq!(map["Damage"], Value::Int(5));
+    ///     if let Value::Compound(display) = &map["display"] {
+    ///         assert!(display.contains_key("Name"));
+    ///         assert!(display.contains_key("Lore"));
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    pub fn merge(&mut self, other: Value) {
+        self.merge_with(other, MergeStrategy::Replace)
+    }
+
+    /// Merge `other` into this value using the given `strategy`.
+    ///
+    /// See `MergeStrategy` for how values under the same key are combined.
+    pub fn merge_with(&mut self, other: Value, strategy: MergeStrategy) {
+        match (self, other) {
+            (Value::Compound(base), Value::Compound(patch)) => {
+                for (key, value) in patch {
+                    match base.get_mut(&key) {
+                        Some(old) => old.merge_with(value, strategy),
+                        None => {
+                            base.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (Value::List(base), Value::List(patch))
+                if strategy == MergeStrategy::AppendLists && same_element_type(base, &patch) =>
+            {
+                base.extend(patch)
+            }
+            (this, other) => *this = other,
+        }
+    }
+}
+
+// Empty lists have no element type yet and can take any elements.
+fn same_element_type(base: &[Value], patch: &[Value]) -> bool {
+    match (base.first(), patch.first()) {
+        (Some(a), Some(b)) => mem::discriminant(a) == mem::discriminant(b),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MergeStrategy;
+    use crate::{Map, Value};
+
+    fn compound(entries: Vec<(&str, Value)>) -> Value {
+        let mut map = Map::new();
+        for (key, value) in entries {
+            map.insert(key.to_string(), value);
+        }
+        Value::Compound(map)
+    }
+
+    fn ints(values: &[i32]) -> Value {
+        Value::List(values.iter().map(|&v| Value::Int(v)).collect())
+    }
+
+    #[test]
+    fn replace_merges_compounds_and_replaces_the_rest() {
+        let mut base = compound(vec![
+            ("a", Value::Int(1)),
+            (
+                "b",
+                compound(vec![("x", Value::Byte(1)), ("y", Value::Byte(2))]),
+            ),
+            ("list", ints(&[1, 2])),
+            ("kept", Value::String("kept".to_string())),
+        ]);
+        let patch = compound(vec![
+            ("a", Value::String("one".to_string())),
+            (
+                "b",
+                compound(vec![("y", Value::Byte(3)), ("z", Value::Byte(4))]),
+            ),
+            ("list", ints(&[3])),
+            ("new", Value::Long(5)),
+        ]);
+        base.merge_with(patch, MergeStrategy::Replace);
+
+        let expected = compound(vec![
+            ("a", Value::String("one".to_string())),
+            (
+                "b",
+                compound(vec![
+                    ("x", Value::Byte(1)),
+                    ("y", Value::Byte(3)),
+                    ("z", Value::Byte(4)),
+                ]),
+            ),
+            ("list", ints(&[3])),
+            ("kept", Value::String("kept".to_string())),
+            ("new", Value::Long(5)),
+        ]);
+        assert_eq!(base, expected);
+    }
+
+    #[test]
+    fn merge_is_replace() {
+        let mut base = compound(vec![("list", ints(&[1, 2]))]);
+        base.merge(compound(vec![("list", ints(&[3]))]));
+        assert_eq!(base, compound(vec![("list", ints(&[3]))]));
+    }
+
+    #[test]
+    fn non_compounds_are_replaced() {
+        let mut base = Value::Int(1);
+        base.merge_with(compound(vec![]), MergeStrategy::AppendLists);
+        assert_eq!(base, compound(vec![]));
+    }
+
+    #[test]
+    fn append_lists_appends_nested_lists() {
+        let mut base = compound(vec![("b", compound(vec![("list", ints(&[1, 2]))]))]);
+        let patch = compound(vec![("b", compound(vec![("list", ints(&[3]))]))]);
+        base.merge_with(patch, MergeStrategy::AppendLists);
+        assert_eq!(
+            base,
+            compound(vec![("b", compound(vec![("list", ints(&[1, 2, 3]))]))])
+        );
+    }
+
+    #[test]
+    fn append_lists_appends_to_and_from_empty_lists() {
+        let mut base = compound(vec![("a", ints(&[])), ("b", ints(&[1]))]);
+        let patch = compound(vec![("a", ints(&[1])), ("b", ints(&[]))]);
+        base.merge_with(patch, MergeStrategy::AppendLists);
+        assert_eq!(base, compound(vec![("a", ints(&[1])), ("b", ints(&[1]))]));
+    }
+
+    #[test]
+    fn append_lists_replaces_lists_of_another_element_type() {
+        let mut base = compound(vec![("list", ints(&[1, 2]))]);
+        let strings = Value::List(vec![Value::String("a".to_string())]);
+        base.merge_with(
+            compound(vec![("list", strings.clone())]),
+            MergeStrategy::AppendLists,
+        );
+        assert_eq!(base, compound(vec![("list", strings)]));
+    }
+}
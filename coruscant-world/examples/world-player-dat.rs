@@ -0,0 +1,35 @@
+use coruscant_world::player::Player;
+use coruscant_world::World;
+
+// Usage: world-player-dat <world folder>
+//
+// Lists the players of a world, and gives each of them a full health bar.
+fn main() -> coruscant_world::Result<()> {
+    let path = std::env::args()
+        .nth(1)
+        .expect("usage: world-player-dat <world folder>");
+    let world = World::open(path)?;
+    if let Some(player) = &world.level_dat().data.player {
+        println!("singleplayer: {}", summary(player));
+    }
+    for uuid in world.player_uuids()? {
+        let mut player = match world.read_player(uuid)? {
+            Some(player) => player,
+            None => continue,
+        };
+        println!("{}: {}", uuid, summary(&player));
+        player.health = 20.0;
+        world.write_player(uuid, &player)?;
+    }
+    Ok(())
+}
+
+fn summary(player: &Player) -> String {
+    format!(
+        "{:?} in {}, level {}, {} items",
+        player.pos,
+        player.dimension.id(),
+        player.xp_level,
+        player.inventory.len()
+    )
+}
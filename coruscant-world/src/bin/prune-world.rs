@@ -0,0 +1,131 @@
+//! `prune-world`, a command-line tool to remove unused chunks from a world
+//! and compact its region files.
+//!
+//! Run `prune-world` without arguments for usage. The world must not be open
+//! in the game while it runs.
+
+use coruscant_world::prune::{ChunkArea, PruneOptions, PruneStats};
+use coruscant_world::World;
+use std::error::Error;
+use std::process;
+
+const USAGE: &str = "\
+usage: prune-world <world> [options]
+
+options:
+  --dimension <name>
+      prune only this dimension, like `minecraft:the_nether`; may be given
+      more than once, all dimensions are pruned by default
+  --min-inhabited <ticks>
+      remove chunks players spent fewer ticks near, 20 ticks a second
+  --keep <x1>,<z1>,<x2>,<z2>
+      remove chunks outside of this area of block coordinates; may be given
+      more than once to keep several areas
+  --dry-run
+      only print what would be removed
+
+Without --min-inhabited and --keep no chunk is removed, and region files
+are only compacted. Entities and points of interest of removed chunks are
+removed too. Region files left without chunks are deleted.";
+
+type Result<T> = core::result::Result<T, Box<dyn Error>>;
+
+fn main() {
+    match run(std::env::args().skip(1).collect()) {
+        Ok(code) => process::exit(code),
+        Err(err) => {
+            eprintln!("prune-world: {}", err);
+            process::exit(2);
+        }
+    }
+}
+
+fn run(mut args: Vec<String>) -> Result<i32> {
+    let mut options = PruneOptions::new();
+    let mut dimensions = Vec::new();
+    if let Some(n) = args.iter().position(|arg| arg == "--dry-run") {
+        args.remove(n);
+        options.dry_run = true;
+    }
+    while let Some(value) = option(&mut args, "--dimension")? {
+        dimensions.push(value);
+    }
+    if let Some(value) = option(&mut args, "--min-inhabited")? {
+        let ticks = value
+            .parse()
+            .map_err(|_| format!("invalid number of ticks `{}`", value))?;
+        options.min_inhabited_time = Some(ticks);
+    }
+    while let Some(value) = option(&mut args, "--keep")? {
+        options.keep_areas.push(parse_area(&value)?);
+    }
+    let path = match args.as_slice() {
+        [path] => path.clone(),
+        [] => {
+            eprintln!("{}", USAGE);
+            return Ok(2);
+        }
+        [_, arg, ..] => return Err(format!("unexpected argument `{}`", arg).into()),
+    };
+
+    let mut world = World::open(&path)?;
+    if dimensions.is_empty() {
+        dimensions = world
+            .dimensions()
+            .iter()
+            .map(|d| d.name().to_owned())
+            .collect();
+    }
+    let mut total = PruneStats::default();
+    for dimension in &dimensions {
+        let stats = world.prune(dimension, &options)?;
+        print_stats(dimension, &stats);
+        total.chunks_removed += stats.chunks_removed;
+        total.regions_rewritten += stats.regions_rewritten;
+        total.regions_deleted += stats.regions_deleted;
+        total.bytes_reclaimed += stats.bytes_reclaimed;
+    }
+    if dimensions.len() > 1 {
+        print_stats("total", &total);
+    }
+    if options.dry_run {
+        println!("dry run, no file was changed");
+    }
+    Ok(0)
+}
+
+// Take the value of an option out of the arguments.
+fn option(args: &mut Vec<String>, name: &str) -> Result<Option<String>> {
+    let n = match args.iter().position(|arg| arg == name) {
+        Some(n) => n,
+        None => return Ok(None),
+    };
+    if n + 1 >= args.len() {
+        return Err(format!("missing value of {}", name).into());
+    }
+    args.remove(n);
+    Ok(Some(args.remove(n)))
+}
+
+fn parse_area(s: &str) -> Result<ChunkArea> {
+    let coords = s
+        .split(',')
+        .map(|n| n.trim().parse::<i32>())
+        .collect::<core::result::Result<Vec<_>, _>>()
+        .map_err(|_| format!("invalid area `{}`", s))?;
+    match coords.as_slice() {
+        &[x1, z1, x2, z2] => Ok(ChunkArea::from_blocks((x1, z1), (x2, z2))),
+        _ => Err(format!("area `{}` is not four coordinates x1,z1,x2,z2", s).into()),
+    }
+}
+
+fn print_stats(name: &str, stats: &PruneStats) {
+    println!(
+        "{}: {} chunks removed, {} region files rewritten, {} deleted, {:.1} MiB reclaimed",
+        name,
+        stats.chunks_removed,
+        stats.regions_rewritten,
+        stats.regions_deleted,
+        stats.bytes_reclaimed as f64 / (1024.0 * 1024.0),
+    );
+}
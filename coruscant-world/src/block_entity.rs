@@ -0,0 +1,575 @@
+//! Block entities, the extra data of blocks like chests, signs and spawners.
+//!
+//! A block entity is a compound keyed by its `id`, with the coordinates of
+//! its block. Vanilla block entity types are read into typed data with
+//! `BlockEntity`; other types are kept as NBT.
+//!
+//! Ref: https://minecraft.gamepedia.com/Chunk_format#Block_entity_format
+use crate::item::ItemStack;
+use crate::parts::{extra_fields, join_parts, read_part, take_id, to_compound};
+use crate::world::BlockPos;
+use coruscant_nbt::Value;
+use serde::de::{DeserializeOwned, Deserializer};
+use serde::ser::{self, Serializer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A block entity, with typed data for vanilla block entity types.
+///
+/// Each typed variant covers the IDs listed on it, with or without the
+/// `minecraft:` namespace. Block entities of other types, and block entities
+/// whose data does not fit the typed layout, are read as `Unknown`. Fields
+/// that are not typed are kept, so reading and writing a block entity keeps
+/// all of its data.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::block_entity::BlockEntity;
+///
+/// let snbt = r#"{id:"minecraft:command_block",x:10,y:64,z:-3,Command:"say hi",
+///     auto:0b,powered:1b,conditionMet:0b,TrackOutput:1b,SuccessCount:0}"#;
+/// let block_entity: BlockEntity = coruscant_nbt::from_str_snbt(snbt).unwrap();
+/// match &block_entity {
+///     BlockEntity::CommandBlock(command_block) => {
+///         assert_eq!(command_block.data.command, "say hi");
+///     }
+///     _ => panic!("not a command block"),
+/// }
+/// assert_eq!(block_entity.id(), Some("minecraft:command_block"));
+/// assert_eq!(block_entity.pos().map(|pos| pos.y), Some(64));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum BlockEntity {
+    /// A container of items: `minecraft:chest`, `minecraft:trapped_chest`,
+    /// `minecraft:barrel`, `minecraft:shulker_box`, `minecraft:dispenser`,
+    /// `minecraft:dropper` or `minecraft:hopper`.
+    Container(BlockEntityOf<Container>),
+    /// A furnace: `minecraft:furnace`, `minecraft:blast_furnace` or
+    /// `minecraft:smoker`.
+    Furnace(BlockEntityOf<Furnace>),
+    /// A sign: `minecraft:sign` or `minecraft:hanging_sign`.
+    Sign(BlockEntityOf<Sign>),
+    /// A monster spawner, `minecraft:mob_spawner`.
+    Spawner(BlockEntityOf<Spawner>),
+    /// A command block of any kind, `minecraft:command_block`.
+    CommandBlock(BlockEntityOf<CommandBlock>),
+    /// A banner, `minecraft:banner`.
+    Banner(BlockEntityOf<Banner>),
+    /// Any other block entity, as NBT.
+    Unknown(Value),
+}
+
+impl BlockEntity {
+    /// Read a block entity from its compound. Never fails: anything that is
+    /// not a known block entity type in the expected layout is `Unknown`.
+    pub fn from_value(value: Value) -> BlockEntity {
+        let id = match &value {
+            Value::Compound(compound) => match compound.get("id") {
+                Some(Value::String(id)) => id.clone(),
+                _ => return BlockEntity::Unknown(value),
+            },
+            _ => return BlockEntity::Unknown(value),
+        };
+        let typed = match id.strip_prefix("minecraft:").unwrap_or(&id) {
+            "chest" | "trapped_chest" | "barrel" | "shulker_box" | "dispenser" | "dropper"
+            | "hopper" => BlockEntityOf::read(&value).map(BlockEntity::Container),
+            "furnace" | "blast_furnace" | "smoker" => {
+                BlockEntityOf::read(&value).map(BlockEntity::Furnace)
+            }
+            "sign" | "hanging_sign" => BlockEntityOf::read(&value).map(BlockEntity::Sign),
+            "mob_spawner" => BlockEntityOf::read(&value).map(BlockEntity::Spawner),
+            "command_block" => BlockEntityOf::read(&value).map(BlockEntity::CommandBlock),
+            "banner" => BlockEntityOf::read(&value).map(BlockEntity::Banner),
+            _ => return BlockEntity::Unknown(value),
+        };
+        typed.unwrap_or(BlockEntity::Unknown(value))
+    }
+
+    /// The compound of the block entity.
+    pub fn to_value(&self) -> coruscant_nbt::Result<Value> {
+        match self {
+            BlockEntity::Container(block_entity) => block_entity.write(),
+            BlockEntity::Furnace(block_entity) => block_entity.write(),
+            BlockEntity::Sign(block_entity) => block_entity.write(),
+            BlockEntity::Spawner(block_entity) => block_entity.write(),
+            BlockEntity::CommandBlock(block_entity) => block_entity.write(),
+            BlockEntity::Banner(block_entity) => block_entity.write(),
+            BlockEntity::Unknown(value) => Ok(value.clone()),
+        }
+    }
+
+    /// The ID of the block entity type, like `minecraft:chest`; `None` for an
+    /// unknown block entity without an ID.
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            BlockEntity::Container(block_entity) => Some(&block_entity.id),
+            BlockEntity::Furnace(block_entity) => Some(&block_entity.id),
+            BlockEntity::Sign(block_entity) => Some(&block_entity.id),
+            BlockEntity::Spawner(block_entity) => Some(&block_entity.id),
+            BlockEntity::CommandBlock(block_entity) => Some(&block_entity.id),
+            BlockEntity::Banner(block_entity) => Some(&block_entity.id),
+            BlockEntity::Unknown(value) => match unknown_field(value, "id") {
+                Some(Value::String(id)) => Some(id),
+                _ => None,
+            },
+        }
+    }
+
+    /// The position of the block, also for unknown block entities; `None` if
+    /// it is not stored.
+    pub fn pos(&self) -> Option<BlockPos> {
+        match self {
+            BlockEntity::Container(block_entity) => Some(block_entity.pos()),
+            BlockEntity::Furnace(block_entity) => Some(block_entity.pos()),
+            BlockEntity::Sign(block_entity) => Some(block_entity.pos()),
+            BlockEntity::Spawner(block_entity) => Some(block_entity.pos()),
+            BlockEntity::CommandBlock(block_entity) => Some(block_entity.pos()),
+            BlockEntity::Banner(block_entity) => Some(block_entity.pos()),
+            BlockEntity::Unknown(value) => {
+                match (
+                    unknown_field(value, "x"),
+                    unknown_field(value, "y"),
+                    unknown_field(value, "z"),
+                ) {
+                    (Some(Value::Int(x)), Some(Value::Int(y)), Some(Value::Int(z))) => {
+                        Some(BlockPos::new(*x, *y, *z))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Items stored in a container or furnace; `None` for other block
+    /// entities.
+    pub fn items(&self) -> Option<&[ItemStack]> {
+        match self {
+            BlockEntity::Container(block_entity) => Some(&block_entity.data.items),
+            BlockEntity::Furnace(block_entity) => Some(&block_entity.data.items),
+            _ => None,
+        }
+    }
+}
+
+fn unknown_field<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Compound(compound) => compound.get(key),
+        _ => None,
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockEntity {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Value::deserialize(deserializer).map(BlockEntity::from_value)
+    }
+}
+
+impl Serialize for BlockEntity {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.to_value()
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+/// A block entity with typed data of its type.
+///
+/// Fields of the compound that are not typed, like `keepPacked` or the
+/// `components` of the block item since 1.20.5, are kept in `extra`.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct BlockEntityOf<T> {
+    /// The ID of the block entity type, like `minecraft:chest`.
+    pub id: String,
+    /// The X coordinate of the block.
+    pub x: i32,
+    /// The Y coordinate of the block.
+    pub y: i32,
+    /// The Z coordinate of the block.
+    pub z: i32,
+    /// Data of the block entity type.
+    pub data: T,
+    /// Fields not covered by the parts above.
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Coordinates {
+    #[serde(rename = "x")]
+    x: i32,
+    #[serde(rename = "y")]
+    y: i32,
+    #[serde(rename = "z")]
+    z: i32,
+}
+
+impl<T: Serialize + DeserializeOwned> BlockEntityOf<T> {
+    pub fn new(id: String, pos: BlockPos, data: T) -> BlockEntityOf<T> {
+        BlockEntityOf {
+            id,
+            x: pos.x,
+            y: pos.y,
+            z: pos.z,
+            data,
+            extra: HashMap::new(),
+        }
+    }
+
+    /// The position of the block.
+    pub fn pos(&self) -> BlockPos {
+        BlockPos::new(self.x, self.y, self.z)
+    }
+
+    fn read(value: &Value) -> coruscant_nbt::Result<BlockEntityOf<T>> {
+        let Coordinates { x, y, z } = read_part(value)?;
+        let data: T = read_part(value)?;
+        let coordinates = to_compound(&Coordinates { x, y, z })?;
+        let mut extra = extra_fields(value, &[&coordinates, &to_compound(&data)?]);
+        let id = take_id(&mut extra);
+        Ok(BlockEntityOf {
+            id,
+            x,
+            y,
+            z,
+            data,
+            extra,
+        })
+    }
+
+    fn write(&self) -> coruscant_nbt::Result<Value> {
+        let coordinates = Coordinates {
+            x: self.x,
+            y: self.y,
+            z: self.z,
+        };
+        let parts = [to_compound(&coordinates)?, to_compound(&self.data)?];
+        Ok(join_parts(&self.id, &self.extra, parts))
+    }
+}
+
+/// Data of a container of items, like a chest or a hopper.
+///
+/// A container that has not been opened since it was generated may have a
+/// loot table instead of items.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Container {
+    /// The items in the container, each with its slot.
+    #[serde(rename = "Items")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<ItemStack>,
+    /// The custom name of the container, as a text component.
+    #[serde(rename = "CustomName")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_name: Option<Value>,
+    /// The lock of the container: the name an item must have to open it
+    /// before 1.21.2, and an item predicate since.
+    #[serde(rename = "Lock")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lock: Option<Value>,
+    /// The loot table the container is filled from when it is opened.
+    #[serde(rename = "LootTable")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loot_table: Option<String>,
+    /// The seed of the loot table.
+    #[serde(rename = "LootTableSeed")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loot_table_seed: Option<i64>,
+}
+
+/// Data of a furnace, blast furnace or smoker.
+///
+/// The timers were renamed in 1.21.4; either set of names is present.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Furnace {
+    /// The input in slot 0, the fuel in slot 1 and the output in slot 2.
+    #[serde(rename = "Items")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<ItemStack>,
+    /// The custom name of the furnace, as a text component.
+    #[serde(rename = "CustomName")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_name: Option<Value>,
+    /// Number of ticks until the fuel runs out, before 1.21.4.
+    #[serde(rename = "BurnTime")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burn_time: Option<i16>,
+    /// Number of ticks the item has been cooking, before 1.21.4.
+    #[serde(rename = "CookTime")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cook_time: Option<i16>,
+    /// Number of ticks the item takes to cook, before 1.21.4.
+    #[serde(rename = "CookTimeTotal")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cook_time_total: Option<i16>,
+    /// Number of ticks until the fuel runs out, since 1.21.4.
+    #[serde(rename = "lit_time_remaining")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lit_time_remaining: Option<i16>,
+    /// Number of ticks the current fuel burns in total, since 1.21.4.
+    #[serde(rename = "lit_total_time")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lit_total_time: Option<i16>,
+    /// Number of ticks the item has been cooking, since 1.21.4.
+    #[serde(rename = "cooking_time_spent")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooking_time_spent: Option<i16>,
+    /// Number of ticks the item takes to cook, since 1.21.4.
+    #[serde(rename = "cooking_total_time")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooking_total_time: Option<i16>,
+    /// Number of times each recipe was used, for the experience it gives.
+    #[serde(rename = "RecipesUsed")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipes_used: Option<HashMap<String, i32>>,
+}
+
+/// Data of a sign or a hanging sign.
+///
+/// Since 1.20 a sign has text on both sides; before, it has four lines of
+/// text on the front.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Sign {
+    /// The text on the front, since 1.20.
+    #[serde(rename = "front_text")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub front_text: Option<SignText>,
+    /// The text on the back, since 1.20.
+    #[serde(rename = "back_text")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub back_text: Option<SignText>,
+    /// True if the sign is waxed and cannot be edited, since 1.20.
+    #[serde(rename = "is_waxed")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_waxed: Option<bool>,
+    /// The first line as a JSON text component, before 1.20.
+    #[serde(rename = "Text1")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text1: Option<String>,
+    /// The second line as a JSON text component, before 1.20.
+    #[serde(rename = "Text2")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text2: Option<String>,
+    /// The third line as a JSON text component, before 1.20.
+    #[serde(rename = "Text3")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text3: Option<String>,
+    /// The fourth line as a JSON text component, before 1.20.
+    #[serde(rename = "Text4")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text4: Option<String>,
+    /// The dye color of the text, like `black`, before 1.20.
+    #[serde(rename = "Color")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// True if the text glows, since 1.17 and before 1.20.
+    #[serde(rename = "GlowingText")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glowing_text: Option<bool>,
+}
+
+impl Sign {
+    /// The lines on the front of the sign, in either layout.
+    pub fn front_lines(&self) -> Vec<Value> {
+        match &self.front_text {
+            Some(text) => text.messages.clone(),
+            None => [&self.text1, &self.text2, &self.text3, &self.text4]
+                .iter()
+                .map(|line| Value::String(line.as_deref().unwrap_or_default().to_owned()))
+                .collect(),
+        }
+    }
+}
+
+/// The text on one side of a sign, since 1.20.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct SignText {
+    /// The four lines, as JSON text components before 1.21.5 and as text
+    /// components since.
+    #[serde(rename = "messages")]
+    pub messages: Vec<Value>,
+    /// The lines shown to players with chat filtering on, if they differ.
+    #[serde(rename = "filtered_messages")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filtered_messages: Option<Vec<Value>>,
+    /// The dye color of the text, like `black`.
+    #[serde(rename = "color")]
+    pub color: String,
+    /// True if the text glows.
+    #[serde(rename = "has_glowing_text")]
+    pub has_glowing_text: bool,
+}
+
+impl SignText {
+    pub fn new(messages: Vec<Value>) -> SignText {
+        SignText {
+            messages,
+            filtered_messages: None,
+            color: "black".to_owned(),
+            has_glowing_text: false,
+        }
+    }
+}
+
+/// Data of a monster spawner.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Spawner {
+    /// The next entity to spawn, as a compound with the entity in `entity`
+    /// since 1.15.
+    #[serde(rename = "SpawnData")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn_data: Option<Value>,
+    /// Entities to choose the next one from, each with a weight.
+    #[serde(rename = "SpawnPotentials")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spawn_potentials: Vec<Value>,
+    /// Number of ticks until the next spawn.
+    #[serde(rename = "Delay")]
+    #[serde(default)]
+    pub delay: i16,
+    /// The least number of ticks between spawns.
+    #[serde(rename = "MinSpawnDelay")]
+    #[serde(default)]
+    pub min_spawn_delay: i16,
+    /// The most number of ticks between spawns.
+    #[serde(rename = "MaxSpawnDelay")]
+    #[serde(default)]
+    pub max_spawn_delay: i16,
+    /// Number of entities spawned at once.
+    #[serde(rename = "SpawnCount")]
+    #[serde(default)]
+    pub spawn_count: i16,
+    /// The most entities allowed nearby for the spawner to spawn.
+    #[serde(rename = "MaxNearbyEntities")]
+    #[serde(default)]
+    pub max_nearby_entities: i16,
+    /// How close a player must be for the spawner to spawn.
+    #[serde(rename = "RequiredPlayerRange")]
+    #[serde(default)]
+    pub required_player_range: i16,
+    /// How far from the spawner entities are spawned.
+    #[serde(rename = "SpawnRange")]
+    #[serde(default)]
+    pub spawn_range: i16,
+}
+
+/// Data of a command block, repeating command block or chain command block.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct CommandBlock {
+    /// The command run by the block.
+    #[serde(rename = "Command")]
+    #[serde(default)]
+    pub command: String,
+    /// The result of the last run of the command.
+    #[serde(rename = "SuccessCount")]
+    #[serde(default)]
+    pub success_count: i32,
+    /// The output of the last run of the command, as a JSON text component.
+    #[serde(rename = "LastOutput")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_output: Option<Value>,
+    /// True if the output of the command is kept.
+    #[serde(rename = "TrackOutput")]
+    #[serde(default)]
+    pub track_output: bool,
+    /// True if the block is powered by redstone.
+    #[serde(rename = "powered")]
+    #[serde(default)]
+    pub powered: bool,
+    /// True if the block runs without redstone.
+    #[serde(rename = "auto")]
+    #[serde(default)]
+    pub auto: bool,
+    /// True if the condition of a conditional block was met at the last run.
+    #[serde(rename = "conditionMet")]
+    #[serde(default)]
+    pub condition_met: bool,
+    /// True if the block runs at most once per tick.
+    #[serde(rename = "UpdateLastExecution")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_last_execution: Option<bool>,
+    /// The game tick of the last run.
+    #[serde(rename = "LastExecution")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_execution: Option<i64>,
+    /// The custom name of the block, used as the sender of its messages.
+    #[serde(rename = "CustomName")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_name: Option<Value>,
+}
+
+impl CommandBlock {
+    pub fn new(command: String) -> CommandBlock {
+        CommandBlock {
+            command,
+            ..CommandBlock::default()
+        }
+    }
+}
+
+/// Data of a banner.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Banner {
+    /// The patterns on the banner, from the bottom layer, since 1.20.5.
+    #[serde(rename = "patterns")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patterns: Option<Vec<BannerPattern>>,
+    /// The patterns on the banner, from the bottom layer, before 1.20.5.
+    #[serde(rename = "Patterns")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legacy_patterns: Option<Vec<LegacyBannerPattern>>,
+    /// The custom name of the banner, as a text component.
+    #[serde(rename = "CustomName")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_name: Option<Value>,
+}
+
+/// A pattern on a banner, since 1.20.5.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct BannerPattern {
+    /// The ID of the pattern, like `minecraft:stripe_bottom`.
+    #[serde(rename = "pattern")]
+    pub pattern: String,
+    /// The dye color, like `red`.
+    #[serde(rename = "color")]
+    pub color: String,
+}
+
+impl BannerPattern {
+    pub fn new(pattern: String, color: String) -> BannerPattern {
+        BannerPattern { pattern, color }
+    }
+}
+
+/// A pattern on a banner, before 1.20.5.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct LegacyBannerPattern {
+    /// The code of the pattern, like `bs` for a bottom stripe.
+    #[serde(rename = "Pattern")]
+    pub pattern: String,
+    /// The dye color, from 0 for white to 15 for black.
+    #[serde(rename = "Color")]
+    pub color: i32,
+}
+
+impl LegacyBannerPattern {
+    pub fn new(pattern: String, color: i32) -> LegacyBannerPattern {
+        LegacyBannerPattern { pattern, color }
+    }
+}
@@ -0,0 +1,585 @@
+//! Chunk format since 1.18 parsed as serde favored Rust structs.
+//!
+//! Chunks are read from and written into region files, see `region`. Older
+//! chunk formats are in `legacy`; use `AnyChunk` to read a chunk of any
+//! version.
+//!
+//! Ref: https://minecraft.gamepedia.com/Chunk_format
+use crate::block_entity::BlockEntity;
+use crate::error;
+use crate::extra::impl_serde_with_extra;
+use coruscant_nbt::{as_nbt_array, Value};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+pub mod legacy;
+
+use legacy::{NumericChunk, PalettedChunk};
+
+/// Data version of 17w47a, the first snapshot of the Flattening, which
+/// replaced numeric block IDs with palettes of block states.
+pub const DATA_VERSION_FLATTENING: i32 = 1451;
+/// Data version of 21w43a, the first snapshot without the `Level` wrapper.
+pub const DATA_VERSION_NO_LEVEL: i32 = 2844;
+
+/// Layout of chunk NBT data, depending on the version that saved it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ChunkFormat {
+    /// Before 1.13: numeric block IDs in nibble arrays, inside `Level`.
+    Numeric,
+    /// From 1.13 to 1.17: palettes of block states, inside `Level`.
+    Paletted,
+    /// Since 1.18: palettes of block states and biomes at the root.
+    Modern,
+}
+
+impl ChunkFormat {
+    /// The format of a chunk with the given `DataVersion`. Chunks saved
+    /// before 1.9 have no data version.
+    pub fn from_data_version(data_version: Option<i32>) -> ChunkFormat {
+        match data_version {
+            Some(v) if v >= DATA_VERSION_NO_LEVEL => ChunkFormat::Modern,
+            Some(v) if v >= DATA_VERSION_FLATTENING => ChunkFormat::Paletted,
+            _ => ChunkFormat::Numeric,
+        }
+    }
+
+    /// Detect the format of chunk NBT data from its `DataVersion`.
+    pub fn detect(data: &[u8]) -> error::Result<ChunkFormat> {
+        #[derive(Deserialize)]
+        struct Probe {
+            #[serde(rename = "DataVersion")]
+            data_version: Option<i32>,
+        }
+        let probe: Probe = coruscant_nbt::from_slice(data)?;
+        Ok(ChunkFormat::from_data_version(probe.data_version))
+    }
+}
+
+/// A chunk in any format.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::chunk::{AnyChunk, Chunk, ChunkFormat};
+///
+/// let chunk = Chunk::new(3465, 0, -4, 0, "minecraft:full".to_string());
+/// let bytes = coruscant_nbt::to_vec(&chunk).unwrap();
+///
+/// let chunk = AnyChunk::from_slice(&bytes).unwrap();
+/// assert_eq!(chunk.format(), ChunkFormat::Modern);
+/// assert_eq!(chunk.data_version(), Some(3465));
+/// ```
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+#[non_exhaustive]
+pub enum AnyChunk {
+    /// A chunk before 1.13.
+    Numeric(NumericChunk),
+    /// A chunk from 1.13 to 1.17.
+    Paletted(PalettedChunk),
+    /// A chunk since 1.18.
+    Modern(Chunk),
+}
+
+impl AnyChunk {
+    /// Deserialize chunk NBT data in the format detected from its
+    /// `DataVersion`.
+    pub fn from_slice(data: &[u8]) -> error::Result<AnyChunk> {
+        let chunk = match ChunkFormat::detect(data)? {
+            ChunkFormat::Numeric => AnyChunk::Numeric(coruscant_nbt::from_slice(data)?),
+            ChunkFormat::Paletted => AnyChunk::Paletted(coruscant_nbt::from_slice(data)?),
+            ChunkFormat::Modern => AnyChunk::Modern(coruscant_nbt::from_slice(data)?),
+        };
+        Ok(chunk)
+    }
+
+    /// The format of this chunk.
+    pub fn format(&self) -> ChunkFormat {
+        match self {
+            AnyChunk::Numeric(_) => ChunkFormat::Numeric,
+            AnyChunk::Paletted(_) => ChunkFormat::Paletted,
+            AnyChunk::Modern(_) => ChunkFormat::Modern,
+        }
+    }
+
+    /// Version of the chunk NBT structure, if any.
+    pub fn data_version(&self) -> Option<i32> {
+        match self {
+            AnyChunk::Numeric(chunk) => chunk.data_version,
+            AnyChunk::Paletted(chunk) => Some(chunk.data_version),
+            AnyChunk::Modern(chunk) => Some(chunk.data_version),
+        }
+    }
+
+    /// Chunk coordinates of this chunk.
+    pub fn position(&self) -> (i32, i32) {
+        match self {
+            AnyChunk::Numeric(chunk) => (chunk.level.x_pos, chunk.level.z_pos),
+            AnyChunk::Paletted(chunk) => (chunk.level.x_pos, chunk.level.z_pos),
+            AnyChunk::Modern(chunk) => (chunk.x_pos, chunk.z_pos),
+        }
+    }
+}
+
+/// Root representation of a chunk.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::chunk::{BlockState, BlockStates, Chunk, Section};
+///
+/// let mut chunk = Chunk::new(3465, 0, -4, 0, "minecraft:full".to_string());
+/// let mut section = Section::new(-4);
+/// let bedrock = BlockState::new("minecraft:bedrock".to_string());
+/// section.block_states = Some(BlockStates::new(vec![bedrock], None));
+/// chunk.sections.push(section);
+///
+/// let bytes = coruscant_nbt::to_vec(&chunk).unwrap();
+/// let read: Chunk = coruscant_nbt::from_slice(&bytes).unwrap();
+/// assert_eq!(read, chunk);
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename = "", remote = "Self")]
+#[non_exhaustive]
+pub struct Chunk {
+    /// Version of the chunk NBT structure.
+    #[serde(rename = "DataVersion")]
+    pub data_version: i32,
+    /// X position of the chunk, in absolute chunk coordinates.
+    #[serde(rename = "xPos")]
+    pub x_pos: i32,
+    /// Lowest Y section position in the chunk, e.g. -4 in 1.18.
+    #[serde(rename = "yPos")]
+    pub y_pos: i32,
+    /// Z position of the chunk, in absolute chunk coordinates.
+    #[serde(rename = "zPos")]
+    pub z_pos: i32,
+    /// Defines the world generation status of this chunk, e.g. `minecraft:full`.
+    /// Versions before 1.20 store it without the namespace, e.g. `full`.
+    #[serde(rename = "Status")]
+    pub status: String,
+    /// Tick when the chunk was last saved.
+    #[serde(rename = "LastUpdate")]
+    pub last_update: i64,
+    /// List of the sections in this chunk, from the lowest to the highest.
+    #[serde(rename = "sections")]
+    pub sections: Vec<Section>,
+    /// Each block entity in this chunk.
+    #[serde(rename = "block_entities")]
+    pub block_entities: Vec<BlockEntity>,
+    /// Several different heightmaps corresponding to 256 values.
+    #[serde(rename = "Heightmaps")]
+    pub heightmaps: Heightmaps,
+    /// A list of 24 lists, one for each section from the bottom, of packed
+    /// positions of blocks that need to be post processed. Each position is
+    /// a short of the form `0x0ZYX` relative to its section.
+    #[serde(rename = "PostProcessing", default)]
+    pub post_processing: Vec<Vec<i16>>,
+    /// Structure data in this chunk.
+    #[serde(rename = "structures")]
+    pub structures: Structures,
+    /// The cumulative number of ticks players have been in this chunk. Used
+    /// for regional difficulty.
+    #[serde(rename = "InhabitedTime")]
+    pub inhabited_time: i64,
+    /// If light was calculated for this chunk. Missing in chunks that are not
+    /// fully generated.
+    #[serde(rename = "isLightOn", skip_serializing_if = "Option::is_none")]
+    pub is_light_on: Option<bool>,
+    /// Blocks that are scheduled to tick.
+    #[serde(rename = "block_ticks", default)]
+    pub block_ticks: Vec<TickEntry>,
+    /// Fluids that are scheduled to tick.
+    #[serde(rename = "fluid_ticks", default)]
+    pub fluid_ticks: Vec<TickEntry>,
+    /// Other data of the chunk, like `blending_data`, `CarvingMasks` or the
+    /// fields of mods, kept so that writing the chunk back loses nothing.
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// A 16×16×16 section of a chunk.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Section {
+    /// The Y position of this section.
+    #[serde(rename = "Y")]
+    pub y: i8,
+    /// Blocks in this section. Missing in sections that only hold light.
+    #[serde(rename = "block_states", skip_serializing_if = "Option::is_none")]
+    pub block_states: Option<BlockStates>,
+    /// Biomes in this section. Missing in sections that only hold light.
+    #[serde(rename = "biomes", skip_serializing_if = "Option::is_none")]
+    pub biomes: Option<Biomes>,
+    /// 2048 bytes recording the amount of block-emitted light in each block,
+    /// 4 bits per block.
+    #[serde(
+        rename = "BlockLight",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub block_light: Option<Vec<i8>>,
+    /// 2048 bytes recording the amount of sunlight or moonlight hitting each
+    /// block, 4 bits per block.
+    #[serde(
+        rename = "SkyLight",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub sky_light: Option<Vec<i8>>,
+}
+
+/// Paletted block states of a section.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct BlockStates {
+    /// Set of different block states used in this section.
+    #[serde(rename = "palette")]
+    pub palette: Vec<BlockState>,
+    /// 4096 indices pointing to the palette, packed into longs. Missing when
+    /// the palette only has one entry, so that the whole section is that
+    /// block.
+    #[serde(
+        rename = "data",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub data: Option<Vec<i64>>,
+}
+
+/// Paletted biomes of a section.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Biomes {
+    /// Set of different biome IDs used in this section.
+    #[serde(rename = "palette")]
+    pub palette: Vec<String>,
+    /// 64 indices pointing to the palette, one for each 4×4×4 cell, packed
+    /// into longs. Missing when the palette only has one entry.
+    #[serde(
+        rename = "data",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub data: Option<Vec<i64>>,
+}
+
+/// A block state, the block ID with its properties.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct BlockState {
+    /// Resource location of the block, e.g. `minecraft:stone`.
+    #[serde(rename = "Name")]
+    pub name: String,
+    /// List of block state properties, with the name being the name of the
+    /// block state property. Missing for blocks without properties.
+    #[serde(
+        rename = "Properties",
+        skip_serializing_if = "BTreeMap::is_empty",
+        default
+    )]
+    pub properties: BTreeMap<String, String>,
+}
+
+/// Heightmaps of a chunk, each a 256 entry array of 9 bit values packed
+/// into 37 longs. Which heightmaps are present depends on the generation
+/// status.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[non_exhaustive]
+pub struct Heightmaps {
+    /// The highest block that blocks motion or contains a fluid.
+    #[serde(
+        rename = "MOTION_BLOCKING",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub motion_blocking: Option<Vec<i64>>,
+    /// The highest block that blocks motion or contains a fluid or is in the
+    /// `minecraft:leaves` tag.
+    #[serde(
+        rename = "MOTION_BLOCKING_NO_LEAVES",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub motion_blocking_no_leaves: Option<Vec<i64>>,
+    /// The highest block that is neither air nor contains a fluid.
+    #[serde(
+        rename = "OCEAN_FLOOR",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub ocean_floor: Option<Vec<i64>>,
+    /// The highest block that is neither air nor contains a fluid, for world
+    /// generation.
+    #[serde(
+        rename = "OCEAN_FLOOR_WG",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub ocean_floor_wg: Option<Vec<i64>>,
+    /// The highest non-air block.
+    #[serde(
+        rename = "WORLD_SURFACE",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub world_surface: Option<Vec<i64>>,
+    /// The highest non-air block, for world generation.
+    #[serde(
+        rename = "WORLD_SURFACE_WG",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub world_surface_wg: Option<Vec<i64>>,
+}
+
+/// Structure data of a chunk.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[non_exhaustive]
+pub struct Structures {
+    /// Coordinates of chunks that contain starts, packed into longs, by
+    /// structure ID.
+    #[serde(rename = "References", serialize_with = "long_array_map")]
+    pub references: HashMap<String, Vec<i64>>,
+    /// Structures that start in this chunk, by structure ID.
+    #[serde(rename = "starts")]
+    pub starts: HashMap<String, Value>,
+}
+
+/// A block or fluid that is scheduled to tick.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct TickEntry {
+    /// The ID of the block or fluid; used to activate the correct block
+    /// update procedure.
+    #[serde(rename = "i")]
+    pub id: String,
+    /// If multiple tile ticks are scheduled for the same tick, tile ticks
+    /// with lower priority are processed first.
+    #[serde(rename = "p")]
+    pub priority: i32,
+    /// The number of ticks until processing should occur. May be negative
+    /// when processing is overdue.
+    #[serde(rename = "t")]
+    pub ticks: i32,
+    /// X position.
+    #[serde(rename = "x")]
+    pub x: i32,
+    /// Y position.
+    #[serde(rename = "y")]
+    pub y: i32,
+    /// Z position.
+    #[serde(rename = "z")]
+    pub z: i32,
+}
+
+impl Chunk {
+    pub fn new(data_version: i32, x_pos: i32, y_pos: i32, z_pos: i32, status: String) -> Chunk {
+        Chunk {
+            data_version,
+            x_pos,
+            y_pos,
+            z_pos,
+            status,
+            last_update: 0,
+            sections: Vec::new(),
+            block_entities: Vec::new(),
+            heightmaps: Heightmaps::new(),
+            post_processing: Vec::new(),
+            structures: Structures::new(),
+            inhabited_time: 0,
+            is_light_on: None,
+            block_ticks: Vec::new(),
+            fluid_ticks: Vec::new(),
+            extra: HashMap::new(),
+        }
+    }
+}
+
+impl_serde_with_extra!(Chunk);
+
+impl Section {
+    pub fn new(y: i8) -> Section {
+        Section {
+            y,
+            block_states: None,
+            biomes: None,
+            block_light: None,
+            sky_light: None,
+        }
+    }
+}
+
+impl BlockStates {
+    pub fn new(palette: Vec<BlockState>, data: Option<Vec<i64>>) -> BlockStates {
+        BlockStates { palette, data }
+    }
+}
+
+impl Biomes {
+    pub fn new(palette: Vec<String>, data: Option<Vec<i64>>) -> Biomes {
+        Biomes { palette, data }
+    }
+}
+
+impl BlockState {
+    pub fn new(name: String) -> BlockState {
+        BlockState {
+            name,
+            properties: BTreeMap::new(),
+        }
+    }
+
+    /// Air, the block of empty space.
+    pub fn air() -> BlockState {
+        BlockState::new("minecraft:air".to_string())
+    }
+
+    /// This block state with a property set.
+    pub fn with_property(mut self, name: &str, value: &str) -> BlockState {
+        self.properties.insert(name.to_owned(), value.to_owned());
+        self
+    }
+
+    /// Value of a block state property, if any.
+    pub fn property(&self, name: &str) -> Option<&str> {
+        self.properties.get(name).map(String::as_str)
+    }
+}
+
+/// Formats the block state as in commands, like
+/// `minecraft:oak_stairs[facing=east,half=top]`.
+impl fmt::Display for BlockState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if !self.properties.is_empty() {
+            f.write_str("[")?;
+            for (i, (name, value)) in self.properties.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(",")?;
+                }
+                write!(f, "{}={}", name, value)?;
+            }
+            f.write_str("]")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a block state as written in commands and Sponge schematic
+/// palettes, like `minecraft:oak_stairs[facing=east,half=top]`.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::BlockState;
+///
+/// let state: BlockState = "minecraft:oak_stairs[facing=east,half=top]".parse().unwrap();
+/// assert_eq!(state.property("facing"), Some("east"));
+/// assert_eq!(state.to_string(), "minecraft:oak_stairs[facing=east,half=top]");
+/// ```
+impl FromStr for BlockState {
+    type Err = ParseBlockStateError;
+
+    fn from_str(s: &str) -> Result<BlockState, ParseBlockStateError> {
+        let (name, properties) = match s.find('[') {
+            Some(start) => match s[start + 1..].strip_suffix(']') {
+                Some(properties) => (&s[..start], properties),
+                None => return Err(ParseBlockStateError(())),
+            },
+            None => (s, ""),
+        };
+        if name.is_empty() {
+            return Err(ParseBlockStateError(()));
+        }
+        let mut state = BlockState::new(name.to_owned());
+        for property in properties.split(',').filter(|p| !p.is_empty()) {
+            match property.split_once('=') {
+                Some((name, value)) if !name.is_empty() => {
+                    state
+                        .properties
+                        .insert(name.trim().to_owned(), value.trim().to_owned());
+                }
+                _ => return Err(ParseBlockStateError(())),
+            }
+        }
+        Ok(state)
+    }
+}
+
+/// Error returned when parsing a block state from a string fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseBlockStateError(());
+
+impl fmt::Display for ParseBlockStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid block state")
+    }
+}
+
+impl std::error::Error for ParseBlockStateError {}
+
+impl Heightmaps {
+    pub fn new() -> Heightmaps {
+        Heightmaps::default()
+    }
+}
+
+impl Structures {
+    pub fn new() -> Structures {
+        Structures::default()
+    }
+}
+
+impl TickEntry {
+    pub fn new(id: String, priority: i32, ticks: i32, x: i32, y: i32, z: i32) -> TickEntry {
+        TickEntry {
+            id,
+            priority,
+            ticks,
+            x,
+            y,
+            z,
+        }
+    }
+}
+
+pub(crate) fn option_as_nbt_array<T, S>(value: &Option<Vec<T>>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    match value {
+        Some(array) => as_nbt_array(array, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn long_array_map<S>(map: &HashMap<String, Vec<i64>>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    struct LongArray<'a>(&'a Vec<i64>);
+
+    impl Serialize for LongArray<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            as_nbt_array(self.0, serializer)
+        }
+    }
+
+    serializer.collect_map(map.iter().map(|(k, v)| (k, LongArray(v))))
+}
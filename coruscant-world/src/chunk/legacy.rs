@@ -0,0 +1,405 @@
+//! Chunk formats before 1.18, where all chunk data is wrapped in a `Level`
+//! compound.
+//!
+//! Before 1.13 (the Flattening) blocks are stored as numeric IDs in nibble
+//! arrays; from 1.13 to 1.17 sections use a palette of block states.
+//!
+//! Ref: https://minecraft.gamepedia.com/Chunk_format/History
+use super::{long_array_map, option_as_nbt_array, BlockState, Heightmaps};
+use crate::block_entity::BlockEntity;
+use crate::extra::impl_serde_with_extra;
+use coruscant_nbt::{as_nbt_array, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Root representation of a chunk before 1.13.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename = "")]
+#[non_exhaustive]
+pub struct NumericChunk {
+    /// Version of the chunk NBT structure. Missing before 1.9.
+    #[serde(rename = "DataVersion", skip_serializing_if = "Option::is_none")]
+    pub data_version: Option<i32>,
+    /// Contains all the chunk data.
+    #[serde(rename = "Level")]
+    pub level: NumericLevel,
+}
+
+/// Chunk data before 1.13.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(remote = "Self")]
+#[non_exhaustive]
+pub struct NumericLevel {
+    /// X position of the chunk.
+    #[serde(rename = "xPos")]
+    pub x_pos: i32,
+    /// Z position of the chunk.
+    #[serde(rename = "zPos")]
+    pub z_pos: i32,
+    /// Tick when the chunk was last saved.
+    #[serde(rename = "LastUpdate")]
+    pub last_update: i64,
+    /// The cumulative number of ticks players have been in this chunk.
+    #[serde(rename = "InhabitedTime", default)]
+    pub inhabited_time: i64,
+    /// If the terrain in this chunk was populated with special things, such
+    /// as ores, trees and structures.
+    #[serde(rename = "TerrainPopulated", skip_serializing_if = "Option::is_none")]
+    pub terrain_populated: Option<bool>,
+    /// If the light in this chunk was calculated.
+    #[serde(rename = "LightPopulated", skip_serializing_if = "Option::is_none")]
+    pub light_populated: Option<bool>,
+    /// Version of the chunk format, always 1.
+    #[serde(rename = "V", skip_serializing_if = "Option::is_none")]
+    pub v: Option<i8>,
+    /// 256 bytes of biome IDs, one for each XZ column, indexed `z * 16 + x`.
+    /// 255 means the biome is yet to be generated.
+    #[serde(
+        rename = "Biomes",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub biomes: Option<Vec<i8>>,
+    /// 256 ints of the lowest Y level where sunlight is full strength, one
+    /// for each XZ column, indexed `z * 16 + x`.
+    #[serde(rename = "HeightMap", serialize_with = "as_nbt_array")]
+    pub height_map: Vec<i32>,
+    /// List of the sections in this chunk.
+    #[serde(rename = "Sections")]
+    pub sections: Vec<NumericSection>,
+    /// Each entity in the chunk.
+    #[serde(rename = "Entities", default)]
+    pub entities: Vec<Value>,
+    /// Each block entity in the chunk.
+    #[serde(rename = "TileEntities", default)]
+    pub tile_entities: Vec<BlockEntity>,
+    /// Blocks that are scheduled to tick.
+    #[serde(rename = "TileTicks", default)]
+    pub tile_ticks: Vec<Value>,
+    /// Other data of the chunk, like the fields of mods, kept so that writing
+    /// the chunk back loses nothing.
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// A 16×16×16 section with numeric block IDs.
+///
+/// Blocks are indexed `y * 256 + z * 16 + x`. Nibble arrays keep two
+/// blocks in a byte, the block with the even index in the lower 4 bits.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct NumericSection {
+    /// The Y index (not coordinate) of this section, from 0 to 15.
+    #[serde(rename = "Y")]
+    pub y: i8,
+    /// 4096 bytes of the lower 8 bits of block IDs.
+    #[serde(rename = "Blocks", serialize_with = "as_nbt_array")]
+    pub blocks: Vec<i8>,
+    /// 2048 bytes of the upper 4 bits of block IDs. Missing when no block ID
+    /// is above 255.
+    #[serde(
+        rename = "Add",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub add: Option<Vec<i8>>,
+    /// 2048 bytes of block data, 4 bits per block.
+    #[serde(rename = "Data", serialize_with = "as_nbt_array")]
+    pub data: Vec<i8>,
+    /// 2048 bytes of block-emitted light, 4 bits per block.
+    #[serde(rename = "BlockLight", serialize_with = "as_nbt_array")]
+    pub block_light: Vec<i8>,
+    /// 2048 bytes of sky light, 4 bits per block.
+    #[serde(rename = "SkyLight", serialize_with = "as_nbt_array")]
+    pub sky_light: Vec<i8>,
+}
+
+/// Root representation of a chunk from 1.13 to 1.17.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename = "")]
+#[non_exhaustive]
+pub struct PalettedChunk {
+    /// Version of the chunk NBT structure.
+    #[serde(rename = "DataVersion")]
+    pub data_version: i32,
+    /// Contains all the chunk data.
+    #[serde(rename = "Level")]
+    pub level: PalettedLevel,
+}
+
+/// Chunk data from 1.13 to 1.17.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(remote = "Self")]
+#[non_exhaustive]
+pub struct PalettedLevel {
+    /// X position of the chunk.
+    #[serde(rename = "xPos")]
+    pub x_pos: i32,
+    /// Z position of the chunk.
+    #[serde(rename = "zPos")]
+    pub z_pos: i32,
+    /// Tick when the chunk was last saved.
+    #[serde(rename = "LastUpdate")]
+    pub last_update: i64,
+    /// The cumulative number of ticks players have been in this chunk.
+    #[serde(rename = "InhabitedTime")]
+    pub inhabited_time: i64,
+    /// Defines the world generation status of this chunk, e.g. `full`.
+    #[serde(rename = "Status")]
+    pub status: String,
+    /// Biome IDs as ints: 256, one for each XZ column before 1.15, and 1024,
+    /// one for each 4×4×4 cell since.
+    #[serde(
+        rename = "Biomes",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub biomes: Option<Vec<i32>>,
+    /// Several different heightmaps corresponding to 256 values.
+    #[serde(rename = "Heightmaps", default)]
+    pub heightmaps: Heightmaps,
+    /// List of the sections in this chunk.
+    #[serde(rename = "Sections")]
+    pub sections: Vec<PalettedSection>,
+    /// Each entity in the chunk. Moved to separate entity files in 1.17.
+    #[serde(rename = "Entities", default)]
+    pub entities: Vec<Value>,
+    /// Each block entity in the chunk.
+    #[serde(rename = "TileEntities", default)]
+    pub tile_entities: Vec<BlockEntity>,
+    /// Blocks that are scheduled to tick.
+    #[serde(rename = "TileTicks", default)]
+    pub tile_ticks: Vec<Value>,
+    /// Fluids that are scheduled to tick.
+    #[serde(rename = "LiquidTicks", default)]
+    pub liquid_ticks: Vec<Value>,
+    /// A list of 16 lists of packed positions of blocks that need to be post
+    /// processed, one for each section.
+    #[serde(rename = "PostProcessing", default)]
+    pub post_processing: Vec<Vec<i16>>,
+    /// Structure data in this chunk.
+    #[serde(rename = "Structures", default)]
+    pub structures: LevelStructures,
+    /// If light was calculated for this chunk.
+    #[serde(rename = "isLightOn", skip_serializing_if = "Option::is_none")]
+    pub is_light_on: Option<bool>,
+    /// Other data of the chunk, like `CarvingMasks`, `ToBeTicked`, `Lights` or
+    /// the fields of mods, kept so that writing the chunk back loses nothing.
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// A 16×16×16 section with a palette of block states.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(remote = "Self")]
+#[non_exhaustive]
+pub struct PalettedSection {
+    /// The Y index of this section.
+    #[serde(rename = "Y")]
+    pub y: i8,
+    /// Set of different block states used in this section. Missing in
+    /// sections that only hold light.
+    #[serde(rename = "Palette", skip_serializing_if = "Option::is_none")]
+    pub palette: Option<Vec<BlockState>>,
+    /// 4096 indices pointing to the palette, packed into longs. Entries span
+    /// two longs before 1.16.
+    #[serde(
+        rename = "BlockStates",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub block_states: Option<Vec<i64>>,
+    /// 2048 bytes of block-emitted light, 4 bits per block.
+    #[serde(
+        rename = "BlockLight",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub block_light: Option<Vec<i8>>,
+    /// 2048 bytes of sky light, 4 bits per block.
+    #[serde(
+        rename = "SkyLight",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub sky_light: Option<Vec<i8>>,
+    /// Other data of the section, like the fields of mods, kept so that
+    /// writing the chunk back loses nothing.
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// Structure data of a chunk from 1.13 to 1.17.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[non_exhaustive]
+pub struct LevelStructures {
+    /// Coordinates of chunks that contain starts, packed into longs, by
+    /// structure ID.
+    #[serde(rename = "References", serialize_with = "long_array_map")]
+    pub references: HashMap<String, Vec<i64>>,
+    /// Structures that start in this chunk, by structure ID.
+    #[serde(rename = "Starts")]
+    pub starts: HashMap<String, Value>,
+}
+
+impl NumericChunk {
+    pub fn new(data_version: Option<i32>, level: NumericLevel) -> NumericChunk {
+        NumericChunk {
+            data_version,
+            level,
+        }
+    }
+}
+
+impl NumericLevel {
+    pub fn new(x_pos: i32, z_pos: i32) -> NumericLevel {
+        NumericLevel {
+            x_pos,
+            z_pos,
+            last_update: 0,
+            inhabited_time: 0,
+            terrain_populated: None,
+            light_populated: None,
+            v: Some(1),
+            biomes: None,
+            height_map: vec![0; 256],
+            sections: Vec::new(),
+            entities: Vec::new(),
+            tile_entities: Vec::new(),
+            tile_ticks: Vec::new(),
+            extra: HashMap::new(),
+        }
+    }
+}
+
+impl NumericSection {
+    /// An empty section filled with air.
+    pub fn new(y: i8) -> NumericSection {
+        NumericSection {
+            y,
+            blocks: vec![0; 4096],
+            add: None,
+            data: vec![0; 2048],
+            block_light: vec![0; 2048],
+            sky_light: vec![0; 2048],
+        }
+    }
+
+    /// Numeric block ID at the given position in this section.
+    pub fn block_id(&self, x: usize, y: usize, z: usize) -> u16 {
+        let index = block_index(x, y, z);
+        let low = self.blocks[index] as u8 as u16;
+        let high = match &self.add {
+            Some(add) => get_nibble(add, index) as u16,
+            None => 0,
+        };
+        (high << 8) | low
+    }
+
+    /// Set the numeric block ID at the given position in this section.
+    pub fn set_block_id(&mut self, x: usize, y: usize, z: usize, id: u16) {
+        let index = block_index(x, y, z);
+        self.blocks[index] = id as u8 as i8;
+        let high = (id >> 8) as u8 & 0xF;
+        if high != 0 || self.add.is_some() {
+            let add = self.add.get_or_insert_with(|| vec![0; 2048]);
+            set_nibble(add, index, high);
+        }
+    }
+
+    /// Block data value at the given position in this section.
+    pub fn data(&self, x: usize, y: usize, z: usize) -> u8 {
+        get_nibble(&self.data, block_index(x, y, z))
+    }
+
+    /// Set the block data value at the given position in this section.
+    pub fn set_data(&mut self, x: usize, y: usize, z: usize, data: u8) {
+        set_nibble(&mut self.data, block_index(x, y, z), data)
+    }
+}
+
+impl PalettedChunk {
+    pub fn new(data_version: i32, level: PalettedLevel) -> PalettedChunk {
+        PalettedChunk {
+            data_version,
+            level,
+        }
+    }
+}
+
+impl PalettedLevel {
+    pub fn new(x_pos: i32, z_pos: i32, status: String) -> PalettedLevel {
+        PalettedLevel {
+            x_pos,
+            z_pos,
+            last_update: 0,
+            inhabited_time: 0,
+            status,
+            biomes: None,
+            heightmaps: Heightmaps::new(),
+            sections: Vec::new(),
+            entities: Vec::new(),
+            tile_entities: Vec::new(),
+            tile_ticks: Vec::new(),
+            liquid_ticks: Vec::new(),
+            post_processing: Vec::new(),
+            structures: LevelStructures::new(),
+            is_light_on: None,
+            extra: HashMap::new(),
+        }
+    }
+}
+
+impl PalettedSection {
+    pub fn new(y: i8) -> PalettedSection {
+        PalettedSection {
+            y,
+            palette: None,
+            block_states: None,
+            block_light: None,
+            sky_light: None,
+            extra: HashMap::new(),
+        }
+    }
+}
+
+impl LevelStructures {
+    pub fn new() -> LevelStructures {
+        LevelStructures::default()
+    }
+}
+
+impl_serde_with_extra!(NumericLevel, PalettedLevel, PalettedSection);
+
+#[inline]
+fn block_index(x: usize, y: usize, z: usize) -> usize {
+    assert!(x < 16 && y < 16 && z < 16, "position out of section");
+    (y << 8) | (z << 4) | x
+}
+
+#[inline]
+fn get_nibble(array: &[i8], index: usize) -> u8 {
+    let byte = array[index >> 1] as u8;
+    if index & 1 == 0 {
+        byte & 0xF
+    } else {
+        byte >> 4
+    }
+}
+
+#[inline]
+fn set_nibble(array: &mut [i8], index: usize, value: u8) {
+    let byte = array[index >> 1] as u8;
+    let byte = if index & 1 == 0 {
+        (byte & 0xF0) | (value & 0xF)
+    } else {
+        (byte & 0x0F) | (value << 4)
+    };
+    array[index >> 1] = byte as i8;
+}
@@ -1,133 +1,541 @@
-use serde::{Serialize, Deserialize};
+//! Entities, as stored in entity chunk files and in chunks before 1.17.
+//!
+//! An entity is a compound keyed by its `id`. Common entity types are read
+//! into typed data with `Entity`; other types are kept as NBT. Since 1.17 the
+//! entities of a chunk are stored apart from its blocks, as an `EntityChunk`
+//! in the region files in `entities/`.
+//!
+//! Ref: https://minecraft.gamepedia.com/Entity_format
+use crate::chunk::BlockState;
+use crate::item::ItemStack;
+use crate::parts::{extra_fields, join_parts, read_part, take_id, to_compound};
+use crate::uuid::Uuid;
+use crate::world::ChunkPos;
+use coruscant_nbt::{as_nbt_array, Value};
+use serde::de::{DeserializeOwned, Deserializer};
+use serde::ser::{self, Serializer};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use coruscant_nbt::as_nbt_array;
 
+/// Data version of 20w45a, since which entities are stored in `entities/`
+/// rather than in chunks.
+pub const DATA_VERSION_ENTITY_CHUNKS: i32 = 2681;
+
+/// An entity, with typed data for common entity types.
+///
+/// Each typed variant covers the IDs listed on it, with or without the
+/// `minecraft:` namespace. Entities of other types, and entities whose data
+/// does not fit the typed layout, are read as `Unknown`. Fields of an entity
+/// that are not typed are kept, so reading and writing an entity keeps all
+/// of its data.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::entity::Entity;
+///
+/// let snbt = r#"{id:"minecraft:zombie",Pos:[1.5d,64.0d,2.5d],Motion:[0.0d,0.0d,0.0d],
+///     Rotation:[0.0f,0.0f],Health:20.0f,IsBaby:1b,UUID:[I;1,2,3,4]}"#;
+/// let entity: Entity = coruscant_nbt::from_str_snbt(snbt).unwrap();
+/// match &entity {
+///     Entity::Zombie(zombie) => {
+///         assert!(zombie.data.is_baby);
+///         assert_eq!(zombie.mob.health, 20.0);
+///     }
+///     _ => panic!("not a zombie"),
+/// }
+/// assert_eq!(entity.id(), Some("minecraft:zombie"));
+/// assert_eq!(entity.entity().unwrap().pos, [1.5, 64.0, 2.5]);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Entity {
+    /// A dropped item, `minecraft:item`.
+    Item(EntityOf<ItemEntity>),
+    /// An experience orb, `minecraft:experience_orb`.
+    ExperienceOrb(EntityOf<ExperienceOrb>),
+    /// A falling block, `minecraft:falling_block`.
+    FallingBlock(EntityOf<FallingBlock>),
+    /// An armor stand, `minecraft:armor_stand`.
+    ArmorStand(EntityOf<ArmorStand>),
+    /// An item frame, `minecraft:item_frame` or `minecraft:glow_item_frame`.
+    ItemFrame(EntityOf<ItemFrame>),
+    /// A villager, `minecraft:villager`.
+    Villager(MobOf<Villager>),
+    /// A zombie, `minecraft:zombie`, `minecraft:husk` or `minecraft:drowned`.
+    Zombie(MobOf<Zombie>),
+    /// A creeper, `minecraft:creeper`.
+    Creeper(MobOf<Creeper>),
+    /// A sheep, `minecraft:sheep`.
+    Sheep(MobOf<Sheep>),
+    /// Any other entity, as NBT.
+    Unknown(Value),
+}
+
+impl Entity {
+    /// Read an entity from its compound. Never fails: anything that is not a
+    /// known entity type in the expected layout is `Unknown`.
+    pub fn from_value(value: Value) -> Entity {
+        let id = match &value {
+            Value::Compound(compound) => match compound.get("id") {
+                Some(Value::String(id)) => id.clone(),
+                _ => return Entity::Unknown(value),
+            },
+            _ => return Entity::Unknown(value),
+        };
+        let typed = match id.strip_prefix("minecraft:").unwrap_or(&id) {
+            "item" => EntityOf::read(&value).map(Entity::Item),
+            "experience_orb" => EntityOf::read(&value).map(Entity::ExperienceOrb),
+            "falling_block" => EntityOf::read(&value).map(Entity::FallingBlock),
+            "armor_stand" => EntityOf::read(&value).map(Entity::ArmorStand),
+            "item_frame" | "glow_item_frame" => EntityOf::read(&value).map(Entity::ItemFrame),
+            "villager" => MobOf::read(&value).map(Entity::Villager),
+            "zombie" | "husk" | "drowned" => MobOf::read(&value).map(Entity::Zombie),
+            "creeper" => MobOf::read(&value).map(Entity::Creeper),
+            "sheep" => MobOf::read(&value).map(Entity::Sheep),
+            _ => return Entity::Unknown(value),
+        };
+        typed.unwrap_or(Entity::Unknown(value))
+    }
+
+    /// The compound of the entity.
+    pub fn to_value(&self) -> coruscant_nbt::Result<Value> {
+        match self {
+            Entity::Item(entity) => entity.write(),
+            Entity::ExperienceOrb(entity) => entity.write(),
+            Entity::FallingBlock(entity) => entity.write(),
+            Entity::ArmorStand(entity) => entity.write(),
+            Entity::ItemFrame(entity) => entity.write(),
+            Entity::Villager(entity) => entity.write(),
+            Entity::Zombie(entity) => entity.write(),
+            Entity::Creeper(entity) => entity.write(),
+            Entity::Sheep(entity) => entity.write(),
+            Entity::Unknown(value) => Ok(value.clone()),
+        }
+    }
+
+    /// The ID of the entity type, like `minecraft:zombie`; `None` for an
+    /// unknown entity without an ID.
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            Entity::Item(entity) => Some(&entity.id),
+            Entity::ExperienceOrb(entity) => Some(&entity.id),
+            Entity::FallingBlock(entity) => Some(&entity.id),
+            Entity::ArmorStand(entity) => Some(&entity.id),
+            Entity::ItemFrame(entity) => Some(&entity.id),
+            Entity::Villager(entity) => Some(&entity.id),
+            Entity::Zombie(entity) => Some(&entity.id),
+            Entity::Creeper(entity) => Some(&entity.id),
+            Entity::Sheep(entity) => Some(&entity.id),
+            Entity::Unknown(Value::Compound(compound)) => match compound.get("id") {
+                Some(Value::String(id)) => Some(id),
+                _ => None,
+            },
+            Entity::Unknown(_) => None,
+        }
+    }
+
+    /// Data common to all entities; `None` for an unknown entity.
+    pub fn entity(&self) -> Option<&EntityData> {
+        match self {
+            Entity::Item(entity) => Some(&entity.entity),
+            Entity::ExperienceOrb(entity) => Some(&entity.entity),
+            Entity::FallingBlock(entity) => Some(&entity.entity),
+            Entity::ArmorStand(entity) => Some(&entity.entity),
+            Entity::ItemFrame(entity) => Some(&entity.entity),
+            Entity::Villager(entity) => Some(&entity.entity),
+            Entity::Zombie(entity) => Some(&entity.entity),
+            Entity::Creeper(entity) => Some(&entity.entity),
+            Entity::Sheep(entity) => Some(&entity.entity),
+            Entity::Unknown(_) => None,
+        }
+    }
+
+    /// Mutable data common to all entities; `None` for an unknown entity.
+    pub fn entity_mut(&mut self) -> Option<&mut EntityData> {
+        match self {
+            Entity::Item(entity) => Some(&mut entity.entity),
+            Entity::ExperienceOrb(entity) => Some(&mut entity.entity),
+            Entity::FallingBlock(entity) => Some(&mut entity.entity),
+            Entity::ArmorStand(entity) => Some(&mut entity.entity),
+            Entity::ItemFrame(entity) => Some(&mut entity.entity),
+            Entity::Villager(entity) => Some(&mut entity.entity),
+            Entity::Zombie(entity) => Some(&mut entity.entity),
+            Entity::Creeper(entity) => Some(&mut entity.entity),
+            Entity::Sheep(entity) => Some(&mut entity.entity),
+            Entity::Unknown(_) => None,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Entity {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Value::deserialize(deserializer).map(Entity::from_value)
+    }
+}
+
+impl Serialize for Entity {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.to_value()
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+/// An entity with typed data of its type.
+///
+/// Every part is read from the whole compound of the entity, so the parts
+/// are plain structs; fields of the compound that no part reads are kept in
+/// `extra`.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct EntityOf<T> {
+    /// The ID of the entity type, like `minecraft:item`.
+    pub id: String,
+    /// Data common to all entities.
+    pub entity: EntityData,
+    /// Data of the entity type.
+    pub data: T,
+    /// Fields not covered by the parts above.
+    pub extra: HashMap<String, Value>,
+}
+
+impl<T: Serialize + DeserializeOwned> EntityOf<T> {
+    pub fn new(id: String, entity: EntityData, data: T) -> EntityOf<T> {
+        EntityOf {
+            id,
+            entity,
+            data,
+            extra: HashMap::new(),
+        }
+    }
+
+    fn read(value: &Value) -> coruscant_nbt::Result<EntityOf<T>> {
+        let entity: EntityData = read_part(value)?;
+        let data: T = read_part(value)?;
+        let mut ans = EntityOf::new(String::new(), entity, data);
+        ans.extra = extra_fields(
+            value,
+            &[&to_compound(&ans.entity)?, &to_compound(&ans.data)?],
+        );
+        ans.id = take_id(&mut ans.extra);
+        Ok(ans)
+    }
+
+    fn write(&self) -> coruscant_nbt::Result<Value> {
+        let parts = [to_compound(&self.entity)?, to_compound(&self.data)?];
+        Ok(join_parts(&self.id, &self.extra, parts))
+    }
+}
+
+/// A mob with typed data of its type.
+///
+/// Like `EntityOf`, with the data common to all mobs as another part.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct MobOf<T> {
+    /// The ID of the entity type, like `minecraft:zombie`.
+    pub id: String,
+    /// Data common to all entities.
+    pub entity: EntityData,
+    /// Data common to all mobs.
+    pub mob: Mob,
+    /// Data of the entity type.
+    pub data: T,
+    /// Fields not covered by the parts above.
+    pub extra: HashMap<String, Value>,
+}
+
+impl<T: Serialize + DeserializeOwned> MobOf<T> {
+    pub fn new(id: String, entity: EntityData, mob: Mob, data: T) -> MobOf<T> {
+        MobOf {
+            id,
+            entity,
+            mob,
+            data,
+            extra: HashMap::new(),
+        }
+    }
+
+    fn read(value: &Value) -> coruscant_nbt::Result<MobOf<T>> {
+        let entity: EntityData = read_part(value)?;
+        let mob: Mob = read_part(value)?;
+        let data: T = read_part(value)?;
+        let mut ans = MobOf::new(String::new(), entity, mob, data);
+        let parts = [
+            &to_compound(&ans.entity)?,
+            &to_compound(&ans.mob)?,
+            &to_compound(&ans.data)?,
+        ];
+        ans.extra = extra_fields(value, &parts);
+        ans.id = take_id(&mut ans.extra);
+        Ok(ans)
+    }
+
+    fn write(&self) -> coruscant_nbt::Result<Value> {
+        let parts = [
+            to_compound(&self.entity)?,
+            to_compound(&self.mob)?,
+            to_compound(&self.data)?,
+        ];
+        Ok(join_parts(&self.id, &self.extra, parts))
+    }
+}
+
+/// Data common to all entities.
+///
+/// Before 1.16 the UUID of an entity is stored as `UUIDMost` and `UUIDLeast`,
+/// which are kept with the other untyped fields.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[non_exhaustive]
 pub struct EntityData {
-    #[serde(rename = "id")]
-    pub id: Option<String>,
+    /// Position of the entity.
     #[serde(rename = "Pos")]
     pub pos: [f64; 3],
+    /// Velocity of the entity in blocks per tick.
     #[serde(rename = "Motion")]
+    #[serde(default)]
     pub motion: [f64; 3],
+    /// Yaw and pitch of the entity in degrees.
     #[serde(rename = "Rotation")]
+    #[serde(default)]
     pub rotation: [f32; 2],
+    /// Distance the entity has fallen.
     #[serde(rename = "FallDistance")]
+    #[serde(default)]
     pub fall_distance: f32,
+    /// Number of ticks until the fire on the entity is put out; negative
+    /// while the entity is not on fire.
     #[serde(rename = "Fire")]
-    pub fire: i16, 
+    #[serde(default)]
+    pub fire: i16,
+    /// Number of ticks of air the entity has left.
     #[serde(rename = "Air")]
+    #[serde(default)]
     pub air: i16,
+    /// True if the entity is touching the ground.
     #[serde(rename = "OnGround")]
+    #[serde(default)]
     pub on_ground: bool,
-    #[serde(rename = "NoGravity")]
-    pub no_gravity: bool,
-    #[serde(rename = "Dimension")]
-    pub dimension: i32,
+    /// True if the entity does not take damage, except from the void and
+    /// players in creative mode.
     #[serde(rename = "Invulnerable")]
+    #[serde(default)]
     pub invulnerable: bool,
+    /// Number of ticks until the entity can go through a portal again.
     #[serde(rename = "PortalCooldown")]
+    #[serde(default)]
     pub portal_cooldown: i32,
-    #[serde(rename = "UUIDMost")]
-    pub uuid_most: i64,
-    #[serde(rename = "UUIDLeast")]
-    pub uuid_least: i64,
+    /// The UUID of the entity, since 1.16.
+    #[serde(rename = "UUID")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<Uuid>,
+    /// The custom name: a JSON text component before 1.21.5, and a text
+    /// component in NBT since.
     #[serde(rename = "CustomName")]
-    pub custom_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_name: Option<Value>,
+    /// True if the custom name is always shown.
     #[serde(rename = "CustomNameVisible")]
-    pub custom_name_visible: bool,
-    #[serde(rename = "Slient")]
-    pub slient: bool,
-    #[serde(rename = "Passengers")]
-    pub passengers: Vec<EntityData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_name_visible: Option<bool>,
+    /// True if the entity makes no sounds.
+    #[serde(rename = "Silent")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub silent: Option<bool>,
+    /// True if the entity is not affected by gravity.
+    #[serde(rename = "NoGravity")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_gravity: Option<bool>,
+    /// True if the entity has a glowing outline.
     #[serde(rename = "Glowing")]
-    pub glowing: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glowing: Option<bool>,
+    /// Scoreboard tags of the entity.
     #[serde(rename = "Tags")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tags: Vec<String>,
+    /// Entities riding this entity.
+    #[serde(rename = "Passengers")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub passengers: Vec<Entity>,
 }
 
+impl EntityData {
+    pub fn new(pos: [f64; 3]) -> EntityData {
+        EntityData {
+            pos,
+            motion: [0.0; 3],
+            rotation: [0.0; 2],
+            fall_distance: 0.0,
+            fire: -1,
+            air: 300,
+            on_ground: false,
+            invulnerable: false,
+            portal_cooldown: 0,
+            uuid: None,
+            custom_name: None,
+            custom_name_visible: None,
+            silent: None,
+            no_gravity: None,
+            glowing: None,
+            tags: Vec::new(),
+            passengers: Vec::new(),
+        }
+    }
+}
+
+/// Data common to all mobs.
+///
+/// Since 1.21.5 hand and armor items are stored in `equipment`, which is kept
+/// with the other untyped fields.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[non_exhaustive]
 pub struct Mob {
+    /// Health of the mob, in half hearts.
     #[serde(rename = "Health")]
     pub health: f32,
+    /// Extra health from absorption.
     #[serde(rename = "AbsorptionAmount")]
+    #[serde(default)]
     pub absorption_amount: f32,
+    /// Number of ticks the mob keeps turning red after being hurt.
     #[serde(rename = "HurtTime")]
+    #[serde(default)]
     pub hurt_time: i16,
+    /// The game tick the mob was last hurt at.
     #[serde(rename = "HurtByTimestamp")]
+    #[serde(default)]
     pub hurt_by_timestamp: i32,
+    /// Number of ticks the mob has been dead for.
     #[serde(rename = "DeathTime")]
+    #[serde(default)]
     pub death_time: i16,
+    /// True if the mob is gliding with an elytra.
     #[serde(rename = "FallFlying")]
+    #[serde(default)]
     pub fall_flying: bool,
-    #[serde(flatten)]
-    pub sleeping_pos: Option<SleepingPos>,
+    /// The X coordinate of the bed the mob sleeps in.
+    #[serde(rename = "SleepingX")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sleeping_x: Option<i32>,
+    /// The Y coordinate of the bed the mob sleeps in.
+    #[serde(rename = "SleepingY")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sleeping_y: Option<i32>,
+    /// The Z coordinate of the bed the mob sleeps in.
+    #[serde(rename = "SleepingZ")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sleeping_z: Option<i32>,
+    /// What the mob remembers, since 1.14.
     #[serde(rename = "Brain")]
-    pub brain: Brain,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brain: Option<Brain>,
+    /// Attributes of the mob. Their layout changed in 1.20.5; see `Attribute`
+    /// for the layout before.
     #[serde(rename = "Attributes")]
-    pub attributes: Vec<Attribute>,
-    #[serde(rename = "ActiveEffects")]
-    pub active_effects: Vec<ActiveEffect>,
-    // #[serde(rename = "HandItems")]
-    // pub hand_items: Vec<Item>,
-    // #[serde(rename = "ArmorItems")]
-    // pub armor_items: Vec<Item>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<Value>,
+    /// Items in the main hand and the off hand.
+    #[serde(rename = "HandItems")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hand_items: Vec<ItemStack>,
+    /// Items in the feet, legs, chest and head slots.
+    #[serde(rename = "ArmorItems")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub armor_items: Vec<ItemStack>,
+    /// Chance of dropping each hand item.
     #[serde(rename = "HandDropChances")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub hand_drop_chances: Vec<f32>,
+    /// Chance of dropping each armor item.
     #[serde(rename = "ArmorDropChances")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub armor_drop_chances: Vec<f32>,
+    /// The loot table used when the mob dies.
     #[serde(rename = "DeathLootTable")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub death_loot_table: Option<String>,
+    /// The seed of the death loot table.
     #[serde(rename = "DeathLootTableSeed")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub death_loot_table_seed: Option<i64>,
+    /// True if the mob picks up items.
     #[serde(rename = "CanPickUpLoot")]
+    #[serde(default)]
     pub can_pick_up_loot: bool,
+    /// True if the mob has no AI.
     #[serde(rename = "NoAI")]
-    pub no_ai: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_ai: Option<bool>,
+    /// True if the mob does not despawn.
     #[serde(rename = "PersistenceRequired")]
+    #[serde(default)]
     pub persistence_required: bool,
+    /// True if the mob is left handed.
     #[serde(rename = "LeftHanded")]
+    #[serde(default)]
     pub left_handed: bool,
+    /// The scoreboard team of the mob.
     #[serde(rename = "Team")]
-    pub team: String,
-    #[serde(rename = "Leashed")]
-    pub leashed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+    /// What the mob is leashed to: a compound of the UUID of an entity or of
+    /// the coordinates of a fence, or since 1.20.5 an int array of the
+    /// coordinates.
     #[serde(rename = "Leash")]
-    pub leash: Option<Leash>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leash: Option<Value>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
-#[non_exhaustive]
-pub struct SleepingPos {
-    #[serde(rename = "SleepingX")]
-    pub sleeping_x: i32,
-    #[serde(rename = "SleepingY")]
-    pub sleeping_y: i32,
-    #[serde(rename = "SleepingZ")]
-    pub sleeping_z: i32,
+impl Mob {
+    pub fn new(health: f32) -> Mob {
+        Mob {
+            health,
+            absorption_amount: 0.0,
+            hurt_time: 0,
+            hurt_by_timestamp: 0,
+            death_time: 0,
+            fall_flying: false,
+            sleeping_x: None,
+            sleeping_y: None,
+            sleeping_z: None,
+            brain: None,
+            attributes: Vec::new(),
+            hand_items: Vec::new(),
+            armor_items: Vec::new(),
+            hand_drop_chances: Vec::new(),
+            armor_drop_chances: Vec::new(),
+            death_loot_table: None,
+            death_loot_table_seed: None,
+            can_pick_up_loot: false,
+            no_ai: None,
+            persistence_required: false,
+            left_handed: false,
+            team: None,
+            leash: None,
+        }
+    }
 }
 
+/// What a mob remembers.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[non_exhaustive]
 pub struct Brain {
+    /// Memories by ID, like `minecraft:home`. Each is a compound of its
+    /// `value` and, for memories that expire, its `ttl`.
     #[serde(rename = "memories")]
-    pub memories: HashMap<String, Memory>,
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
-#[non_exhaustive]
-pub enum Memory {
-    MemoryPosition { 
-        #[serde(rename = "pos")]
-        #[serde(serialize_with = "as_nbt_array")]
-        pos: [i32; 3],
-        #[serde(rename = "dimension")]
-        dimension: String,
-    },
+    #[serde(default)]
+    pub memories: HashMap<String, Value>,
 }
 
+/// An attribute of a mob, before 1.20.5.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[non_exhaustive]
 pub struct Attribute {
@@ -136,9 +544,11 @@ pub struct Attribute {
     #[serde(rename = "Base")]
     pub base: f64,
     #[serde(rename = "Modifiers")]
+    #[serde(default)]
     pub modifiers: Vec<Modifier>,
 }
 
+/// A modifier of an attribute, before 1.20.5.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[non_exhaustive]
 pub struct Modifier {
@@ -148,12 +558,11 @@ pub struct Modifier {
     pub amount: f64,
     #[serde(rename = "Operation")]
     pub operation: i32,
-    #[serde(rename = "UUIDMost")]
-    pub uuid_most: i64,
-    #[serde(rename = "UUIDLeast")]
-    pub uuid_least: i64,
+    #[serde(flatten, with = "crate::uuid::most_least")]
+    pub uuid: Uuid,
 }
 
+/// A status effect on a mob, before 1.20.2.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[non_exhaustive]
 pub struct ActiveEffect {
@@ -171,22 +580,418 @@ pub struct ActiveEffect {
     pub show_icon: bool,
 }
 
+/// Data of a dropped item.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ItemEntity {
+    /// The dropped items.
+    #[serde(rename = "Item")]
+    pub item: ItemStack,
+    /// Number of ticks the item has existed; it despawns at 6000.
+    #[serde(rename = "Age")]
+    #[serde(default)]
+    pub age: i16,
+    /// Number of ticks until the item can be picked up.
+    #[serde(rename = "PickupDelay")]
+    #[serde(default)]
+    pub pickup_delay: i16,
+    /// Health of the item; it is destroyed at 0.
+    #[serde(rename = "Health")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<i16>,
+    /// The UUID of the only player who can pick the item up.
+    #[serde(rename = "Owner")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<Uuid>,
+    /// The UUID of the entity that dropped the item.
+    #[serde(rename = "Thrower")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thrower: Option<Uuid>,
+}
+
+impl ItemEntity {
+    pub fn new(item: ItemStack) -> ItemEntity {
+        ItemEntity {
+            item,
+            age: 0,
+            pickup_delay: 0,
+            health: None,
+            owner: None,
+            thrower: None,
+        }
+    }
+}
+
+/// Data of an experience orb.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[non_exhaustive]
-pub enum Leash {
-    ToEntity {
-        #[serde(rename = "UUIDMost")]
-        uuid_most: i64,
-        #[serde(rename = "UUIDLeast")]
-        uuid_least: i64,
-    },
-    ToFence {
-        #[serde(rename = "X")]
-        x: i32,
-        #[serde(rename = "Y")]
-        y: i32,
-        #[serde(rename = "Z")]
-        z: i32,
-    },
+pub struct ExperienceOrb {
+    /// Experience points the orb gives.
+    #[serde(rename = "Value")]
+    pub value: i16,
+    /// Number of ticks the orb has existed; it despawns at 6000.
+    #[serde(rename = "Age")]
+    #[serde(default)]
+    pub age: i16,
+    /// Number of orbs merged into this one, since 1.17.
+    #[serde(rename = "Count")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<i32>,
 }
 
+impl ExperienceOrb {
+    pub fn new(value: i16) -> ExperienceOrb {
+        ExperienceOrb {
+            value,
+            age: 0,
+            count: None,
+        }
+    }
+}
+
+/// Data of a falling block, since 1.13.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct FallingBlock {
+    /// The block that is falling.
+    #[serde(rename = "BlockState")]
+    pub block_state: BlockState,
+    /// Number of ticks the block has been falling.
+    #[serde(rename = "Time")]
+    #[serde(default)]
+    pub time: i32,
+    /// True if the block drops as an item when it cannot be placed.
+    #[serde(rename = "DropItem")]
+    #[serde(default)]
+    pub drop_item: bool,
+    /// True if the block hurts entities it lands on.
+    #[serde(rename = "HurtEntities")]
+    #[serde(default)]
+    pub hurt_entities: bool,
+    /// The block entity data of the block, placed with it.
+    #[serde(rename = "TileEntityData")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_entity_data: Option<Value>,
+}
+
+impl FallingBlock {
+    pub fn new(block_state: BlockState) -> FallingBlock {
+        FallingBlock {
+            block_state,
+            time: 0,
+            drop_item: true,
+            hurt_entities: false,
+            block_entity_data: None,
+        }
+    }
+}
+
+/// Data of an armor stand.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ArmorStand {
+    /// True if the armor stand is invisible.
+    #[serde(rename = "Invisible")]
+    #[serde(default)]
+    pub invisible: bool,
+    /// True if the armor stand has no base plate.
+    #[serde(rename = "NoBasePlate")]
+    #[serde(default)]
+    pub no_base_plate: bool,
+    /// True if the armor stand shows its arms.
+    #[serde(rename = "ShowArms")]
+    #[serde(default)]
+    pub show_arms: bool,
+    /// True if the armor stand is small.
+    #[serde(rename = "Small")]
+    #[serde(default)]
+    pub small: bool,
+    /// True if the armor stand has no hitbox.
+    #[serde(rename = "Marker")]
+    #[serde(default)]
+    pub marker: bool,
+    /// Bit field of the slots that cannot be changed.
+    #[serde(rename = "DisabledSlots")]
+    #[serde(default)]
+    pub disabled_slots: i32,
+    /// Rotations of the body parts, by part like `Head` or `LeftArm`.
+    #[serde(rename = "Pose")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pose: Option<Value>,
+    /// Items in the main hand and the off hand.
+    #[serde(rename = "HandItems")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hand_items: Vec<ItemStack>,
+    /// Items in the feet, legs, chest and head slots.
+    #[serde(rename = "ArmorItems")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub armor_items: Vec<ItemStack>,
+}
+
+impl ArmorStand {
+    pub fn new() -> ArmorStand {
+        ArmorStand {
+            invisible: false,
+            no_base_plate: false,
+            show_arms: false,
+            small: false,
+            marker: false,
+            disabled_slots: 0,
+            pose: None,
+            hand_items: Vec::new(),
+            armor_items: Vec::new(),
+        }
+    }
+}
+
+impl Default for ArmorStand {
+    fn default() -> ArmorStand {
+        ArmorStand::new()
+    }
+}
+
+/// Data of an item frame or a glow item frame.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ItemFrame {
+    /// The X coordinate of the block the frame hangs on.
+    #[serde(rename = "TileX")]
+    pub tile_x: i32,
+    /// The Y coordinate of the block the frame hangs on.
+    #[serde(rename = "TileY")]
+    pub tile_y: i32,
+    /// The Z coordinate of the block the frame hangs on.
+    #[serde(rename = "TileZ")]
+    pub tile_z: i32,
+    /// The direction the frame faces: 0 down, 1 up, 2 north, 3 south, 4 west
+    /// and 5 east.
+    #[serde(rename = "Facing")]
+    pub facing: i8,
+    /// The item in the frame.
+    #[serde(rename = "Item")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<ItemStack>,
+    /// Chance of dropping the item when the frame breaks.
+    #[serde(rename = "ItemDropChance")]
+    #[serde(default)]
+    pub item_drop_chance: f32,
+    /// Rotation of the item, in steps of 45 degrees.
+    #[serde(rename = "ItemRotation")]
+    #[serde(default)]
+    pub item_rotation: i8,
+    /// True if the frame is invisible.
+    #[serde(rename = "Invisible")]
+    #[serde(default)]
+    pub invisible: bool,
+    /// True if the frame cannot be broken or changed.
+    #[serde(rename = "Fixed")]
+    #[serde(default)]
+    pub fixed: bool,
+}
+
+impl ItemFrame {
+    pub fn new(tile_x: i32, tile_y: i32, tile_z: i32, facing: i8) -> ItemFrame {
+        ItemFrame {
+            tile_x,
+            tile_y,
+            tile_z,
+            facing,
+            item: None,
+            item_drop_chance: 1.0,
+            item_rotation: 0,
+            invisible: false,
+            fixed: false,
+        }
+    }
+}
+
+/// Data of a villager.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Villager {
+    /// Profession, biome type and level of the villager, since 1.14.
+    #[serde(rename = "VillagerData")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub villager_data: Option<VillagerData>,
+    /// The trades the villager offers, as a compound of `Recipes`.
+    #[serde(rename = "Offers")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offers: Option<Value>,
+    /// Items the villager carries.
+    #[serde(rename = "Inventory")]
+    #[serde(default)]
+    pub inventory: Vec<ItemStack>,
+    /// Trading experience of the villager.
+    #[serde(rename = "Xp")]
+    #[serde(default)]
+    pub xp: i32,
+    /// Age of the villager in ticks; negative for a baby.
+    #[serde(rename = "Age")]
+    #[serde(default)]
+    pub age: i32,
+}
+
+impl Villager {
+    pub fn new(villager_data: VillagerData) -> Villager {
+        Villager {
+            villager_data: Some(villager_data),
+            offers: None,
+            inventory: Vec::new(),
+            xp: 0,
+            age: 0,
+        }
+    }
+}
+
+/// Profession, biome type and level of a villager.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct VillagerData {
+    /// The profession, like `minecraft:farmer`.
+    #[serde(rename = "profession")]
+    pub profession: String,
+    /// The biome type, like `minecraft:plains`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The level, from 1 for a novice to 5 for a master.
+    #[serde(rename = "level")]
+    pub level: i32,
+}
+
+impl VillagerData {
+    pub fn new(profession: String, kind: String, level: i32) -> VillagerData {
+        VillagerData {
+            profession,
+            kind,
+            level,
+        }
+    }
+}
+
+/// Data of a zombie, husk or drowned.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Zombie {
+    /// True if the zombie is a baby.
+    #[serde(rename = "IsBaby")]
+    #[serde(default)]
+    pub is_baby: bool,
+    /// True if the zombie can break doors.
+    #[serde(rename = "CanBreakDoors")]
+    #[serde(default)]
+    pub can_break_doors: bool,
+    /// Number of ticks until the zombie turns into a drowned; -1 if it is
+    /// not turning.
+    #[serde(rename = "DrownedConversionTime")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drowned_conversion_time: Option<i32>,
+    /// Number of ticks the zombie has been under water.
+    #[serde(rename = "InWaterTime")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_water_time: Option<i32>,
+}
+
+/// Data of a creeper.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Creeper {
+    /// Number of ticks the creeper takes to explode.
+    #[serde(rename = "Fuse")]
+    #[serde(default = "default_fuse")]
+    pub fuse: i16,
+    /// Radius of the explosion.
+    #[serde(rename = "ExplosionRadius")]
+    #[serde(default = "default_explosion_radius")]
+    pub explosion_radius: i8,
+    /// True if the creeper was struck by lightning.
+    #[serde(rename = "powered")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub powered: Option<bool>,
+    /// True if the creeper was ignited by flint and steel.
+    #[serde(rename = "ignited")]
+    #[serde(default)]
+    pub ignited: bool,
+}
+
+fn default_fuse() -> i16 {
+    30
+}
+
+fn default_explosion_radius() -> i8 {
+    3
+}
+
+impl Default for Creeper {
+    fn default() -> Creeper {
+        Creeper {
+            fuse: default_fuse(),
+            explosion_radius: default_explosion_radius(),
+            powered: None,
+            ignited: false,
+        }
+    }
+}
+
+/// Data of a sheep.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Sheep {
+    /// The wool color, from 0 for white to 15 for black.
+    #[serde(rename = "Color")]
+    #[serde(default)]
+    pub color: i8,
+    /// True if the sheep has been sheared.
+    #[serde(rename = "Sheared")]
+    #[serde(default)]
+    pub sheared: bool,
+}
+
+/// The entities of a chunk, as stored in the region files in `entities/`
+/// since 1.17.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::entity::EntityChunk;
+/// use coruscant_world::world::ChunkPos;
+///
+/// let chunk = EntityChunk::new(2730, ChunkPos::new(3, -4), Vec::new());
+/// let bytes = coruscant_nbt::to_vec(&chunk).unwrap();
+/// let read: EntityChunk = coruscant_nbt::from_slice(&bytes).unwrap();
+/// assert_eq!(read.position(), ChunkPos::new(3, -4));
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename = "")]
+#[non_exhaustive]
+pub struct EntityChunk {
+    /// The version of the data, as in chunks.
+    #[serde(rename = "DataVersion")]
+    pub data_version: i32,
+    /// The X and Z coordinates of the chunk.
+    #[serde(rename = "Position")]
+    #[serde(serialize_with = "position_array")]
+    pub position: [i32; 2],
+    /// The entities in the chunk.
+    #[serde(rename = "Entities")]
+    #[serde(default)]
+    pub entities: Vec<Entity>,
+}
+
+fn position_array<S: Serializer>(position: &[i32; 2], serializer: S) -> Result<S::Ok, S::Error> {
+    as_nbt_array(&&position[..], serializer)
+}
+
+impl EntityChunk {
+    pub fn new(data_version: i32, pos: ChunkPos, entities: Vec<Entity>) -> EntityChunk {
+        EntityChunk {
+            data_version,
+            position: [pos.x, pos.z],
+            entities,
+        }
+    }
+
+    /// The position of the chunk.
+    pub fn position(&self) -> ChunkPos {
+        ChunkPos::new(self.position[0], self.position[1])
+    }
+}
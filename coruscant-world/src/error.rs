@@ -0,0 +1,106 @@
+//! When reading or writing world files goes wrong.
+use crate::chunk::{BlockState, ChunkFormat};
+use crate::world::{BlockPos, ChunkPos};
+use core::fmt;
+use std::io;
+
+/// Alias for a `Result` with the error type `coruscant_world::Error`.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Errors that can occur when reading or writing world files.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// The NBT data in a file could not be encoded or decoded.
+    Nbt(coruscant_nbt::Error),
+    /// A region file is damaged, for example its header points past the end
+    /// of the file.
+    CorruptRegion(&'static str),
+    /// A region file opened read-only was written to.
+    ReadOnlyRegion,
+    /// A chunk uses a compression scheme that is not supported.
+    UnknownCompression(u8),
+    /// Chunk data is inconsistent, for example a palette index out of range.
+    CorruptChunk(&'static str),
+    /// The world is opened by another program, which holds `session.lock`.
+    SessionLocked,
+    /// The world has no dimension with this name.
+    UnknownDimension(String),
+    /// The chunk has not been generated.
+    ChunkNotGenerated(ChunkPos),
+    /// The block is below or above the heights of its dimension.
+    BlockOutOfWorld(BlockPos),
+    /// The operation does not support chunks in this format.
+    UnsupportedChunkFormat(ChunkFormat),
+    /// Schematic data is inconsistent, for example a palette index out of
+    /// range.
+    CorruptSchematic(&'static str),
+    /// Map data is inconsistent, for example its colors do not fill the
+    /// map.
+    CorruptMap(&'static str),
+    /// The block has no numeric ID, so it cannot be written in a format from
+    /// before 1.13.
+    NoLegacyId(BlockState),
+    /// Data cannot be upgraded past this data version, because a fix of the
+    /// game at that version is not implemented or needs context the data
+    /// lacks.
+    UnsupportedUpgrade(i32, &'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => fmt::Display::fmt(e, f),
+            Error::Nbt(e) => fmt::Display::fmt(e, f),
+            Error::CorruptRegion(msg) => write!(f, "corrupt region file: {}", msg),
+            Error::ReadOnlyRegion => f.write_str("region file is opened read-only"),
+            Error::UnknownCompression(id) => write!(f, "unknown compression scheme {}", id),
+            Error::CorruptChunk(msg) => write!(f, "corrupt chunk: {}", msg),
+            Error::SessionLocked => f.write_str("world is in use by another program"),
+            Error::UnknownDimension(name) => write!(f, "unknown dimension {}", name),
+            Error::ChunkNotGenerated(pos) => {
+                write!(f, "chunk at {}, {} is not generated", pos.x, pos.z)
+            }
+            Error::BlockOutOfWorld(pos) => {
+                write!(
+                    f,
+                    "block at {}, {}, {} is outside the world",
+                    pos.x, pos.y, pos.z
+                )
+            }
+            Error::UnsupportedChunkFormat(format) => {
+                write!(f, "unsupported chunk format {:?}", format)
+            }
+            Error::CorruptSchematic(msg) => write!(f, "corrupt schematic: {}", msg),
+            Error::CorruptMap(msg) => write!(f, "corrupt map: {}", msg),
+            Error::NoLegacyId(state) => write!(f, "block {} has no numeric ID", state),
+            Error::UnsupportedUpgrade(version, msg) => {
+                write!(f, "cannot upgrade past data version {}: {}", version, msg)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Nbt(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<coruscant_nbt::Error> for Error {
+    fn from(e: coruscant_nbt::Error) -> Self {
+        Error::Nbt(e)
+    }
+}
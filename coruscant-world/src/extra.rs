@@ -0,0 +1,48 @@
+//! Structs that keep the fields they have no typed field for in `extra`.
+//!
+//! Such a struct derives its fields with `#[serde(remote = "Self")]` and a
+//! flattened `extra` map, and `impl_serde_with_extra!` implements its serde
+//! traits. Serde buffers flattened fields, which loses the difference between
+//! arrays and lists, so the struct is read through a `Value` and `extra` is
+//! filled again from that value.
+use coruscant_nbt::Value;
+use std::collections::HashMap;
+
+macro_rules! impl_serde_with_extra {
+    ($($ty:ident),*) => {$(
+        impl<'de> serde::Deserialize<'de> for $ty {
+            fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let value =
+                    <coruscant_nbt::Value as serde::Deserialize>::deserialize(deserializer)?;
+                let mut ans = $ty::deserialize(value.clone()).map_err(serde::de::Error::custom)?;
+                $crate::extra::keep_arrays(&mut ans.extra, value);
+                Ok(ans)
+            }
+        }
+
+        impl serde::Serialize for $ty {
+            fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                $ty::serialize(self, serializer)
+            }
+        }
+    )*};
+}
+
+pub(crate) use impl_serde_with_extra;
+
+// Replace the buffered fields in `extra` with the same fields of the value read.
+pub(crate) fn keep_arrays(extra: &mut HashMap<String, Value>, value: Value) {
+    if let Value::Compound(compound) = value {
+        for (key, value) in compound {
+            if let Some(extra) = extra.get_mut(&key) {
+                *extra = value;
+            }
+        }
+    }
+}
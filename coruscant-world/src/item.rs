@@ -0,0 +1,243 @@
+//! Item stacks, as stored in inventories, containers and item entities.
+//!
+//! Before 1.20.5 an item stack has a byte `Count` and an optional free-form
+//! `tag` compound. Since 1.20.5 (24w09a) it has an int `count` and its data
+//! is split into typed `components`, keyed by component ID like
+//! `minecraft:damage`. `ItemStack` reads both layouts and writes the one it
+//! was read in.
+//!
+//! Ref: https://minecraft.gamepedia.com/Player.dat_format#Item_structure
+use crate::extra::impl_serde_with_extra;
+use coruscant_nbt::Value;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Data version of 24w09a, since which items store components.
+pub const DATA_VERSION_ITEM_COMPONENTS: i32 = 3819;
+
+/// ID of the empty item.
+pub const AIR: &str = "minecraft:air";
+
+/// Which layout an item stack is stored in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ItemFormat {
+    /// Byte `Count` and a `tag` compound, before 1.20.5.
+    Tag,
+    /// Int `count` and `components`, since 1.20.5.
+    Components,
+}
+
+impl ItemFormat {
+    /// The item layout used by data with the given `DataVersion`.
+    pub fn from_data_version(data_version: i32) -> ItemFormat {
+        if data_version >= DATA_VERSION_ITEM_COMPONENTS {
+            ItemFormat::Components
+        } else {
+            ItemFormat::Tag
+        }
+    }
+}
+
+/// A stack of items.
+///
+/// An empty stack, with ID `minecraft:air` or a count of zero, is written as
+/// an empty compound, as the game does for empty hand and armor slots.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::item::{ItemFormat, ItemStack};
+///
+/// let mut sword = ItemStack::new("minecraft:iron_sword".to_string(), 1, ItemFormat::Tag);
+/// sword.set_damage(10);
+/// assert_eq!(sword.damage(), Some(10));
+///
+/// let modern = ItemStack::new("minecraft:iron_sword".to_string(), 1, ItemFormat::Components);
+/// assert_eq!(modern.damage(), None);
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(from = "RawItemStack", into = "RawItemStack")]
+#[non_exhaustive]
+pub struct ItemStack {
+    /// The resource location of the item, like `minecraft:diamond`.
+    pub id: String,
+    /// Number of items in the stack.
+    pub count: i32,
+    /// The slot the stack is in, for stacks in an inventory or container.
+    pub slot: Option<i8>,
+    /// Data of the item besides its ID and count.
+    pub data: ItemData,
+    /// Fields not covered above, like the short `Damage` of items before 1.13.
+    pub extra: HashMap<String, Value>,
+}
+
+/// Data of an item stack, in one of the two layouts.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ItemData {
+    /// The contents of `tag` before 1.20.5, like `Damage` or `display`; empty
+    /// if the item has no tag.
+    Tag(HashMap<String, Value>),
+    /// The `components` since 1.20.5, by component ID like `minecraft:damage`.
+    Components(HashMap<String, Value>),
+}
+
+impl ItemStack {
+    /// A stack with no tag or components, in the given layout.
+    pub fn new(id: String, count: i32, format: ItemFormat) -> ItemStack {
+        let data = match format {
+            ItemFormat::Tag => ItemData::Tag(HashMap::new()),
+            ItemFormat::Components => ItemData::Components(HashMap::new()),
+        };
+        ItemStack {
+            id,
+            count,
+            slot: None,
+            data,
+            extra: HashMap::new(),
+        }
+    }
+
+    /// An empty stack, as found in empty hand and armor slots.
+    pub fn empty(format: ItemFormat) -> ItemStack {
+        ItemStack::new(AIR.to_owned(), 0, format)
+    }
+
+    /// The same stack in the given slot.
+    pub fn with_slot(mut self, slot: i8) -> ItemStack {
+        self.slot = Some(slot);
+        self
+    }
+
+    /// Whether the stack holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.id == AIR || self.count <= 0
+    }
+
+    /// The layout the stack is stored in.
+    pub fn format(&self) -> ItemFormat {
+        match self.data {
+            ItemData::Tag(_) => ItemFormat::Tag,
+            ItemData::Components(_) => ItemFormat::Components,
+        }
+    }
+
+    /// Damage taken by the item, from `Damage` in the tag or the
+    /// `minecraft:damage` component.
+    pub fn damage(&self) -> Option<i32> {
+        let damage = match &self.data {
+            ItemData::Tag(tag) => tag.get("Damage"),
+            ItemData::Components(components) => components.get("minecraft:damage"),
+        };
+        match damage {
+            Some(Value::Int(damage)) => Some(*damage),
+            _ => None,
+        }
+    }
+
+    /// Set the damage taken by the item.
+    pub fn set_damage(&mut self, damage: i32) {
+        match &mut self.data {
+            ItemData::Tag(tag) => tag.insert("Damage".to_owned(), Value::Int(damage)),
+            ItemData::Components(components) => {
+                components.insert("minecraft:damage".to_owned(), Value::Int(damage))
+            }
+        };
+    }
+}
+
+// Fields of both layouts, deciding the layout by which fields are present.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Self")]
+struct RawItemStack {
+    #[serde(rename = "Slot")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    slot: Option<i8>,
+    #[serde(rename = "id")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(rename = "Count")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    count_byte: Option<i8>,
+    #[serde(rename = "count")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    count: Option<i32>,
+    #[serde(rename = "tag")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tag: Option<HashMap<String, Value>>,
+    #[serde(rename = "components")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    components: Option<HashMap<String, Value>>,
+    #[serde(flatten)]
+    extra: HashMap<String, Value>,
+}
+
+impl_serde_with_extra!(RawItemStack);
+
+impl From<RawItemStack> for ItemStack {
+    fn from(raw: RawItemStack) -> Self {
+        let id = match raw.id {
+            Some(id) => id,
+            None if raw.count_byte.is_some() => {
+                return ItemStack {
+                    extra: raw.extra,
+                    ..ItemStack::empty(ItemFormat::Tag)
+                }
+            }
+            None => {
+                return ItemStack {
+                    extra: raw.extra,
+                    ..ItemStack::empty(ItemFormat::Components)
+                }
+            }
+        };
+        if raw.count_byte.is_some() || raw.tag.is_some() {
+            ItemStack {
+                id,
+                count: raw.count_byte.unwrap_or(1).into(),
+                slot: raw.slot,
+                data: ItemData::Tag(raw.tag.unwrap_or_default()),
+                extra: raw.extra,
+            }
+        } else {
+            ItemStack {
+                id,
+                count: raw.count.unwrap_or(1),
+                slot: raw.slot,
+                data: ItemData::Components(raw.components.unwrap_or_default()),
+                extra: raw.extra,
+            }
+        }
+    }
+}
+
+impl From<ItemStack> for RawItemStack {
+    fn from(item: ItemStack) -> Self {
+        let empty = item.is_empty();
+        let mut raw = RawItemStack {
+            slot: None,
+            id: None,
+            count_byte: None,
+            count: None,
+            tag: None,
+            components: None,
+            extra: item.extra,
+        };
+        if empty {
+            return raw;
+        }
+        raw.slot = item.slot;
+        raw.id = Some(item.id);
+        match item.data {
+            ItemData::Tag(tag) => {
+                raw.count_byte = Some(item.count.clamp(0, i8::MAX.into()) as i8);
+                raw.tag = Some(tag).filter(|tag| !tag.is_empty());
+            }
+            ItemData::Components(components) => {
+                raw.count = Some(item.count);
+                raw.components = Some(components).filter(|c| !c.is_empty());
+            }
+        }
+        raw
+    }
+}
@@ -0,0 +1,650 @@
+//! Numeric block IDs and data values, used before 1.13.
+//!
+//! Before the Flattening in 1.13 a block was stored as a numeric ID with a
+//! four bit data value, like `35:14` for red wool. `block_state` maps these
+//! to modern block states, and `legacy_id` maps back. Block states are named
+//! as in 1.20.3, like `minecraft:dirt_path` rather than the
+//! `minecraft:grass_path` of 1.13 to 1.16.
+//!
+//! Only properties stored in the data value are set; the game fills in the
+//! others from the default state of the block. Properties that depend on
+//! neighbouring blocks, like fence connections, are left at their defaults,
+//! and blocks whose look was stored in a block entity, like beds and banners,
+//! take a fixed color.
+//!
+//! Ref: https://minecraft.gamepedia.com/Java_Edition_data_values/Pre-flattening
+use crate::chunk::BlockState;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Data version of 1.20.3, whose block names `block_state` returns.
+pub const DATA_VERSION: i32 = 3698;
+
+const COLORS: [&str; 16] = [
+    "white",
+    "orange",
+    "magenta",
+    "light_blue",
+    "yellow",
+    "lime",
+    "pink",
+    "gray",
+    "light_gray",
+    "cyan",
+    "purple",
+    "blue",
+    "brown",
+    "green",
+    "red",
+    "black",
+];
+
+const WOODS: [&str; 6] = ["oak", "spruce", "birch", "jungle", "acacia", "dark_oak"];
+
+// Facing of blocks that can face all six directions, like pistons.
+const FACING_6: [&str; 6] = ["down", "up", "north", "south", "west", "east"];
+
+// Facing of horizontal blocks stored as 0 to 3, like beds and pumpkins.
+const FACING_SWNE: [&str; 4] = ["south", "west", "north", "east"];
+
+// Facing of stairs.
+const FACING_STAIRS: [&str; 4] = ["east", "west", "south", "north"];
+
+// Facing of trapdoors.
+const FACING_TRAPDOOR: [&str; 4] = ["north", "south", "west", "east"];
+
+// Facing of doors.
+const FACING_DOOR: [&str; 4] = ["east", "south", "west", "north"];
+
+const RAIL_SHAPES: [&str; 10] = [
+    "north_south",
+    "east_west",
+    "ascending_east",
+    "ascending_west",
+    "ascending_north",
+    "ascending_south",
+    "south_east",
+    "south_west",
+    "north_west",
+    "north_east",
+];
+
+const STONE_SLABS: [&str; 8] = [
+    "smooth_stone",
+    "sandstone",
+    "petrified_oak",
+    "cobblestone",
+    "brick",
+    "stone_brick",
+    "nether_brick",
+    "quartz",
+];
+
+/// The block state of a numeric block ID and data value; `None` for IDs that
+/// were never used.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::legacy_ids;
+///
+/// let wool = legacy_ids::block_state(35, 14).unwrap();
+/// assert_eq!(wool.name, "minecraft:red_wool");
+/// let stairs = legacy_ids::block_state(53, 6).unwrap();
+/// assert_eq!(stairs.to_string(), "minecraft:oak_stairs[facing=south,half=top]");
+/// assert_eq!(legacy_ids::legacy_id(&stairs), Some((53, 6)));
+/// ```
+pub fn block_state(id: u16, data: u8) -> Option<BlockState> {
+    let data = data & 15;
+    let d = usize::from(data);
+    let s = |name: &str| BlockState::new(format!("minecraft:{}", name));
+    let bit = |mask: u8| if data & mask != 0 { "true" } else { "false" };
+    let half = |mask: u8| if data & mask != 0 { "top" } else { "bottom" };
+    let axis = || ["y", "x", "z", "y"][d >> 2];
+    let state = match id {
+        0 => s("air"),
+        1 => s([
+            "stone",
+            "granite",
+            "polished_granite",
+            "diorite",
+            "polished_diorite",
+            "andesite",
+            "polished_andesite",
+        ]
+        .get(d)?),
+        2 => s("grass_block").with_property("snowy", "false"),
+        3 => match data {
+            1 => s("coarse_dirt"),
+            2 => s("podzol").with_property("snowy", "false"),
+            _ => s("dirt"),
+        },
+        4 => s("cobblestone"),
+        5 => s(&format!("{}_planks", WOODS.get(d)?)),
+        6 => s(&format!("{}_sapling", WOODS.get(d & 7)?))
+            .with_property("stage", if data & 8 != 0 { "1" } else { "0" }),
+        7 => s("bedrock"),
+        8 | 9 => s("water").with_property("level", &data.to_string()),
+        10 | 11 => s("lava").with_property("level", &data.to_string()),
+        12 => s(["sand", "red_sand"].get(d)?),
+        13 => s("gravel"),
+        14 => s("gold_ore"),
+        15 => s("iron_ore"),
+        16 => s("coal_ore"),
+        17 | 162 => {
+            let wood = if id == 17 {
+                WOODS[d & 3]
+            } else {
+                *WOODS[4..].get(d & 3)?
+            };
+            match d >> 2 {
+                3 => s(&format!("{}_wood", wood)).with_property("axis", "y"),
+                _ => s(&format!("{}_log", wood)).with_property("axis", axis()),
+            }
+        }
+        18 | 161 => {
+            let wood = if id == 18 {
+                WOODS[d & 3]
+            } else {
+                *WOODS[4..].get(d & 3)?
+            };
+            s(&format!("{}_leaves", wood))
+                .with_property("distance", "7")
+                .with_property("persistent", bit(4))
+        }
+        19 => s(["sponge", "wet_sponge"].get(d)?),
+        20 => s("glass"),
+        21 => s("lapis_ore"),
+        22 => s("lapis_block"),
+        23 | 158 => s(if id == 23 { "dispenser" } else { "dropper" })
+            .with_property("facing", FACING_6.get(d & 7)?)
+            .with_property("triggered", bit(8)),
+        24 | 179 => {
+            let kinds = if id == 24 {
+                ["sandstone", "chiseled_sandstone", "cut_sandstone"]
+            } else {
+                [
+                    "red_sandstone",
+                    "chiseled_red_sandstone",
+                    "cut_red_sandstone",
+                ]
+            };
+            s(kinds.get(d)?)
+        }
+        25 => s("note_block"),
+        26 => s("red_bed")
+            .with_property("facing", FACING_SWNE[d & 3])
+            .with_property("occupied", bit(4))
+            .with_property("part", if data & 8 != 0 { "head" } else { "foot" }),
+        27 | 28 | 157 => s(match id {
+            27 => "powered_rail",
+            28 => "detector_rail",
+            _ => "activator_rail",
+        })
+        .with_property("shape", RAIL_SHAPES.get(d & 7).filter(|_| d & 7 < 6)?)
+        .with_property("powered", bit(8)),
+        29 | 33 => s(if id == 29 { "sticky_piston" } else { "piston" })
+            .with_property("facing", FACING_6.get(d & 7)?)
+            .with_property("extended", bit(8)),
+        30 => s("cobweb"),
+        31 => s(["dead_bush", "short_grass", "fern"].get(d)?),
+        32 => s("dead_bush"),
+        34 => s("piston_head")
+            .with_property("facing", FACING_6.get(d & 7)?)
+            .with_property("type", if data & 8 != 0 { "sticky" } else { "normal" }),
+        35 => s(&format!("{}_wool", COLORS[d])),
+        36 => s("moving_piston")
+            .with_property("facing", FACING_6.get(d & 7)?)
+            .with_property("type", if data & 8 != 0 { "sticky" } else { "normal" }),
+        37 => s("dandelion"),
+        38 => s([
+            "poppy",
+            "blue_orchid",
+            "allium",
+            "azure_bluet",
+            "red_tulip",
+            "orange_tulip",
+            "white_tulip",
+            "pink_tulip",
+            "oxeye_daisy",
+        ]
+        .get(d)?),
+        39 => s("brown_mushroom"),
+        40 => s("red_mushroom"),
+        41 => s("gold_block"),
+        42 => s("iron_block"),
+        43 => match data {
+            8 => s("smooth_stone"),
+            9 => s("smooth_sandstone"),
+            15 => s("smooth_quartz"),
+            _ => s(&format!("{}_slab", STONE_SLABS.get(d)?)).with_property("type", "double"),
+        },
+        44 => s(&format!("{}_slab", STONE_SLABS[d & 7])).with_property("type", half(8)),
+        45 => s("bricks"),
+        46 => s("tnt").with_property("unstable", bit(1)),
+        47 => s("bookshelf"),
+        48 => s("mossy_cobblestone"),
+        49 => s("obsidian"),
+        50 | 75 | 76 => {
+            let (floor, wall) = match id {
+                50 => ("torch", "wall_torch"),
+                _ => ("redstone_torch", "redstone_wall_torch"),
+            };
+            let state = match data {
+                1..=4 => s(wall).with_property("facing", ["east", "west", "south", "north"][d - 1]),
+                _ => s(floor),
+            };
+            match id {
+                50 => state,
+                _ => state.with_property("lit", if id == 76 { "true" } else { "false" }),
+            }
+        }
+        51 => s("fire").with_property("age", &data.to_string()),
+        52 => s("spawner"),
+        53 | 67 | 108 | 109 | 114 | 128 | 134 | 135 | 136 | 156 | 163 | 164 | 180 | 203 => {
+            let name = match id {
+                53 => "oak_stairs",
+                67 => "cobblestone_stairs",
+                108 => "brick_stairs",
+                109 => "stone_brick_stairs",
+                114 => "nether_brick_stairs",
+                128 => "sandstone_stairs",
+                134 => "spruce_stairs",
+                135 => "birch_stairs",
+                136 => "jungle_stairs",
+                156 => "quartz_stairs",
+                163 => "acacia_stairs",
+                164 => "dark_oak_stairs",
+                180 => "red_sandstone_stairs",
+                _ => "purpur_stairs",
+            };
+            s(name)
+                .with_property("facing", FACING_STAIRS[d & 3])
+                .with_property("half", half(4))
+        }
+        54 | 130 | 146 => {
+            let name = match id {
+                54 => "chest",
+                130 => "ender_chest",
+                _ => "trapped_chest",
+            };
+            s(name).with_property("facing", horizontal_2345(data))
+        }
+        55 => s("redstone_wire").with_property("power", &data.to_string()),
+        56 => s("diamond_ore"),
+        57 => s("diamond_block"),
+        58 => s("crafting_table"),
+        59 => s("wheat").with_property("age", &(data & 7).to_string()),
+        60 => s("farmland").with_property("moisture", &(data & 7).to_string()),
+        61 | 62 => s("furnace")
+            .with_property("facing", horizontal_2345(data))
+            .with_property("lit", if id == 62 { "true" } else { "false" }),
+        63 => s("oak_sign").with_property("rotation", &data.to_string()),
+        64 | 71 | 193 | 194 | 195 | 196 | 197 => {
+            let name = match id {
+                64 => "oak_door",
+                71 => "iron_door",
+                193 => "spruce_door",
+                194 => "birch_door",
+                195 => "jungle_door",
+                196 => "acacia_door",
+                _ => "dark_oak_door",
+            };
+            if data & 8 == 0 {
+                s(name)
+                    .with_property("facing", FACING_DOOR[d & 3])
+                    .with_property("open", bit(4))
+                    .with_property("half", "lower")
+            } else {
+                s(name)
+                    .with_property("hinge", if data & 1 != 0 { "right" } else { "left" })
+                    .with_property("powered", bit(2))
+                    .with_property("half", "upper")
+            }
+        }
+        65 => s("ladder").with_property("facing", horizontal_2345(data)),
+        66 => s("rail").with_property("shape", RAIL_SHAPES.get(d)?),
+        68 => s("oak_wall_sign").with_property("facing", horizontal_2345(data)),
+        69 => {
+            let (face, facing) = [
+                ("ceiling", "west"),
+                ("wall", "east"),
+                ("wall", "west"),
+                ("wall", "south"),
+                ("wall", "north"),
+                ("floor", "north"),
+                ("floor", "west"),
+                ("ceiling", "north"),
+            ][d & 7];
+            s("lever")
+                .with_property("face", face)
+                .with_property("facing", facing)
+                .with_property("powered", bit(8))
+        }
+        70 | 72 => s(if id == 70 {
+            "stone_pressure_plate"
+        } else {
+            "oak_pressure_plate"
+        })
+        .with_property("powered", bit(1)),
+        73 | 74 => s("redstone_ore").with_property("lit", if id == 74 { "true" } else { "false" }),
+        77 | 143 => {
+            let (face, facing) = match d & 7 {
+                0 => ("ceiling", "north"),
+                1 => ("wall", "east"),
+                2 => ("wall", "west"),
+                3 => ("wall", "south"),
+                4 => ("wall", "north"),
+                _ => ("floor", "north"),
+            };
+            s(if id == 77 {
+                "stone_button"
+            } else {
+                "oak_button"
+            })
+            .with_property("face", face)
+            .with_property("facing", facing)
+            .with_property("powered", bit(8))
+        }
+        78 => s("snow").with_property("layers", &((data & 7) + 1).to_string()),
+        79 => s("ice"),
+        80 => s("snow_block"),
+        81 => s("cactus").with_property("age", &data.to_string()),
+        82 => s("clay"),
+        83 => s("sugar_cane").with_property("age", &data.to_string()),
+        84 => s("jukebox").with_property("has_record", bit(1)),
+        85 => s("oak_fence"),
+        86 | 91 => s(if id == 86 {
+            "carved_pumpkin"
+        } else {
+            "jack_o_lantern"
+        })
+        .with_property("facing", FACING_SWNE[d & 3]),
+        87 => s("netherrack"),
+        88 => s("soul_sand"),
+        89 => s("glowstone"),
+        90 => s("nether_portal").with_property("axis", if data == 2 { "z" } else { "x" }),
+        92 => s("cake").with_property("bites", &(data & 7).min(6).to_string()),
+        93 | 94 => s("repeater")
+            .with_property("facing", FACING_SWNE[d & 3])
+            .with_property("delay", &((data >> 2) + 1).to_string())
+            .with_property("powered", if id == 94 { "true" } else { "false" }),
+        95 => s(&format!("{}_stained_glass", COLORS[d])),
+        96 | 167 => s(if id == 96 {
+            "oak_trapdoor"
+        } else {
+            "iron_trapdoor"
+        })
+        .with_property("facing", FACING_TRAPDOOR[d & 3])
+        .with_property("open", bit(4))
+        .with_property("half", half(8)),
+        97 => s([
+            "infested_stone",
+            "infested_cobblestone",
+            "infested_stone_bricks",
+            "infested_mossy_stone_bricks",
+            "infested_cracked_stone_bricks",
+            "infested_chiseled_stone_bricks",
+        ]
+        .get(d)?),
+        98 => s([
+            "stone_bricks",
+            "mossy_stone_bricks",
+            "cracked_stone_bricks",
+            "chiseled_stone_bricks",
+        ]
+        .get(d)?),
+        99 | 100 => {
+            let name = match (id, data) {
+                (_, 10) | (_, 15) => "mushroom_stem",
+                (99, _) => "brown_mushroom_block",
+                _ => "red_mushroom_block",
+            };
+            let sides = if data == 0 { "false" } else { "true" };
+            let mut state = s(name);
+            for side in &["north", "east", "south", "west"] {
+                state = state.with_property(side, sides);
+            }
+            let top = if data == 0 || data == 10 {
+                "false"
+            } else {
+                "true"
+            };
+            state
+                .with_property("up", top)
+                .with_property("down", if data == 15 { "true" } else { "false" })
+        }
+        101 => s("iron_bars"),
+        102 => s("glass_pane"),
+        103 => s("melon"),
+        104 => s("pumpkin_stem").with_property("age", &(data & 7).to_string()),
+        105 => s("melon_stem").with_property("age", &(data & 7).to_string()),
+        106 => s("vine")
+            .with_property("south", bit(1))
+            .with_property("west", bit(2))
+            .with_property("north", bit(4))
+            .with_property("east", bit(8)),
+        107 | 183 | 184 | 185 | 186 | 187 => {
+            let name = match id {
+                107 => "oak_fence_gate",
+                183 => "spruce_fence_gate",
+                184 => "birch_fence_gate",
+                185 => "jungle_fence_gate",
+                186 => "dark_oak_fence_gate",
+                _ => "acacia_fence_gate",
+            };
+            s(name)
+                .with_property("facing", FACING_SWNE[d & 3])
+                .with_property("open", bit(4))
+        }
+        110 => s("mycelium").with_property("snowy", "false"),
+        111 => s("lily_pad"),
+        112 => s("nether_bricks"),
+        113 => s("nether_brick_fence"),
+        115 => s("nether_wart").with_property("age", &(data & 3).to_string()),
+        116 => s("enchanting_table"),
+        117 => s("brewing_stand")
+            .with_property("has_bottle_0", bit(1))
+            .with_property("has_bottle_1", bit(2))
+            .with_property("has_bottle_2", bit(4)),
+        118 => match data & 3 {
+            0 => s("cauldron"),
+            level => s("water_cauldron").with_property("level", &level.to_string()),
+        },
+        119 => s("end_portal"),
+        120 => s("end_portal_frame")
+            .with_property("facing", FACING_SWNE[d & 3])
+            .with_property("eye", bit(4)),
+        121 => s("end_stone"),
+        122 => s("dragon_egg"),
+        123 | 124 => {
+            s("redstone_lamp").with_property("lit", if id == 124 { "true" } else { "false" })
+        }
+        125 => s(&format!("{}_slab", WOODS.get(d)?)).with_property("type", "double"),
+        126 => s(&format!("{}_slab", WOODS.get(d & 7)?)).with_property("type", half(8)),
+        127 => s("cocoa")
+            .with_property("facing", FACING_SWNE[d & 3])
+            .with_property("age", &((data >> 2) & 3).min(2).to_string()),
+        129 => s("emerald_ore"),
+        131 => s("tripwire_hook")
+            .with_property("facing", FACING_SWNE[d & 3])
+            .with_property("attached", bit(4))
+            .with_property("powered", bit(8)),
+        132 => s("tripwire")
+            .with_property("powered", bit(1))
+            .with_property("attached", bit(4))
+            .with_property("disarmed", bit(8)),
+        133 => s("emerald_block"),
+        137 | 210 | 211 => s(match id {
+            137 => "command_block",
+            210 => "repeating_command_block",
+            _ => "chain_command_block",
+        })
+        .with_property("facing", FACING_6.get(d & 7)?)
+        .with_property("conditional", bit(8)),
+        138 => s("beacon"),
+        139 => s(["cobblestone_wall", "mossy_cobblestone_wall"].get(d)?),
+        140 => s("flower_pot"),
+        141 => s("carrots").with_property("age", &(data & 7).to_string()),
+        142 => s("potatoes").with_property("age", &(data & 7).to_string()),
+        144 => match d & 7 {
+            1 => s("skeleton_skull").with_property("rotation", "0"),
+            _ => s("skeleton_wall_skull").with_property("facing", horizontal_2345(data & 7)),
+        },
+        145 => s(["anvil", "chipped_anvil", "damaged_anvil"].get(d >> 2)?)
+            .with_property("facing", FACING_SWNE[d & 3]),
+        147 | 148 => s(if id == 147 {
+            "light_weighted_pressure_plate"
+        } else {
+            "heavy_weighted_pressure_plate"
+        })
+        .with_property("power", &data.to_string()),
+        149 | 150 => s("comparator")
+            .with_property("facing", FACING_SWNE[d & 3])
+            .with_property("mode", if data & 4 != 0 { "subtract" } else { "compare" })
+            .with_property(
+                "powered",
+                if id == 150 || data & 8 != 0 {
+                    "true"
+                } else {
+                    "false"
+                },
+            ),
+        151 | 178 => s("daylight_detector")
+            .with_property("inverted", if id == 178 { "true" } else { "false" })
+            .with_property("power", &data.to_string()),
+        152 => s("redstone_block"),
+        153 => s("nether_quartz_ore"),
+        154 => s("hopper")
+            .with_property("facing", FACING_6.get(d & 7).filter(|f| **f != "up")?)
+            .with_property("enabled", if data & 8 != 0 { "false" } else { "true" }),
+        155 => match data {
+            0 => s("quartz_block"),
+            1 => s("chiseled_quartz_block"),
+            2..=4 => s("quartz_pillar").with_property("axis", ["y", "x", "z"][d - 2]),
+            _ => return None,
+        },
+        159 => s(&format!("{}_terracotta", COLORS[d])),
+        160 => s(&format!("{}_stained_glass_pane", COLORS[d])),
+        165 => s("slime_block"),
+        166 => s("barrier"),
+        168 => s(["prismarine", "prismarine_bricks", "dark_prismarine"].get(d)?),
+        169 => s("sea_lantern"),
+        170 => s("hay_block").with_property("axis", axis()),
+        171 => s(&format!("{}_carpet", COLORS[d])),
+        172 => s("terracotta"),
+        173 => s("coal_block"),
+        174 => s("packed_ice"),
+        175 => {
+            let name = if data & 8 != 0 {
+                "sunflower"
+            } else {
+                [
+                    "sunflower",
+                    "lilac",
+                    "tall_grass",
+                    "large_fern",
+                    "rose_bush",
+                    "peony",
+                ]
+                .get(d)?
+            };
+            s(name).with_property("half", if data & 8 != 0 { "upper" } else { "lower" })
+        }
+        176 => s("white_banner").with_property("rotation", &data.to_string()),
+        177 => s("white_wall_banner").with_property("facing", horizontal_2345(data)),
+        181 => match data {
+            8 => s("smooth_red_sandstone"),
+            _ => s("red_sandstone_slab").with_property("type", "double"),
+        },
+        182 => s("red_sandstone_slab").with_property("type", half(8)),
+        188 => s("spruce_fence"),
+        189 => s("birch_fence"),
+        190 => s("jungle_fence"),
+        191 => s("dark_oak_fence"),
+        192 => s("acacia_fence"),
+        198 => s("end_rod").with_property("facing", FACING_6.get(d & 7)?),
+        199 => s("chorus_plant"),
+        200 => s("chorus_flower").with_property("age", &data.min(5).to_string()),
+        201 => s("purpur_block"),
+        202 => s("purpur_pillar").with_property("axis", axis()),
+        204 => s("purpur_slab").with_property("type", "double"),
+        205 => s("purpur_slab").with_property("type", half(8)),
+        206 => s("end_stone_bricks"),
+        207 => s("beetroots").with_property("age", &(data & 3).to_string()),
+        208 => s("dirt_path"),
+        209 => s("end_gateway"),
+        212 => s("frosted_ice").with_property("age", &(data & 3).to_string()),
+        213 => s("magma_block"),
+        214 => s("nether_wart_block"),
+        215 => s("red_nether_bricks"),
+        216 => s("bone_block").with_property("axis", axis()),
+        217 => s("structure_void"),
+        218 => s("observer")
+            .with_property("facing", FACING_6.get(d & 7)?)
+            .with_property("powered", bit(8)),
+        219..=234 => s(&format!("{}_shulker_box", COLORS[usize::from(id - 219)]))
+            .with_property("facing", FACING_6.get(d & 7)?),
+        235..=250 => s(&format!(
+            "{}_glazed_terracotta",
+            COLORS[usize::from(id - 235)]
+        ))
+        .with_property("facing", FACING_SWNE[d & 3]),
+        251 => s(&format!("{}_concrete", COLORS[d])),
+        252 => s(&format!("{}_concrete_powder", COLORS[d])),
+        255 => {
+            s("structure_block").with_property("mode", ["save", "load", "corner", "data"].get(d)?)
+        }
+        _ => return None,
+    };
+    Some(state)
+}
+
+// Facing of wall blocks stored as 2 to 5, like ladders and wall signs.
+fn horizontal_2345(data: u8) -> &'static str {
+    match data {
+        3 => "south",
+        4 => "west",
+        5 => "east",
+        _ => "north",
+    }
+}
+
+/// The numeric block ID and data value of a block state; `None` for blocks
+/// added since 1.13.
+///
+/// Properties not stored in the data value are ignored, so for example any
+/// oak fence maps to `85:0`.
+pub fn legacy_id(state: &BlockState) -> Option<(u16, u8)> {
+    let candidates = reverse_table().get(&state.name)?;
+    candidates
+        .iter()
+        .find(|(legacy, _)| {
+            legacy
+                .properties
+                .iter()
+                .all(|(name, value)| state.property(name) == Some(value.as_str()))
+        })
+        .map(|(_, id)| *id)
+}
+
+// Block states of every ID and data value, by block name.
+type ReverseTable = HashMap<String, Vec<(BlockState, (u16, u8))>>;
+
+fn reverse_table() -> &'static ReverseTable {
+    static TABLE: OnceLock<ReverseTable> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = ReverseTable::new();
+        for id in 0..256 {
+            for data in 0..16 {
+                if let Some(state) = block_state(id, data) {
+                    let candidates = table.entry(state.name.clone()).or_default();
+                    if candidates.iter().all(|(s, _)| *s != state) {
+                        candidates.push((state, (id, data)));
+                    }
+                }
+            }
+        }
+        table
+    })
+}
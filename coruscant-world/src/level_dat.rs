@@ -1,7 +1,11 @@
 //! Level format parsed as serde favored Rust structs.
 //!
 //! Ref: https://minecraft.gamepedia.com/Level_format
-use serde::{Deserialize, Serialize};
+use crate::player::Player;
+use crate::upgrade::DATA_VERSION_INT_ARRAY_UUIDS;
+use crate::uuid::Uuid;
+use coruscant_nbt::{Map, Value};
+use serde::{ser, Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
 
 /// Root repersentation of `level.dat` file.
@@ -16,18 +20,32 @@ pub struct LevelDat {
 
 /// Container for all the level data.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(remote = "Self")]
 #[non_exhaustive]
 pub struct Data {
     /// A ID/BossEvent collection of bossbars. ID of a bossbar is a string like
     /// `custom::boss`.
+    ///
+    /// `None` before 1.13.
     #[serde(rename = "CustomBossEvents")]
-    pub custom_boss_events: HashMap<String, BossEvent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_boss_events: Option<HashMap<String, BossEvent>>,
     /// Options for datapacks.
+    ///
+    /// `None` before 1.13.
     #[serde(rename = "DataPacks")]
-    pub data_packs: DataPacks,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_packs: Option<DataPacks>,
     /// This tag contains level data specific to certain dimensions.
+    ///
+    /// `None` since 1.16, where the dragon fight moved to `DragonFight`.
     #[serde(rename = "DimensionData")]
-    pub dimension_data: DimensionData,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimension_data: Option<DimensionData>,
+    /// Data for the ender dragon fight, since 1.16. Only appears after the end is entered.
+    #[serde(rename = "DragonFight")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dragon_fight: Option<DragonFlight>,
     /// The NBT version of the level, 19133.
     #[serde(rename = "version")]
     pub version_int: i32,
@@ -43,18 +61,45 @@ pub struct Data {
     /// or "debug_all_block_states".
     ///
     /// Not case sensitive, but always written in the case here.
+    ///
+    /// `None` since 1.16, where the generator is described by `WorldGenSettings`.
     #[serde(rename = "generatorName")]
-    pub generator_name: String,
-    // todo: generator version; generator settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator_name: Option<String>,
+    /// The version of the generator. Tells the "default" generator apart from
+    /// the old "default_1_1".
+    ///
+    /// `None` since 1.16.
+    #[serde(rename = "generatorVersion")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator_version: Option<i32>,
+    /// Settings of the "flat", "buffet" or customized generator. A string
+    /// before 1.13 and a compound after.
+    ///
+    /// `None` since 1.16.
+    #[serde(rename = "generatorOptions")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator_options: Option<Value>,
     /// The [random level seed] used to generate consistent terrain.
     ///
+    /// `None` since 1.16, where the seed is in `WorldGenSettings`; use
+    /// [`Data::seed`] to get it from either place.
+    ///
     /// [random level seed]: https://minecraft.gamepedia.com/Seed_(level_generation)
     #[serde(rename = "RandomSeed")]
-    pub random_seed: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub random_seed: Option<i64>,
     /// True if the map generator should place structures such as villages, strongholds,
     /// and mineshafts. Defaults to true. Always true if the world type is Customized.
+    ///
+    /// `None` since 1.16; use [`Data::generate_features`] to get it from either place.
     #[serde(rename = "MapFeatures")]
-    pub map_features: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub map_features: Option<bool>,
+    /// Generation settings of the world and each of its dimensions, since 1.16.
+    #[serde(rename = "WorldGenSettings")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub world_gen_settings: Option<WorldGenSettings>,
     /// The Unix time in milliseconds when the level was last loaded.
     #[serde(rename = "LastPlayed")]
     pub last_played: i64,
@@ -72,8 +117,11 @@ pub struct Data {
     /// An integer displaying the [data version].
     ///
     /// [data version]: https://minecraft.gamepedia.com/Data_version
+    ///
+    /// `None` before 1.9.
     #[serde(rename = "DataVersion")]
-    pub data_version: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_version: Option<i32>,
     /// The current difficulty setting. 0 is Peaceful, 1 is Easy, 2 is Normal, and 3 is Hard.
     /// Defaults to 2.
     #[serde(rename = "Difficulty")]
@@ -149,25 +197,393 @@ pub struct Data {
     /// The number of ticks until "clear weather" has ended.
     #[serde(rename = "clearWeatherTime")]
     pub clear_weather_time: i32,
-    // #[serde(rename = "Player")]
-    // player:
-    // #[serde(rename = "GameRules")]
-    // game_rules:
-    /// The UUID of the current wandering trader in the world.
+    /// The state of the singleplayer player, in the same layout as the files in
+    /// `playerdata`. Used instead of the player file of the same player.
+    ///
+    /// `None` for worlds created by a server.
+    #[serde(rename = "Player")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player: Option<Player>,
+    /// The [game rules] of the world.
+    ///
+    /// [game rules]: https://minecraft.gamepedia.com/Game_rule
+    #[serde(rename = "GameRules")]
+    #[serde(default)]
+    pub game_rules: GameRules,
+    /// Functions scheduled by the `/schedule` command. Not written when empty,
+    /// as before 1.14.
+    #[serde(rename = "ScheduledEvents")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scheduled_events: Vec<ScheduledEvent>,
+    /// Brands of the servers that have opened the world, for example `vanilla`.
+    /// Not written when empty, as before 1.14.
+    #[serde(rename = "ServerBrands")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub server_brands: Vec<String>,
+    /// True if the world was opened by a modified client or server, that is, a
+    /// server brand other than `vanilla`. Not written when false.
+    #[serde(rename = "WasModded")]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub was_modded: bool,
+    /// The UUID of the current wandering trader in the world. A string before 1.16
+    /// and an int array since; both are read.
+    ///
+    /// `None` if no wandering trader has spawned.
     #[serde(rename = "WanderingTraderId")]
-    pub wandering_trader_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wandering_trader_id: Option<Uuid>,
     /// The current chance of the wandering trader spawning next attempt.
     ///  
     /// This value is the percentage and will be divided by 10 when loaded by the game,
     /// for example a value of 50 means 5.0% chance.
+    ///
+    /// `None` before 1.14.
     #[serde(rename = "WanderingTraderSpawnChance")]
-    pub wandering_trader_spawn_chance: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wandering_trader_spawn_chance: Option<i32>,
     /// The amount of ticks until another wandering trader is attempted to spawn.
+    ///
+    /// `None` before 1.14.
     #[serde(rename = "WanderingTraderSpawnDelay")]
-    pub wandering_trader_spawn_delay: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wandering_trader_spawn_delay: Option<i32>,
     /// Information about the Minecraft version the world was saved in.
+    ///
+    /// `None` before 1.9.
     #[serde(rename = "Version")]
-    pub version: Version,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<Version>,
+}
+
+impl<'de> Deserialize<'de> for Data {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Data::deserialize(deserializer)
+    }
+}
+
+impl Serialize for Data {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        struct Fields<'a>(&'a Data);
+
+        impl Serialize for Fields<'_> {
+            fn serialize<S: Serializer>(
+                &self,
+                serializer: S,
+            ) -> core::result::Result<S::Ok, S::Error> {
+                Data::serialize(self.0, serializer)
+            }
+        }
+
+        // `DragonFlight` writes the dragon UUID as `Dragon`; split it into the
+        // two longs for levels saved before UUIDs became int arrays.
+        let mut value = coruscant_nbt::to_value(Fields(self)).map_err(ser::Error::custom)?;
+        if self.data_version.unwrap_or(0) < DATA_VERSION_INT_ARRAY_UUIDS {
+            if let Value::Compound(data) = &mut value {
+                split_dragon_uuid(data);
+                if let Some(Value::Compound(dimensions)) = data.get_mut("DimensionData") {
+                    for (_, dimension) in dimensions.iter_mut() {
+                        if let Value::Compound(dimension) = dimension {
+                            split_dragon_uuid(dimension);
+                        }
+                    }
+                }
+            }
+        }
+        value.serialize(serializer)
+    }
+}
+
+fn split_dragon_uuid(parent: &mut Map<String, Value>) {
+    let fight = match parent.get_mut("DragonFight") {
+        Some(Value::Compound(fight)) => fight,
+        _ => return,
+    };
+    if let Some(uuid) = fight.remove("Dragon") {
+        if let Ok(uuid) = coruscant_nbt::from_value::<Uuid>(uuid) {
+            fight.insert("DragonUUIDMost".to_owned(), Value::Long(uuid.most()));
+            fight.insert("DragonUUIDLeast".to_owned(), Value::Long(uuid.least()));
+        }
+    }
+}
+
+impl Data {
+    /// The seed of the world, from `WorldGenSettings` since 1.16 and from
+    /// `RandomSeed` before.
+    pub fn seed(&self) -> Option<i64> {
+        match &self.world_gen_settings {
+            Some(settings) => Some(settings.seed),
+            None => self.random_seed,
+        }
+    }
+
+    /// Whether structures are generated, from `WorldGenSettings` since 1.16 and
+    /// from `MapFeatures` before.
+    pub fn generate_features(&self) -> bool {
+        match &self.world_gen_settings {
+            Some(settings) => settings.generate_features,
+            None => self.map_features.unwrap_or(true),
+        }
+    }
+}
+
+/// The [game rules] of a world.
+///
+/// Every rule is stored as a string: `"true"` or `"false"` for boolean rules
+/// and a decimal number for integer rules. A rule that is not stored takes
+/// its default value in the game.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::level_dat::GameRules;
+///
+/// let mut rules = GameRules::new();
+/// rules.set("doDaylightCycle", false);
+/// rules.set("randomTickSpeed", 3);
+/// assert_eq!(rules.get_bool("doDaylightCycle"), Some(false));
+/// assert_eq!(rules.get_int("randomTickSpeed"), Some(3));
+/// assert_eq!(rules.get("keepInventory"), None);
+/// ```
+///
+/// [game rules]: https://minecraft.gamepedia.com/Game_rule
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(transparent)]
+pub struct GameRules {
+    rules: HashMap<String, String>,
+}
+
+impl GameRules {
+    /// Game rules with no rule stored.
+    pub fn new() -> GameRules {
+        GameRules::default()
+    }
+
+    /// The stored value of a rule.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.rules.get(name).map(String::as_str)
+    }
+
+    /// The value of a boolean rule; `None` if the rule is not stored or is
+    /// not a boolean.
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        self.get(name)?.parse().ok()
+    }
+
+    /// The value of an integer rule; `None` if the rule is not stored or is
+    /// not an integer.
+    pub fn get_int(&self, name: &str) -> Option<i32> {
+        self.get(name)?.parse().ok()
+    }
+
+    /// Store the value of a rule, returning the old value.
+    pub fn set<T: ToString>(&mut self, name: &str, value: T) -> Option<String> {
+        self.rules.insert(name.to_owned(), value.to_string())
+    }
+
+    /// Remove a rule so that it takes its default value, returning the old value.
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        self.rules.remove(name)
+    }
+
+    /// Names and values of all stored rules, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.rules.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+/// A function scheduled by the `/schedule` command.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ScheduledEvent {
+    /// What to run when the event triggers.
+    #[serde(rename = "Callback")]
+    pub callback: Callback,
+    /// The name of the event, the same as the name of the function or tag.
+    #[serde(rename = "Name")]
+    pub name: String,
+    /// The game time in ticks at which the event triggers.
+    #[serde(rename = "TriggerTime")]
+    pub trigger_time: i64,
+}
+
+impl ScheduledEvent {
+    pub fn new(callback: Callback, name: String, trigger_time: i64) -> ScheduledEvent {
+        ScheduledEvent {
+            callback,
+            name,
+            trigger_time,
+        }
+    }
+}
+
+/// What a scheduled event runs.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Callback {
+    /// `minecraft:function` for a function or `minecraft:function_tag` for a
+    /// function tag.
+    #[serde(rename = "Type")]
+    pub kind: String,
+    /// The resource location of the function or tag.
+    #[serde(rename = "Name")]
+    pub name: String,
+}
+
+impl Callback {
+    pub fn new(kind: String, name: String) -> Callback {
+        Callback { kind, name }
+    }
+}
+
+/// Generation settings of a world since 1.16.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct WorldGenSettings {
+    /// The [random level seed] used to generate consistent terrain.
+    ///
+    /// [random level seed]: https://minecraft.gamepedia.com/Seed_(level_generation)
+    #[serde(rename = "seed")]
+    pub seed: i64,
+    /// True if the map generator should place structures such as villages,
+    /// strongholds, and mineshafts.
+    #[serde(rename = "generate_features")]
+    pub generate_features: bool,
+    /// True if a bonus chest was placed near the spawn point when the world was created.
+    #[serde(rename = "bonus_chest")]
+    pub bonus_chest: bool,
+    /// Settings of each dimension, by dimension ID like `minecraft:overworld`.
+    #[serde(rename = "dimensions")]
+    pub dimensions: HashMap<String, WorldGenDimension>,
+    /// Settings of a customized world from before 1.13, kept when the world
+    /// is upgraded.
+    #[serde(rename = "legacy_custom_options")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legacy_custom_options: Option<String>,
+}
+
+impl WorldGenSettings {
+    pub fn new(
+        seed: i64,
+        generate_features: bool,
+        bonus_chest: bool,
+        dimensions: HashMap<String, WorldGenDimension>,
+    ) -> WorldGenSettings {
+        WorldGenSettings {
+            seed,
+            generate_features,
+            bonus_chest,
+            dimensions,
+            legacy_custom_options: None,
+        }
+    }
+}
+
+/// Generation settings of a single dimension.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct WorldGenDimension {
+    /// The dimension type: either the ID of a dimension type like
+    /// `minecraft:overworld`, or an inline compound of dimension type settings.
+    #[serde(rename = "type")]
+    pub dimension_type: Value,
+    /// The terrain generator.
+    #[serde(rename = "generator")]
+    pub generator: ChunkGenerator,
+}
+
+impl WorldGenDimension {
+    pub fn new(dimension_type: Value, generator: ChunkGenerator) -> WorldGenDimension {
+        WorldGenDimension {
+            dimension_type,
+            generator,
+        }
+    }
+}
+
+/// The terrain generator of a dimension.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ChunkGenerator {
+    /// The kind of generator: `minecraft:noise`, `minecraft:flat` or `minecraft:debug`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Generator settings. For the noise generator, the ID of the noise settings like
+    /// `minecraft:overworld` or an inline compound of noise settings. For the flat
+    /// generator, a compound of layers, biome and structures.
+    #[serde(rename = "settings")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<Value>,
+    /// Where the biomes come from. Only the noise generator has a biome source.
+    #[serde(rename = "biome_source")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub biome_source: Option<BiomeSource>,
+    /// The seed of the generator. Only before 1.19; since then the world seed is used.
+    #[serde(rename = "seed")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+}
+
+impl ChunkGenerator {
+    pub fn new(kind: String) -> ChunkGenerator {
+        ChunkGenerator {
+            kind,
+            settings: None,
+            biome_source: None,
+            seed: None,
+        }
+    }
+}
+
+/// The biome source of a noise generator.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct BiomeSource {
+    /// The kind of biome source: `minecraft:multi_noise`, `minecraft:the_end`,
+    /// `minecraft:fixed` or `minecraft:checkerboard`. Before 1.18 it can also be
+    /// `minecraft:vanilla_layered`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The preset of a multi noise source, `minecraft:overworld` or `minecraft:nether`.
+    /// Since 1.19.4 this is the ID of a biome parameter list.
+    #[serde(rename = "preset")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    /// The biome of a fixed source.
+    #[serde(rename = "biome")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub biome: Option<String>,
+    /// The biomes of a checkerboard source, or the biomes and their noise
+    /// parameters of a custom multi noise source.
+    #[serde(rename = "biomes")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub biomes: Option<Value>,
+    /// True if the vanilla layered source generates large biomes. Only before 1.18.
+    #[serde(rename = "large_biomes")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub large_biomes: Option<bool>,
+    /// The seed of the biome source. Only before 1.19; since then the world seed is used.
+    #[serde(rename = "seed")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+}
+
+impl BiomeSource {
+    pub fn new(kind: String) -> BiomeSource {
+        BiomeSource {
+            kind,
+            preset: None,
+            biome: None,
+            biomes: None,
+            large_biomes: None,
+            seed: None,
+        }
+    }
 }
 
 /// Represents one single bossbar in `CustomBossEvents`.
@@ -214,18 +630,12 @@ pub struct BossEvent {
 }
 
 /// Uuid of a single player.
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
-#[non_exhaustive]
-pub struct PlayerUuid {
-    /// The least significant bits of the player's Universally Unique IDentifier.
-    /// This is joined with M to form the player's unique ID.
-    #[serde(rename = "L")]
-    pub least: i64,
-    /// The most significant bits of the player's Universally Unique IDentifier.
-    /// This is joined with L to form the player's unique ID.
-    #[serde(rename = "M")]
-    pub most: i64,
-}
+///
+/// Written as a compound of the most and least significant bits `M` and `L`. An int
+/// array, as since 1.16, is also read.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(transparent)]
+pub struct PlayerUuid(#[serde(with = "crate::uuid::compound")] pub Uuid);
 
 /// Options for datapacks.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -255,7 +665,8 @@ pub struct EnderDimensionData {
     /// Data for the ender dragon fight. Only appears after the end is entered.
     ///
     /// `None` for the end is not entered.
-    #[serde(rename = "DragonFlight")]
+    #[serde(rename = "DragonFight")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub dragon_flight: Option<DragonFlight>,
 }
 
@@ -264,8 +675,11 @@ pub struct EnderDimensionData {
 #[non_exhaustive]
 pub struct DragonFlight {
     /// Location of the End's exit portal that the ender dragon flies to upon it's death
+    ///
+    /// `None` until the exit portal is placed.
     #[serde(rename = "ExitPortalLocation")]
-    pub exit_portal_location: ExitPortalLocation,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_portal_location: Option<ExitPortalLocation>,
     /// Contains a list of locations of the [End gateway portals] that haven't been spawned.
     ///
     /// [End gateway portals]: https://minecraft.gamepedia.com/End_gateway_portal
@@ -274,32 +688,90 @@ pub struct DragonFlight {
     /// If the dragon is currently alive. // todo: true for alive?
     #[serde(rename = "DragonKilled")]
     pub dragon_killed: bool,
-    /// The least significant bits of the current Ender Dragon's Universally Unique IDentifier.
-    /// This is joined with DragonUUIDMost to form the dragon's unique ID.
-    #[serde(rename = "DragonUUIDLeast")]
-    pub dragon_uuid_least: i64,
-    /// The most significant bits of the current Ender Dragon's Universally Unique IDentifier.
-    /// This is joined with DragonUUIDLeast to form the dragon's unique ID.
-    #[serde(rename = "DragonUUIDMost")]
-    pub dragon_uuid_most: i64,
+    /// The UUID of the current Ender Dragon, `None` if there is no dragon.
+    ///
+    /// Stored as `Dragon` since 20w12a and as `DragonUUIDMost` and `DragonUUIDLeast`
+    /// before; both are read, and `Data` writes the one of its data version.
+    #[serde(flatten, with = "dragon_uuid")]
+    pub dragon_uuid: Option<Uuid>,
     /// If the ender dragon has ever been defeated. Used to determine EXP given by dragon.
     #[serde(rename = "PreviouslyKilled")]
     pub previously_killed: bool,
+    /// True if the game should scan the end for existing end crystals and the dragon
+    /// when the end is next loaded, since 1.16.
+    #[serde(rename = "NeedsStateScanning")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub needs_state_scanning: Option<bool>,
+}
+
+mod dragon_uuid {
+    use crate::uuid::Uuid;
+    use serde::ser::{SerializeMap, Serializer};
+    use serde::Deserializer;
+
+    const FIELDS: &[&str] = &["DragonUUIDMost", "DragonUUIDLeast", "Dragon"];
+
+    pub fn serialize<S: Serializer>(uuid: &Option<Uuid>, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(uuid) = uuid {
+            map.serialize_entry(FIELDS[2], uuid)?;
+        }
+        map.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Uuid>, D::Error> {
+        crate::uuid::deserialize_halves(FIELDS, deserializer)
+    }
 }
 
 /// Location of the End's exit portal that the ender dragon flies to upon it's death
+///
+/// Stored as a compound of `X`, `Y` and `Z`, or since 1.20.5 as an int array of
+/// the three coordinates. Both are read; the compound is written.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(from = "ExitPortalRepr")]
 #[non_exhaustive]
 pub struct ExitPortalLocation {
     /// The X coordinate of the portal.
     #[serde(rename = "X")]
-    pub x: i8,
+    pub x: i32,
     /// The Y coordinate of the portal.
     #[serde(rename = "Y")]
-    pub y: i8,
+    pub y: i32,
     /// The Z coordinate of the portal.
     #[serde(rename = "Z")]
-    pub z: i8,
+    pub z: i32,
+}
+
+impl ExitPortalLocation {
+    pub fn new(x: i32, y: i32, z: i32) -> ExitPortalLocation {
+        ExitPortalLocation { x, y, z }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExitPortalRepr {
+    Compound {
+        #[serde(rename = "X")]
+        x: i32,
+        #[serde(rename = "Y")]
+        y: i32,
+        #[serde(rename = "Z")]
+        z: i32,
+    },
+    IntArray([i32; 3]),
+}
+
+impl From<ExitPortalRepr> for ExitPortalLocation {
+    fn from(repr: ExitPortalRepr) -> Self {
+        match repr {
+            ExitPortalRepr::Compound { x, y, z } => ExitPortalLocation { x, y, z },
+            ExitPortalRepr::IntArray([x, y, z]) => ExitPortalLocation { x, y, z },
+        }
+    }
 }
 
 /// Information about the Minecraft version the world was saved in.
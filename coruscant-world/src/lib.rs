@@ -4,3 +4,31 @@
 
 pub mod level_dat;
 pub mod entity;
+pub mod block_entity;
+pub mod item;
+pub mod player;
+pub mod chunk;
+pub mod palette;
+pub mod error;
+pub mod region;
+pub mod world;
+pub mod uuid;
+pub mod structure;
+pub mod schematic;
+pub mod litematic;
+pub mod saved_data;
+pub mod map_data;
+pub mod scoreboard;
+pub mod raids;
+pub mod poi;
+pub mod legacy_ids;
+pub mod upgrade;
+pub mod prune;
+pub mod lighting;
+mod extra;
+mod parts;
+
+pub use error::{Error, Result};
+pub use chunk::BlockState;
+pub use world::{BlockPos, ChunkPos, World};
+pub use uuid::Uuid;
@@ -0,0 +1,717 @@
+//! Heightmaps and light of chunks, computed from their blocks.
+//!
+//! Chunks store for each column the height of the highest block of a few
+//! kinds, and for each block the light coming from the sky and from light
+//! emitting blocks, 4 bits each. Both go stale when blocks are edited.
+//! They can be computed again with `recompute_heightmaps` and
+//! `recompute_light`, or the light dropped with `strip_light` so that the
+//! game computes it again when the chunk loads.
+//!
+//! How blocks take part is looked up in a `BlockTable`. The vanilla table is
+//! an approximation: blocks are either full cubes or let light through, and
+//! light does not cross chunk borders, so the edges of caves open to a
+//! neighbouring chunk come out darker than the game would light them.
+//!
+//! Ref: https://minecraft.gamepedia.com/Light
+use crate::chunk::{BlockState, Chunk};
+use crate::error::Result;
+use crate::palette::{self, Packing, PalettedContainer};
+use std::collections::{HashMap, VecDeque};
+
+/// How a block takes part in heightmaps and light.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BlockTraits {
+    /// Whether the block is empty space.
+    pub air: bool,
+    /// Light levels lost passing through the block, 15 for opaque blocks.
+    pub opacity: u8,
+    /// Light level the block emits.
+    pub light_emission: u8,
+    /// Whether the block has a collision box, so that mobs and falling
+    /// blocks rest on it.
+    pub motion_blocking: bool,
+    /// Whether the block holds a fluid, by itself or waterlogged.
+    pub fluid: bool,
+    /// Whether the block is in the `minecraft:leaves` tag.
+    pub leaves: bool,
+}
+
+impl BlockTraits {
+    /// Empty space, like air and cave air.
+    pub const AIR: BlockTraits = BlockTraits {
+        air: true,
+        opacity: 0,
+        light_emission: 0,
+        motion_blocking: false,
+        fluid: false,
+        leaves: false,
+    };
+    /// A full block that light does not pass, like stone.
+    pub const OPAQUE: BlockTraits = BlockTraits {
+        air: false,
+        opacity: 15,
+        light_emission: 0,
+        motion_blocking: true,
+        fluid: false,
+        leaves: false,
+    };
+    /// A solid block that light passes, like glass or stairs.
+    pub const TRANSPARENT: BlockTraits = BlockTraits {
+        air: false,
+        opacity: 0,
+        light_emission: 0,
+        motion_blocking: true,
+        fluid: false,
+        leaves: false,
+    };
+    /// A block without collision that light passes, like flowers and
+    /// torches.
+    pub const PASSABLE: BlockTraits = BlockTraits {
+        air: false,
+        opacity: 0,
+        light_emission: 0,
+        motion_blocking: false,
+        fluid: false,
+        leaves: false,
+    };
+
+    /// These traits with light dimmed by `opacity` levels.
+    pub fn with_opacity(mut self, opacity: u8) -> BlockTraits {
+        self.opacity = opacity.min(15);
+        self
+    }
+
+    /// These traits with a light level emitted.
+    pub fn with_light_emission(mut self, light_emission: u8) -> BlockTraits {
+        self.light_emission = light_emission.min(15);
+        self
+    }
+
+    /// These traits for a fluid.
+    pub fn with_fluid(mut self) -> BlockTraits {
+        self.fluid = true;
+        self
+    }
+
+    /// These traits for leaves.
+    pub fn with_leaves(mut self) -> BlockTraits {
+        self.leaves = true;
+        self
+    }
+}
+
+/// Traits of blocks by their name, or by the end of their name.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::lighting::{BlockTable, BlockTraits};
+/// use coruscant_world::BlockState;
+///
+/// let mut table = BlockTable::vanilla();
+/// let lamp = BlockTraits::OPAQUE.with_light_emission(15);
+/// table.insert("mymod:lamp", lamp);
+///
+/// let traits = table.get(&BlockState::new("mymod:lamp".to_string()));
+/// assert_eq!(traits.light_emission, 15);
+/// let glass = table.get(&BlockState::new("minecraft:red_stained_glass".to_string()));
+/// assert_eq!(glass.opacity, 0);
+/// let stone = table.get(&BlockState::new("minecraft:stone".to_string()));
+/// assert_eq!(stone, BlockTraits::OPAQUE);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockTable {
+    names: HashMap<String, BlockTraits>,
+    suffixes: Vec<(String, BlockTraits)>,
+    default: BlockTraits,
+}
+
+impl BlockTable {
+    /// A table knowing only air; any other block is opaque.
+    pub fn new() -> BlockTable {
+        let mut table = BlockTable {
+            names: HashMap::new(),
+            suffixes: Vec::new(),
+            default: BlockTraits::OPAQUE,
+        };
+        for name in &["air", "cave_air", "void_air"] {
+            table.insert(&format!("minecraft:{}", name), BlockTraits::AIR);
+        }
+        table
+    }
+
+    /// A table of the vanilla blocks that are not opaque or emit light.
+    pub fn vanilla() -> BlockTable {
+        let mut table = BlockTable::new();
+        let water = BlockTraits::PASSABLE.with_opacity(1).with_fluid();
+        for (traits, names) in &[
+            (water, WATER_NAMES),
+            (water.with_light_emission(15), &["lava"][..]),
+            (BlockTraits::PASSABLE, PASSABLE_NAMES),
+            (BlockTraits::TRANSPARENT, TRANSPARENT_NAMES),
+            (BlockTraits::TRANSPARENT.with_opacity(1), TRANSLUCENT_NAMES),
+        ] {
+            for name in *names {
+                table.insert(&format!("minecraft:{}", name), *traits);
+            }
+        }
+        for suffix in PASSABLE_SUFFIXES {
+            table.insert_suffix(suffix, BlockTraits::PASSABLE);
+        }
+        for suffix in TRANSPARENT_SUFFIXES {
+            table.insert_suffix(suffix, BlockTraits::TRANSPARENT);
+        }
+        let leaves = BlockTraits::TRANSPARENT.with_opacity(1).with_leaves();
+        table.insert_suffix("_leaves", leaves);
+        for &(name, level) in LIGHT_EMISSION {
+            let name = format!("minecraft:{}", name);
+            let traits = table.get(&BlockState::new(name.clone()));
+            table.insert(&name, traits.with_light_emission(level));
+        }
+        table
+    }
+
+    /// Set the traits of the block with this name, like
+    /// `minecraft:glowstone`.
+    pub fn insert(&mut self, name: &str, traits: BlockTraits) {
+        self.names.insert(name.to_owned(), traits);
+    }
+
+    /// Set the traits of blocks whose name ends with `suffix`, like
+    /// `_stairs`, unless they are inserted by name. Suffixes inserted first
+    /// are tried first.
+    pub fn insert_suffix(&mut self, suffix: &str, traits: BlockTraits) {
+        self.suffixes.push((suffix.to_owned(), traits));
+    }
+
+    /// Set the traits of blocks that are neither inserted by name nor match
+    /// a suffix.
+    pub fn set_default(&mut self, traits: BlockTraits) {
+        self.default = traits;
+    }
+
+    /// Traits of the block state.
+    ///
+    /// A few properties are taken into account whatever the block: blocks
+    /// with `waterlogged=true` hold a fluid, blocks with `lit=false` emit no
+    /// light, and slabs with `type=double` are opaque.
+    pub fn get(&self, state: &BlockState) -> BlockTraits {
+        let mut traits = match self.names.get(&state.name) {
+            Some(traits) => *traits,
+            None => self
+                .suffixes
+                .iter()
+                .find(|(suffix, _)| state.name.ends_with(suffix.as_str()))
+                .map_or(self.default, |(_, traits)| *traits),
+        };
+        if state.property("waterlogged") == Some("true") {
+            traits.fluid = true;
+            traits.opacity = traits.opacity.max(1);
+        }
+        if state.property("lit") == Some("false") {
+            traits.light_emission = 0;
+        }
+        if state.name.ends_with("_slab") && state.property("type") == Some("double") {
+            traits.opacity = 15;
+        }
+        traits
+    }
+}
+
+impl Default for BlockTable {
+    fn default() -> BlockTable {
+        BlockTable::new()
+    }
+}
+
+// Blocks that are always full of water.
+const WATER_NAMES: &[&str] = &[
+    "water",
+    "bubble_column",
+    "seagrass",
+    "tall_seagrass",
+    "kelp",
+    "kelp_plant",
+];
+
+const PASSABLE_NAMES: &[&str] = &[
+    "short_grass",
+    "grass",
+    "tall_grass",
+    "fern",
+    "large_fern",
+    "dead_bush",
+    "dandelion",
+    "poppy",
+    "blue_orchid",
+    "allium",
+    "azure_bluet",
+    "oxeye_daisy",
+    "cornflower",
+    "lily_of_the_valley",
+    "wither_rose",
+    "sunflower",
+    "lilac",
+    "rose_bush",
+    "peony",
+    "torchflower",
+    "pitcher_plant",
+    "pink_petals",
+    "sugar_cane",
+    "wheat",
+    "carrots",
+    "potatoes",
+    "beetroots",
+    "nether_wart",
+    "sweet_berry_bush",
+    "vine",
+    "glow_lichen",
+    "hanging_roots",
+    "cave_vines",
+    "cave_vines_plant",
+    "weeping_vines",
+    "weeping_vines_plant",
+    "twisting_vines",
+    "twisting_vines_plant",
+    "crimson_roots",
+    "warped_roots",
+    "nether_sprouts",
+    "brown_mushroom",
+    "red_mushroom",
+    "crimson_fungus",
+    "warped_fungus",
+    "cobweb",
+    "fire",
+    "soul_fire",
+    "torch",
+    "wall_torch",
+    "soul_torch",
+    "soul_wall_torch",
+    "redstone_torch",
+    "redstone_wall_torch",
+    "redstone_wire",
+    "tripwire",
+    "tripwire_hook",
+    "lever",
+    "snow",
+    "structure_void",
+    "light",
+    "nether_portal",
+    "end_portal",
+    "end_gateway",
+];
+
+const PASSABLE_SUFFIXES: &[&str] = &[
+    "_sapling",
+    "_tulip",
+    "_button",
+    "_pressure_plate",
+    "rail",
+    "_sign",
+    "_banner",
+    "_coral",
+    "_coral_fan",
+];
+
+const TRANSPARENT_NAMES: &[&str] = &[
+    "iron_bars",
+    "chain",
+    "lantern",
+    "soul_lantern",
+    "ladder",
+    "scaffolding",
+    "flower_pot",
+    "lectern",
+    "enchanting_table",
+    "anvil",
+    "chipped_anvil",
+    "damaged_anvil",
+    "bell",
+    "brewing_stand",
+    "cauldron",
+    "water_cauldron",
+    "lava_cauldron",
+    "powder_snow_cauldron",
+    "hopper",
+    "campfire",
+    "soul_campfire",
+    "end_rod",
+    "lightning_rod",
+    "cactus",
+    "beacon",
+    "conduit",
+    "chest",
+    "trapped_chest",
+    "ender_chest",
+    "dragon_egg",
+    "end_portal_frame",
+    "sculk_sensor",
+    "amethyst_cluster",
+    "sea_pickle",
+    "composter",
+    "grindstone",
+    "stonecutter",
+    "daylight_detector",
+    "repeater",
+    "comparator",
+    "bamboo",
+    "dirt_path",
+    "farmland",
+];
+
+// Solid blocks that dim light like water.
+const TRANSLUCENT_NAMES: &[&str] = &["ice", "frosted_ice", "honey_block", "slime_block"];
+
+const TRANSPARENT_SUFFIXES: &[&str] = &[
+    "glass",
+    "glass_pane",
+    "_slab",
+    "_stairs",
+    "_fence",
+    "_fence_gate",
+    "_wall",
+    "_door",
+    "_trapdoor",
+    "_carpet",
+    "_bed",
+    "_head",
+    "_skull",
+    "_candle",
+    "candle",
+];
+
+// Light levels of the vanilla blocks that emit light, lit where they have
+// a `lit` property.
+const LIGHT_EMISSION: &[(&str, u8)] = &[
+    ("glowstone", 15),
+    ("sea_lantern", 15),
+    ("jack_o_lantern", 15),
+    ("beacon", 15),
+    ("conduit", 15),
+    ("end_gateway", 15),
+    ("end_portal", 15),
+    ("fire", 15),
+    ("lantern", 15),
+    ("campfire", 15),
+    ("shroomlight", 15),
+    ("redstone_lamp", 15),
+    ("ochre_froglight", 15),
+    ("verdant_froglight", 15),
+    ("pearlescent_froglight", 15),
+    ("torch", 14),
+    ("wall_torch", 14),
+    ("end_rod", 14),
+    ("furnace", 13),
+    ("blast_furnace", 13),
+    ("smoker", 13),
+    ("nether_portal", 11),
+    ("soul_torch", 10),
+    ("soul_wall_torch", 10),
+    ("soul_lantern", 10),
+    ("soul_fire", 10),
+    ("soul_campfire", 10),
+    ("crying_obsidian", 10),
+    ("redstone_ore", 9),
+    ("deepslate_redstone_ore", 9),
+    ("redstone_torch", 7),
+    ("redstone_wall_torch", 7),
+    ("glow_lichen", 7),
+    ("enchanting_table", 7),
+    ("ender_chest", 7),
+    ("amethyst_cluster", 5),
+    ("magma_block", 3),
+    ("brewing_stand", 1),
+    ("brown_mushroom", 1),
+    ("dragon_egg", 1),
+    ("end_portal_frame", 1),
+    ("sculk_sensor", 1),
+];
+
+/// Compute the heightmaps of the chunk from its blocks, in a dimension
+/// `height` blocks high from the bottom of the chunk at `yPos`, like 384 in
+/// the overworld. See `Dimension::y_range`.
+///
+/// The height sets the bits of each value, so it has to be the one of the
+/// dimension, not only of the sections that have blocks. Blocks of sections
+/// above it are left out.
+///
+/// `MOTION_BLOCKING`, `MOTION_BLOCKING_NO_LEAVES`, `OCEAN_FLOOR` and
+/// `WORLD_SURFACE` are always written; the heightmaps used during world
+/// generation only if the chunk has them.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::chunk::{Chunk, Section};
+/// use coruscant_world::lighting::{heightmap_values, recompute_heightmaps, BlockTable};
+/// use coruscant_world::palette::PalettedContainer;
+/// use coruscant_world::BlockState;
+///
+/// let mut chunk = Chunk::new(3700, 0, -4, 0, "minecraft:full".to_string());
+/// for y in -4..20 {
+///     let mut blocks = PalettedContainer::blocks(BlockState::air());
+///     if y == 3 {
+///         let leaves = BlockState::new("minecraft:oak_leaves".to_string());
+///         blocks.set(0, 5, 0, BlockState::new("minecraft:stone".to_string()));
+///         blocks.set(0, 9, 0, leaves);
+///     }
+///     let mut section = Section::new(y);
+///     section.block_states = Some(blocks.to_block_states());
+///     chunk.sections.push(section);
+/// }
+/// recompute_heightmaps(&mut chunk, &BlockTable::vanilla(), 384).unwrap();
+///
+/// // heights count from the bottom of the world, at -64
+/// let world_surface = heightmap_values(chunk.heightmaps.world_surface.as_ref().unwrap(), 384);
+/// assert_eq!(world_surface[0], 58 + 64);
+/// let no_leaves = chunk.heightmaps.motion_blocking_no_leaves.as_ref().unwrap();
+/// assert_eq!(heightmap_values(no_leaves, 384)[0], 54 + 64);
+/// assert_eq!(world_surface[1], 0);
+/// ```
+pub fn recompute_heightmaps(chunk: &mut Chunk, table: &BlockTable, height: usize) -> Result<()> {
+    let blocks = Blocks::new(chunk, table, height)?;
+    let bits = heightmap_bits(height);
+    let heightmap = |kind: fn(&BlockTraits) -> bool| {
+        let values: Vec<u16> = (0..256)
+            .map(|i| blocks.highest(i % 16, i / 16, kind))
+            .collect();
+        Some(palette::pack(&values, bits, Packing::Aligned))
+    };
+    let heightmaps = &mut chunk.heightmaps;
+    heightmaps.motion_blocking = heightmap(|t| t.motion_blocking || t.fluid);
+    heightmaps.motion_blocking_no_leaves =
+        heightmap(|t| (t.motion_blocking || t.fluid) && !t.leaves);
+    heightmaps.ocean_floor = heightmap(|t| t.motion_blocking);
+    heightmaps.world_surface = heightmap(|t| !t.air);
+    if heightmaps.ocean_floor_wg.is_some() {
+        heightmaps.ocean_floor_wg = heightmaps.ocean_floor.clone();
+    }
+    if heightmaps.world_surface_wg.is_some() {
+        heightmaps.world_surface_wg = heightmaps.world_surface.clone();
+    }
+    Ok(())
+}
+
+/// The 256 heights of a packed heightmap of a world `height` blocks high,
+/// indexed `z * 16 + x`.
+pub fn heightmap_values(heightmap: &[i64], height: usize) -> Vec<u16> {
+    let bits = heightmap_bits(height);
+    palette::unpack(heightmap, bits, 256, Packing::Aligned).unwrap_or_else(|_| vec![0; 256])
+}
+
+/// Compute the block light and, if the dimension has a sky, the sky light
+/// of the chunk from its blocks, and mark its light as calculated.
+///
+/// Light is spread inside the chunk only; see the module documentation.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::chunk::{Chunk, Section};
+/// use coruscant_world::lighting::{recompute_light, BlockTable};
+/// use coruscant_world::palette::PalettedContainer;
+/// use coruscant_world::BlockState;
+///
+/// let mut blocks = PalettedContainer::blocks(BlockState::air());
+/// blocks.set(8, 8, 8, BlockState::new("minecraft:glowstone".to_string()));
+/// let mut section = Section::new(0);
+/// section.block_states = Some(blocks.to_block_states());
+/// let mut chunk = Chunk::new(3700, 0, 0, 0, "minecraft:full".to_string());
+/// chunk.sections.push(section);
+///
+/// recompute_light(&mut chunk, &BlockTable::vanilla(), true).unwrap();
+/// let section = &chunk.sections[0];
+/// let light = |x: usize, y: usize, z: usize| {
+///     let i = (y * 16 + z) * 16 + x;
+///     (section.block_light.as_ref().unwrap()[i / 2] as u8 >> (i % 2 * 4)) & 15
+/// };
+/// assert_eq!(light(8, 8, 8), 15);
+/// assert_eq!(light(8, 8, 10), 13);
+/// assert_eq!(light(0, 0, 0), 0);
+/// assert_eq!(chunk.sections[0].sky_light.as_ref().unwrap()[0], -1);
+/// assert_eq!(chunk.is_light_on, Some(true));
+/// ```
+pub fn recompute_light(chunk: &mut Chunk, table: &BlockTable, sky_light: bool) -> Result<()> {
+    let blocks = Blocks::new(chunk, table, populated_height(chunk))?;
+    let len = blocks.traits.len();
+    let mut block = vec![0u8; len];
+    let mut queue = VecDeque::new();
+    for (i, traits) in blocks.traits.iter().enumerate() {
+        if traits.light_emission > 0 {
+            block[i] = traits.light_emission;
+            queue.push_back(i);
+        }
+    }
+    blocks.spread(&mut block, queue);
+
+    let mut sky = vec![0u8; len];
+    if sky_light {
+        let mut queue = VecDeque::new();
+        for column in 0..256 {
+            let mut level = 15u8;
+            for y in (0..blocks.height).rev() {
+                let i = y * 256 + column;
+                let opacity = blocks.traits[i].opacity;
+                if level < 15 || opacity > 0 {
+                    level = level.saturating_sub(opacity.max(1));
+                }
+                if level == 0 {
+                    break;
+                }
+                sky[i] = level;
+                queue.push_back(i);
+            }
+        }
+        blocks.spread(&mut sky, queue);
+    }
+
+    let top = blocks.bottom + (blocks.height / 16) as i32;
+    for section in &mut chunk.sections {
+        let y = i32::from(section.y);
+        if y < blocks.bottom || y >= top {
+            section.block_light = None;
+            let above = y >= top && sky_light;
+            section.sky_light = if above { Some(vec![-1; 2048]) } else { None };
+            continue;
+        }
+        let start = (y - blocks.bottom) as usize * 4096;
+        let range = start..start + 4096;
+        let block = &block[range.clone()];
+        let has_block_light = block.iter().any(|&level| level > 0);
+        section.block_light = if has_block_light {
+            Some(nibbles(block))
+        } else {
+            None
+        };
+        section.sky_light = if sky_light {
+            Some(nibbles(&sky[range]))
+        } else {
+            None
+        };
+    }
+    chunk.is_light_on = Some(true);
+    Ok(())
+}
+
+/// Remove the light of the chunk and mark it as not calculated, so that the
+/// game lights the chunk again when it loads.
+pub fn strip_light(chunk: &mut Chunk) {
+    for section in &mut chunk.sections {
+        section.block_light = None;
+        section.sky_light = None;
+    }
+    chunk.is_light_on = Some(false);
+}
+
+// Height from the bottom of the chunk to the top of its highest section
+// with blocks, which is all of the world in chunks the game saved.
+fn populated_height(chunk: &Chunk) -> usize {
+    let bottom = chunk.y_pos;
+    let top = chunk
+        .sections
+        .iter()
+        .filter(|s| s.block_states.is_some())
+        .map(|s| i32::from(s.y) + 1)
+        .max()
+        .unwrap_or(bottom)
+        .max(bottom);
+    (top - bottom) as usize * 16
+}
+
+// Traits of all blocks of a chunk, from the bottom of the world up to
+// `height` blocks above, indexed `(y * 16 + z) * 16 + x`.
+struct Blocks {
+    bottom: i32,
+    height: usize,
+    traits: Vec<BlockTraits>,
+}
+
+impl Blocks {
+    fn new(chunk: &Chunk, table: &BlockTable, height: usize) -> Result<Blocks> {
+        let bottom = chunk.y_pos;
+        let height = height / 16 * 16;
+        let top = bottom + (height / 16) as i32;
+        let mut traits = vec![BlockTraits::AIR; height * 256];
+        let mut cache: HashMap<BlockState, BlockTraits> = HashMap::new();
+        for section in &chunk.sections {
+            let y = i32::from(section.y);
+            let block_states = match &section.block_states {
+                Some(block_states) if y >= bottom && y < top => block_states,
+                _ => continue,
+            };
+            let container = PalettedContainer::from_block_states(block_states)?;
+            let start = (y - bottom) as usize * 4096;
+            for (i, slot) in traits[start..start + 4096].iter_mut().enumerate() {
+                let state = container
+                    .get(i % 16, i / 256, i / 16 % 16)
+                    .expect("inside the section");
+                *slot = match cache.get(state) {
+                    Some(traits) => *traits,
+                    None => *cache.entry(state.clone()).or_insert(table.get(state)),
+                };
+            }
+        }
+        Ok(Blocks {
+            bottom,
+            height,
+            traits,
+        })
+    }
+
+    // Height above the bottom of the top of the highest block of a kind in
+    // a column, or 0 if there is none.
+    fn highest(&self, x: usize, z: usize, kind: fn(&BlockTraits) -> bool) -> u16 {
+        (0..self.height)
+            .rev()
+            .find(|y| kind(&self.traits[(y * 16 + z) * 16 + x]))
+            .map_or(0, |y| y as u16 + 1)
+    }
+
+    // Flood light from the queued blocks, each step losing at least a
+    // level, or the opacity of the block entered.
+    fn spread(&self, light: &mut [u8], mut queue: VecDeque<usize>) {
+        while let Some(i) = queue.pop_front() {
+            let (x, z, y) = (i % 16, i / 16 % 16, i / 256);
+            let mut neighbours = Vec::with_capacity(6);
+            if x > 0 {
+                neighbours.push(i - 1);
+            }
+            if x < 15 {
+                neighbours.push(i + 1);
+            }
+            if z > 0 {
+                neighbours.push(i - 16);
+            }
+            if z < 15 {
+                neighbours.push(i + 16);
+            }
+            if y > 0 {
+                neighbours.push(i - 256);
+            }
+            if y + 1 < self.height {
+                neighbours.push(i + 256);
+            }
+            for n in neighbours {
+                let level = light[i].saturating_sub(self.traits[n].opacity.max(1));
+                if level > light[n] {
+                    light[n] = level;
+                    queue.push_back(n);
+                }
+            }
+        }
+    }
+}
+
+// Bits of each height in a heightmap, enough for heights 0 to `height`.
+fn heightmap_bits(height: usize) -> u32 {
+    (usize::BITS - height.leading_zeros()).max(1)
+}
+
+// Light levels packed two in a byte, the first in the low half.
+fn nibbles(levels: &[u8]) -> Vec<i8> {
+    levels
+        .chunks(2)
+        .map(|pair| (pair[0] | pair[1] << 4) as i8)
+        .collect()
+}
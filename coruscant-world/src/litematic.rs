@@ -0,0 +1,364 @@
+//! Litematica schematics, the `.litematic` files of the Litematica mod.
+//!
+//! A Litematica schematic is a gzip compressed NBT file with metadata and
+//! any number of named regions. Each region is a box of blocks with its own
+//! palette and indices packed back to back into longs, like the chunk
+//! sections before 1.16, along with its block entities, entities and
+//! scheduled ticks. A region grows from its position along its size, which
+//! is negative for axes it extends towards negative coordinates.
+//!
+//! Ref: https://github.com/maruohon/litematica
+use crate::block_entity::BlockEntity;
+use crate::chunk::{option_as_nbt_array, BlockState};
+use crate::entity::Entity;
+use crate::error::{Error, Result};
+use crate::palette::{self, Packing, PalettedContainer};
+use crate::schematic::{Schematic, SchematicBlockEntity, SchematicEntity};
+use crate::world::BlockPos;
+use coruscant_nbt::{as_nbt_array, Compression, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// Version of the Litematica format written by `Litematic::new`.
+pub const LITEMATIC_VERSION: i32 = 6;
+
+/// A Litematica schematic.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::litematic::{Litematic, Region};
+/// use coruscant_world::{BlockPos, BlockState};
+///
+/// let mut litematic = Litematic::new(3465, "House".to_string());
+/// let mut region = Region::new(BlockPos::new(0, 0, 0), BlockPos::new(-2, 3, 2));
+/// let mut blocks = region.blocks().unwrap();
+/// blocks.set(1, 2, 0, BlockState::new("minecraft:stone".to_string()));
+/// region.set_blocks(&blocks);
+/// litematic.regions.insert("House".to_string(), region);
+/// litematic.update_metadata().unwrap();
+///
+/// assert_eq!(litematic.metadata.total_blocks, 1);
+/// assert_eq!(litematic.regions["House"].min_corner(), BlockPos::new(-1, 0, 0));
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename = "")]
+#[non_exhaustive]
+pub struct Litematic {
+    /// Version of the game the schematic was saved in.
+    #[serde(rename = "MinecraftDataVersion")]
+    pub data_version: i32,
+    /// Version of the Litematica format.
+    #[serde(rename = "Version")]
+    pub version: i32,
+    /// Minor version of the Litematica format, since version 6.
+    #[serde(
+        rename = "SubVersion",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub sub_version: Option<i32>,
+    #[serde(rename = "Metadata")]
+    pub metadata: Metadata,
+    /// Regions by name.
+    #[serde(rename = "Regions")]
+    pub regions: BTreeMap<String, Region>,
+}
+
+/// Description of a Litematica schematic, shown in its browser.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Metadata {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Author")]
+    pub author: String,
+    #[serde(rename = "Description")]
+    pub description: String,
+    /// Number of regions.
+    #[serde(rename = "RegionCount")]
+    pub region_count: i32,
+    /// Number of blocks in the boxes of all regions.
+    #[serde(rename = "TotalVolume")]
+    pub total_volume: i32,
+    /// Number of blocks other than air in all regions.
+    #[serde(rename = "TotalBlocks")]
+    pub total_blocks: i32,
+    /// Time of creation, in milliseconds since the Unix epoch.
+    #[serde(rename = "TimeCreated")]
+    pub time_created: i64,
+    /// Time of the last change, in milliseconds since the Unix epoch.
+    #[serde(rename = "TimeModified")]
+    pub time_modified: i64,
+    /// Size of the box enclosing all regions.
+    #[serde(rename = "EnclosingSize")]
+    pub enclosing_size: BlockPos,
+    /// Preview image as ARGB pixels, usually 140×140.
+    #[serde(
+        rename = "PreviewImageData",
+        serialize_with = "option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub preview_image_data: Option<Vec<i32>>,
+}
+
+/// A box of blocks in a Litematica schematic.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Region {
+    /// Position of the corner the region grows from, relative to the
+    /// schematic origin.
+    #[serde(rename = "Position")]
+    pub position: BlockPos,
+    /// Size of the region along each axis, negative for axes it extends
+    /// towards negative coordinates from its position.
+    #[serde(rename = "Size")]
+    pub size: BlockPos,
+    /// Block states in the region.
+    #[serde(rename = "BlockStatePalette")]
+    pub palette: Vec<BlockState>,
+    /// Palette indices of the blocks packed into longs, with at least 2 bits
+    /// each and spanning longs. Indexed from the lowest corner, ordered by
+    /// Y, then Z, then X.
+    #[serde(rename = "BlockStates", serialize_with = "as_nbt_array")]
+    pub block_states: Vec<i64>,
+    /// Block entities, with coordinates relative to the lowest corner.
+    #[serde(rename = "TileEntities", default)]
+    pub tile_entities: Vec<BlockEntity>,
+    /// Entities, with positions relative to the lowest corner.
+    #[serde(rename = "Entities", default)]
+    pub entities: Vec<Entity>,
+    #[serde(rename = "PendingBlockTicks", default)]
+    pub pending_block_ticks: Vec<Value>,
+    #[serde(rename = "PendingFluidTicks", default)]
+    pub pending_fluid_ticks: Vec<Value>,
+}
+
+impl Litematic {
+    pub fn new(data_version: i32, name: String) -> Litematic {
+        Litematic {
+            data_version,
+            version: LITEMATIC_VERSION,
+            sub_version: Some(1),
+            metadata: Metadata::new(name),
+            regions: BTreeMap::new(),
+        }
+    }
+
+    /// Read a Litematica schematic file.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Litematic> {
+        let file = BufReader::new(File::open(path)?);
+        Ok(coruscant_nbt::from_gzip_reader(file)?)
+    }
+
+    /// Write a Litematica schematic file.
+    ///
+    /// The data is written to a temporary file next to `path` first, which
+    /// then replaces the old file.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let mut file = BufWriter::new(File::create(&tmp)?);
+        coruscant_nbt::to_gzip_writer(&mut file, self, Compression::default())?;
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Count the regions, volume and blocks, and compute the enclosing
+    /// size for the metadata.
+    pub fn update_metadata(&mut self) -> Result<()> {
+        let mut total_volume = 0;
+        let mut total_blocks = 0;
+        let mut min = [i32::MAX; 3];
+        let mut max = [i32::MIN; 3];
+        for region in self.regions.values() {
+            let blocks = region.blocks()?;
+            let [size_x, size_y, size_z] = blocks.size();
+            for y in 0..size_y {
+                for z in 0..size_z {
+                    for x in 0..size_x {
+                        let block = blocks.get(x, y, z).expect("inside the region");
+                        if block.name != "minecraft:air" {
+                            total_blocks += 1;
+                        }
+                    }
+                }
+            }
+            total_volume += (size_x * size_y * size_z) as i32;
+            let low = region.min_corner();
+            let low = [low.x, low.y, low.z];
+            for axis in 0..3 {
+                min[axis] = min[axis].min(low[axis]);
+                max[axis] = max[axis].max(low[axis] + blocks.size()[axis] as i32);
+            }
+        }
+        self.metadata.region_count = self.regions.len() as i32;
+        self.metadata.total_volume = total_volume;
+        self.metadata.total_blocks = total_blocks;
+        self.metadata.enclosing_size = if self.regions.is_empty() {
+            BlockPos::new(0, 0, 0)
+        } else {
+            BlockPos::new(max[0] - min[0], max[1] - min[1], max[2] - min[2])
+        };
+        Ok(())
+    }
+}
+
+impl Metadata {
+    pub fn new(name: String) -> Metadata {
+        Metadata {
+            name,
+            author: String::new(),
+            description: String::new(),
+            region_count: 0,
+            total_volume: 0,
+            total_blocks: 0,
+            time_created: 0,
+            time_modified: 0,
+            enclosing_size: BlockPos::new(0, 0, 0),
+            preview_image_data: None,
+        }
+    }
+}
+
+impl Region {
+    /// A region filled with air.
+    pub fn new(position: BlockPos, size: BlockPos) -> Region {
+        let mut region = Region {
+            position,
+            size,
+            palette: Vec::new(),
+            block_states: Vec::new(),
+            tile_entities: Vec::new(),
+            entities: Vec::new(),
+            pending_block_ticks: Vec::new(),
+            pending_fluid_ticks: Vec::new(),
+        };
+        let size = region.box_size();
+        region.set_blocks(&PalettedContainer::with_size(size, 2, BlockState::air()));
+        region
+    }
+
+    /// Lowest corner of the region, relative to the schematic origin.
+    pub fn min_corner(&self) -> BlockPos {
+        let low = |position: i32, size: i32| {
+            if size < 0 {
+                position + size + 1
+            } else {
+                position
+            }
+        };
+        BlockPos::new(
+            low(self.position.x, self.size.x),
+            low(self.position.y, self.size.y),
+            low(self.position.z, self.size.z),
+        )
+    }
+
+    /// Decode the blocks, indexed from the lowest corner.
+    pub fn blocks(&self) -> Result<PalettedContainer<BlockState>> {
+        PalettedContainer::from_packed_with_size(
+            self.box_size(),
+            2,
+            self.palette.clone(),
+            Some(&self.block_states),
+            Packing::Spanning,
+        )
+    }
+
+    /// Encode the blocks into the palette and packed indices.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the container does not have the size of the region.
+    pub fn set_blocks(&mut self, blocks: &PalettedContainer<BlockState>) {
+        let size = self.box_size();
+        assert_eq!(blocks.size(), size, "container size differs from region");
+        let (palette, data) = blocks.to_packed(Packing::Spanning);
+        // Litematica keeps the indices even when all blocks are the same
+        let data = data.unwrap_or_else(|| {
+            palette::pack(&vec![0; size[0] * size[1] * size[2]], 2, Packing::Spanning)
+        });
+        self.palette = palette;
+        self.block_states = data;
+    }
+
+    /// Convert into a schematic, for example to place it into a world with
+    /// `Schematic::to_structure`.
+    pub fn to_schematic(&self, data_version: i32) -> Result<Schematic> {
+        let blocks = self.blocks()?;
+        let [size_x, size_y, size_z] = blocks.size();
+        let mut schematic =
+            Schematic::new(data_version, [size_x as i32, size_y as i32, size_z as i32]);
+        schematic.offset = {
+            let low = self.min_corner();
+            [low.x, low.y, low.z]
+        };
+        schematic.palette = blocks.palette().to_vec();
+        let indices: HashMap<&BlockState, u32> = (schematic.palette.iter())
+            .enumerate()
+            .map(|(i, state)| (state, i as u32))
+            .collect();
+        for y in 0..size_y {
+            for z in 0..size_z {
+                for x in 0..size_x {
+                    let i = (y * size_z + z) * size_x + x;
+                    schematic.blocks[i] = indices[blocks.get(x, y, z).expect("inside the region")];
+                }
+            }
+        }
+        for block_entity in &self.tile_entities {
+            let pos = block_entity
+                .pos()
+                .ok_or(Error::CorruptSchematic("block entity without position"))?;
+            let mut nbt = block_entity.to_value()?;
+            if let Value::Compound(compound) = &mut nbt {
+                for key in &["x", "y", "z"] {
+                    compound.remove(*key);
+                }
+            }
+            schematic
+                .block_entities
+                .push(SchematicBlockEntity::new([pos.x, pos.y, pos.z], nbt));
+        }
+        for entity in &self.entities {
+            let pos = entity
+                .entity()
+                .map(|e| e.pos)
+                .or_else(|| unknown_entity_pos(entity))
+                .ok_or(Error::CorruptSchematic("entity without position"))?;
+            schematic
+                .entities
+                .push(SchematicEntity::new(pos, entity.clone()));
+        }
+        Ok(schematic)
+    }
+
+    // Size of the box of blocks along each axis.
+    fn box_size(&self) -> [usize; 3] {
+        [
+            self.size.x.unsigned_abs() as usize,
+            self.size.y.unsigned_abs() as usize,
+            self.size.z.unsigned_abs() as usize,
+        ]
+    }
+}
+
+fn unknown_entity_pos(entity: &Entity) -> Option<[f64; 3]> {
+    match entity {
+        Entity::Unknown(Value::Compound(compound)) => match compound.get("Pos") {
+            Some(Value::List(pos)) => match pos.as_slice() {
+                [Value::Double(x), Value::Double(y), Value::Double(z)] => Some([*x, *y, *z]),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,317 @@
+//! Map item data, the `data/map_<id>.dat` files of the overworld.
+//!
+//! A filled map item refers to its data by ID. The data holds the area the
+//! map shows and a 128×128 image, one byte per pixel: the upper six bits
+//! select a base color and the lower two one of four shades of it.
+//!
+//! Ref: https://minecraft.gamepedia.com/Map_item_format
+use crate::error::{self, Error};
+use coruscant_nbt::{as_nbt_array, Value};
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+
+/// Width and height of a map image, in pixels.
+pub const MAP_SIZE: usize = 128;
+
+/// Data of a map item.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::map_data::{MapData, MAP_SIZE};
+///
+/// let mut map = MapData::new(0, 0, "minecraft:overworld".to_string());
+/// // water in its brightest shade
+/// map.colors[0] = 12 * 4 + 2;
+/// let rgba = map.to_rgba().unwrap();
+/// assert_eq!(rgba.len(), MAP_SIZE * MAP_SIZE * 4);
+/// assert_eq!(&rgba[..4], &[0x40, 0x40, 0xFF, 0xFF]);
+/// // nothing drawn yet is transparent
+/// assert_eq!(&rgba[4..8], &[0, 0, 0, 0]);
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct MapData {
+    /// How zoomed in the map is: a pixel covers `2^scale` blocks, from 0 to
+    /// 4.
+    #[serde(rename = "scale")]
+    pub scale: i8,
+    /// The dimension the map shows.
+    #[serde(rename = "dimension")]
+    pub dimension: MapDimension,
+    /// Whether markers for players and item frames are shown.
+    #[serde(rename = "trackingPosition")]
+    #[serde(default)]
+    pub tracking_position: bool,
+    /// Whether player markers are shown when players are far outside the
+    /// map.
+    #[serde(rename = "unlimitedTracking")]
+    #[serde(default)]
+    pub unlimited_tracking: bool,
+    /// Whether the map was locked in a cartography table, so it no longer
+    /// updates.
+    #[serde(rename = "locked")]
+    #[serde(default)]
+    pub locked: bool,
+    /// X coordinate of the center of the map.
+    #[serde(rename = "xCenter")]
+    pub x_center: i32,
+    /// Z coordinate of the center of the map.
+    #[serde(rename = "zCenter")]
+    pub z_center: i32,
+    /// Width of the map image, always 128, before 1.13.
+    #[serde(rename = "width")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<i16>,
+    /// Height of the map image, always 128, before 1.13.
+    #[serde(rename = "height")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<i16>,
+    /// Banners marked on the map.
+    #[serde(rename = "banners")]
+    #[serde(default)]
+    pub banners: Vec<MapBanner>,
+    /// Item frames holding the map, for their markers.
+    #[serde(rename = "frames")]
+    #[serde(default)]
+    pub frames: Vec<MapFrame>,
+    /// Color of every pixel, row by row from the north west corner.
+    #[serde(rename = "colors", serialize_with = "as_nbt_array")]
+    pub colors: Vec<i8>,
+}
+
+/// The dimension of a map: a resource location since 1.16, a numeric ID
+/// before.
+///
+/// The numeric ID is an int since 1.13 and a byte before; either is written
+/// back with the tag it was read with.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum MapDimension {
+    /// Resource location, like `minecraft:overworld`.
+    Name(String),
+    /// Numeric ID: 0 for the overworld, -1 for the Nether and 1 for the End.
+    Id(i32),
+    /// Numeric ID stored as a byte, before 1.13.
+    LegacyId(i8),
+}
+
+/// A banner marked on a map.
+///
+/// The fields were renamed in 1.20.5; either set of names is present.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct MapBanner {
+    /// Position of the banner, before 1.20.5.
+    #[serde(rename = "Pos")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legacy_pos: Option<MarkerPos>,
+    /// Dye color of the banner, before 1.20.5.
+    #[serde(rename = "Color")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legacy_color: Option<String>,
+    /// Custom name of the banner as a JSON text component, before 1.20.5.
+    #[serde(rename = "Name")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legacy_name: Option<String>,
+    /// Position of the banner, since 1.20.5.
+    #[serde(rename = "pos", serialize_with = "option_pos_array")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pos: Option<[i32; 3]>,
+    /// Dye color of the banner, since 1.20.5; white when missing.
+    #[serde(rename = "color")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Custom name of the banner as a text component, since 1.20.5.
+    #[serde(rename = "name")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<Value>,
+}
+
+/// An item frame holding a map.
+///
+/// The fields were renamed in 1.20.5; either set of names is present.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct MapFrame {
+    /// Position of the item frame, before 1.20.5.
+    #[serde(rename = "Pos")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legacy_pos: Option<MarkerPos>,
+    /// Rotation of the marker in degrees, before 1.20.5.
+    #[serde(rename = "Rotation")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legacy_rotation: Option<i32>,
+    /// ID of the item frame entity, before 1.20.5.
+    #[serde(rename = "EntityId")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legacy_entity_id: Option<i32>,
+    /// Position of the item frame, since 1.20.5.
+    #[serde(rename = "pos", serialize_with = "option_pos_array")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pos: Option<[i32; 3]>,
+    /// Rotation of the marker in degrees, since 1.20.5.
+    #[serde(rename = "rotation")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<i32>,
+    /// ID of the item frame entity, since 1.20.5.
+    #[serde(rename = "entity_id")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity_id: Option<i32>,
+}
+
+/// Position of a marker before 1.20.5.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MarkerPos {
+    #[serde(rename = "X")]
+    pub x: i32,
+    #[serde(rename = "Y")]
+    pub y: i32,
+    #[serde(rename = "Z")]
+    pub z: i32,
+}
+
+impl MapData {
+    /// An empty map centered on the given position.
+    pub fn new(x_center: i32, z_center: i32, dimension: String) -> MapData {
+        MapData {
+            scale: 0,
+            dimension: MapDimension::Name(dimension),
+            tracking_position: true,
+            unlimited_tracking: false,
+            locked: false,
+            x_center,
+            z_center,
+            width: None,
+            height: None,
+            banners: Vec::new(),
+            frames: Vec::new(),
+            colors: vec![0; MAP_SIZE * MAP_SIZE],
+        }
+    }
+
+    /// Color of the pixel at the given column and row as RGBA, or `None` if
+    /// the pixel is outside the map or its colors.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<[u8; 4]> {
+        if x >= MAP_SIZE || y >= MAP_SIZE {
+            return None;
+        }
+        let color = self.colors.get(y * MAP_SIZE + x)?;
+        Some(map_color(*color as u8))
+    }
+
+    /// The map image as RGBA pixels, row by row from the north west corner.
+    ///
+    /// Fails with `Error::CorruptMap` if there are not 128×128 colors.
+    pub fn to_rgba(&self) -> error::Result<Vec<u8>> {
+        if self.colors.len() != MAP_SIZE * MAP_SIZE {
+            return Err(Error::CorruptMap("color count does not match the map size"));
+        }
+        let rgba = self
+            .colors
+            .iter()
+            .flat_map(|color| map_color(*color as u8))
+            .collect();
+        Ok(rgba)
+    }
+
+    /// The map image, for example to save it as PNG with `save`.
+    ///
+    /// Fails with `Error::CorruptMap` if there are not 128×128 colors.
+    pub fn render(&self) -> error::Result<image::RgbaImage> {
+        let size = MAP_SIZE as u32;
+        image::RgbaImage::from_raw(size, size, self.to_rgba()?)
+            .ok_or(Error::CorruptMap("color count does not match the map size"))
+    }
+}
+
+impl MapDimension {
+    /// Resource location of the dimension, also for numeric IDs.
+    pub fn name(&self) -> &str {
+        match self {
+            MapDimension::Name(name) => name,
+            MapDimension::Id(-1) | MapDimension::LegacyId(-1) => "minecraft:the_nether",
+            MapDimension::Id(1) | MapDimension::LegacyId(1) => "minecraft:the_end",
+            MapDimension::Id(_) | MapDimension::LegacyId(_) => "minecraft:overworld",
+        }
+    }
+}
+
+// Serde buffers untagged enums, which loses the difference between ints and
+// bytes, so the dimension is read through a `Value`.
+impl<'de> Deserialize<'de> for MapDimension {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Value::deserialize(deserializer)? {
+            Value::String(name) => Ok(MapDimension::Name(name)),
+            Value::Int(id) => Ok(MapDimension::Id(id)),
+            Value::Byte(id) => Ok(MapDimension::LegacyId(id)),
+            _ => Err(de::Error::custom(
+                "map dimension is not a string, int or byte",
+            )),
+        }
+    }
+}
+
+impl MapBanner {
+    /// Position of the banner in either format.
+    pub fn block_pos(&self) -> Option<[i32; 3]> {
+        self.pos
+            .or_else(|| self.legacy_pos.map(|p| [p.x, p.y, p.z]))
+    }
+}
+
+impl MapFrame {
+    /// Position of the item frame in either format.
+    pub fn block_pos(&self) -> Option<[i32; 3]> {
+        self.pos
+            .or_else(|| self.legacy_pos.map(|p| [p.x, p.y, p.z]))
+    }
+}
+
+impl MarkerPos {
+    pub fn new(x: i32, y: i32, z: i32) -> MarkerPos {
+        MarkerPos { x, y, z }
+    }
+}
+
+/// Base colors of blocks on maps as RGB, indexed by the upper six bits of a
+/// map pixel. Base color 0 is transparent.
+pub const BASE_COLORS: [u32; 62] = [
+    0x000000, 0x7FB238, 0xF7E9A3, 0xC7C7C7, 0xFF0000, 0xA0A0FF, 0xA7A7A7, 0x007C00, 0xFFFFFF,
+    0xA4A8B8, 0x976D4D, 0x707070, 0x4040FF, 0x8F7748, 0xFFFCF5, 0xD87F33, 0xB24CD8, 0x6699D8,
+    0xE5E533, 0x7FCC19, 0xF27FA5, 0x4C4C4C, 0x999999, 0x4C7F99, 0x7F3FB2, 0x334CB2, 0x664C33,
+    0x667F33, 0x993333, 0x191919, 0xFAEE4D, 0x5CDBD5, 0x4A80FF, 0x00D93A, 0x815631, 0x700200,
+    0xD1B1A1, 0x9F5224, 0x95576C, 0x706C8A, 0xBA8524, 0x677535, 0xA04D4E, 0x392923, 0x876B62,
+    0x575C5C, 0x7A4958, 0x4C3E5C, 0x4C3223, 0x4C522A, 0x8E3C2E, 0x251610, 0xBD3031, 0x943F61,
+    0x5C191D, 0x167E86, 0x3A8E8C, 0x562C3E, 0x14B485, 0x646464, 0xD8AF93, 0x7FA796,
+];
+
+/// Brightness of the four shades of a base color, out of 255, indexed by
+/// the lower two bits of a map pixel.
+pub const SHADE_MULTIPLIERS: [u32; 4] = [180, 220, 255, 135];
+
+/// RGBA of a map pixel value. Base color 0 and unknown base colors are
+/// transparent.
+pub fn map_color(color: u8) -> [u8; 4] {
+    let base = match BASE_COLORS.get(usize::from(color >> 2)) {
+        Some(base) if color >> 2 != 0 => *base,
+        _ => return [0; 4],
+    };
+    let shade = SHADE_MULTIPLIERS[usize::from(color & 3)];
+    let channel = |shift: u32| ((base >> shift & 0xFF) * shade / 255) as u8;
+    [channel(16), channel(8), channel(0), 0xFF]
+}
+
+fn option_pos_array<S: Serializer>(
+    pos: &Option<[i32; 3]>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match pos {
+        Some(pos) => as_nbt_array(&&pos[..], serializer),
+        None => serializer.serialize_none(),
+    }
+}
@@ -0,0 +1,352 @@
+//! Paletted containers, the storage of blocks and biomes in chunk sections.
+//!
+//! A section stores a palette of the distinct values in it and, for each
+//! position, an index into that palette. The indices are packed into longs
+//! with as few bits as the palette size allows. Since 1.16 (20w17a) an index
+//! never spans two longs, leaving the high bits of a long unused; before,
+//! indices are packed back to back and may span two longs.
+//!
+//! Ref: https://minecraft.gamepedia.com/Chunk_format
+use crate::chunk::{Biomes, BlockState, BlockStates};
+use crate::error::{Error, Result};
+
+/// Data version of 20w17a, since which indices do not span longs.
+pub const DATA_VERSION_ALIGNED_PACKING: i32 = 2527;
+
+/// Most entries a palette can have, with indices of at most 16 bits.
+pub const MAX_PALETTE_LEN: usize = 1 << 16;
+
+/// How indices are packed into longs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Packing {
+    /// Indices are packed back to back and may span two longs, before 1.16.
+    Spanning,
+    /// Indices do not span longs, since 1.16.
+    Aligned,
+}
+
+impl Packing {
+    /// The packing used by chunks with the given `DataVersion`.
+    pub fn from_data_version(data_version: i32) -> Packing {
+        if data_version >= DATA_VERSION_ALIGNED_PACKING {
+            Packing::Aligned
+        } else {
+            Packing::Spanning
+        }
+    }
+
+    /// Number of longs needed to pack `len` indices of `bits` bits each.
+    pub fn packed_len(self, len: usize, bits: u32) -> usize {
+        let bits = bits as usize;
+        match self {
+            Packing::Spanning => (len * bits).div_ceil(64),
+            Packing::Aligned => len.div_ceil(64 / bits),
+        }
+    }
+}
+
+/// Pack `indices` of `bits` bits each into longs.
+///
+/// Heightmaps are packed the same way, with 9 bits for each of their 256
+/// columns.
+pub fn pack(indices: &[u16], bits: u32, packing: Packing) -> Vec<i64> {
+    let mut ans = vec![0u64; packing.packed_len(indices.len(), bits)];
+    for (i, &index) in indices.iter().enumerate() {
+        let value = index as u64;
+        match packing {
+            Packing::Spanning => {
+                let bit = i * bits as usize;
+                let (word, offset) = (bit / 64, bit % 64);
+                ans[word] |= value << offset;
+                if offset + bits as usize > 64 {
+                    ans[word + 1] |= value >> (64 - offset);
+                }
+            }
+            Packing::Aligned => {
+                let per_long = 64 / bits as usize;
+                let (word, offset) = (i / per_long, i % per_long * bits as usize);
+                ans[word] |= value << offset;
+            }
+        }
+    }
+    ans.into_iter().map(|word| word as i64).collect()
+}
+
+/// Unpack `len` indices of `bits` bits each from longs.
+pub fn unpack(data: &[i64], bits: u32, len: usize, packing: Packing) -> Result<Vec<u16>> {
+    if bits == 0 || bits > 16 {
+        return Err(Error::CorruptChunk("invalid bits per entry"));
+    }
+    if data.len() != packing.packed_len(len, bits) {
+        return Err(Error::CorruptChunk("invalid packed data length"));
+    }
+    let mask = (1u64 << bits) - 1;
+    let ans = (0..len)
+        .map(|i| {
+            let value = match packing {
+                Packing::Spanning => {
+                    let bit = i * bits as usize;
+                    let (word, offset) = (bit / 64, bit % 64);
+                    let mut value = data[word] as u64 >> offset;
+                    if offset + bits as usize > 64 {
+                        value |= (data[word + 1] as u64) << (64 - offset);
+                    }
+                    value
+                }
+                Packing::Aligned => {
+                    let per_long = 64 / bits as usize;
+                    let (word, offset) = (i / per_long, i % per_long * bits as usize);
+                    data[word] as u64 >> offset
+                }
+            };
+            (value & mask) as u16
+        })
+        .collect();
+    Ok(ans)
+}
+
+/// Values of a cube of positions stored with a palette.
+///
+/// Positions are indexed `(y * edge + z) * edge + x`, with `edge` 16 for
+/// blocks and 4 for biomes, one biome for each 4×4×4 cell of a section.
+/// Containers made `with_size` hold a box instead, like the regions of
+/// Litematica schematics, indexed `(y * size_z + z) * size_x + x`.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::chunk::BlockState;
+/// use coruscant_world::palette::{Packing, PalettedContainer};
+///
+/// let air = BlockState::new("minecraft:air".to_string());
+/// let stone = BlockState::new("minecraft:stone".to_string());
+/// let mut blocks = PalettedContainer::blocks(air.clone());
+/// assert_eq!(blocks.set(1, 2, 3, stone.clone()), Some(air.clone()));
+/// assert_eq!(blocks.get(1, 2, 3), Some(&stone));
+/// assert_eq!(blocks.get(0, 0, 0), Some(&air));
+/// assert_eq!(blocks.get(16, 0, 0), None);
+///
+/// let (palette, data) = blocks.to_packed(Packing::Spanning);
+/// let read = PalettedContainer::from_packed(16, 4, palette, data.as_deref(), Packing::Spanning)
+///     .unwrap();
+/// assert_eq!(read, blocks);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PalettedContainer<T> {
+    size: [usize; 3],
+    min_bits: u32,
+    palette: Vec<T>,
+    indices: Vec<u16>,
+}
+
+impl<T: Clone + PartialEq> PalettedContainer<T> {
+    /// A container with every position set to `value`.
+    ///
+    /// Packed indices take at least `min_bits` bits: 4 for blocks and 1 for
+    /// biomes.
+    pub fn new(edge: usize, min_bits: u32, value: T) -> Self {
+        Self::with_size([edge; 3], min_bits, value)
+    }
+
+    /// A container of the given X, Y and Z size with every position set to
+    /// `value`.
+    pub fn with_size(size: [usize; 3], min_bits: u32, value: T) -> Self {
+        PalettedContainer {
+            size,
+            min_bits,
+            palette: vec![value],
+            indices: vec![0; size[0] * size[1] * size[2]],
+        }
+    }
+
+    /// A 16×16×16 container of block states.
+    pub fn blocks(value: T) -> Self {
+        Self::new(16, 4, value)
+    }
+
+    /// A 4×4×4 container of biomes.
+    pub fn biomes(value: T) -> Self {
+        Self::new(4, 1, value)
+    }
+
+    /// Decode a palette and its packed indices.
+    ///
+    /// `data` may be missing only if the palette has a single entry.
+    pub fn from_packed(
+        edge: usize,
+        min_bits: u32,
+        palette: Vec<T>,
+        data: Option<&[i64]>,
+        packing: Packing,
+    ) -> Result<Self> {
+        Self::from_packed_with_size([edge; 3], min_bits, palette, data, packing)
+    }
+
+    /// Decode a palette and its packed indices into a container of the
+    /// given X, Y and Z size.
+    pub fn from_packed_with_size(
+        size: [usize; 3],
+        min_bits: u32,
+        palette: Vec<T>,
+        data: Option<&[i64]>,
+        packing: Packing,
+    ) -> Result<Self> {
+        let len = size[0] * size[1] * size[2];
+        if palette.is_empty() {
+            return Err(Error::CorruptChunk("empty palette"));
+        }
+        let indices = match data {
+            Some(data) => {
+                let bits = bits_for(palette.len(), min_bits);
+                unpack(data, bits, len, packing)?
+            }
+            None if palette.len() == 1 => vec![0; len],
+            None => return Err(Error::CorruptChunk("missing packed data")),
+        };
+        if indices.iter().any(|&i| i as usize >= palette.len()) {
+            return Err(Error::CorruptChunk("palette index out of range"));
+        }
+        Ok(PalettedContainer {
+            size,
+            min_bits,
+            palette,
+            indices,
+        })
+    }
+
+    /// Edge length of the cube; the X size of containers made `with_size`.
+    pub fn edge(&self) -> usize {
+        self.size[0]
+    }
+
+    /// X, Y and Z size of the container.
+    pub fn size(&self) -> [usize; 3] {
+        self.size
+    }
+
+    /// Values in this container, each appearing once; may contain values no
+    /// longer used until the container is packed.
+    pub fn palette(&self) -> &[T] {
+        &self.palette
+    }
+
+    /// Bits each index would take when packed.
+    pub fn bits(&self) -> u32 {
+        bits_for(self.palette.len(), self.min_bits)
+    }
+
+    /// Value at the given position; `None` outside the container.
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Option<&T> {
+        let index = self.indices[self.index(x, y, z)?];
+        Some(&self.palette[index as usize])
+    }
+
+    /// Set the value at the given position. The palette grows when the value
+    /// is new.
+    ///
+    /// Returns the old value, or `None` if the position is outside the
+    /// container or the value is new and `MAX_PALETTE_LEN` other values are
+    /// in use; the container is left unchanged then.
+    pub fn set(&mut self, x: usize, y: usize, z: usize, value: T) -> Option<T> {
+        let index = self.index(x, y, z)?;
+        let palette_index = match self.palette.iter().position(|v| v == &value) {
+            Some(i) => i,
+            None => {
+                if self.palette.len() >= MAX_PALETTE_LEN {
+                    self.compact();
+                }
+                if self.palette.len() >= MAX_PALETTE_LEN {
+                    return None;
+                }
+                self.palette.push(value);
+                self.palette.len() - 1
+            }
+        };
+        let old = core::mem::replace(&mut self.indices[index], palette_index as u16);
+        Some(self.palette[old as usize].clone())
+    }
+
+    /// Whether every position holds the same value.
+    pub fn is_uniform(&self) -> bool {
+        self.indices.iter().all(|&i| i == self.indices[0])
+    }
+
+    /// Remove values that are not used at any position from the palette,
+    /// keeping the order of the rest.
+    pub fn compact(&mut self) {
+        let mut used = vec![false; self.palette.len()];
+        for &i in &self.indices {
+            used[i as usize] = true;
+        }
+        let mut remap = vec![0u16; self.palette.len()];
+        let mut next = 0;
+        for (i, &used) in used.iter().enumerate() {
+            if used {
+                remap[i] = next as u16;
+                next += 1;
+            }
+        }
+        let mut used = used.into_iter();
+        self.palette.retain(|_| used.next().unwrap_or(false));
+        for i in &mut self.indices {
+            *i = remap[*i as usize];
+        }
+    }
+
+    /// Encode into a compacted palette and packed indices. Indices are
+    /// `None` when the palette has a single entry.
+    pub fn to_packed(&self, packing: Packing) -> (Vec<T>, Option<Vec<i64>>) {
+        let mut compacted = self.clone();
+        compacted.compact();
+        if compacted.palette.len() == 1 {
+            return (compacted.palette, None);
+        }
+        let data = pack(&compacted.indices, compacted.bits(), packing);
+        (compacted.palette, Some(data))
+    }
+
+    #[inline]
+    fn index(&self, x: usize, y: usize, z: usize) -> Option<usize> {
+        let [size_x, size_y, size_z] = self.size;
+        if x < size_x && y < size_y && z < size_z {
+            Some((y * size_z + z) * size_x + x)
+        } else {
+            None
+        }
+    }
+}
+
+impl PalettedContainer<BlockState> {
+    /// Decode the block states of a section since 1.18.
+    pub fn from_block_states(block_states: &BlockStates) -> Result<Self> {
+        let data = block_states.data.as_deref();
+        Self::from_packed(16, 4, block_states.palette.clone(), data, Packing::Aligned)
+    }
+
+    /// Encode into the block states of a section since 1.18.
+    pub fn to_block_states(&self) -> BlockStates {
+        let (palette, data) = self.to_packed(Packing::Aligned);
+        BlockStates::new(palette, data)
+    }
+}
+
+impl PalettedContainer<String> {
+    /// Decode the biomes of a section since 1.18.
+    pub fn from_biomes(biomes: &Biomes) -> Result<Self> {
+        let data = biomes.data.as_deref();
+        Self::from_packed(4, 1, biomes.palette.clone(), data, Packing::Aligned)
+    }
+
+    /// Encode into the biomes of a section since 1.18.
+    pub fn to_biomes(&self) -> Biomes {
+        let (palette, data) = self.to_packed(Packing::Aligned);
+        Biomes::new(palette, data)
+    }
+}
+
+// Bits needed to index a palette of `len` entries.
+#[inline]
+pub(crate) fn bits_for(len: usize, min_bits: u32) -> u32 {
+    let bits = usize::BITS - (len.max(1) - 1).leading_zeros();
+    bits.max(min_bits)
+}
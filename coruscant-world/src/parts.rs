@@ -0,0 +1,57 @@
+//! Typed data read in parts from the compound of an entity or block entity.
+//!
+//! Every part is a plain struct read from the whole compound. Parts are read
+//! this way rather than through `#[serde(flatten)]`, which buffers fields and
+//! loses the difference between arrays and lists.
+use coruscant_nbt::{Map, Value};
+use serde::de::DeserializeOwned;
+use serde::ser::{self, Serialize};
+use std::collections::HashMap;
+
+pub(crate) fn read_part<T: DeserializeOwned>(value: &Value) -> coruscant_nbt::Result<T> {
+    coruscant_nbt::from_value(value.clone())
+}
+
+pub(crate) fn to_compound<T: Serialize>(part: &T) -> coruscant_nbt::Result<Map<String, Value>> {
+    match coruscant_nbt::to_value(part)? {
+        Value::Compound(compound) => Ok(compound),
+        _ => Err(ser::Error::custom("part is not a compound")),
+    }
+}
+
+// Fields of the compound that none of the parts write.
+pub(crate) fn extra_fields(value: &Value, parts: &[&Map<String, Value>]) -> HashMap<String, Value> {
+    let mut ans = HashMap::new();
+    if let Value::Compound(compound) = value {
+        for (key, value) in compound {
+            if parts.iter().all(|part| !part.contains_key(key)) {
+                ans.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    ans
+}
+
+pub(crate) fn take_id(extra: &mut HashMap<String, Value>) -> String {
+    match extra.remove("id") {
+        Some(Value::String(id)) => id,
+        _ => unreachable!("typed data is dispatched on its id"),
+    }
+}
+
+pub(crate) fn join_parts<I>(id: &str, extra: &HashMap<String, Value>, parts: I) -> Value
+where
+    I: IntoIterator<Item = Map<String, Value>>,
+{
+    let mut ans = Map::new();
+    ans.insert("id".to_owned(), Value::String(id.to_owned()));
+    for (key, value) in extra {
+        ans.insert(key.clone(), value.clone());
+    }
+    for part in parts {
+        for (key, value) in part {
+            ans.insert(key, value);
+        }
+    }
+    Value::Compound(ans)
+}
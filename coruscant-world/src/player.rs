@@ -0,0 +1,340 @@
+//! Player data, as stored in `playerdata/<uuid>.dat` and as the `Player`
+//! of a singleplayer `level.dat`.
+//!
+//! Ref: https://minecraft.gamepedia.com/Player.dat_format
+use crate::error::Result;
+use crate::extra::impl_serde_with_extra;
+use crate::item::ItemStack;
+use crate::uuid::Uuid;
+use crate::world::{OVERWORLD, THE_END, THE_NETHER};
+use coruscant_nbt::{Compression, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// Data of a single player.
+///
+/// Fields this model has no field for are kept in `extra`, so that a player
+/// read and written again keeps all its data.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::item::{ItemFormat, ItemStack};
+/// use coruscant_world::player::{DimensionId, Player};
+///
+/// let overworld = DimensionId::Id("minecraft:overworld".to_string());
+/// let mut player = Player::new(2586, [0.5, 64.0, 0.5], overworld);
+/// let apple = ItemStack::new("minecraft:apple".to_string(), 3, ItemFormat::Tag).with_slot(0);
+/// player.inventory.push(apple);
+///
+/// let bytes = coruscant_nbt::to_vec(&player).unwrap();
+/// let read: Player = coruscant_nbt::from_slice(&bytes).unwrap();
+/// assert_eq!(read, player);
+/// assert_eq!(read.dimension.id(), "minecraft:overworld");
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename = "", remote = "Self")]
+#[non_exhaustive]
+pub struct Player {
+    /// An integer displaying the [data version].
+    ///
+    /// [data version]: https://minecraft.gamepedia.com/Data_version
+    #[serde(rename = "DataVersion")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_version: Option<i32>,
+    /// The dimension the player is in.
+    #[serde(rename = "Dimension")]
+    pub dimension: DimensionId,
+    /// The position of the player.
+    #[serde(rename = "Pos")]
+    pub pos: [f64; 3],
+    /// The velocity of the player in blocks per tick.
+    #[serde(rename = "Motion")]
+    pub motion: [f64; 3],
+    /// The yaw and pitch of the player in degrees.
+    #[serde(rename = "Rotation")]
+    pub rotation: [f32; 2],
+    /// The health of the player, where 20 is ten full hearts.
+    #[serde(rename = "Health")]
+    pub health: f32,
+    /// The food level of the player, where 20 is a full bar.
+    #[serde(rename = "foodLevel")]
+    pub food_level: i32,
+    /// The food saturation level of the player.
+    #[serde(rename = "foodSaturationLevel")]
+    pub food_saturation_level: f32,
+    /// The food exhaustion level of the player.
+    #[serde(rename = "foodExhaustionLevel")]
+    pub food_exhaustion_level: f32,
+    /// Ticks until the player heals from or is hurt by the food level.
+    #[serde(rename = "foodTickTimer")]
+    pub food_tick_timer: i32,
+    /// The level shown on the experience bar.
+    #[serde(rename = "XpLevel")]
+    pub xp_level: i32,
+    /// The progress across the experience bar to the next level, from 0 to 1.
+    #[serde(rename = "XpP")]
+    pub xp_progress: f32,
+    /// The total amount of experience the player has collected over time.
+    #[serde(rename = "XpTotal")]
+    pub xp_total: i32,
+    /// The seed for the enchanting table.
+    #[serde(rename = "XpSeed")]
+    #[serde(default)]
+    pub xp_seed: i32,
+    /// The score shown upon death.
+    #[serde(rename = "Score")]
+    #[serde(default)]
+    pub score: i32,
+    /// The game mode of the player. 0 is Survival, 1 is Creative, 2 is Adventure and
+    /// 3 is Spectator.
+    #[serde(rename = "playerGameType")]
+    pub game_type: i32,
+    /// The game mode of the player before it was last changed.
+    #[serde(rename = "previousPlayerGameType")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_game_type: Option<i32>,
+    /// The hotbar slot the player holds, from 0 to 8.
+    #[serde(rename = "SelectedItemSlot")]
+    pub selected_item_slot: i32,
+    /// The items in the inventory, each with its slot: 0 to 8 for the hotbar, 9 to 35
+    /// for the main inventory, 100 to 103 for feet, legs, chest and head armor, and
+    /// -106 for the offhand.
+    #[serde(rename = "Inventory")]
+    #[serde(default)]
+    pub inventory: Vec<ItemStack>,
+    /// The items in the ender chest, each with its slot from 0 to 26.
+    #[serde(rename = "EnderItems")]
+    #[serde(default)]
+    pub ender_items: Vec<ItemStack>,
+    /// The abilities of the player.
+    #[serde(rename = "abilities")]
+    pub abilities: Abilities,
+    /// The X coordinate of the player's spawn point, from a bed or respawn anchor.
+    #[serde(rename = "SpawnX")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn_x: Option<i32>,
+    /// The Y coordinate of the player's spawn point.
+    #[serde(rename = "SpawnY")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn_y: Option<i32>,
+    /// The Z coordinate of the player's spawn point.
+    #[serde(rename = "SpawnZ")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn_z: Option<i32>,
+    /// True if the player spawns at the spawn point even if the bed or anchor is
+    /// missing.
+    #[serde(rename = "SpawnForced")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn_forced: Option<bool>,
+    /// The yaw the player faces when respawning, since 1.16.
+    #[serde(rename = "SpawnAngle")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn_angle: Option<f32>,
+    /// The dimension of the spawn point, since 1.16.
+    #[serde(rename = "SpawnDimension")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn_dimension: Option<String>,
+    /// The UUID of the player, since 1.16. Before, it is stored as `UUIDMost` and
+    /// `UUIDLeast`, which are kept in `extra`.
+    #[serde(rename = "UUID")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<Uuid>,
+    /// The recipes the player has unlocked.
+    #[serde(rename = "recipeBook")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipe_book: Option<RecipeBook>,
+    /// Other data of the player, like `Attributes` or `ActiveEffects`.
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl Player {
+    /// A player with full health and food, an empty inventory and survival
+    /// abilities.
+    pub fn new(data_version: i32, pos: [f64; 3], dimension: DimensionId) -> Player {
+        Player {
+            data_version: Some(data_version),
+            dimension,
+            pos,
+            motion: [0.0; 3],
+            rotation: [0.0; 2],
+            health: 20.0,
+            food_level: 20,
+            food_saturation_level: 5.0,
+            food_exhaustion_level: 0.0,
+            food_tick_timer: 0,
+            xp_level: 0,
+            xp_progress: 0.0,
+            xp_total: 0,
+            xp_seed: 0,
+            score: 0,
+            game_type: 0,
+            previous_game_type: None,
+            selected_item_slot: 0,
+            inventory: Vec::new(),
+            ender_items: Vec::new(),
+            abilities: Abilities::new(),
+            spawn_x: None,
+            spawn_y: None,
+            spawn_z: None,
+            spawn_forced: None,
+            spawn_angle: None,
+            spawn_dimension: None,
+            uuid: None,
+            recipe_book: None,
+            extra: HashMap::new(),
+        }
+    }
+
+    /// Read a gzip compressed player file, like `playerdata/<uuid>.dat`.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Player> {
+        let file = BufReader::new(File::open(path)?);
+        Ok(coruscant_nbt::from_gzip_reader(file)?)
+    }
+
+    /// Write a gzip compressed player file.
+    ///
+    /// The data is written to a temporary file next to `path` first, which then
+    /// replaces the old file, so that a failed write does not lose the player.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let mut file = BufWriter::new(File::create(&tmp)?);
+        coruscant_nbt::to_gzip_writer(&mut file, self, Compression::default())?;
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+impl_serde_with_extra!(Player);
+
+/// The dimension an entity is in: a number before 1.16, and a dimension ID
+/// since.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum DimensionId {
+    /// -1 for the Nether, 0 for the Overworld and 1 for the End.
+    Number(i32),
+    /// A dimension ID like `minecraft:overworld`.
+    Id(String),
+}
+
+impl DimensionId {
+    /// The dimension ID, also for numbered dimensions. Unknown numbers give
+    /// an empty string.
+    pub fn id(&self) -> &str {
+        match self {
+            DimensionId::Number(-1) => THE_NETHER,
+            DimensionId::Number(0) => OVERWORLD,
+            DimensionId::Number(1) => THE_END,
+            DimensionId::Number(_) => "",
+            DimensionId::Id(id) => id,
+        }
+    }
+}
+
+/// The abilities of a player.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Abilities {
+    /// The walking speed of the player, 0.1 by default.
+    #[serde(rename = "walkSpeed")]
+    pub walk_speed: f32,
+    /// The flying speed of the player, 0.05 by default.
+    #[serde(rename = "flySpeed")]
+    pub fly_speed: f32,
+    /// True if the player can fly.
+    #[serde(rename = "mayfly")]
+    pub may_fly: bool,
+    /// True if the player is flying.
+    #[serde(rename = "flying")]
+    pub flying: bool,
+    /// True if the player takes no damage, except from the void.
+    #[serde(rename = "invulnerable")]
+    pub invulnerable: bool,
+    /// True if the player can place and destroy blocks.
+    #[serde(rename = "mayBuild")]
+    pub may_build: bool,
+    /// True if the player can break blocks instantly.
+    #[serde(rename = "instabuild")]
+    pub instant_build: bool,
+}
+
+impl Abilities {
+    /// The abilities of a player in Survival.
+    pub fn new() -> Abilities {
+        Abilities {
+            walk_speed: 0.1,
+            fly_speed: 0.05,
+            may_fly: false,
+            flying: false,
+            invulnerable: false,
+            may_build: true,
+            instant_build: false,
+        }
+    }
+}
+
+impl Default for Abilities {
+    fn default() -> Self {
+        Abilities::new()
+    }
+}
+
+/// The recipe book of a player.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct RecipeBook {
+    /// IDs of the recipes the player has unlocked.
+    #[serde(rename = "recipes")]
+    #[serde(default)]
+    pub recipes: Vec<String>,
+    /// IDs of unlocked recipes that are not yet seen in the recipe book.
+    #[serde(rename = "toBeDisplayed")]
+    #[serde(default)]
+    pub to_be_displayed: Vec<String>,
+    /// True if the recipe book is open in the crafting table.
+    #[serde(rename = "isGuiOpen")]
+    #[serde(default)]
+    pub is_gui_open: bool,
+    /// True if the crafting recipe book shows only craftable recipes.
+    #[serde(rename = "isFilteringCraftable")]
+    #[serde(default)]
+    pub is_filtering_craftable: bool,
+    /// True if the recipe book is open in the furnace.
+    #[serde(rename = "isFurnaceGuiOpen")]
+    #[serde(default)]
+    pub is_furnace_gui_open: bool,
+    /// True if the furnace recipe book shows only craftable recipes.
+    #[serde(rename = "isFurnaceFilteringCraftable")]
+    #[serde(default)]
+    pub is_furnace_filtering_craftable: bool,
+    /// True if the recipe book is open in the blast furnace.
+    #[serde(rename = "isBlastingFurnaceGuiOpen")]
+    #[serde(default)]
+    pub is_blasting_furnace_gui_open: bool,
+    /// True if the blast furnace recipe book shows only craftable recipes.
+    #[serde(rename = "isBlastingFurnaceFilteringCraftable")]
+    #[serde(default)]
+    pub is_blasting_furnace_filtering_craftable: bool,
+    /// True if the recipe book is open in the smoker.
+    #[serde(rename = "isSmokerGuiOpen")]
+    #[serde(default)]
+    pub is_smoker_gui_open: bool,
+    /// True if the smoker recipe book shows only craftable recipes.
+    #[serde(rename = "isSmokerFilteringCraftable")]
+    #[serde(default)]
+    pub is_smoker_filtering_craftable: bool,
+}
+
+impl RecipeBook {
+    pub fn new() -> RecipeBook {
+        RecipeBook::default()
+    }
+}
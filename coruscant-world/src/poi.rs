@@ -0,0 +1,197 @@
+//! Points of interest, the blocks villagers, bees and other mobs look for.
+//!
+//! Since 1.14 every dimension keeps the points of interest of a chunk in
+//! the region files in `poi/`, apart from its blocks: job site blocks, beds,
+//! bells, beehives, nether portals and a few more. The records are sorted
+//! into sections of 16 blocks high, like the blocks of a chunk. Records left
+//! behind when their block is gone confuse villagers, which keep walking to
+//! a job site or bed that no longer exists; see `World::check_poi`.
+//!
+//! Ref: https://minecraft.gamepedia.com/Java_Edition_level_format#poi
+use crate::chunk::BlockState;
+use crate::world::BlockPos;
+use coruscant_nbt::as_nbt_array;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::HashMap;
+
+/// The points of interest of a chunk, as stored in `poi/`.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::poi::{PoiChunk, PoiRecord};
+/// use coruscant_world::BlockPos;
+///
+/// let mut chunk = PoiChunk::new(3700);
+/// let bell = BlockPos::new(3, -20, 7);
+/// chunk.insert(PoiRecord::new("minecraft:meeting".to_string(), bell, 32));
+/// assert_eq!(chunk.records().count(), 1);
+/// assert!(chunk.sections.contains_key("-2"));
+///
+/// let bytes = coruscant_nbt::to_vec(&chunk).unwrap();
+/// let read: PoiChunk = coruscant_nbt::from_slice(&bytes).unwrap();
+/// assert_eq!(read, chunk);
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename = "")]
+#[non_exhaustive]
+pub struct PoiChunk {
+    /// The version of the data, as in chunks; missing in files of 1.14
+    /// snapshots.
+    #[serde(rename = "DataVersion")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_version: Option<i32>,
+    /// Sections by their Y coordinate in sections, as a string like `-4`.
+    #[serde(rename = "Sections")]
+    #[serde(default)]
+    pub sections: HashMap<String, PoiSection>,
+}
+
+/// The points of interest of a 16×16×16 section of a chunk.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct PoiSection {
+    /// Whether the records are up to date. The game scans the blocks of
+    /// sections that are not valid for points of interest when their chunk
+    /// loads.
+    #[serde(rename = "Valid")]
+    pub valid: bool,
+    /// Points of interest in the section.
+    #[serde(rename = "Records")]
+    #[serde(default)]
+    pub records: Vec<PoiRecord>,
+}
+
+/// A point of interest.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct PoiRecord {
+    /// Type of the point of interest, like `minecraft:home` for beds or
+    /// `minecraft:librarian` for lecterns.
+    #[serde(rename = "type")]
+    pub poi_type: String,
+    /// Position of the block.
+    #[serde(rename = "pos", serialize_with = "pos_array")]
+    pub pos: [i32; 3],
+    /// Number of mobs that can still claim the point of interest, like 0
+    /// for a bed a villager sleeps in.
+    #[serde(rename = "free_tickets")]
+    pub free_tickets: i32,
+}
+
+/// A point of interest whose block is gone, found by `World::check_poi`.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct OrphanedPoi {
+    /// The record.
+    pub record: PoiRecord,
+    /// The block at the position of the record, or `None` if its chunk is
+    /// not generated.
+    pub block: Option<BlockState>,
+}
+
+impl PoiChunk {
+    pub fn new(data_version: i32) -> PoiChunk {
+        PoiChunk {
+            data_version: Some(data_version),
+            sections: HashMap::new(),
+        }
+    }
+
+    /// All records of the chunk, in no particular order.
+    pub fn records(&self) -> impl Iterator<Item = &PoiRecord> + '_ {
+        self.sections.values().flat_map(|s| s.records.iter())
+    }
+
+    /// Add a record to the section holding its position, replacing a record
+    /// at the same position.
+    pub fn insert(&mut self, record: PoiRecord) {
+        let key = (record.pos[1] >> 4).to_string();
+        let section = self.sections.entry(key).or_insert_with(|| PoiSection {
+            valid: true,
+            records: Vec::new(),
+        });
+        section.records.retain(|r| r.pos != record.pos);
+        section.records.push(record);
+    }
+
+    /// Keep only the records for which `f` returns true, returning the
+    /// removed records.
+    pub fn retain<F: FnMut(&PoiRecord) -> bool>(&mut self, mut f: F) -> Vec<PoiRecord> {
+        let mut removed = Vec::new();
+        for section in self.sections.values_mut() {
+            let (kept, gone) = section.records.drain(..).partition(|r| f(r));
+            section.records = kept;
+            removed.extend::<Vec<_>>(gone);
+        }
+        removed
+    }
+}
+
+impl PoiRecord {
+    pub fn new(poi_type: String, pos: BlockPos, free_tickets: i32) -> PoiRecord {
+        PoiRecord {
+            poi_type,
+            pos: [pos.x, pos.y, pos.z],
+            free_tickets,
+        }
+    }
+
+    /// Position of the block.
+    pub fn block_pos(&self) -> BlockPos {
+        BlockPos::new(self.pos[0], self.pos[1], self.pos[2])
+    }
+}
+
+/// Whether the block state is one the vanilla point of interest type stands
+/// for, or `None` if the type is not a vanilla one.
+///
+/// Beds are points of interest at their head only.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::poi::fits_block;
+/// use coruscant_world::BlockState;
+///
+/// let bed = BlockState::new("minecraft:red_bed".to_string()).with_property("part", "head");
+/// assert_eq!(fits_block("minecraft:home", &bed), Some(true));
+/// assert_eq!(fits_block("minecraft:meeting", &bed), Some(false));
+/// assert_eq!(fits_block("mymod:altar", &bed), None);
+/// ```
+pub fn fits_block(poi_type: &str, state: &BlockState) -> Option<bool> {
+    let block = strip_namespace(&state.name);
+    let fits = match strip_namespace(poi_type) {
+        "armorer" => block == "blast_furnace",
+        "butcher" => block == "smoker",
+        "cartographer" => block == "cartography_table",
+        "cleric" => block == "brewing_stand",
+        "farmer" => block == "composter",
+        "fisherman" => block == "barrel",
+        "fletcher" => block == "fletching_table",
+        "leatherworker" => block.ends_with("cauldron"),
+        "librarian" => block == "lectern",
+        "mason" => block == "stonecutter",
+        "shepherd" => block == "loom",
+        "toolsmith" => block == "smithing_table",
+        "weaponsmith" => block == "grindstone",
+        "home" => block.ends_with("_bed") && state.property("part") == Some("head"),
+        "meeting" => block == "bell",
+        "beehive" => block == "beehive",
+        "bee_nest" => block == "bee_nest",
+        "nether_portal" => block == "nether_portal",
+        "lodestone" => block == "lodestone",
+        "lightning_rod" => block.ends_with("lightning_rod"),
+        "test_instance" => block == "test_instance_block",
+        _ => return None,
+    };
+    Some(fits)
+}
+
+fn strip_namespace(id: &str) -> &str {
+    id.strip_prefix("minecraft:").unwrap_or(id)
+}
+
+fn pos_array<S: Serializer>(pos: &[i32; 3], serializer: S) -> Result<S::Ok, S::Error> {
+    as_nbt_array(&&pos[..], serializer)
+}
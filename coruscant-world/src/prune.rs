@@ -0,0 +1,254 @@
+//! Pruning of chunks and compaction of region files.
+//!
+//! Worlds of long-running servers fill up with chunks players only flew
+//! over, which the game keeps forever. A chunk's `InhabitedTime`, the number
+//! of ticks players spent near it, tells them apart from the chunks players
+//! built in. Pruned chunks are generated again when players come back.
+//!
+//! Removing or rewriting chunks leaves free sectors in region files, which
+//! the game reuses only when a chunk fits into them. Compaction rewrites the
+//! files without them.
+//!
+//! Each region file is rewritten into a temporary file that is renamed over
+//! the old one, so a crash never leaves a damaged region. See
+//! `World::prune`.
+//!
+//! Ref: https://minecraft.gamepedia.com/Chunk_format
+use crate::error::Result;
+use crate::region::{RegionFile, SECTOR_SIZE};
+use crate::world::{ChunkPos, Dimension};
+use coruscant_nbt::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// A rectangle of chunks, bounds included.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::prune::ChunkArea;
+/// use coruscant_world::ChunkPos;
+///
+/// let spawn = ChunkArea::around(ChunkPos::new(0, 0), 8);
+/// assert!(spawn.contains(ChunkPos::new(-8, 8)));
+/// assert!(!spawn.contains(ChunkPos::new(9, 0)));
+///
+/// let base = ChunkArea::from_blocks((1000, -40), (900, 200));
+/// assert_eq!(base.min, ChunkPos::new(56, -3));
+/// assert_eq!(base.max, ChunkPos::new(62, 12));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct ChunkArea {
+    /// Chunk with the lowest X and Z coordinates.
+    pub min: ChunkPos,
+    /// Chunk with the highest X and Z coordinates.
+    pub max: ChunkPos,
+}
+
+impl ChunkArea {
+    /// The area between two opposite corner chunks.
+    pub fn new(a: ChunkPos, b: ChunkPos) -> ChunkArea {
+        ChunkArea {
+            min: ChunkPos::new(a.x.min(b.x), a.z.min(b.z)),
+            max: ChunkPos::new(a.x.max(b.x), a.z.max(b.z)),
+        }
+    }
+
+    /// The area of chunks holding the blocks between two opposite corners,
+    /// given as X and Z block coordinates.
+    pub fn from_blocks(a: (i32, i32), b: (i32, i32)) -> ChunkArea {
+        ChunkArea::new(
+            ChunkPos::from_block(a.0, a.1),
+            ChunkPos::from_block(b.0, b.1),
+        )
+    }
+
+    /// The square of chunks at most `radius` chunks away from `center`.
+    pub fn around(center: ChunkPos, radius: i32) -> ChunkArea {
+        let radius = radius.abs();
+        ChunkArea {
+            min: ChunkPos::new(center.x - radius, center.z - radius),
+            max: ChunkPos::new(center.x + radius, center.z + radius),
+        }
+    }
+
+    /// Whether the chunk lies in this area.
+    pub fn contains(&self, pos: ChunkPos) -> bool {
+        (self.min.x..=self.max.x).contains(&pos.x) && (self.min.z..=self.max.z).contains(&pos.z)
+    }
+}
+
+/// Which chunks `World::prune` removes.
+///
+/// A chunk is removed if it was inhabited for less than
+/// `min_inhabited_time`, or if there are keep areas and it lies outside all
+/// of them. With neither set, no chunk is removed and region files are only
+/// compacted.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::prune::{ChunkArea, PruneOptions};
+/// use coruscant_world::ChunkPos;
+///
+/// let mut options = PruneOptions::new();
+/// // chunks players spent less than a minute near
+/// options.min_inhabited_time = Some(20 * 60);
+/// options.keep_areas.push(ChunkArea::around(ChunkPos::new(0, 0), 64));
+/// assert!(options.removes(ChunkPos::new(0, 0), 0));
+/// assert!(options.removes(ChunkPos::new(100, 0), 100_000));
+/// assert!(!options.removes(ChunkPos::new(3, 3), 100_000));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PruneOptions {
+    /// Remove chunks inhabited for fewer ticks than this.
+    pub min_inhabited_time: Option<i64>,
+    /// Remove chunks outside all of these areas, unless empty.
+    pub keep_areas: Vec<ChunkArea>,
+    /// Only count what would be removed and reclaimed, without changing
+    /// any file.
+    pub dry_run: bool,
+}
+
+impl PruneOptions {
+    pub fn new() -> PruneOptions {
+        PruneOptions::default()
+    }
+
+    /// Whether a chunk at this position, inhabited for this many ticks, is
+    /// removed.
+    pub fn removes(&self, pos: ChunkPos, inhabited_time: i64) -> bool {
+        let idle = matches!(self.min_inhabited_time, Some(min) if inhabited_time < min);
+        let outside =
+            !self.keep_areas.is_empty() && !self.keep_areas.iter().any(|a| a.contains(pos));
+        idle || outside
+    }
+
+    // Whether chunks have to be read to tell whether they are removed.
+    fn needs_inhabited_time(&self) -> bool {
+        self.min_inhabited_time.is_some()
+    }
+}
+
+/// What `World::prune` did, or would do on a dry run.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PruneStats {
+    /// Number of terrain chunks removed.
+    pub chunks_removed: usize,
+    /// Number of region files rewritten, of all kinds.
+    pub regions_rewritten: usize,
+    /// Number of region files deleted because no chunk was left in them.
+    pub regions_deleted: usize,
+    /// Number of bytes the region files shrank by.
+    pub bytes_reclaimed: u64,
+}
+
+// Prune the terrain of the dimension, then remove the entities and points
+// of interest of the removed chunks along with it.
+pub(crate) fn prune_dimension(dimension: &Dimension, options: &PruneOptions) -> Result<PruneStats> {
+    let mut stats = PruneStats::default();
+    let mut removed = HashSet::new();
+    for ((region_x, region_z), path) in dimension.region_files()? {
+        let region = open(&path, options.dry_run)?;
+        let mut remove = HashSet::new();
+        for (x, z) in region.chunks() {
+            let pos = ChunkPos::new(region_x * 32 + x, region_z * 32 + z);
+            let inhabited_time = if options.needs_inhabited_time() {
+                let chunk: Option<Value> = region.read_chunk(x, z)?;
+                chunk.as_ref().map_or(0, inhabited_time)
+            } else {
+                0
+            };
+            if options.removes(pos, inhabited_time) {
+                remove.insert((x, z));
+                removed.insert(pos);
+            }
+        }
+        stats.chunks_removed += remove.len();
+        rewrite(region, &remove, options.dry_run, &mut stats)?;
+    }
+    for ((region_x, region_z), path) in dimension
+        .entity_files()?
+        .into_iter()
+        .chain(dimension.poi_files()?)
+    {
+        let region = open(&path, options.dry_run)?;
+        let remove = region
+            .chunks()
+            .filter(|(x, z)| removed.contains(&ChunkPos::new(region_x * 32 + x, region_z * 32 + z)))
+            .collect();
+        rewrite(region, &remove, options.dry_run, &mut stats)?;
+    }
+    Ok(stats)
+}
+
+// Delete the region if no chunk is left, or rewrite it if chunks are
+// removed or sectors are free.
+fn rewrite(
+    mut region: RegionFile,
+    remove: &HashSet<(i32, i32)>,
+    dry_run: bool,
+    stats: &mut PruneStats,
+) -> Result<()> {
+    let len = file_len(region.path())?;
+    if region.chunks().all(|chunk| remove.contains(&chunk)) {
+        if !dry_run {
+            // emptied first, which deletes the `.mcc` files of the chunks
+            region.retain_chunks(|_, _| false)?;
+            fs::remove_file(region.path())?;
+        }
+        stats.regions_deleted += 1;
+        stats.bytes_reclaimed += len;
+        return Ok(());
+    }
+    if remove.is_empty() && region.free_sectors() == 0 && len % SECTOR_SIZE as u64 == 0 {
+        return Ok(());
+    }
+    if dry_run {
+        let removed: usize = remove
+            .iter()
+            .map(|&(x, z)| region.chunk_sectors(x, z))
+            .sum();
+        stats.regions_rewritten += 1;
+        stats.bytes_reclaimed += ((region.free_sectors() + removed) * SECTOR_SIZE) as u64;
+        return Ok(());
+    }
+    region.retain_chunks(|x, z| !remove.contains(&(x, z)))?;
+    stats.regions_rewritten += 1;
+    stats.bytes_reclaimed += len.saturating_sub(file_len(region.path())?);
+    Ok(())
+}
+
+// `InhabitedTime` at the root of the chunk since 1.18, and in `Level`
+// before.
+fn inhabited_time(chunk: &Value) -> i64 {
+    let chunk = match chunk {
+        Value::Compound(chunk) => chunk,
+        _ => return 0,
+    };
+    let inhabited = match chunk.get("Level") {
+        Some(Value::Compound(level)) => level.get("InhabitedTime"),
+        _ => chunk.get("InhabitedTime"),
+    };
+    match inhabited {
+        Some(Value::Long(ticks)) => *ticks,
+        Some(Value::Int(ticks)) => i64::from(*ticks),
+        _ => 0,
+    }
+}
+
+// Dry runs change no file, so they work on read-only worlds too.
+fn open(path: &Path, dry_run: bool) -> Result<RegionFile> {
+    match dry_run {
+        true => RegionFile::open_read_only(path),
+        false => RegionFile::open(path),
+    }
+}
+
+fn file_len(path: &Path) -> Result<u64> {
+    Ok(fs::metadata(path)?.len())
+}
@@ -0,0 +1,249 @@
+//! Raids format parsed as serde favored Rust structs.
+//!
+//! The raids of a dimension are kept in the saved data file
+//! `data/raids.dat` of the overworld, `DIM-1/data/raids_nether.dat` and
+//! `DIM1/data/raids_end.dat`; see `file_name`. 1.21.5 renamed every field,
+//! so the data is one of two variants of `Raids`.
+//!
+//! Ref: https://minecraft.gamepedia.com/Raid#Data_values
+use crate::uuid::Uuid;
+use coruscant_nbt::{as_nbt_array, Value};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Data version of 25w02a (1.21.5), since which raids are saved with
+/// lowercase field names.
+pub const DATA_VERSION_RAIDS_CODEC: i32 = 4298;
+
+/// Name of the raids saved data file of a dimension, without `.dat`.
+pub fn file_name(dimension: &str) -> &'static str {
+    match dimension {
+        "minecraft:the_nether" => "raids_nether",
+        "minecraft:the_end" => "raids_end",
+        _ => "raids",
+    }
+}
+
+/// Raids of a dimension, in the format of the game version that saved them.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::raids::Raids;
+///
+/// let snbt = r#"{Raids: [], NextAvailableID: 3, Tick: 1200}"#;
+/// let raids: Raids = coruscant_nbt::from_str_snbt(snbt).unwrap();
+/// assert_eq!(raids.next_id(), 3);
+/// assert!(matches!(raids, Raids::Legacy(_)));
+/// ```
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+#[non_exhaustive]
+pub enum Raids {
+    /// Raids since 1.21.5.
+    Current(CurrentRaids),
+    /// Raids before 1.21.5.
+    Legacy(LegacyRaids),
+}
+
+/// Raids of a dimension before 1.21.5.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct LegacyRaids {
+    /// Raids in progress.
+    #[serde(rename = "Raids")]
+    pub raids: Vec<LegacyRaid>,
+    /// ID of the next raid.
+    #[serde(rename = "NextAvailableID")]
+    pub next_available_id: i32,
+    /// Game ticks the raids were updated for.
+    #[serde(rename = "Tick")]
+    pub tick: i32,
+}
+
+/// A raid before 1.21.5.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct LegacyRaid {
+    #[serde(rename = "Id")]
+    pub id: i32,
+    /// Whether the first wave has spawned.
+    #[serde(rename = "Started")]
+    pub started: bool,
+    /// Whether the raid is still going on.
+    #[serde(rename = "Active")]
+    pub active: bool,
+    /// Game ticks since the raid was triggered.
+    #[serde(rename = "TicksActive")]
+    pub ticks_active: i64,
+    /// Level of the Bad Omen effect that triggered the raid.
+    #[serde(rename = "BadOmenLevel")]
+    pub bad_omen_level: i32,
+    /// Number of waves spawned so far.
+    #[serde(rename = "GroupsSpawned")]
+    pub groups_spawned: i32,
+    /// Game ticks until the next wave.
+    #[serde(rename = "PreRaidTicks")]
+    pub pre_raid_ticks: i32,
+    /// Game ticks since the raid ended.
+    #[serde(rename = "PostRaidTicks")]
+    pub post_raid_ticks: i32,
+    /// Health of the raiders of the current wave when it spawned.
+    #[serde(rename = "TotalHealth")]
+    pub total_health: f32,
+    /// Number of waves, depending on the difficulty.
+    #[serde(rename = "NumGroups")]
+    pub num_groups: i32,
+    /// `ongoing`, `victory`, `loss` or `stopped`.
+    #[serde(rename = "Status")]
+    pub status: String,
+    /// X coordinate of the center of the raid.
+    #[serde(rename = "CX")]
+    pub center_x: i32,
+    /// Y coordinate of the center of the raid.
+    #[serde(rename = "CY")]
+    pub center_y: i32,
+    /// Z coordinate of the center of the raid.
+    #[serde(rename = "CZ")]
+    pub center_z: i32,
+    /// Players who took part in the raid, for the Hero of the Village
+    /// effect.
+    #[serde(rename = "HeroesOfTheVillage", serialize_with = "uuid_list")]
+    #[serde(default)]
+    pub heroes_of_the_village: Vec<Uuid>,
+}
+
+/// Raids of a dimension since 1.21.5.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct CurrentRaids {
+    /// Raids in progress.
+    #[serde(rename = "raids")]
+    #[serde(default)]
+    pub raids: Vec<CurrentRaid>,
+    /// ID of the next raid.
+    #[serde(rename = "next_id")]
+    pub next_id: i32,
+    /// Game ticks the raids were updated for.
+    #[serde(rename = "tick")]
+    pub tick: i32,
+}
+
+/// A raid since 1.21.5, with the fields of `LegacyRaid` renamed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct CurrentRaid {
+    #[serde(rename = "id")]
+    pub id: i32,
+    #[serde(rename = "started")]
+    pub started: bool,
+    #[serde(rename = "active")]
+    pub active: bool,
+    #[serde(rename = "ticks_active")]
+    pub ticks_active: i64,
+    #[serde(rename = "raid_omen_level")]
+    pub raid_omen_level: i32,
+    #[serde(rename = "groups_spawned")]
+    pub groups_spawned: i32,
+    /// Game ticks until the next wave.
+    #[serde(rename = "cooldown_ticks")]
+    pub cooldown_ticks: i32,
+    #[serde(rename = "post_raid_ticks")]
+    pub post_raid_ticks: i32,
+    #[serde(rename = "total_health")]
+    pub total_health: f32,
+    #[serde(rename = "group_count")]
+    pub group_count: i32,
+    #[serde(rename = "status")]
+    pub status: String,
+    #[serde(rename = "heroes_of_the_village", serialize_with = "uuid_list")]
+    #[serde(default)]
+    pub heroes_of_the_village: Vec<Uuid>,
+    /// Center of the raid.
+    #[serde(rename = "center", serialize_with = "pos_array")]
+    pub center: [i32; 3],
+}
+
+impl Raids {
+    /// No raids, in the format of the given data version.
+    pub fn new(data_version: i32) -> Raids {
+        if data_version >= DATA_VERSION_RAIDS_CODEC {
+            Raids::Current(CurrentRaids::default())
+        } else {
+            Raids::Legacy(LegacyRaids::default())
+        }
+    }
+
+    /// Number of raids in progress.
+    pub fn len(&self) -> usize {
+        match self {
+            Raids::Current(raids) => raids.raids.len(),
+            Raids::Legacy(raids) => raids.raids.len(),
+        }
+    }
+
+    /// Whether there are no raids in progress.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// ID of the next raid.
+    pub fn next_id(&self) -> i32 {
+        match self {
+            Raids::Current(raids) => raids.next_id,
+            Raids::Legacy(raids) => raids.next_available_id,
+        }
+    }
+
+    /// Centers of the raids in progress.
+    pub fn centers(&self) -> Vec<[i32; 3]> {
+        match self {
+            Raids::Current(raids) => raids.raids.iter().map(|r| r.center).collect(),
+            Raids::Legacy(raids) => (raids.raids.iter())
+                .map(|r| [r.center_x, r.center_y, r.center_z])
+                .collect(),
+        }
+    }
+
+    /// Remove all raids. Raiders already spawned stay in the world.
+    pub fn clear(&mut self) {
+        match self {
+            Raids::Current(raids) => raids.raids.clear(),
+            Raids::Legacy(raids) => raids.raids.clear(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Raids {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Buffered as `Value`, which keeps NBT bytes usable as booleans
+        let value = Value::deserialize(deserializer)?;
+        let current = match &value {
+            Value::Compound(compound) => compound.contains_key("next_id"),
+            _ => false,
+        };
+        if current {
+            coruscant_nbt::from_value(value)
+                .map(Raids::Current)
+                .map_err(de::Error::custom)
+        } else {
+            coruscant_nbt::from_value(value)
+                .map(Raids::Legacy)
+                .map_err(de::Error::custom)
+        }
+    }
+}
+
+fn pos_array<S: Serializer>(pos: &[i32; 3], serializer: S) -> Result<S::Ok, S::Error> {
+    as_nbt_array(&&pos[..], serializer)
+}
+
+fn uuid_list<S: Serializer>(uuids: &[Uuid], serializer: S) -> Result<S::Ok, S::Error> {
+    // Built as `Value`, as the serializer writes arrays in lists as lists
+    let uuids = uuids
+        .iter()
+        .map(|uuid| Value::IntArray(uuid.to_ints().to_vec()));
+    Value::List(uuids.collect()).serialize(serializer)
+}
@@ -0,0 +1,643 @@
+//! Anvil region files, `r.X.Z.mca`, which store the chunks of a world.
+//!
+//! A region file holds 32×32 chunks. It starts with an 8 KiB header: a table
+//! of 1024 locations, 4 bytes each, giving the offset of a chunk in 4 KiB
+//! sectors (3 bytes) and the number of sectors it takes (1 byte), followed
+//! by a table of 1024 big endian last modification times in epoch seconds.
+//! Each chunk then starts at its sector with a 4 byte big endian length, a
+//! byte of compression scheme and the compressed NBT data, padded with zeros
+//! to a whole number of sectors.
+//!
+//! A chunk that takes more than 255 sectors is stored in a separate file
+//! `c.X.Z.mcc` next to the region file, named after the chunk's world
+//! coordinates; the region file only keeps its compression scheme, with the
+//! `0x80` bit set.
+//!
+//! Ref: https://minecraft.gamepedia.com/Region_file_format
+use crate::error::{Error, Result};
+use coruscant_nbt::Compression;
+use flate2::{
+    read::{GzDecoder, ZlibDecoder},
+    write::{GzEncoder, ZlibEncoder},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Size of a sector in a region file.
+pub const SECTOR_SIZE: usize = 4096;
+
+const CHUNK_COUNT: usize = 1024;
+const HEADER_SECTORS: usize = 2;
+const MAX_SECTOR_COUNT: usize = 255;
+const EXTERNAL_FLAG: u8 = 0x80;
+
+/// How the NBT data of a chunk is compressed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CompressionScheme {
+    /// GZip (RFC1952), unused by vanilla in practice.
+    Gzip,
+    /// Zlib (RFC1950), what vanilla writes.
+    Zlib,
+    /// Not compressed, since 1.15.1.
+    Uncompressed,
+}
+
+impl CompressionScheme {
+    /// The id of this scheme in a region file.
+    pub fn id(self) -> u8 {
+        match self {
+            CompressionScheme::Gzip => 1,
+            CompressionScheme::Zlib => 2,
+            CompressionScheme::Uncompressed => 3,
+        }
+    }
+
+    /// The scheme with the given id, without the external chunk bit.
+    pub fn from_id(id: u8) -> Result<Self> {
+        match id {
+            1 => Ok(CompressionScheme::Gzip),
+            2 => Ok(CompressionScheme::Zlib),
+            3 => Ok(CompressionScheme::Uncompressed),
+            id => Err(Error::UnknownCompression(id)),
+        }
+    }
+
+    /// Compress NBT data with this scheme.
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>> {
+        let ans = match self {
+            CompressionScheme::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()?
+            }
+            CompressionScheme::Zlib => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()?
+            }
+            CompressionScheme::Uncompressed => data.to_vec(),
+        };
+        Ok(ans)
+    }
+
+    /// Decompress data compressed with this scheme back into NBT.
+    pub fn decompress(self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut ans = Vec::with_capacity(data.len() * 4);
+        match self {
+            CompressionScheme::Gzip => GzDecoder::new(data).read_to_end(&mut ans)?,
+            CompressionScheme::Zlib => ZlibDecoder::new(data).read_to_end(&mut ans)?,
+            CompressionScheme::Uncompressed => return Ok(data.to_vec()),
+        };
+        Ok(ans)
+    }
+}
+
+/// The file name of the region with the given region coordinates.
+pub fn region_file_name(region_x: i32, region_z: i32) -> String {
+    format!("r.{}.{}.mca", region_x, region_z)
+}
+
+/// The region coordinates in a file name like `r.-1.2.mca`.
+pub fn parse_region_file_name(name: &str) -> Option<(i32, i32)> {
+    let name = name.strip_prefix("r.")?.strip_suffix(".mca")?;
+    let mut parts = name.split('.');
+    let x = parts.next()?.parse().ok()?;
+    let z = parts.next()?.parse().ok()?;
+    match parts.next() {
+        Some(_) => None,
+        None => Some((x, z)),
+    }
+}
+
+/// An open region file.
+///
+/// Chunks are addressed by chunk coordinates, which are taken modulo 32, so
+/// both world chunk coordinates and coordinates local to the region work.
+/// Writes go to the file at once; there is nothing to flush. Reads do not
+/// move the cursor of the file, so a region shared between threads can be
+/// read from all of them.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_nbt::Value;
+/// use coruscant_world::region::RegionFile;
+/// # let dir = std::env::temp_dir().join("coruscant-world-region-doc");
+/// # std::fs::create_dir_all(&dir).unwrap();
+/// # let _ = std::fs::remove_file(dir.join("r.0.0.mca"));
+///
+/// let mut region = RegionFile::create(dir.join("r.0.0.mca")).unwrap();
+/// let chunk = coruscant_nbt::snbt::parse("{xPos:3,zPos:4,Status:\"full\"}").unwrap();
+/// region.write_chunk(3, 4, &chunk).unwrap();
+///
+/// let region = RegionFile::open(dir.join("r.0.0.mca")).unwrap();
+/// assert!(region.chunk_exists(3, 4));
+/// let read: Option<Value> = region.read_chunk(3, 4).unwrap();
+/// assert_eq!(read, Some(chunk));
+/// assert_eq!(region.read_chunk::<Value>(0, 0).unwrap(), None);
+/// ```
+#[derive(Debug)]
+pub struct RegionFile {
+    file: File,
+    writable: bool,
+    path: PathBuf,
+    position: Option<(i32, i32)>,
+    locations: Vec<u32>,
+    timestamps: Vec<u32>,
+    used_sectors: Vec<bool>,
+}
+
+impl RegionFile {
+    /// Open an existing region file for reading and writing.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(&path)?;
+        Self::from_file(file, path.as_ref(), true)
+    }
+
+    /// Open an existing region file for reading only, like the files of a
+    /// world on a read-only mount. Writing to the region fails with
+    /// `Error::ReadOnlyRegion`.
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(&path)?;
+        Self::from_file(file, path.as_ref(), false)
+    }
+
+    /// Open a region file for reading and writing, creating an empty one if
+    /// it does not exist.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        Self::from_file(file, path.as_ref(), true)
+    }
+
+    fn from_file(mut file: File, path: &Path, writable: bool) -> Result<Self> {
+        let len = file.metadata()?.len() as usize;
+        let mut header = vec![0; HEADER_SECTORS * SECTOR_SIZE];
+        if len == 0 {
+            // a new file, or an empty one that vanilla reads as no chunks
+            if writable {
+                file.write_all(&header)?;
+            }
+        } else if len < HEADER_SECTORS * SECTOR_SIZE {
+            return Err(Error::CorruptRegion("truncated header"));
+        } else {
+            read_exact_at(&file, &mut header, 0)?;
+        }
+        let mut words = header
+            .chunks_exact(4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+        let locations: Vec<u32> = words.by_ref().take(CHUNK_COUNT).collect();
+        let timestamps: Vec<u32> = words.collect();
+
+        let sectors = len.max(HEADER_SECTORS * SECTOR_SIZE).div_ceil(SECTOR_SIZE);
+        let mut used_sectors = vec![false; sectors];
+        used_sectors[..HEADER_SECTORS]
+            .iter_mut()
+            .for_each(|s| *s = true);
+        for &location in &locations {
+            let (offset, count) = split_location(location);
+            // out of range entries are reported when the chunk is read
+            if offset >= HEADER_SECTORS && offset + count <= sectors {
+                used_sectors[offset..offset + count]
+                    .iter_mut()
+                    .for_each(|s| *s = true);
+            }
+        }
+        let position = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(parse_region_file_name);
+        Ok(RegionFile {
+            file,
+            writable,
+            path: path.to_owned(),
+            position,
+            locations,
+            timestamps,
+            used_sectors,
+        })
+    }
+
+    /// Path of this region file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Region coordinates taken from the file name, if it has the form
+    /// `r.X.Z.mca`.
+    pub fn position(&self) -> Option<(i32, i32)> {
+        self.position
+    }
+
+    /// Whether the chunk is stored in this region.
+    pub fn chunk_exists(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        self.locations[index(chunk_x, chunk_z)] != 0
+    }
+
+    /// Last time the chunk was saved, in seconds since the epoch.
+    pub fn timestamp(&self, chunk_x: i32, chunk_z: i32) -> Option<u32> {
+        let index = index(chunk_x, chunk_z);
+        if self.locations[index] == 0 {
+            return None;
+        }
+        Some(self.timestamps[index])
+    }
+
+    /// Region local coordinates of all stored chunks.
+    pub fn chunks(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.locations
+            .iter()
+            .enumerate()
+            .filter(|(_, &location)| location != 0)
+            .map(|(index, _)| ((index % 32) as i32, (index / 32) as i32))
+    }
+
+    /// Read the chunk and deserialize it from NBT.
+    pub fn read_chunk<T: DeserializeOwned>(&self, chunk_x: i32, chunk_z: i32) -> Result<Option<T>> {
+        match self.read_chunk_data(chunk_x, chunk_z)? {
+            Some(data) => Ok(Some(coruscant_nbt::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Read the uncompressed NBT data of the chunk.
+    pub fn read_chunk_data(&self, chunk_x: i32, chunk_z: i32) -> Result<Option<Vec<u8>>> {
+        match self.read_chunk_raw(chunk_x, chunk_z)? {
+            Some((scheme, payload)) => Ok(Some(scheme.decompress(&payload)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Read the compressed payload of the chunk, following it into its
+    /// `.mcc` file if it is stored outside of the region.
+    pub fn read_chunk_raw(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+    ) -> Result<Option<(CompressionScheme, Vec<u8>)>> {
+        let (offset, count) = split_location(self.locations[index(chunk_x, chunk_z)]);
+        if count == 0 {
+            return Ok(None);
+        }
+        if offset < HEADER_SECTORS || offset + count > self.used_sectors.len() {
+            return Err(Error::CorruptRegion("chunk sectors out of file"));
+        }
+        let start = (offset * SECTOR_SIZE) as u64;
+        let mut head = [0; 5];
+        read_exact_at(&self.file, &mut head, start)?;
+        let len = u32::from_be_bytes([head[0], head[1], head[2], head[3]]) as usize;
+        if len == 0 || len + 4 > count * SECTOR_SIZE {
+            return Err(Error::CorruptRegion("invalid chunk length"));
+        }
+        let scheme_id = head[4];
+        let scheme = CompressionScheme::from_id(scheme_id & !EXTERNAL_FLAG)?;
+        let payload = if scheme_id & EXTERNAL_FLAG != 0 {
+            fs::read(self.external_path(chunk_x, chunk_z)?)?
+        } else {
+            let mut payload = vec![0; len - 1];
+            read_exact_at(&self.file, &mut payload, start + 5)?;
+            payload
+        };
+        Ok(Some((scheme, payload)))
+    }
+
+    /// Serialize the chunk into NBT and write it with Zlib compression, as
+    /// vanilla does.
+    pub fn write_chunk<T: Serialize + ?Sized>(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk: &T,
+    ) -> Result<()> {
+        let data = coruscant_nbt::to_vec(chunk)?;
+        self.write_chunk_data(chunk_x, chunk_z, &data, CompressionScheme::Zlib)
+    }
+
+    /// Compress NBT data with the given scheme and write it as the chunk.
+    pub fn write_chunk_data(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        data: &[u8],
+        scheme: CompressionScheme,
+    ) -> Result<()> {
+        let payload = scheme.compress(data)?;
+        self.write_chunk_raw(chunk_x, chunk_z, scheme, &payload)
+    }
+
+    /// Write an already compressed payload as the chunk.
+    ///
+    /// The chunk is placed into the first free run of sectors that fits,
+    /// or appended at the end of the file. Payloads too large for 255
+    /// sectors are written to a `.mcc` file. The old sectors of the chunk
+    /// are only freed after the header points to the new ones.
+    pub fn write_chunk_raw(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        scheme: CompressionScheme,
+        payload: &[u8],
+    ) -> Result<()> {
+        self.check_writable()?;
+        let index = index(chunk_x, chunk_z);
+        let mut count = sector_count(payload.len());
+        let mut scheme_id = scheme.id();
+        let mut body = payload;
+        let external_path = self.external_path(chunk_x, chunk_z);
+        let mut stale_external = None;
+        if count > MAX_SECTOR_COUNT {
+            let external_path = external_path?;
+            fs::write(external_path, payload)?;
+            scheme_id |= EXTERNAL_FLAG;
+            body = &[];
+            count = 1;
+        } else {
+            stale_external = external_path.ok();
+        }
+
+        let offset = self.allocate(count);
+        let mut buf = Vec::with_capacity(count * SECTOR_SIZE);
+        buf.extend_from_slice(&(body.len() as u32 + 1).to_be_bytes());
+        buf.push(scheme_id);
+        buf.extend_from_slice(body);
+        buf.resize(count * SECTOR_SIZE, 0);
+        self.file
+            .seek(SeekFrom::Start((offset * SECTOR_SIZE) as u64))?;
+        self.file.write_all(&buf)?;
+
+        let old = self.locations[index];
+        self.set_header(index, ((offset as u32) << 8) | count as u32, now())?;
+        self.free(old);
+        // only now the header no longer points at an external chunk
+        if let Some(external_path) = stale_external {
+            remove_if_exists(&external_path)?;
+        }
+        Ok(())
+    }
+
+    /// Remove the chunk from the region, returning whether it was stored.
+    pub fn remove_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<bool> {
+        self.check_writable()?;
+        let index = index(chunk_x, chunk_z);
+        let old = self.locations[index];
+        if old == 0 {
+            return Ok(false);
+        }
+        self.set_header(index, 0, 0)?;
+        self.free(old);
+        if let Ok(external_path) = self.external_path(chunk_x, chunk_z) {
+            remove_if_exists(&external_path)?;
+        }
+        Ok(true)
+    }
+
+    /// Flush all writes of this region file to disk.
+    pub fn sync_all(&self) -> Result<()> {
+        Ok(self.file.sync_all()?)
+    }
+
+    /// Whether no chunk is stored in this region.
+    pub fn is_empty(&self) -> bool {
+        self.locations.iter().all(|&location| location == 0)
+    }
+
+    /// Number of sectors the chunk takes in the file, or 0 if it is not
+    /// stored.
+    pub fn chunk_sectors(&self, chunk_x: i32, chunk_z: i32) -> usize {
+        split_location(self.locations[index(chunk_x, chunk_z)]).1
+    }
+
+    /// Number of sectors in the file that no chunk uses, left behind by
+    /// chunks that were moved or removed.
+    pub fn free_sectors(&self) -> usize {
+        self.used_sectors.iter().filter(|&&used| !used).count()
+    }
+
+    /// Rewrite the region file without free sectors. See `retain_chunks`.
+    pub fn compact(&mut self) -> Result<()> {
+        self.retain_chunks(|_, _| true).map(|_| ())
+    }
+
+    /// Rewrite the region file with only the chunks for which `keep`
+    /// returns true, packed one after another. Returns the region local
+    /// coordinates of the removed chunks.
+    ///
+    /// The new file is written next to this one as `r.X.Z.mca.tmp`, flushed
+    /// to disk and then renamed over it, so that a crash leaves either the
+    /// old or the new region, never half of one. Chunks are copied as they
+    /// are, keeping their compression and timestamps; the `.mcc` files of
+    /// removed chunks are deleted after the rename.
+    pub fn retain_chunks<F: FnMut(i32, i32) -> bool>(
+        &mut self,
+        keep: F,
+    ) -> Result<Vec<(i32, i32)>> {
+        self.check_writable()?;
+        let mut name = self.path.file_name().unwrap_or_default().to_owned();
+        name.push(".tmp");
+        let temp_path = self.path.with_file_name(name);
+        let result = self.write_retained(&temp_path, keep).and_then(|removed| {
+            fs::rename(&temp_path, &self.path)?;
+            Ok(removed)
+        });
+        let removed = match result {
+            Ok(removed) => removed,
+            Err(e) => {
+                // the old file is untouched, only the partial copy goes
+                let _ = fs::remove_file(&temp_path);
+                return Err(e);
+            }
+        };
+        *self = RegionFile::open(&self.path)?;
+        for &(x, z) in &removed {
+            if let Ok(external_path) = self.external_path(x, z) {
+                remove_if_exists(&external_path)?;
+            }
+        }
+        Ok(removed)
+    }
+
+    // Copy the kept chunks into a new region file, returning the removed
+    // ones.
+    fn write_retained<F: FnMut(i32, i32) -> bool>(
+        &self,
+        temp_path: &Path,
+        mut keep: F,
+    ) -> Result<Vec<(i32, i32)>> {
+        let mut temp = File::create(temp_path)?;
+        let mut header = vec![0; HEADER_SECTORS * SECTOR_SIZE];
+        temp.write_all(&header)?;
+        let mut removed = Vec::new();
+        let mut next = HEADER_SECTORS;
+        for (x, z) in self.chunks().collect::<Vec<_>>() {
+            let index = index(x, z);
+            if !keep(x, z) {
+                removed.push((x, z));
+                continue;
+            }
+            let sectors = self.read_sectors(self.locations[index])?;
+            let location = ((next as u32) << 8) | (sectors.len() / SECTOR_SIZE) as u32;
+            header[index * 4..index * 4 + 4].copy_from_slice(&location.to_be_bytes());
+            let timestamp = self.timestamps[index].to_be_bytes();
+            header[SECTOR_SIZE + index * 4..SECTOR_SIZE + index * 4 + 4]
+                .copy_from_slice(&timestamp);
+            temp.write_all(&sectors)?;
+            next += sectors.len() / SECTOR_SIZE;
+        }
+        temp.seek(SeekFrom::Start(0))?;
+        temp.write_all(&header)?;
+        temp.sync_all()?;
+        Ok(removed)
+    }
+
+    fn check_writable(&self) -> Result<()> {
+        match self.writable {
+            true => Ok(()),
+            false => Err(Error::ReadOnlyRegion),
+        }
+    }
+
+    fn external_path(&self, chunk_x: i32, chunk_z: i32) -> Result<PathBuf> {
+        let (region_x, region_z) = self.position.ok_or(Error::CorruptRegion(
+            "external chunk of a region file not named r.X.Z.mca",
+        ))?;
+        let x = region_x * 32 + chunk_x.rem_euclid(32);
+        let z = region_z * 32 + chunk_z.rem_euclid(32);
+        Ok(self.path.with_file_name(format!("c.{}.{}.mcc", x, z)))
+    }
+
+    // The sectors of a chunk, trimmed to the ones its length needs and
+    // padded with zeros if the file ends early.
+    fn read_sectors(&self, location: u32) -> Result<Vec<u8>> {
+        let (offset, count) = split_location(location);
+        if offset < HEADER_SECTORS || offset + count > self.used_sectors.len() {
+            return Err(Error::CorruptRegion("chunk sectors out of file"));
+        }
+        let mut sectors = vec![0; count * SECTOR_SIZE];
+        let read = read_at(&self.file, &mut sectors, (offset * SECTOR_SIZE) as u64)?;
+        if read < 5 {
+            return Err(Error::CorruptRegion("invalid chunk length"));
+        }
+        let len = u32::from_be_bytes([sectors[0], sectors[1], sectors[2], sectors[3]]) as usize;
+        if len == 0 || len + 4 > count * SECTOR_SIZE {
+            return Err(Error::CorruptRegion("invalid chunk length"));
+        }
+        sectors.resize(sector_count(len - 1) * SECTOR_SIZE, 0);
+        Ok(sectors)
+    }
+
+    fn set_header(&mut self, index: usize, location: u32, timestamp: u32) -> Result<()> {
+        self.file.seek(SeekFrom::Start((index * 4) as u64))?;
+        self.file.write_all(&location.to_be_bytes())?;
+        self.file
+            .seek(SeekFrom::Start((SECTOR_SIZE + index * 4) as u64))?;
+        self.file.write_all(&timestamp.to_be_bytes())?;
+        self.locations[index] = location;
+        self.timestamps[index] = timestamp;
+        Ok(())
+    }
+
+    // First fit over the sector bitmap, or grow the file.
+    fn allocate(&mut self, count: usize) -> usize {
+        let mut run = 0;
+        for (sector, &used) in self.used_sectors.iter().enumerate() {
+            if used {
+                run = 0;
+                continue;
+            }
+            run += 1;
+            if run == count {
+                let start = sector + 1 - count;
+                self.used_sectors[start..=sector]
+                    .iter_mut()
+                    .for_each(|s| *s = true);
+                return start;
+            }
+        }
+        let start = self.used_sectors.len() - run;
+        self.used_sectors.resize(start + count, true);
+        self.used_sectors[start..]
+            .iter_mut()
+            .for_each(|s| *s = true);
+        start
+    }
+
+    fn free(&mut self, location: u32) {
+        let (offset, count) = split_location(location);
+        if offset >= HEADER_SECTORS && offset + count <= self.used_sectors.len() {
+            self.used_sectors[offset..offset + count]
+                .iter_mut()
+                .for_each(|s| *s = false);
+        }
+    }
+}
+
+#[inline]
+fn index(chunk_x: i32, chunk_z: i32) -> usize {
+    (chunk_x.rem_euclid(32) + chunk_z.rem_euclid(32) * 32) as usize
+}
+
+#[inline]
+fn split_location(location: u32) -> (usize, usize) {
+    ((location >> 8) as usize, (location & 0xFF) as usize)
+}
+
+// Sectors taken by a payload with its length and compression scheme.
+#[inline]
+fn sector_count(payload_len: usize) -> usize {
+    (payload_len + 5).div_ceil(SECTOR_SIZE)
+}
+
+fn now() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as u32)
+        .unwrap_or(0)
+}
+
+// Read into `buf` from `offset` without moving the cursor of the file,
+// returning the number of bytes read, which is short only at the end of
+// the file.
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    #[cfg(unix)]
+    use std::os::unix::fs::FileExt;
+    #[cfg(windows)]
+    use std::os::windows::fs::FileExt;
+
+    let mut read = 0;
+    while read < buf.len() {
+        let pos = offset + read as u64;
+        #[cfg(unix)]
+        let result = file.read_at(&mut buf[read..], pos);
+        #[cfg(windows)]
+        let result = file.seek_read(&mut buf[read..], pos);
+        match result {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(read)
+}
+
+fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    if read_at(file, buf, offset)? < buf.len() {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
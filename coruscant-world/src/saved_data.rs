@@ -0,0 +1,209 @@
+//! Saved data, the files in the `data/` folder of a dimension.
+//!
+//! Each file is a gzip compressed NBT file named after its data, like
+//! `data/map_0.dat` or `data/raids.dat`, with the data in a `data` compound
+//! and, since 1.13, the version of the game that saved it. Besides the
+//! generic `SavedData`, this module has the smaller files: `idcounts.dat`,
+//! `random_sequences.dat` and `chunks.dat`. Larger ones have their own
+//! modules, like `map_data`, `raids` and `scoreboard`.
+//!
+//! Ref: https://minecraft.gamepedia.com/Java_Edition_level_format#data_folder
+use crate::error::Result;
+use crate::world::ChunkPos;
+use coruscant_nbt::{as_nbt_array, Compression, Value};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// Root of a saved data file, holding data of type `T`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename = "")]
+#[non_exhaustive]
+pub struct SavedData<T> {
+    /// Version of the game the file was saved in, since 1.13.
+    #[serde(rename = "DataVersion")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_version: Option<i32>,
+    #[serde(rename = "data")]
+    pub data: T,
+}
+
+impl<T> SavedData<T> {
+    pub fn new(data_version: i32, data: T) -> SavedData<T> {
+        SavedData {
+            data_version: Some(data_version),
+            data,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> SavedData<T> {
+    /// Read a gzip compressed saved data file.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<SavedData<T>> {
+        let file = BufReader::new(File::open(path)?);
+        Ok(coruscant_nbt::from_gzip_reader(file)?)
+    }
+}
+
+impl<T: Serialize> SavedData<T> {
+    /// Write a gzip compressed saved data file.
+    ///
+    /// The data is written to a temporary file next to `path` first, which
+    /// then replaces the old file.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let mut file = BufWriter::new(File::create(&tmp)?);
+        coruscant_nbt::to_gzip_writer(&mut file, self, Compression::default())?;
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// The last IDs handed out, in `data/idcounts.dat` of the overworld.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct IdCounts {
+    /// ID of the last map created; missing before the first one.
+    #[serde(rename = "map")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub map: Option<i32>,
+}
+
+/// Random sequences of loot tables and other random events, since 1.20, in
+/// `data/random_sequences.dat` of the overworld.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct RandomSequences {
+    /// Salt mixed into the seeds of new sequences, since 1.20.2.
+    #[serde(rename = "salt")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<i32>,
+    /// Whether the world seed is mixed into the seeds of new sequences,
+    /// since 1.20.2.
+    #[serde(rename = "include_world_seed")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_world_seed: Option<bool>,
+    /// Whether the sequence ID is mixed into the seeds of new sequences,
+    /// since 1.20.2.
+    #[serde(rename = "include_sequence_id")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_sequence_id: Option<bool>,
+    /// Sequences by ID, like `minecraft:chests/simple_dungeon`.
+    #[serde(rename = "sequences")]
+    #[serde(default)]
+    pub sequences: HashMap<String, RandomSequence>,
+}
+
+/// State of one random sequence.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct RandomSequence {
+    /// The two longs of the Xoroshiro128++ generator.
+    #[serde(rename = "source", serialize_with = "as_nbt_array")]
+    pub source: Vec<i64>,
+}
+
+/// Chunks kept loaded, in `data/chunks.dat` of each dimension.
+///
+/// Before 1.21.5 the file lists the chunks forced by `/forceload`; since,
+/// it keeps all chunk tickets that last across restarts, forced chunks
+/// among them.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::saved_data::ForcedChunks;
+/// use coruscant_world::ChunkPos;
+///
+/// let mut forced = ForcedChunks::default();
+/// forced.set_forced(vec![ChunkPos::new(-1, 2)]);
+/// assert_eq!(forced.forced_chunks(), vec![ChunkPos::new(-1, 2)]);
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct ForcedChunks {
+    /// Positions of forced chunks, X in the lower and Z in the upper 32 bits,
+    /// before 1.21.5.
+    #[serde(
+        rename = "Forced",
+        serialize_with = "crate::chunk::option_as_nbt_array",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub forced: Option<Vec<i64>>,
+    /// Chunk tickets since 1.21.5, each with its `chunk_pos` and `type`.
+    #[serde(rename = "tickets")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tickets: Option<Vec<Value>>,
+}
+
+impl IdCounts {
+    /// The ID for the next map, counting it as handed out.
+    pub fn next_map_id(&mut self) -> i32 {
+        let id = self.map.map_or(0, |id| id + 1);
+        self.map = Some(id);
+        id
+    }
+}
+
+impl ForcedChunks {
+    /// Chunks forced by `/forceload` in either format.
+    pub fn forced_chunks(&self) -> Vec<ChunkPos> {
+        if let Some(forced) = &self.forced {
+            return forced.iter().map(|pos| unpack_chunk_pos(*pos)).collect();
+        }
+        let tickets = self.tickets.iter().flatten();
+        tickets
+            .filter_map(|ticket| match ticket {
+                Value::Compound(ticket) => match (ticket.get("type"), ticket.get("chunk_pos")) {
+                    (Some(Value::String(kind)), Some(Value::Long(pos)))
+                        if kind == "minecraft:forced" =>
+                    {
+                        Some(unpack_chunk_pos(*pos))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Replace the chunks forced by `/forceload`, keeping other tickets.
+    pub fn set_forced(&mut self, chunks: Vec<ChunkPos>) {
+        let packed = chunks.iter().map(|pos| pack_chunk_pos(*pos));
+        match &mut self.tickets {
+            Some(tickets) => {
+                tickets.retain(|ticket| match ticket {
+                    Value::Compound(ticket) => {
+                        ticket.get("type") != Some(&Value::String("minecraft:forced".to_owned()))
+                    }
+                    _ => true,
+                });
+                for pos in packed {
+                    let mut ticket = coruscant_nbt::Map::new();
+                    ticket.insert("chunk_pos".to_owned(), Value::Long(pos));
+                    ticket.insert(
+                        "type".to_owned(),
+                        Value::String("minecraft:forced".to_owned()),
+                    );
+                    tickets.push(Value::Compound(ticket));
+                }
+            }
+            None => self.forced = Some(packed.collect()),
+        }
+    }
+}
+
+fn pack_chunk_pos(pos: ChunkPos) -> i64 {
+    i64::from(pos.x) & 0xFFFF_FFFF | i64::from(pos.z) << 32
+}
+
+fn unpack_chunk_pos(pos: i64) -> ChunkPos {
+    ChunkPos::new(pos as i32, (pos >> 32) as i32)
+}
@@ -0,0 +1,764 @@
+//! Schematics, the `.schem` and `.schematic` files of WorldEdit and MCEdit.
+//!
+//! The Sponge schematic format, saved as `.schem` since WorldEdit 7, keeps a
+//! palette of block states as strings and one varint palette index per
+//! block. Version 1 and 2 put all fields in the root compound, version 3
+//! nests them in a `Schematic` compound and the blocks in a `Blocks`
+//! compound. The older MCEdit format, saved as `.schematic`, keeps numeric
+//! block IDs and data values from before 1.13; these are converted with
+//! `legacy_ids`.
+//!
+//! A `Schematic` holds the blocks independent of the format they were read
+//! from. Use `to_structure` and `Structure::place` to paste a schematic
+//! into a world.
+//!
+//! Ref: https://github.com/SpongePowered/Schematic-Specification
+use crate::chunk::{BlockState, DATA_VERSION_FLATTENING};
+use crate::entity::Entity;
+use crate::error::{Error, Result};
+use crate::legacy_ids;
+use crate::structure::{Structure, StructureEntity};
+use coruscant_nbt::{Compression, Map, Value};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// A file format of schematics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SchematicFormat {
+    /// Sponge schematic version 1, from WorldEdit 7.0 for 1.13. Block
+    /// entities are kept, entities and the data version are not.
+    Sponge1,
+    /// Sponge schematic version 2, from WorldEdit 7.0 to 7.2.
+    Sponge2,
+    /// Sponge schematic version 3, from WorldEdit 7.3.
+    Sponge3,
+    /// MCEdit schematic with numeric block IDs, from MCEdit and WorldEdit
+    /// before 1.13. Blocks added since then cannot be written.
+    MCEdit,
+}
+
+/// A schematic: a box of blocks with block entities and entities.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::schematic::Schematic;
+/// use coruscant_world::BlockState;
+///
+/// let mut schematic = Schematic::new(3465, [2, 1, 2]);
+/// let stone = BlockState::new("minecraft:stone".to_string());
+/// assert_eq!(schematic.set_block([1, 0, 1], stone.clone()), Some(BlockState::air()));
+/// assert_eq!(schematic.block([1, 0, 1]), Some(&stone));
+/// assert_eq!(schematic.block([0, 0, 0]), Some(&BlockState::air()));
+/// assert_eq!(schematic.block([2, 0, 0]), None);
+/// assert_eq!(schematic.set_block([2, 0, 0], stone), None);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Schematic {
+    /// Version of the game the blocks and entities were saved in. Block
+    /// states converted from MCEdit schematics use the names of
+    /// `legacy_ids::DATA_VERSION`.
+    pub data_version: i32,
+    /// Width, height and length: the size along the X, Y and Z axes.
+    pub size: [i32; 3],
+    /// Position of the lowest corner in the world the schematic was copied
+    /// from, as saved by WorldEdit.
+    pub offset: [i32; 3],
+    /// Block states in the schematic.
+    pub palette: Vec<BlockState>,
+    /// Palette index of every block, ordered by Y, then Z, then X: the block
+    /// at `x, y, z` is at `x + z * width + y * width * length`.
+    pub blocks: Vec<u32>,
+    /// Block entities, with positions relative to the lowest corner.
+    pub block_entities: Vec<SchematicBlockEntity>,
+    /// Entities, with positions relative to the lowest corner.
+    pub entities: Vec<SchematicEntity>,
+    /// Additional data saved by the program that wrote the schematic, like
+    /// its name, author and the WorldEdit paste offset.
+    pub metadata: Option<Value>,
+}
+
+/// A block entity of a schematic.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct SchematicBlockEntity {
+    /// Position relative to the lowest corner of the schematic.
+    pub pos: [i32; 3],
+    /// Block entity data with its `id`, without coordinates.
+    pub nbt: Value,
+}
+
+/// An entity of a schematic.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct SchematicEntity {
+    /// Position relative to the lowest corner of the schematic.
+    pub pos: [f64; 3],
+    /// Entity data.
+    pub nbt: Entity,
+}
+
+impl SchematicBlockEntity {
+    pub fn new(pos: [i32; 3], nbt: Value) -> SchematicBlockEntity {
+        SchematicBlockEntity { pos, nbt }
+    }
+}
+
+impl SchematicEntity {
+    pub fn new(pos: [f64; 3], nbt: Entity) -> SchematicEntity {
+        SchematicEntity { pos, nbt }
+    }
+}
+
+impl Schematic {
+    /// A schematic of the given size filled with air.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of blocks overflows `usize`.
+    pub fn new(data_version: i32, size: [i32; 3]) -> Schematic {
+        let volume = volume(size).expect("schematic size overflows");
+        Schematic {
+            data_version,
+            size,
+            offset: [0; 3],
+            palette: vec![BlockState::air()],
+            blocks: vec![0; volume],
+            block_entities: Vec::new(),
+            entities: Vec::new(),
+            metadata: None,
+        }
+    }
+
+    /// Read a schematic file in any of the supported formats.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Schematic> {
+        let file = BufReader::new(File::open(path)?);
+        let value: Value = coruscant_nbt::from_gzip_reader(file)?;
+        Schematic::from_value(&value)
+    }
+
+    /// Write a schematic file in the given format.
+    ///
+    /// The data is written to a temporary file next to `path` first, which
+    /// then replaces the old file.
+    pub fn write<P: AsRef<Path>>(&self, path: P, format: SchematicFormat) -> Result<()> {
+        let (name, value) = self.to_value(format)?;
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let mut file = BufWriter::new(File::create(&tmp)?);
+        coruscant_nbt::to_gzip_writer(&mut file, (name, &value), Compression::default())?;
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// The format of schematic NBT data, if it is a schematic.
+    pub fn detect(value: &Value) -> Option<SchematicFormat> {
+        let root = match value {
+            Value::Compound(root) => root,
+            _ => return None,
+        };
+        if let Some(Value::Compound(nested)) = root.get("Schematic") {
+            return match nested.get("Version") {
+                Some(Value::Int(3)) => Some(SchematicFormat::Sponge3),
+                _ => None,
+            };
+        }
+        match root.get("Version") {
+            Some(Value::Int(1)) => Some(SchematicFormat::Sponge1),
+            Some(Value::Int(2)) => Some(SchematicFormat::Sponge2),
+            _ if root.contains_key("Blocks") && root.contains_key("Data") => {
+                Some(SchematicFormat::MCEdit)
+            }
+            _ => None,
+        }
+    }
+
+    /// Parse schematic NBT data in any of the supported formats.
+    pub fn from_value(value: &Value) -> Result<Schematic> {
+        let format =
+            Schematic::detect(value).ok_or(Error::CorruptSchematic("unknown schematic format"))?;
+        let root = match value {
+            Value::Compound(root) => root,
+            _ => unreachable!("detected as schematic"),
+        };
+        match format {
+            SchematicFormat::Sponge3 => match root.get("Schematic") {
+                Some(Value::Compound(nested)) => read_sponge(nested, format),
+                _ => unreachable!("detected as version 3"),
+            },
+            SchematicFormat::MCEdit => read_mcedit(root),
+            _ => read_sponge(root, format),
+        }
+    }
+
+    /// Schematic NBT data in the given format, with the name of its root
+    /// compound.
+    pub fn to_value(&self, format: SchematicFormat) -> Result<(&'static str, Value)> {
+        self.check()?;
+        match format {
+            SchematicFormat::Sponge3 => {
+                let mut root = Map::new();
+                root.insert("Schematic".to_owned(), self.write_sponge(format)?);
+                Ok(("", Value::Compound(root)))
+            }
+            SchematicFormat::MCEdit => Ok(("Schematic", self.write_mcedit()?)),
+            _ => Ok(("Schematic", self.write_sponge(format)?)),
+        }
+    }
+
+    /// The block state at the given position; `None` outside the schematic.
+    pub fn block(&self, pos: [i32; 3]) -> Option<&BlockState> {
+        let index = *self.blocks.get(self.index(pos)?)?;
+        self.palette.get(index as usize)
+    }
+
+    /// Set the block state at the given position, adding it to the palette.
+    ///
+    /// Returns the old block state, or `None` if the position is outside the
+    /// schematic or its block is not in the palette; the schematic is left
+    /// unchanged then.
+    pub fn set_block(&mut self, pos: [i32; 3], state: BlockState) -> Option<BlockState> {
+        let index = self.index(pos)?;
+        let old = self.palette.get(*self.blocks.get(index)? as usize)?.clone();
+        let state = match self.palette.iter().position(|s| *s == state) {
+            Some(state) => state,
+            None => {
+                self.palette.push(state);
+                self.palette.len() - 1
+            }
+        };
+        self.blocks[index] = state as u32;
+        Some(old)
+    }
+
+    /// Convert into a structure template. Structure voids are left out, so
+    /// that placing the structure keeps the blocks there.
+    pub fn to_structure(&self) -> Result<Structure> {
+        self.check()?;
+        let mut structure = Structure::new(self.data_version, self.size);
+        let mut block_entities: HashMap<[i32; 3], &Value> = HashMap::new();
+        for block_entity in &self.block_entities {
+            block_entities.insert(block_entity.pos, &block_entity.nbt);
+        }
+        let [width, height, length] = self.size;
+        for y in 0..height {
+            for z in 0..length {
+                for x in 0..width {
+                    let state = self
+                        .block([x, y, z])
+                        .ok_or(Error::CorruptSchematic("block state out of palette"))?;
+                    if state.name == "minecraft:structure_void" {
+                        continue;
+                    }
+                    let nbt = block_entities.get(&[x, y, z]).map(|nbt| (*nbt).clone());
+                    structure.set_block([x, y, z], state.clone(), nbt);
+                }
+            }
+        }
+        for entity in &self.entities {
+            let nbt = entity.nbt.clone();
+            structure
+                .entities
+                .push(StructureEntity::new(entity.pos, nbt));
+        }
+        Ok(structure)
+    }
+
+    /// Convert a structure template into a schematic. Positions without a
+    /// block in the structure become structure voids.
+    pub fn from_structure(structure: &Structure) -> Schematic {
+        let mut schematic = Schematic::new(structure.data_version, structure.size);
+        schematic.palette = vec![BlockState::new("minecraft:structure_void".to_owned())];
+        for (pos, state, nbt) in structure.blocks() {
+            if schematic.set_block(pos, state.clone()).is_none() {
+                continue;
+            }
+            if let Some(nbt) = nbt {
+                schematic
+                    .block_entities
+                    .push(SchematicBlockEntity::new(pos, nbt.clone()));
+            }
+        }
+        for entity in &structure.entities {
+            schematic
+                .entities
+                .push(SchematicEntity::new(entity.pos, entity.nbt.clone()));
+        }
+        schematic
+    }
+
+    fn index(&self, [x, y, z]: [i32; 3]) -> Option<usize> {
+        let [width, height, length] = self.size;
+        if x < 0 || y < 0 || z < 0 || x >= width || y >= height || z >= length {
+            return None;
+        }
+        let (x, y, z) = (x as usize, y as usize, z as usize);
+        let (width, length) = (width as usize, length as usize);
+        Some(x + z * width + y * width * length)
+    }
+
+    // Whether blocks and palette indices match the size and palette.
+    fn check(&self) -> Result<()> {
+        if Some(self.blocks.len()) != volume(self.size) {
+            return Err(Error::CorruptSchematic("block count does not match size"));
+        }
+        if self
+            .blocks
+            .iter()
+            .any(|b| *b as usize >= self.palette.len())
+        {
+            return Err(Error::CorruptSchematic("block state out of palette"));
+        }
+        Ok(())
+    }
+
+    fn write_sponge(&self, format: SchematicFormat) -> Result<Value> {
+        let mut root = Map::new();
+        let version = match format {
+            SchematicFormat::Sponge1 => 1,
+            SchematicFormat::Sponge2 => 2,
+            _ => 3,
+        };
+        root.insert("Version".to_owned(), Value::Int(version));
+        if version >= 2 {
+            root.insert("DataVersion".to_owned(), Value::Int(self.data_version));
+        }
+        insert_size(&mut root, self.size)?;
+        root.insert("Offset".to_owned(), Value::IntArray(self.offset.to_vec()));
+        if let Some(metadata) = &self.metadata {
+            root.insert("Metadata".to_owned(), metadata.clone());
+        }
+        let mut palette = Map::new();
+        for (i, state) in self.palette.iter().enumerate() {
+            palette.insert(state.to_string(), Value::Int(i as i32));
+        }
+        let mut data = Vec::with_capacity(self.blocks.len());
+        for block in &self.blocks {
+            write_varint(&mut data, *block);
+        }
+        let mut block_entities = Vec::new();
+        for block_entity in &self.block_entities {
+            let mut nbt = compound_of(&block_entity.nbt)?;
+            let id = match nbt.remove("id") {
+                Some(id) => id,
+                None => return Err(Error::CorruptSchematic("block entity without id")),
+            };
+            let mut entry = Map::new();
+            if version == 3 {
+                entry.insert("Data".to_owned(), Value::Compound(nbt));
+            } else {
+                entry = nbt;
+            }
+            entry.insert("Pos".to_owned(), Value::IntArray(block_entity.pos.to_vec()));
+            entry.insert("Id".to_owned(), id);
+            block_entities.push(Value::Compound(entry));
+        }
+        let mut entities = Vec::new();
+        for entity in &self.entities {
+            let mut nbt = compound_of(&entity.nbt.to_value()?)?;
+            let id = match nbt.remove("id") {
+                Some(id) => id,
+                None => return Err(Error::CorruptSchematic("entity without id")),
+            };
+            let pos = Value::List(entity.pos.iter().map(|c| Value::Double(*c)).collect());
+            let mut entry = Map::new();
+            if version == 3 {
+                entry.insert("Data".to_owned(), Value::Compound(nbt));
+            } else {
+                entry = nbt;
+            }
+            entry.insert("Pos".to_owned(), pos);
+            entry.insert("Id".to_owned(), id);
+            entities.push(Value::Compound(entry));
+        }
+        if version == 3 {
+            let mut blocks = Map::new();
+            blocks.insert("Palette".to_owned(), Value::Compound(palette));
+            blocks.insert("Data".to_owned(), Value::ByteArray(data));
+            blocks.insert("BlockEntities".to_owned(), Value::List(block_entities));
+            root.insert("Blocks".to_owned(), Value::Compound(blocks));
+        } else {
+            let palette_max = self.palette.len() as i32;
+            root.insert("PaletteMax".to_owned(), Value::Int(palette_max));
+            root.insert("Palette".to_owned(), Value::Compound(palette));
+            root.insert("BlockData".to_owned(), Value::ByteArray(data));
+            let key = if version == 1 {
+                "TileEntities"
+            } else {
+                "BlockEntities"
+            };
+            root.insert(key.to_owned(), Value::List(block_entities));
+        }
+        if version >= 2 {
+            root.insert("Entities".to_owned(), Value::List(entities));
+        }
+        Ok(Value::Compound(root))
+    }
+
+    fn write_mcedit(&self) -> Result<Value> {
+        let mut ids = Vec::with_capacity(self.palette.len());
+        for state in &self.palette {
+            match legacy_ids::legacy_id(state) {
+                Some(id) => ids.push(id),
+                None => return Err(Error::NoLegacyId(state.clone())),
+            }
+        }
+        let mut blocks = Vec::with_capacity(self.blocks.len());
+        let mut data = Vec::with_capacity(self.blocks.len());
+        let mut add = vec![0u8; self.blocks.len().div_ceil(2)];
+        for (i, block) in self.blocks.iter().enumerate() {
+            let (id, value) = ids[*block as usize];
+            blocks.push(id as u8 as i8);
+            data.push(value as i8);
+            let high = (id >> 8) as u8 & 15;
+            add[i >> 1] |= if i & 1 == 0 { high } else { high << 4 };
+        }
+        let mut root = Map::new();
+        insert_size(&mut root, self.size)?;
+        root.insert("Materials".to_owned(), Value::String("Alpha".to_owned()));
+        root.insert("Blocks".to_owned(), Value::ByteArray(blocks));
+        root.insert("Data".to_owned(), Value::ByteArray(data));
+        if add.iter().any(|a| *a != 0) {
+            let add = add.into_iter().map(|a| a as i8).collect();
+            root.insert("AddBlocks".to_owned(), Value::ByteArray(add));
+        }
+        let mut block_entities = Vec::new();
+        for block_entity in &self.block_entities {
+            let mut nbt = compound_of(&block_entity.nbt)?;
+            for (key, c) in ["x", "y", "z"].iter().zip(&block_entity.pos) {
+                nbt.insert((*key).to_owned(), Value::Int(*c));
+            }
+            block_entities.push(Value::Compound(nbt));
+        }
+        root.insert("TileEntities".to_owned(), Value::List(block_entities));
+        let mut entities = Vec::new();
+        for entity in &self.entities {
+            let mut nbt = compound_of(&entity.nbt.to_value()?)?;
+            let pos = entity.pos.iter().map(|c| Value::Double(*c)).collect();
+            nbt.insert("Pos".to_owned(), Value::List(pos));
+            entities.push(Value::Compound(nbt));
+        }
+        root.insert("Entities".to_owned(), Value::List(entities));
+        for (key, c) in ["WEOriginX", "WEOriginY", "WEOriginZ"]
+            .iter()
+            .zip(&self.offset)
+        {
+            root.insert((*key).to_owned(), Value::Int(*c));
+        }
+        if let Some(Value::Compound(metadata)) = &self.metadata {
+            for key in &["WEOffsetX", "WEOffsetY", "WEOffsetZ"] {
+                if let Some(value) = metadata.get(*key) {
+                    root.insert((*key).to_owned(), value.clone());
+                }
+            }
+        }
+        Ok(Value::Compound(root))
+    }
+}
+
+fn read_sponge(root: &Map<String, Value>, format: SchematicFormat) -> Result<Schematic> {
+    let (size, volume) = read_size(root)?;
+    let data_version = match root.get("DataVersion") {
+        Some(Value::Int(v)) => *v,
+        _ => DATA_VERSION_FLATTENING,
+    };
+    // the blocks are allocated once their data is checked against the size
+    let mut schematic = Schematic::new(data_version, [0; 3]);
+    schematic.size = size;
+    if let Some(Value::IntArray(offset)) = root.get("Offset") {
+        if let [x, y, z] = offset[..] {
+            schematic.offset = [x, y, z];
+        }
+    }
+    schematic.metadata = root.get("Metadata").cloned();
+
+    let blocks = match (format, root.get("Blocks")) {
+        (SchematicFormat::Sponge3, Some(Value::Compound(blocks))) => Some(blocks),
+        (SchematicFormat::Sponge3, _) => None,
+        _ => Some(root),
+    };
+    let blocks = match blocks {
+        Some(blocks) => blocks,
+        // a schematic of only air may have no blocks
+        None => {
+            schematic.blocks = air_blocks(volume)?;
+            return Ok(schematic);
+        }
+    };
+    let (palette_key, data_key) = match format {
+        SchematicFormat::Sponge3 => ("Palette", "Data"),
+        _ => ("Palette", "BlockData"),
+    };
+    let mut palette = Vec::new();
+    if let Some(Value::Compound(entries)) = blocks.get(palette_key) {
+        for (state, index) in entries {
+            let index = match index {
+                Value::Int(index) => usize::try_from(*index)
+                    .map_err(|_| Error::CorruptSchematic("negative palette index"))?,
+                _ => return Err(Error::CorruptSchematic("palette index is not an int")),
+            };
+            if index >= entries.len() {
+                return Err(Error::CorruptSchematic("palette index out of range"));
+            }
+            let state = state
+                .parse()
+                .map_err(|_| Error::CorruptSchematic("invalid block state in palette"))?;
+            if palette.len() <= index {
+                palette.resize(index + 1, None);
+            }
+            palette[index] = Some(state);
+        }
+    }
+    if !palette.is_empty() {
+        schematic.palette = palette
+            .into_iter()
+            .map(|state| state.unwrap_or_else(BlockState::air))
+            .collect();
+    }
+    schematic.blocks = match blocks.get(data_key) {
+        Some(Value::ByteArray(data)) => read_varints(data, volume)?,
+        _ => air_blocks(volume)?,
+    };
+    schematic.check()?;
+
+    let key = match format {
+        SchematicFormat::Sponge1 => "TileEntities",
+        _ => "BlockEntities",
+    };
+    if let Some(Value::List(list)) = blocks.get(key) {
+        for entry in list {
+            let mut entry = compound_of(entry)?;
+            let pos = match entry.remove("Pos") {
+                Some(Value::IntArray(pos)) if pos.len() == 3 => [pos[0], pos[1], pos[2]],
+                _ => return Err(Error::CorruptSchematic("block entity without position")),
+            };
+            let nbt = entry_nbt(entry, format);
+            schematic
+                .block_entities
+                .push(SchematicBlockEntity::new(pos, nbt));
+        }
+    }
+
+    if let Some(Value::List(list)) = root.get("Entities") {
+        for entry in list {
+            let entry = compound_of(entry)?;
+            let pos = list_pos(entry.get("Pos"))
+                .ok_or(Error::CorruptSchematic("entity without position"))?;
+            let nbt = Entity::from_value(entry_nbt(entry, format));
+            schematic.entities.push(SchematicEntity::new(pos, nbt));
+        }
+    }
+    Ok(schematic)
+}
+
+// Block entity or entity data of a Sponge schematic entry, which is in
+// `Data` since version 3 and next to `Pos` and `Id` before.
+fn entry_nbt(mut entry: Map<String, Value>, format: SchematicFormat) -> Value {
+    let id = entry.remove("Id");
+    let mut nbt = match format {
+        SchematicFormat::Sponge3 => match entry.remove("Data") {
+            Some(Value::Compound(data)) => data,
+            _ => Map::new(),
+        },
+        _ => entry,
+    };
+    if let Some(id) = id {
+        nbt.insert("id".to_owned(), id);
+    }
+    Value::Compound(nbt)
+}
+
+fn read_mcedit(root: &Map<String, Value>) -> Result<Schematic> {
+    let (size, volume) = read_size(root)?;
+    let (ids, data) = match (root.get("Blocks"), root.get("Data")) {
+        (Some(Value::ByteArray(ids)), Some(Value::ByteArray(data))) => (ids, data),
+        _ => return Err(Error::CorruptSchematic("missing block arrays")),
+    };
+    if ids.len() != volume || data.len() != volume {
+        return Err(Error::CorruptSchematic("block count does not match size"));
+    }
+    let mut schematic = Schematic::new(legacy_ids::DATA_VERSION, size);
+    let add = match root.get("AddBlocks") {
+        Some(Value::ByteArray(add)) => &add[..],
+        _ => &[],
+    };
+    let mut palette: HashMap<(u16, u8), u32> = HashMap::new();
+    schematic.palette.clear();
+    for i in 0..volume {
+        let high = match add.get(i >> 1) {
+            Some(a) if i & 1 == 0 => *a as u8 & 15,
+            Some(a) => *a as u8 >> 4,
+            None => 0,
+        };
+        let id = u16::from(high) << 8 | u16::from(ids[i] as u8);
+        let key = (id, data[i] as u8 & 15);
+        let index = match palette.get(&key) {
+            Some(index) => *index,
+            None => {
+                // unknown IDs, like those of mods, become air
+                let state = legacy_ids::block_state(key.0, key.1).unwrap_or_else(BlockState::air);
+                let index = match schematic.palette.iter().position(|s| *s == state) {
+                    Some(index) => index as u32,
+                    None => {
+                        schematic.palette.push(state);
+                        schematic.palette.len() as u32 - 1
+                    }
+                };
+                palette.insert(key, index);
+                index
+            }
+        };
+        schematic.blocks[i] = index;
+    }
+    if schematic.palette.is_empty() {
+        schematic.palette.push(BlockState::air());
+    }
+
+    let origin: Vec<i32> = ["WEOriginX", "WEOriginY", "WEOriginZ"]
+        .iter()
+        .filter_map(|key| match root.get(*key) {
+            Some(Value::Int(c)) => Some(*c),
+            _ => None,
+        })
+        .collect();
+    if let [x, y, z] = origin[..] {
+        schematic.offset = [x, y, z];
+    }
+    let mut metadata = Map::new();
+    for key in &["WEOffsetX", "WEOffsetY", "WEOffsetZ"] {
+        if let Some(value) = root.get(*key) {
+            metadata.insert((*key).to_owned(), value.clone());
+        }
+    }
+    if !metadata.is_empty() {
+        schematic.metadata = Some(Value::Compound(metadata));
+    }
+
+    if let Some(Value::List(list)) = root.get("TileEntities") {
+        for entry in list {
+            let mut nbt = compound_of(entry)?;
+            let mut pos = [0; 3];
+            for (key, c) in ["x", "y", "z"].iter().zip(&mut pos) {
+                match nbt.remove(*key) {
+                    Some(Value::Int(v)) => *c = v,
+                    _ => return Err(Error::CorruptSchematic("block entity without position")),
+                }
+            }
+            schematic
+                .block_entities
+                .push(SchematicBlockEntity::new(pos, Value::Compound(nbt)));
+        }
+    }
+    if let Some(Value::List(list)) = root.get("Entities") {
+        for entry in list {
+            let pos = match entry {
+                Value::Compound(entry) => list_pos(entry.get("Pos")),
+                _ => None,
+            }
+            .ok_or(Error::CorruptSchematic("entity without position"))?;
+            let nbt = Entity::from_value(entry.clone());
+            schematic.entities.push(SchematicEntity::new(pos, nbt));
+        }
+    }
+    Ok(schematic)
+}
+
+// The size of a schematic and its number of blocks.
+fn read_size(root: &Map<String, Value>) -> Result<([i32; 3], usize)> {
+    let mut size = [0; 3];
+    for (key, s) in ["Width", "Height", "Length"].iter().zip(&mut size) {
+        *s = match root.get(*key) {
+            // sizes are unsigned shorts
+            Some(Value::Short(v)) => i32::from(*v as u16),
+            Some(Value::Int(v)) if *v >= 0 => *v,
+            _ => return Err(Error::CorruptSchematic("missing size")),
+        };
+    }
+    let volume = volume(size).ok_or(Error::CorruptSchematic("size out of range"))?;
+    Ok((size, volume))
+}
+
+// The number of blocks in a schematic of the given size, unless it
+// overflows.
+fn volume(size: [i32; 3]) -> Option<usize> {
+    (size.iter()).try_fold(1_usize, |volume, s| volume.checked_mul((*s).max(0) as usize))
+}
+
+// The blocks of a schematic of only air, failing instead of aborting when
+// the size read from a file does not fit in memory.
+fn air_blocks(volume: usize) -> Result<Vec<u32>> {
+    let mut blocks = Vec::new();
+    blocks
+        .try_reserve_exact(volume)
+        .map_err(|_| Error::CorruptSchematic("size out of range"))?;
+    blocks.resize(volume, 0);
+    Ok(blocks)
+}
+
+fn insert_size(root: &mut Map<String, Value>, size: [i32; 3]) -> Result<()> {
+    for (key, s) in ["Width", "Height", "Length"].iter().zip(&size) {
+        let s = u16::try_from(*s).map_err(|_| Error::CorruptSchematic("size out of range"))?;
+        root.insert((*key).to_owned(), Value::Short(s as i16));
+    }
+    Ok(())
+}
+
+fn compound_of(value: &Value) -> Result<Map<String, Value>> {
+    match value {
+        Value::Compound(compound) => Ok(compound.clone()),
+        _ => Err(Error::CorruptSchematic("expected a compound")),
+    }
+}
+
+fn list_pos(pos: Option<&Value>) -> Option<[f64; 3]> {
+    match pos {
+        Some(Value::List(pos)) => match pos.as_slice() {
+            [Value::Double(x), Value::Double(y), Value::Double(z)] => Some([*x, *y, *z]),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn read_varints(data: &[i8], count: usize) -> Result<Vec<u32>> {
+    // every value takes at least one byte
+    if data.len() < count {
+        return Err(Error::CorruptSchematic("block count does not match size"));
+    }
+    let mut values = Vec::with_capacity(count);
+    let mut value = 0u32;
+    let mut shift = 0;
+    for byte in data {
+        let byte = *byte as u8;
+        if shift >= 32 {
+            return Err(Error::CorruptSchematic("varint too long"));
+        }
+        value |= u32::from(byte & 0x7f) << shift;
+        if byte & 0x80 != 0 {
+            shift += 7;
+            continue;
+        }
+        values.push(value);
+        value = 0;
+        shift = 0;
+    }
+    if shift != 0 || values.len() != count {
+        return Err(Error::CorruptSchematic("block count does not match size"));
+    }
+    Ok(values)
+}
+
+fn write_varint(data: &mut Vec<i8>, mut value: u32) {
+    while value >= 0x80 {
+        data.push((value as u8 & 0x7f | 0x80) as i8);
+        value >>= 7;
+    }
+    data.push(value as i8);
+}
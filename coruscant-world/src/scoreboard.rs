@@ -0,0 +1,378 @@
+//! Scoreboard format parsed as serde favored Rust structs.
+//!
+//! The scoreboard of a world is kept in `data/scoreboard.dat` of the
+//! overworld, a saved data file; read it with `World::read_data`.
+//!
+//! Ref: https://minecraft.gamepedia.com/Scoreboard#NBT_format
+use coruscant_nbt::Value;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Data version of 23w31a (1.20.2), since which display slots are saved by
+/// name instead of number.
+pub const DATA_VERSION_NAMED_DISPLAY_SLOTS: i32 = 3567;
+/// Data version of 25w02a (1.21.5), since which text components are saved as
+/// NBT instead of JSON strings.
+pub const DATA_VERSION_NBT_TEXT: i32 = 4298;
+
+/// Data of a scoreboard.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::scoreboard::{DisplaySlot, Objective, Score, Scoreboard};
+///
+/// let mut scoreboard = Scoreboard::new();
+/// let objective = Objective::new("kills", "playerKillCount", "Kills", 3700);
+/// scoreboard.objectives.push(objective);
+/// scoreboard.player_scores.push(Score::new("Steve", "kills", 3));
+/// scoreboard.set_display_slot(DisplaySlot::Sidebar, Some("kills"), 3700);
+///
+/// assert_eq!(scoreboard.score("Steve", "kills"), Some(3));
+/// assert_eq!(scoreboard.display_slots["sidebar"], "kills");
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Scoreboard {
+    /// Objectives scores are kept for.
+    #[serde(rename = "Objectives")]
+    #[serde(default)]
+    pub objectives: Vec<Objective>,
+    /// Scores of players and entities.
+    #[serde(rename = "PlayerScores")]
+    #[serde(default)]
+    pub player_scores: Vec<Score>,
+    /// Teams.
+    #[serde(rename = "Teams")]
+    #[serde(default)]
+    pub teams: Vec<Team>,
+    /// Objective shown in each display slot, by slot. Slots are named
+    /// `slot_<number>` before 1.20.2, like `sidebar` since; see
+    /// `DisplaySlot`.
+    #[serde(rename = "DisplaySlots")]
+    #[serde(default)]
+    pub display_slots: HashMap<String, String>,
+}
+
+/// An objective of a scoreboard.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Objective {
+    /// Internal name of the objective.
+    #[serde(rename = "Name")]
+    pub name: String,
+    /// Criterion that changes the scores, like `dummy` or
+    /// `minecraft.mined:minecraft.stone`.
+    #[serde(rename = "CriteriaName")]
+    pub criteria_name: String,
+    /// Name shown for the objective: a JSON text component string before
+    /// 1.21.5, a text component since.
+    #[serde(rename = "DisplayName")]
+    pub display_name: Value,
+    /// How scores are shown in the player list: `integer` or `hearts`.
+    #[serde(rename = "RenderType")]
+    #[serde(default = "default_render_type")]
+    pub render_type: String,
+    /// Whether the display name follows changes of the criterion, since
+    /// 1.20.3.
+    #[serde(rename = "display_auto_update")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_auto_update: Option<bool>,
+    /// How scores are formatted, since 1.20.3.
+    #[serde(rename = "format")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<Value>,
+}
+
+/// The score of a player or entity for an objective.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Score {
+    /// Name of the player, or UUID of the entity, holding the score.
+    #[serde(rename = "Name")]
+    pub name: String,
+    /// Name of the objective.
+    #[serde(rename = "Objective")]
+    pub objective: String,
+    #[serde(rename = "Score")]
+    pub score: i32,
+    /// Whether the score is locked, so that `/trigger` cannot change it.
+    #[serde(rename = "Locked")]
+    #[serde(default)]
+    pub locked: bool,
+    /// Name shown instead of the holder, since 1.20.3.
+    #[serde(rename = "display")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<Value>,
+    /// How this score is formatted, since 1.20.3.
+    #[serde(rename = "format")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<Value>,
+}
+
+/// A team of a scoreboard.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Team {
+    /// Internal name of the team.
+    #[serde(rename = "Name")]
+    pub name: String,
+    /// Name shown for the team, a text component like
+    /// `Objective::display_name`.
+    #[serde(rename = "DisplayName")]
+    pub display_name: Value,
+    /// Color of the names of members, like `red`; `reset` or missing for
+    /// none.
+    #[serde(rename = "TeamColor")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team_color: Option<String>,
+    #[serde(rename = "AllowFriendlyFire")]
+    #[serde(default = "default_true")]
+    pub allow_friendly_fire: bool,
+    #[serde(rename = "SeeFriendlyInvisibles")]
+    #[serde(default = "default_true")]
+    pub see_friendly_invisibles: bool,
+    /// Whose name tags of members are shown: `always`, `never`,
+    /// `hideForOtherTeams` or `hideForOwnTeam`.
+    #[serde(rename = "NameTagVisibility")]
+    #[serde(default = "default_visibility")]
+    pub name_tag_visibility: String,
+    /// To whom deaths of members are announced, with the same values as
+    /// `name_tag_visibility`.
+    #[serde(rename = "DeathMessageVisibility")]
+    #[serde(default = "default_visibility")]
+    pub death_message_visibility: String,
+    /// Who members push: `always`, `never`, `pushOtherTeams` or
+    /// `pushOwnTeam`.
+    #[serde(rename = "CollisionRule")]
+    #[serde(default = "default_visibility")]
+    pub collision_rule: String,
+    /// Text component in front of the names of members.
+    #[serde(rename = "MemberNamePrefix")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub member_name_prefix: Option<Value>,
+    /// Text component after the names of members.
+    #[serde(rename = "MemberNameSuffix")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub member_name_suffix: Option<Value>,
+    /// Names of players and UUIDs of entities in the team.
+    #[serde(rename = "Players")]
+    #[serde(default)]
+    pub players: Vec<String>,
+}
+
+/// A place where the scores of an objective are shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DisplaySlot {
+    /// The player list.
+    List,
+    /// The sidebar.
+    Sidebar,
+    /// Below the name tags of players.
+    BelowName,
+    /// The sidebar, for players in a team of the color with this index from
+    /// 0 for `black` to 15 for `white`.
+    SidebarTeam(u8),
+}
+
+const TEAM_COLORS: [&str; 16] = [
+    "black",
+    "dark_blue",
+    "dark_green",
+    "dark_aqua",
+    "dark_red",
+    "dark_purple",
+    "gold",
+    "gray",
+    "dark_gray",
+    "blue",
+    "green",
+    "aqua",
+    "red",
+    "light_purple",
+    "yellow",
+    "white",
+];
+
+impl Scoreboard {
+    pub fn new() -> Scoreboard {
+        Scoreboard::default()
+    }
+
+    /// The score of a holder for an objective, if set.
+    pub fn score(&self, name: &str, objective: &str) -> Option<i32> {
+        self.player_scores
+            .iter()
+            .find(|s| s.name == name && s.objective == objective)
+            .map(|s| s.score)
+    }
+
+    /// Set the score of a holder for an objective, adding it if missing.
+    pub fn set_score(&mut self, name: &str, objective: &str, score: i32) {
+        match (self.player_scores.iter_mut()).find(|s| s.name == name && s.objective == objective) {
+            Some(s) => s.score = score,
+            None => self.player_scores.push(Score::new(name, objective, score)),
+        }
+    }
+
+    /// Remove an objective with its scores and the display slots showing it.
+    pub fn remove_objective(&mut self, name: &str) {
+        self.objectives.retain(|o| o.name != name);
+        self.player_scores.retain(|s| s.objective != name);
+        self.display_slots.retain(|_, o| o != name);
+    }
+
+    /// Show an objective in a display slot, or clear the slot with `None`.
+    /// The slot is named as in the given data version.
+    pub fn set_display_slot(
+        &mut self,
+        slot: DisplaySlot,
+        objective: Option<&str>,
+        data_version: i32,
+    ) {
+        let key = slot.key(data_version);
+        match objective {
+            Some(objective) => {
+                self.display_slots.insert(key, objective.to_owned());
+            }
+            None => {
+                self.display_slots.remove(&key);
+            }
+        }
+    }
+}
+
+impl Objective {
+    /// A new objective showing `display_name` as plain text, saved as in the
+    /// given data version.
+    pub fn new(
+        name: &str,
+        criteria_name: &str,
+        display_name: &str,
+        data_version: i32,
+    ) -> Objective {
+        Objective {
+            name: name.to_owned(),
+            criteria_name: criteria_name.to_owned(),
+            display_name: text_component(display_name, data_version),
+            render_type: default_render_type(),
+            display_auto_update: None,
+            format: None,
+        }
+    }
+}
+
+impl Score {
+    pub fn new(name: &str, objective: &str, score: i32) -> Score {
+        Score {
+            name: name.to_owned(),
+            objective: objective.to_owned(),
+            score,
+            locked: false,
+            display: None,
+            format: None,
+        }
+    }
+}
+
+impl Team {
+    /// A new team showing `display_name` as plain text, saved as in the
+    /// given data version.
+    pub fn new(name: &str, display_name: &str, data_version: i32) -> Team {
+        Team {
+            name: name.to_owned(),
+            display_name: text_component(display_name, data_version),
+            team_color: None,
+            allow_friendly_fire: true,
+            see_friendly_invisibles: true,
+            name_tag_visibility: default_visibility(),
+            death_message_visibility: default_visibility(),
+            collision_rule: default_visibility(),
+            member_name_prefix: None,
+            member_name_suffix: None,
+            players: Vec::new(),
+        }
+    }
+}
+
+impl DisplaySlot {
+    /// Key of the slot in `Scoreboard::display_slots` in the given data
+    /// version.
+    pub fn key(self, data_version: i32) -> String {
+        if data_version < DATA_VERSION_NAMED_DISPLAY_SLOTS {
+            let number = match self {
+                DisplaySlot::List => 0,
+                DisplaySlot::Sidebar => 1,
+                DisplaySlot::BelowName => 2,
+                DisplaySlot::SidebarTeam(color) => 3 + u32::from(color & 15),
+            };
+            return format!("slot_{}", number);
+        }
+        match self {
+            DisplaySlot::List => "list".to_owned(),
+            DisplaySlot::Sidebar => "sidebar".to_owned(),
+            DisplaySlot::BelowName => "below_name".to_owned(),
+            DisplaySlot::SidebarTeam(color) => {
+                format!("sidebar.team.{}", TEAM_COLORS[usize::from(color & 15)])
+            }
+        }
+    }
+
+    /// The slot of a key of `Scoreboard::display_slots` in either format.
+    pub fn from_key(key: &str) -> Option<DisplaySlot> {
+        let slot = match key {
+            "slot_0" | "list" => DisplaySlot::List,
+            "slot_1" | "sidebar" => DisplaySlot::Sidebar,
+            "slot_2" | "below_name" => DisplaySlot::BelowName,
+            _ => {
+                let color = match key.strip_prefix("slot_") {
+                    Some(number) => number.parse::<u8>().ok()?.checked_sub(3)?,
+                    None => {
+                        let name = key.strip_prefix("sidebar.team.")?;
+                        TEAM_COLORS.iter().position(|c| *c == name)? as u8
+                    }
+                };
+                if color > 15 {
+                    return None;
+                }
+                DisplaySlot::SidebarTeam(color)
+            }
+        };
+        Some(slot)
+    }
+}
+
+/// A plain text component as saved in the given data version: a JSON string
+/// before 1.21.5, a compound since.
+pub fn text_component(text: &str, data_version: i32) -> Value {
+    if data_version >= DATA_VERSION_NBT_TEXT {
+        let mut compound = coruscant_nbt::Map::new();
+        compound.insert("text".to_owned(), Value::String(text.to_owned()));
+        return Value::Compound(compound);
+    }
+    let mut json = String::from("{\"text\":\"");
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push_str("\"}");
+    Value::String(json)
+}
+
+fn default_render_type() -> String {
+    "integer".to_owned()
+}
+
+fn default_visibility() -> String {
+    "always".to_owned()
+}
+
+fn default_true() -> bool {
+    true
+}
@@ -0,0 +1,641 @@
+//! Structure templates, the `.nbt` files saved by structure blocks.
+//!
+//! A structure template is a gzip compressed NBT file with the size of the
+//! structure, a palette of block states, the blocks that are not structure
+//! voids, and the entities. Saved structures are found in
+//! `generated/<namespace>/structures/` of a world; vanilla ones in the
+//! `data/<namespace>/structure/` folder of a data pack.
+//!
+//! Ref: https://minecraft.gamepedia.com/Structure_file
+use crate::block_entity::BlockEntity;
+use crate::chunk::BlockState;
+use crate::entity::{Entity, EntityChunk, DATA_VERSION_ENTITY_CHUNKS};
+use crate::error::{Error, Result};
+use crate::upgrade::DATA_VERSION_UNVERSIONED;
+use crate::uuid::Uuid;
+use crate::world::{BlockPos, ChunkPos, World};
+use coruscant_nbt::{Compression, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+/// A structure template.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::structure::{Mirror, Rotation, Structure};
+/// use coruscant_world::BlockState;
+///
+/// let mut structure = Structure::new(3465, [2, 1, 1]);
+/// let stairs = BlockState::new("minecraft:oak_stairs".to_string())
+///     .with_property("facing", "north");
+/// structure.set_block([1, 0, 0], stairs, None);
+///
+/// let rotated = structure.transformed(Mirror::None, Rotation::Clockwise90);
+/// assert_eq!(rotated.size, [1, 1, 2]);
+/// let (pos, state, _) = rotated.blocks().next().unwrap();
+/// assert_eq!(pos, [0, 0, 1]);
+/// assert_eq!(state.property("facing"), Some("east"));
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename = "")]
+#[non_exhaustive]
+pub struct Structure {
+    /// The version of the data.
+    #[serde(rename = "DataVersion")]
+    pub data_version: i32,
+    /// The size of the structure along the X, Y and Z axes.
+    #[serde(rename = "size")]
+    pub size: [i32; 3],
+    /// The block states used by `blocks`.
+    #[serde(rename = "palette")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub palette: Vec<BlockState>,
+    /// Several palettes of the same length, one of which is chosen at random
+    /// when the structure is placed, as in shipwrecks. Used instead of
+    /// `palette`.
+    #[serde(rename = "palettes")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub palettes: Vec<Vec<BlockState>>,
+    /// The blocks of the structure. Positions without a block are left as
+    /// they are when the structure is placed, like structure voids.
+    #[serde(rename = "blocks")]
+    pub blocks: Vec<StructureBlock>,
+    /// The entities of the structure.
+    #[serde(rename = "entities")]
+    #[serde(default)]
+    pub entities: Vec<StructureEntity>,
+}
+
+/// A block of a structure.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct StructureBlock {
+    /// The position of the block in the structure.
+    #[serde(rename = "pos")]
+    pub pos: [i32; 3],
+    /// The index of the block state in the palette.
+    #[serde(rename = "state")]
+    pub state: i32,
+    /// The block entity of the block, without its coordinates.
+    #[serde(rename = "nbt")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbt: Option<Value>,
+}
+
+impl StructureBlock {
+    pub fn new(pos: [i32; 3], state: i32) -> StructureBlock {
+        StructureBlock {
+            pos,
+            state,
+            nbt: None,
+        }
+    }
+}
+
+/// An entity of a structure.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct StructureEntity {
+    /// The exact position of the entity in the structure.
+    #[serde(rename = "pos")]
+    pub pos: [f64; 3],
+    /// The position of the block the entity is in.
+    #[serde(rename = "blockPos")]
+    pub block_pos: [i32; 3],
+    /// The entity, without a UUID. Its `Pos` is that of the entity when it
+    /// was saved, and is replaced when the structure is placed.
+    #[serde(rename = "nbt")]
+    pub nbt: Entity,
+}
+
+impl StructureEntity {
+    pub fn new(pos: [f64; 3], nbt: Entity) -> StructureEntity {
+        StructureEntity {
+            pos,
+            block_pos: [
+                pos[0].floor() as i32,
+                pos[1].floor() as i32,
+                pos[2].floor() as i32,
+            ],
+            nbt,
+        }
+    }
+}
+
+/// How to rotate a structure around the vertical axis, seen from above.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Rotation {
+    None,
+    Clockwise90,
+    Clockwise180,
+    Counterclockwise90,
+}
+
+impl Rotation {
+    fn quarter_turns(self) -> i32 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Clockwise90 => 1,
+            Rotation::Clockwise180 => 2,
+            Rotation::Counterclockwise90 => 3,
+        }
+    }
+}
+
+/// How to mirror a structure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mirror {
+    None,
+    /// Flip along the Z axis, swapping north and south.
+    LeftRight,
+    /// Flip along the X axis, swapping east and west.
+    FrontBack,
+}
+
+impl Structure {
+    /// An empty structure of the given size.
+    pub fn new(data_version: i32, size: [i32; 3]) -> Structure {
+        Structure {
+            data_version,
+            size,
+            palette: Vec::new(),
+            palettes: Vec::new(),
+            blocks: Vec::new(),
+            entities: Vec::new(),
+        }
+    }
+
+    /// Read a structure file.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Structure> {
+        let file = BufReader::new(File::open(path)?);
+        Ok(coruscant_nbt::from_gzip_reader(file)?)
+    }
+
+    /// Write a structure file.
+    ///
+    /// The data is written to a temporary file next to `path` first, which
+    /// then replaces the old file.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let mut file = BufWriter::new(File::create(&tmp)?);
+        coruscant_nbt::to_gzip_writer(&mut file, self, Compression::default())?;
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// The palette in use: `palette`, or the first of `palettes`.
+    pub fn palette(&self) -> &[BlockState] {
+        match self.palettes.first() {
+            Some(palette) if self.palette.is_empty() => palette,
+            _ => &self.palette,
+        }
+    }
+
+    /// Positions, block states and block entities of the blocks, with the
+    /// block states from `palette`.
+    pub fn blocks(&self) -> impl Iterator<Item = ([i32; 3], &BlockState, Option<&Value>)> {
+        let palette = self.palette();
+        self.blocks.iter().filter_map(move |block| {
+            let state = palette.get(usize::try_from(block.state).ok()?)?;
+            Some((block.pos, state, block.nbt.as_ref()))
+        })
+    }
+
+    /// Set the block at the given position, adding its state to the palette.
+    /// With several palettes, the state is added to all of them.
+    pub fn set_block(&mut self, pos: [i32; 3], state: BlockState, nbt: Option<Value>) {
+        let index = match self.palette().iter().position(|s| *s == state) {
+            Some(index) => index,
+            None if self.palette.is_empty() && !self.palettes.is_empty() => {
+                for palette in &mut self.palettes {
+                    palette.push(state.clone());
+                }
+                self.palettes[0].len() - 1
+            }
+            None => {
+                self.palette.push(state);
+                self.palette.len() - 1
+            }
+        };
+        self.blocks.retain(|block| block.pos != pos);
+        let mut block = StructureBlock::new(pos, index as i32);
+        block.nbt = nbt;
+        self.blocks.push(block);
+    }
+
+    /// Save the blocks, block entities and entities in the box of the given
+    /// size from `origin` into a structure.
+    ///
+    /// Blocks of chunks that are not generated are left out. Entities are
+    /// only read from worlds since 1.17, which store them in `entities/`.
+    pub fn from_world(
+        world: &mut World,
+        dimension: &str,
+        origin: BlockPos,
+        size: [i32; 3],
+    ) -> Result<Structure> {
+        let data_version = world.level_dat().data.data_version;
+        let mut structure = Structure::new(data_version.unwrap_or(DATA_VERSION_UNVERSIONED), size);
+        let mut palette: HashMap<BlockState, i32> = HashMap::new();
+        for y in 0..size[1] {
+            for z in 0..size[2] {
+                for x in 0..size[0] {
+                    let pos = BlockPos::new(origin.x + x, origin.y + y, origin.z + z);
+                    let state = match world.get_block(dimension, pos)? {
+                        Some(state) => state,
+                        None => continue,
+                    };
+                    let next = palette.len() as i32;
+                    let index = *palette.entry(state.clone()).or_insert_with(|| {
+                        structure.palette.push(state);
+                        next
+                    });
+                    let mut block = StructureBlock::new([x, y, z], index);
+                    if let Some(block_entity) = world.block_entity(dimension, pos)? {
+                        let mut nbt = block_entity.to_value()?;
+                        remove_keys(&mut nbt, &["x", "y", "z"]);
+                        block.nbt = Some(nbt);
+                    }
+                    structure.blocks.push(block);
+                }
+            }
+        }
+        if structure.data_version >= DATA_VERSION_ENTITY_CHUNKS {
+            let min = ChunkPos::from_block(origin.x, origin.z);
+            let max = ChunkPos::from_block(origin.x + size[0] - 1, origin.z + size[2] - 1);
+            for chunk_x in min.x..=max.x {
+                for chunk_z in min.z..=max.z {
+                    let chunk = world.read_entities(dimension, ChunkPos::new(chunk_x, chunk_z))?;
+                    for entity in chunk.map(|c| c.entities).unwrap_or_default() {
+                        let mut nbt = entity.to_value()?;
+                        let pos = match entity_pos(&nbt) {
+                            Some(pos) => pos,
+                            None => continue,
+                        };
+                        let pos = [
+                            pos[0] - f64::from(origin.x),
+                            pos[1] - f64::from(origin.y),
+                            pos[2] - f64::from(origin.z),
+                        ];
+                        let inside = (0..3).all(|i| pos[i] >= 0.0 && pos[i] < f64::from(size[i]));
+                        if inside {
+                            remove_keys(&mut nbt, &["UUID", "UUIDMost", "UUIDLeast"]);
+                            let nbt = Entity::from_value(nbt);
+                            structure.entities.push(StructureEntity::new(pos, nbt));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(structure)
+    }
+
+    /// Place the structure into a world with its lowest corner at `origin`.
+    ///
+    /// Blocks are set through `World::set_block`, and are only written on
+    /// `World::flush`; blocks in chunks that are not generated fail with
+    /// `Error::ChunkNotGenerated`. Entities are given new UUIDs and written
+    /// into `entities/` at once, and are left out in worlds before 1.17.
+    pub fn place(&self, world: &mut World, dimension: &str, origin: BlockPos) -> Result<()> {
+        let palette = self.palette();
+        for block in &self.blocks {
+            let state = usize::try_from(block.state)
+                .ok()
+                .and_then(|i| palette.get(i))
+                .ok_or(Error::CorruptChunk("structure block state out of palette"))?;
+            let pos = BlockPos::new(
+                origin.x + block.pos[0],
+                origin.y + block.pos[1],
+                origin.z + block.pos[2],
+            );
+            world.set_block(dimension, pos, state.clone())?;
+            let block_entity = block.nbt.as_ref().map(|nbt| {
+                let mut nbt = nbt.clone();
+                if let Value::Compound(compound) = &mut nbt {
+                    compound.insert("x".to_owned(), Value::Int(pos.x));
+                    compound.insert("y".to_owned(), Value::Int(pos.y));
+                    compound.insert("z".to_owned(), Value::Int(pos.z));
+                }
+                BlockEntity::from_value(nbt)
+            });
+            world.set_block_entity(dimension, pos, block_entity)?;
+        }
+        let data_version = world
+            .level_dat()
+            .data
+            .data_version
+            .unwrap_or(DATA_VERSION_UNVERSIONED);
+        if data_version < DATA_VERSION_ENTITY_CHUNKS {
+            return Ok(());
+        }
+        let mut chunks: BTreeMap<ChunkPos, Vec<Entity>> = BTreeMap::new();
+        for entity in &self.entities {
+            let pos = [
+                f64::from(origin.x) + entity.pos[0],
+                f64::from(origin.y) + entity.pos[1],
+                f64::from(origin.z) + entity.pos[2],
+            ];
+            let mut nbt = entity.nbt.to_value()?;
+            if let Value::Compound(compound) = &mut nbt {
+                let pos = pos.iter().map(|c| Value::Double(*c)).collect();
+                compound.insert("Pos".to_owned(), Value::List(pos));
+                compound.insert("UUID".to_owned(), coruscant_nbt::to_value(random_uuid()?)?);
+            }
+            let chunk = ChunkPos::from_block(pos[0].floor() as i32, pos[2].floor() as i32);
+            chunks
+                .entry(chunk)
+                .or_default()
+                .push(Entity::from_value(nbt));
+        }
+        for (pos, entities) in chunks {
+            let mut chunk = match world.read_entities(dimension, pos)? {
+                Some(chunk) => chunk,
+                None => EntityChunk::new(data_version, pos, Vec::new()),
+            };
+            chunk.entities.extend(entities);
+            world.write_entities(dimension, &chunk)?;
+        }
+        Ok(())
+    }
+
+    /// The structure mirrored and then rotated, with its block states and
+    /// entities turned to match. The structure stays in the box from the
+    /// origin, with X and Z sizes swapped by quarter turns.
+    ///
+    /// Entities are moved and their yaw is turned; other directions stored
+    /// in entities, like the facing of item frames, are left as they are.
+    pub fn transformed(&self, mirror: Mirror, rotation: Rotation) -> Structure {
+        let transform = Transform::new(mirror, rotation, self.size);
+        let palettes = self
+            .palettes
+            .iter()
+            .map(|palette| {
+                palette
+                    .iter()
+                    .map(|s| transform_block_state(s, mirror, rotation))
+                    .collect()
+            })
+            .collect();
+        let blocks = self
+            .blocks
+            .iter()
+            .map(|block| StructureBlock {
+                pos: transform.block(block.pos),
+                state: block.state,
+                nbt: block.nbt.clone(),
+            })
+            .collect();
+        let entities = self
+            .entities
+            .iter()
+            .map(|entity| {
+                let pos = transform.point(entity.pos);
+                let mut nbt = entity.nbt.clone();
+                match &mut nbt {
+                    Entity::Unknown(Value::Compound(compound)) => {
+                        if let Some(Value::List(rotation)) = compound.get_mut("Rotation") {
+                            if let Some(Value::Float(yaw)) = rotation.get_mut(0) {
+                                *yaw = transform.yaw(*yaw);
+                            }
+                        }
+                    }
+                    nbt => {
+                        if let Some(data) = nbt.entity_mut() {
+                            data.rotation[0] = transform.yaw(data.rotation[0]);
+                        }
+                    }
+                }
+                StructureEntity::new(pos, nbt)
+            })
+            .collect();
+        Structure {
+            data_version: self.data_version,
+            size: transform.size(),
+            palette: self
+                .palette
+                .iter()
+                .map(|s| transform_block_state(s, mirror, rotation))
+                .collect(),
+            palettes,
+            blocks,
+            entities,
+        }
+    }
+}
+
+/// The block state mirrored and then rotated: its facing, rotation, axis,
+/// rail and stair shapes, door hinges, chest halves and connections to
+/// neighbouring blocks are turned to match.
+pub fn transform_block_state(state: &BlockState, mirror: Mirror, rotation: Rotation) -> BlockState {
+    let mut ans = BlockState::new(state.name.clone());
+    for (name, value) in &state.properties {
+        match name.as_str() {
+            "north" | "east" | "south" | "west" => {
+                let name = transform_direction(name, mirror, rotation);
+                ans.properties.insert(name.to_owned(), value.clone());
+            }
+            _ => {
+                ans.properties.insert(name.clone(), value.clone());
+            }
+        }
+    }
+    let facing = state.property("facing");
+    for (name, value) in ans.properties.iter_mut() {
+        let new = match name.as_str() {
+            "facing" => transform_direction(value, mirror, rotation).to_owned(),
+            "orientation" => value
+                .split('_')
+                .map(|d| transform_direction(d, mirror, rotation))
+                .collect::<Vec<_>>()
+                .join("_"),
+            "axis" if rotation.quarter_turns() % 2 == 1 => match value.as_str() {
+                "x" => "z".to_owned(),
+                "z" => "x".to_owned(),
+                other => other.to_owned(),
+            },
+            "rotation" => match value.parse::<i32>() {
+                Ok(r) => transform_rotation(r, mirror, rotation).to_string(),
+                Err(_) => continue,
+            },
+            "hinge" if mirror != Mirror::None => swap_left_right(value),
+            "type" if mirror != Mirror::None && state.name.ends_with("chest") => {
+                swap_left_right(value)
+            }
+            "shape" if value.contains("left") || value.contains("right") => {
+                // stairs: the shape only flips when mirrored along the facing
+                let flips = matches!(
+                    (mirror, facing),
+                    (Mirror::LeftRight, Some("north"))
+                        | (Mirror::LeftRight, Some("south"))
+                        | (Mirror::FrontBack, Some("east"))
+                        | (Mirror::FrontBack, Some("west"))
+                );
+                if flips {
+                    swap_left_right(value)
+                } else {
+                    continue;
+                }
+            }
+            "shape" => transform_rail_shape(value, mirror, rotation),
+            _ => continue,
+        };
+        *value = new;
+    }
+    ans
+}
+
+fn swap_left_right(value: &str) -> String {
+    if value.contains("left") {
+        value.replace("left", "right")
+    } else {
+        value.replace("right", "left")
+    }
+}
+
+fn transform_rail_shape(shape: &str, mirror: Mirror, rotation: Rotation) -> String {
+    if let Some(d) = shape.strip_prefix("ascending_") {
+        return format!("ascending_{}", transform_direction(d, mirror, rotation));
+    }
+    let mut parts: Vec<&str> = shape
+        .split('_')
+        .map(|d| transform_direction(d, mirror, rotation))
+        .collect();
+    // vanilla names put north and south first, and name straight rails
+    // north_south and east_west
+    let order = |d: &&str| match *d {
+        "north" => 0,
+        "south" => 1,
+        "east" => 2,
+        _ => 3,
+    };
+    parts.sort_by_key(order);
+    parts.join("_")
+}
+
+const HORIZONTAL: [&str; 4] = ["north", "east", "south", "west"];
+
+fn transform_direction(direction: &str, mirror: Mirror, rotation: Rotation) -> &str {
+    let mirrored = match (mirror, direction) {
+        (Mirror::LeftRight, "north") => "south",
+        (Mirror::LeftRight, "south") => "north",
+        (Mirror::FrontBack, "east") => "west",
+        (Mirror::FrontBack, "west") => "east",
+        _ => direction,
+    };
+    match HORIZONTAL.iter().position(|d| *d == mirrored) {
+        Some(i) => HORIZONTAL[(i + rotation.quarter_turns() as usize) % 4],
+        None => mirrored,
+    }
+}
+
+// The 16 step rotation of signs, banners and skulls: 0 is south, 4 west, 8
+// north and 12 east.
+fn transform_rotation(r: i32, mirror: Mirror, rotation: Rotation) -> i32 {
+    let mirrored = match mirror {
+        Mirror::None => r,
+        Mirror::LeftRight => 8 - r,
+        Mirror::FrontBack => 16 - r,
+    };
+    (mirrored + 4 * rotation.quarter_turns()).rem_euclid(16)
+}
+
+// Positions in the box of a structure, mirrored and then rotated.
+struct Transform {
+    mirror: Mirror,
+    rotation: Rotation,
+    size: [i32; 3],
+}
+
+impl Transform {
+    fn new(mirror: Mirror, rotation: Rotation, size: [i32; 3]) -> Transform {
+        Transform {
+            mirror,
+            rotation,
+            size,
+        }
+    }
+
+    fn size(&self) -> [i32; 3] {
+        match self.rotation.quarter_turns() % 2 {
+            0 => self.size,
+            _ => [self.size[2], self.size[1], self.size[0]],
+        }
+    }
+
+    // Blocks are transformed by their cells, so a block at the far edge
+    // stays inside the box.
+    fn block(&self, pos: [i32; 3]) -> [i32; 3] {
+        let p = self.point([
+            f64::from(pos[0]) + 0.5,
+            f64::from(pos[1]),
+            f64::from(pos[2]) + 0.5,
+        ]);
+        [p[0].floor() as i32, pos[1], p[2].floor() as i32]
+    }
+
+    fn point(&self, pos: [f64; 3]) -> [f64; 3] {
+        let (sx, sz) = (f64::from(self.size[0]), f64::from(self.size[2]));
+        let (mut x, y, mut z) = (pos[0], pos[1], pos[2]);
+        match self.mirror {
+            Mirror::None => {}
+            Mirror::LeftRight => z = sz - z,
+            Mirror::FrontBack => x = sx - x,
+        }
+        let (x, z) = match self.rotation {
+            Rotation::None => (x, z),
+            Rotation::Clockwise90 => (sz - z, x),
+            Rotation::Clockwise180 => (sx - x, sz - z),
+            Rotation::Counterclockwise90 => (z, sx - x),
+        };
+        [x, y, z]
+    }
+
+    // Yaw in degrees: 0 faces south and 90 west.
+    fn yaw(&self, yaw: f32) -> f32 {
+        let mirrored = match self.mirror {
+            Mirror::None => yaw,
+            Mirror::LeftRight => 180.0 - yaw,
+            Mirror::FrontBack => -yaw,
+        };
+        (mirrored + 90.0 * self.rotation.quarter_turns() as f32).rem_euclid(360.0)
+    }
+}
+
+fn entity_pos(nbt: &Value) -> Option<[f64; 3]> {
+    match nbt {
+        Value::Compound(compound) => match compound.get("Pos") {
+            Some(Value::List(pos)) => match pos.as_slice() {
+                [Value::Double(x), Value::Double(y), Value::Double(z)] => Some([*x, *y, *z]),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn remove_keys(nbt: &mut Value, keys: &[&str]) {
+    if let Value::Compound(compound) = nbt {
+        for key in keys {
+            compound.remove(*key);
+        }
+    }
+}
+
+// A random version 4 UUID, from the random source of the system.
+fn random_uuid() -> Result<Uuid> {
+    let mut bytes = [0; 16];
+    getrandom::fill(&mut bytes).map_err(io::Error::from)?;
+    let bits = u128::from_be_bytes(bytes);
+    let bits = bits & !(0xf << 76) | 0x4 << 76;
+    let bits = bits & !(0x3 << 62) | 0x2 << 62;
+    Ok(Uuid::from_u128(bits))
+}
@@ -0,0 +1,612 @@
+//! Upgrading data saved by older versions of the game.
+//!
+//! The game upgrades the data it loads with DataFixerUpper: every change of
+//! a format comes with a fix registered at the `DataVersion` that introduced
+//! the change, and data is upgraded by running the fixes newer than its
+//! version in order. `Upgrader` does the same for NBT values, so that old
+//! worlds can be upgraded without running the game.
+//!
+//! Fixes are registered for a `DataType`. Data of one type holds data of
+//! other types, like the items in the inventory of an entity or the block
+//! entities of a chunk; the fixes of a version run on this nested data too,
+//! before the fixes of the next version.
+//!
+//! `Upgrader::vanilla` has fixes for:
+//!
+//! - entities split by variant, like husks from zombies, and entity and
+//!   block entity IDs namespaced in 1.11;
+//! - the Flattening (1.13): numeric block IDs in chunk sections become a
+//!   palette of block states, with the names of `legacy_ids`;
+//! - chunk statuses renamed in 1.14;
+//! - UUIDs stored as an int array instead of two longs or a string, also in
+//!   `level.dat` (1.16);
+//! - block states packed so that indices do not span longs (1.16);
+//! - chunks without the `Level` compound, with biomes stored in sections
+//!   (1.18);
+//! - chunks of the overworld extended below Y 0, marked for the game to
+//!   generate the terrain there (1.18);
+//! - item stacks with components instead of a `tag` compound (1.20.5).
+//!
+//! The Flattening also changed item IDs and merged some block entities,
+//! like beds and flower pots, into block states. These fixes are not
+//! implemented: upgrading data holding such items or block entities past
+//! 1.13 fails with `Error::UnsupportedUpgrade`, so that no data is marked
+//! with a version it was not upgraded to. So does upgrading past 1.20.5 an
+//! item with a `tag` field whose component is not implemented, like
+//! `CanDestroy` or `Trim`. Chunks need a `ChunkContext` to be upgraded past
+//! 1.18, since the new heights depend on their dimension.
+//!
+//! Ref: https://minecraft.gamepedia.com/Data_version
+use crate::chunk::DATA_VERSION_FLATTENING;
+use crate::chunk::DATA_VERSION_NO_LEVEL;
+use crate::error::Result;
+use crate::item::DATA_VERSION_ITEM_COMPONENTS;
+use crate::palette::DATA_VERSION_ALIGNED_PACKING;
+use crate::region::RegionFile;
+use core::fmt;
+use coruscant_nbt::{Map, Value};
+
+mod chunks;
+mod flattening;
+mod ids;
+mod items;
+mod uuids;
+
+/// Version assumed for data without a `DataVersion`, saved before 1.9.
+pub const DATA_VERSION_UNVERSIONED: i32 = 99;
+
+/// Data version of 16w32a, since which block entity IDs are namespaced.
+pub const DATA_VERSION_BLOCK_ENTITY_IDS: i32 = 704;
+
+/// Data version of 16w32a, since which entity IDs are namespaced.
+pub const DATA_VERSION_ENTITY_IDS: i32 = 705;
+
+/// Data version of 1.14, by which chunk statuses were renamed.
+pub const DATA_VERSION_CHUNK_STATUSES: i32 = 1952;
+
+/// Data version of 20w12a, since which UUIDs are stored as int arrays.
+pub const DATA_VERSION_INT_ARRAY_UUIDS: i32 = 2514;
+
+// Compound added to the root of chunks while they are upgraded, like the
+// game does, for the fixes that depend on their dimension.
+const CONTEXT: &str = "__context";
+
+/// Kind of data a fix applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DataType {
+    /// A chunk in `region/`, with its block entities and, before 1.17, its
+    /// entities.
+    Chunk,
+    /// The entities of a chunk in `entities/`, since 1.17.
+    EntityChunk,
+    /// An entity, including players.
+    Entity,
+    /// A block entity.
+    BlockEntity,
+    /// An item stack.
+    ItemStack,
+    /// The root of `level.dat`, with the single player in `Data.Player`.
+    Level,
+}
+
+/// Where a chunk is saved, which some chunk fixes depend on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ChunkContext {
+    /// Name of the dimension of the chunk, like `minecraft:overworld`.
+    pub dimension: String,
+    /// Type of the generator of the dimension, like `minecraft:noise` or
+    /// `minecraft:flat`.
+    pub generator: String,
+}
+
+impl ChunkContext {
+    pub fn new(dimension: String, generator: String) -> ChunkContext {
+        ChunkContext {
+            dimension,
+            generator,
+        }
+    }
+}
+
+type FixFn = dyn Fn(&mut Value) -> Result<()> + Send + Sync;
+
+struct Fix {
+    data_type: DataType,
+    version: i32,
+    fix: Box<FixFn>,
+}
+
+/// Fixes by data version, applied to NBT values in order.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_nbt::Value;
+/// use coruscant_world::upgrade::{rename_field, DataType, Upgrader};
+///
+/// let mut upgrader = Upgrader::new();
+/// upgrader.register(DataType::Entity, 2000, |entity| {
+///     if let Value::Compound(entity) = entity {
+///         rename_field(entity, "Fire", "fire_ticks");
+///     }
+///     Ok(())
+/// });
+///
+/// let mut zombie: Value = coruscant_nbt::from_str_snbt(
+///     r#"{id: "minecraft:zombie", Fire: 20s, DataVersion: 1500}"#,
+/// ).unwrap();
+/// assert!(upgrader.upgrade_root(DataType::Entity, &mut zombie, 2100).unwrap());
+/// let expected = r#"{id: "minecraft:zombie", fire_ticks: 20s, DataVersion: 2100}"#;
+/// assert_eq!(zombie, coruscant_nbt::from_str_snbt(expected).unwrap());
+/// ```
+#[derive(Default)]
+pub struct Upgrader {
+    fixes: Vec<Fix>,
+}
+
+impl Upgrader {
+    /// An upgrader without fixes.
+    pub fn new() -> Upgrader {
+        Upgrader { fixes: Vec::new() }
+    }
+
+    /// An upgrader with the fixes of the game supported by this crate.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use coruscant_nbt::{from_str_snbt, Value};
+    /// use coruscant_world::upgrade::{DataType, Upgrader};
+    ///
+    /// let upgrader = Upgrader::vanilla();
+    /// let mut wolf: Value = from_str_snbt(
+    ///     r#"{id: "minecraft:wolf", UUIDMost: 1L, UUIDLeast: 2L,
+    ///         HandItems: [{id: "minecraft:bone", Count: 1b, tag: {RepairCost: 3}}]}"#,
+    /// ).unwrap();
+    /// upgrader.upgrade(DataType::Entity, &mut wolf, 1976, 3953).unwrap();
+    /// let expected = r#"{id: "minecraft:wolf", UUID: [I; 0, 1, 0, 2],
+    ///     HandItems: [{id: "minecraft:bone", count: 1, components: {"minecraft:repair_cost": 3}}]}"#;
+    /// assert_eq!(wolf, from_str_snbt(expected).unwrap());
+    /// ```
+    pub fn vanilla() -> Upgrader {
+        let mut upgrader = Upgrader::new();
+        for (version, fix) in &ids::SPLITS {
+            upgrader.register(DataType::Entity, *version, *fix);
+        }
+        upgrader.register(
+            DataType::BlockEntity,
+            DATA_VERSION_BLOCK_ENTITY_IDS,
+            ids::block_entity,
+        );
+        upgrader.register(DataType::Entity, DATA_VERSION_ENTITY_IDS, ids::entity);
+        upgrader.register(DataType::Chunk, DATA_VERSION_FLATTENING, chunks::flatten);
+        upgrader.register(
+            DataType::Entity,
+            DATA_VERSION_FLATTENING,
+            flattening::entity,
+        );
+        upgrader.register(
+            DataType::BlockEntity,
+            DATA_VERSION_FLATTENING,
+            flattening::block_entity,
+        );
+        upgrader.register(
+            DataType::ItemStack,
+            DATA_VERSION_FLATTENING,
+            flattening::item_stack,
+        );
+        upgrader.register(
+            DataType::Chunk,
+            DATA_VERSION_CHUNK_STATUSES,
+            chunks::rename_statuses,
+        );
+        upgrader.register(
+            DataType::Entity,
+            DATA_VERSION_INT_ARRAY_UUIDS,
+            uuids::entity,
+        );
+        upgrader.register(
+            DataType::BlockEntity,
+            DATA_VERSION_INT_ARRAY_UUIDS,
+            uuids::block_entity,
+        );
+        upgrader.register(
+            DataType::ItemStack,
+            DATA_VERSION_INT_ARRAY_UUIDS,
+            uuids::item_stack,
+        );
+        upgrader.register(DataType::Level, DATA_VERSION_INT_ARRAY_UUIDS, uuids::level);
+        upgrader.register(
+            DataType::Chunk,
+            DATA_VERSION_ALIGNED_PACKING,
+            chunks::align_packing,
+        );
+        upgrader.register(DataType::Chunk, DATA_VERSION_NO_LEVEL, chunks::remove_level);
+        upgrader.register(
+            DataType::ItemStack,
+            DATA_VERSION_ITEM_COMPONENTS,
+            items::to_components,
+        );
+        upgrader
+    }
+
+    /// Register a fix upgrading data of the given type to `version`. It runs
+    /// on data older than `version` upgraded to `version` or newer, after
+    /// the fixes registered before it for the same version.
+    pub fn register<F>(&mut self, data_type: DataType, version: i32, fix: F)
+    where
+        F: Fn(&mut Value) -> Result<()> + Send + Sync + 'static,
+    {
+        let index = self.fixes.partition_point(|f| f.version <= version);
+        let fix = Fix {
+            data_type,
+            version,
+            fix: Box::new(fix),
+        };
+        self.fixes.insert(index, fix);
+    }
+
+    /// Upgrade data of the given type from version `from` to version `to`,
+    /// including the data nested in it. The `DataVersion` of the data is
+    /// left as is, and so is the whole value if a fix fails.
+    pub fn upgrade(
+        &self,
+        data_type: DataType,
+        value: &mut Value,
+        from: i32,
+        to: i32,
+    ) -> Result<()> {
+        let mut versions: Vec<i32> = (self.fixes.iter())
+            .map(|f| f.version)
+            .filter(|&version| from < version && version <= to)
+            .collect();
+        versions.dedup();
+        if versions.is_empty() {
+            return Ok(());
+        }
+        let mut upgraded = value.clone();
+        for version in versions {
+            self.apply(data_type, &mut upgraded, version)?;
+        }
+        *value = upgraded;
+        Ok(())
+    }
+
+    /// Upgrade data saved with its `DataVersion`, like a chunk or
+    /// `level.dat`, to version `to`, and set its `DataVersion`. Data without
+    /// a `DataVersion` counts as `DATA_VERSION_UNVERSIONED`.
+    ///
+    /// Returns whether the data was older than `to`. If a fix fails, the
+    /// data is left as is, with its old `DataVersion`. Chunks are upgraded
+    /// with `upgrade_chunk`, as some of their fixes need a `ChunkContext`.
+    pub fn upgrade_root(&self, data_type: DataType, value: &mut Value, to: i32) -> Result<bool> {
+        let from = match root_of(data_type, value) {
+            Some(root) => match root.get("DataVersion") {
+                Some(Value::Int(version)) => *version,
+                _ => DATA_VERSION_UNVERSIONED,
+            },
+            None => return Ok(false),
+        };
+        if from >= to {
+            return Ok(false);
+        }
+        self.upgrade(data_type, value, from, to)?;
+        if let Some(root) = root_of(data_type, value) {
+            root.insert("DataVersion".to_owned(), Value::Int(to));
+        }
+        Ok(true)
+    }
+
+    /// Upgrade a chunk in `region/` like `upgrade_root`, giving its fixes
+    /// the dimension and generator of the chunk.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use coruscant_nbt::{from_str_snbt, Value};
+    /// use coruscant_world::upgrade::{ChunkContext, Upgrader};
+    ///
+    /// let mut chunk: Value = from_str_snbt(
+    ///     r#"{DataVersion: 2730, Level: {xPos: 0, zPos: 0, Status: "full", Sections: []}}"#,
+    /// ).unwrap();
+    /// let nether = ChunkContext::new("minecraft:the_nether".into(), "minecraft:noise".into());
+    /// assert!(Upgrader::vanilla().upgrade_chunk(&mut chunk, &nether, 3953).unwrap());
+    /// if let Value::Compound(chunk) = &chunk {
+    ///     assert_eq!(chunk.get("yPos"), Some(&Value::Int(0)));
+    ///     assert!(!chunk.contains_key("__context"));
+    /// }
+    /// ```
+    pub fn upgrade_chunk(
+        &self,
+        chunk: &mut Value,
+        context: &ChunkContext,
+        to: i32,
+    ) -> Result<bool> {
+        let root = match chunk {
+            Value::Compound(root) => root,
+            _ => return Ok(false),
+        };
+        let mut compound = Map::new();
+        compound.insert(
+            "dimension".to_owned(),
+            Value::String(context.dimension.clone()),
+        );
+        compound.insert(
+            "generator".to_owned(),
+            Value::String(context.generator.clone()),
+        );
+        root.insert(CONTEXT.to_owned(), Value::Compound(compound));
+        let upgraded = self.upgrade_root(DataType::Chunk, chunk, to);
+        if let Value::Compound(root) = chunk {
+            root.remove(CONTEXT);
+        }
+        upgraded
+    }
+
+    /// Upgrade every chunk of a region file to version `to`, returning the
+    /// number of chunks written back. Chunks of `DataType::Chunk` are
+    /// upgraded with `upgrade_chunk` if given a `context`.
+    ///
+    /// Stops at the first chunk that cannot be upgraded, leaving it and the
+    /// chunks not yet upgraded as they are.
+    pub fn upgrade_region(
+        &self,
+        data_type: DataType,
+        region: &mut RegionFile,
+        context: Option<&ChunkContext>,
+        to: i32,
+    ) -> Result<usize> {
+        let chunks: Vec<_> = region.chunks().collect();
+        let mut upgraded = 0;
+        for (x, z) in chunks {
+            let mut chunk: Value = match region.read_chunk(x, z)? {
+                Some(chunk) => chunk,
+                None => continue,
+            };
+            let older = match context {
+                Some(context) if data_type == DataType::Chunk => {
+                    self.upgrade_chunk(&mut chunk, context, to)?
+                }
+                _ => self.upgrade_root(data_type, &mut chunk, to)?,
+            };
+            if older {
+                region.write_chunk(x, z, &chunk)?;
+                upgraded += 1;
+            }
+        }
+        Ok(upgraded)
+    }
+
+    fn apply(&self, data_type: DataType, value: &mut Value, version: i32) -> Result<()> {
+        let fixes = self.fixes.iter().filter(|f| f.version == version);
+        for fix in fixes.filter(|f| f.data_type == data_type) {
+            (fix.fix)(value)?;
+        }
+        for_each_nested(data_type, value, &mut |data_type, nested| {
+            self.apply(data_type, nested, version)
+        })
+    }
+}
+
+impl fmt::Debug for Upgrader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fixes: Vec<_> = (self.fixes.iter())
+            .map(|fix| (fix.data_type, fix.version))
+            .collect();
+        f.debug_struct("Upgrader").field("fixes", &fixes).finish()
+    }
+}
+
+/// Move the value of field `from` to field `to`, returning whether there
+/// was such a field.
+pub fn rename_field(compound: &mut Map<String, Value>, from: &str, to: &str) -> bool {
+    match compound.remove(from) {
+        Some(value) => {
+            compound.insert(to.to_owned(), value);
+            true
+        }
+        None => false,
+    }
+}
+
+// The compound holding the `DataVersion` of data of the type.
+fn root_of(data_type: DataType, value: &mut Value) -> Option<&mut Map<String, Value>> {
+    match (data_type, value) {
+        (DataType::Level, Value::Compound(root)) => match root.get_mut("Data") {
+            Some(Value::Compound(data)) => Some(data),
+            _ => None,
+        },
+        (_, Value::Compound(root)) => Some(root),
+        _ => None,
+    }
+}
+
+type Visit<'a> = dyn FnMut(DataType, &mut Value) -> Result<()> + 'a;
+
+// Call `f` on the data of other types stored in the value.
+fn for_each_nested(data_type: DataType, value: &mut Value, f: &mut Visit) -> Result<()> {
+    let compound = match value {
+        Value::Compound(compound) => compound,
+        _ => return Ok(()),
+    };
+    match data_type {
+        DataType::Chunk => {
+            if let Some(Value::Compound(level)) = compound.get_mut("Level") {
+                chunk_nested(level, f)?;
+            }
+            chunk_nested(compound, f)
+        }
+        DataType::EntityChunk => each_in_list(compound, "Entities", DataType::Entity, f),
+        DataType::Entity => entity_nested(compound, f),
+        DataType::BlockEntity => block_entity_nested(compound, f),
+        DataType::ItemStack => item_nested(compound, f),
+        DataType::Level => match compound.get_mut("Data") {
+            Some(Value::Compound(data)) => each_field(data, "Player", DataType::Entity, f),
+            _ => Ok(()),
+        },
+    }
+}
+
+fn chunk_nested(chunk: &mut Map<String, Value>, f: &mut Visit) -> Result<()> {
+    each_in_list(chunk, "Entities", DataType::Entity, f)?;
+    each_in_list(chunk, "entities", DataType::Entity, f)?;
+    each_in_list(chunk, "TileEntities", DataType::BlockEntity, f)?;
+    each_in_list(chunk, "block_entities", DataType::BlockEntity, f)
+}
+
+fn entity_nested(entity: &mut Map<String, Value>, f: &mut Visit) -> Result<()> {
+    spawner_nested(entity, f)?;
+    each_in_list(entity, "Passengers", DataType::Entity, f)?;
+    for key in &["ShoulderEntityLeft", "ShoulderEntityRight"] {
+        each_field(entity, key, DataType::Entity, f)?;
+    }
+    if let Some(Value::Compound(vehicle)) = entity.get_mut("RootVehicle") {
+        each_field(vehicle, "Entity", DataType::Entity, f)?;
+    }
+    for key in &[
+        "Items",
+        "Inventory",
+        "EnderItems",
+        "HandItems",
+        "ArmorItems",
+    ] {
+        each_in_list(entity, key, DataType::ItemStack, f)?;
+    }
+    for key in &[
+        "Item",
+        "SaddleItem",
+        "ArmorItem",
+        "DecorItem",
+        "body_armor_item",
+    ] {
+        each_field(entity, key, DataType::ItemStack, f)?;
+    }
+    if let Some(Value::Compound(equipment)) = entity.get_mut("equipment") {
+        for (_, item) in equipment.iter_mut() {
+            f(DataType::ItemStack, item)?;
+        }
+    }
+    if let Some(Value::Compound(offers)) = entity.get_mut("Offers") {
+        if let Some(Value::List(recipes)) = offers.get_mut("Recipes") {
+            for recipe in recipes {
+                if let Value::Compound(recipe) = recipe {
+                    for key in &["buy", "buyB", "sell"] {
+                        each_field(recipe, key, DataType::ItemStack, f)?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn block_entity_nested(block_entity: &mut Map<String, Value>, f: &mut Visit) -> Result<()> {
+    spawner_nested(block_entity, f)?;
+    each_in_list(block_entity, "Items", DataType::ItemStack, f)?;
+    for key in &["Item", "RecordItem", "Book", "item"] {
+        each_field(block_entity, key, DataType::ItemStack, f)?;
+    }
+    for (bees, entity) in &[("Bees", "EntityData"), ("bees", "entity_data")] {
+        if let Some(Value::List(bees)) = block_entity.get_mut(*bees) {
+            for bee in bees {
+                if let Value::Compound(bee) = bee {
+                    each_field(bee, entity, DataType::Entity, f)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// The entities of a spawner block entity or minecart, with the entity
+// compounds wrapped in `entity` since 1.18.
+fn spawner_nested(spawner: &mut Map<String, Value>, f: &mut Visit) -> Result<()> {
+    if let Some(spawn_data @ Value::Compound(_)) = spawner.get_mut("SpawnData") {
+        spawn_data_nested(spawn_data, f)?;
+    }
+    if let Some(Value::List(potentials)) = spawner.get_mut("SpawnPotentials") {
+        for potential in potentials {
+            if let Value::Compound(potential) = potential {
+                each_field(potential, "Entity", DataType::Entity, f)?;
+                if let Some(data @ Value::Compound(_)) = potential.get_mut("data") {
+                    spawn_data_nested(data, f)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn spawn_data_nested(spawn_data: &mut Value, f: &mut Visit) -> Result<()> {
+    match spawn_data {
+        Value::Compound(data) if data.contains_key("entity") => {
+            each_field(data, "entity", DataType::Entity, f)
+        }
+        _ => f(DataType::Entity, spawn_data),
+    }
+}
+
+fn item_nested(item: &mut Map<String, Value>, f: &mut Visit) -> Result<()> {
+    if let Some(Value::Compound(tag)) = item.get_mut("tag") {
+        each_field(tag, "BlockEntityTag", DataType::BlockEntity, f)?;
+        each_field(tag, "EntityTag", DataType::Entity, f)?;
+        // of bundles and crossbows
+        each_in_list(tag, "Items", DataType::ItemStack, f)?;
+        each_in_list(tag, "ChargedProjectiles", DataType::ItemStack, f)?;
+    }
+    if let Some(Value::Compound(components)) = item.get_mut("components") {
+        let block_entity = "minecraft:block_entity_data";
+        each_field(components, block_entity, DataType::BlockEntity, f)?;
+        each_field(components, "minecraft:entity_data", DataType::Entity, f)?;
+        each_in_list(
+            components,
+            "minecraft:bundle_contents",
+            DataType::ItemStack,
+            f,
+        )?;
+        each_in_list(
+            components,
+            "minecraft:charged_projectiles",
+            DataType::ItemStack,
+            f,
+        )?;
+        if let Some(Value::List(slots)) = components.get_mut("minecraft:container") {
+            for slot in slots {
+                if let Value::Compound(slot) = slot {
+                    each_field(slot, "item", DataType::ItemStack, f)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn each_field(
+    compound: &mut Map<String, Value>,
+    key: &str,
+    data_type: DataType,
+    f: &mut Visit,
+) -> Result<()> {
+    match compound.get_mut(key) {
+        Some(value @ Value::Compound(_)) => f(data_type, value),
+        _ => Ok(()),
+    }
+}
+
+fn each_in_list(
+    compound: &mut Map<String, Value>,
+    key: &str,
+    data_type: DataType,
+    f: &mut Visit,
+) -> Result<()> {
+    if let Some(Value::List(list)) = compound.get_mut(key) {
+        for value in list {
+            if let Value::Compound(_) = value {
+                f(data_type, value)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,506 @@
+//! Fixes of the chunk format.
+use super::{rename_field, CONTEXT};
+use crate::chunk::{BlockState, DATA_VERSION_NO_LEVEL};
+use crate::error::{Error, Result};
+use crate::legacy_ids;
+use crate::palette::{self, Packing, PalettedContainer};
+use coruscant_nbt::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::mem;
+
+// Renamed with the removal of the `Level` compound in 1.18.
+const LEVEL_RENAMES: [(&str, &str); 6] = [
+    ("Sections", "sections"),
+    ("TileEntities", "block_entities"),
+    ("TileTicks", "block_ticks"),
+    ("LiquidTicks", "fluid_ticks"),
+    ("Structures", "structures"),
+    ("Entities", "entities"),
+];
+
+// Chunk statuses of 1.13 and their names since 1.14.
+const STATUS_RENAMES: [(&str, &str); 9] = [
+    ("base", "surface"),
+    ("carved", "carvers"),
+    ("liquid_carved", "liquid_carvers"),
+    ("decorated", "features"),
+    ("lighted", "light"),
+    ("mobs_spawned", "spawn"),
+    ("finalized", "heightmaps"),
+    ("fullchunk", "full"),
+    ("postprocessed", "full"),
+];
+
+// Chunk statuses of 1.17 from the generation of the terrain on.
+const STATUSES_AFTER_NOISE: [&str; 9] = [
+    "noise",
+    "surface",
+    "carvers",
+    "liquid_carvers",
+    "features",
+    "light",
+    "spawn",
+    "heightmaps",
+    "full",
+];
+
+/// Numeric block IDs of sections to a palette of block states, at 1.13.
+pub(super) fn flatten(chunk: &mut Value) -> Result<()> {
+    let level = match level_mut(chunk) {
+        Some(level) => level,
+        None => return Ok(()),
+    };
+    if let Some(Value::List(sections)) = level.get_mut("Sections") {
+        let mut states = HashMap::new();
+        for section in sections {
+            if let Value::Compound(section) = section {
+                flatten_section(section, &mut states)?;
+            }
+        }
+    }
+    if let Some(Value::ByteArray(biomes)) = level.get("Biomes") {
+        let biomes = biomes.iter().map(|b| i32::from(*b as u8)).collect();
+        level.insert("Biomes".to_owned(), Value::IntArray(biomes));
+    }
+    // replaced by `Status` and `Heightmaps`
+    level.remove("LightPopulated");
+    level.remove("HeightMap");
+    if !level.contains_key("Status") {
+        let populated = matches!(level.remove("TerrainPopulated"), Some(Value::Byte(1)));
+        let status = if populated { "postprocessed" } else { "empty" };
+        level.insert("Status".to_owned(), Value::String(status.to_owned()));
+    }
+    Ok(())
+}
+
+fn flatten_section(
+    section: &mut Map<String, Value>,
+    states: &mut HashMap<(u16, u8), BlockState>,
+) -> Result<()> {
+    let blocks = match section.remove("Blocks") {
+        Some(Value::ByteArray(blocks)) if blocks.len() == 4096 => blocks,
+        Some(_) => return Err(Error::CorruptChunk("Blocks is not 4096 bytes")),
+        None => return Ok(()),
+    };
+    let add = nibbles(section.remove("Add"))?;
+    let data = nibbles(section.remove("Data"))?;
+    let mut container = PalettedContainer::blocks(BlockState::air());
+    for (i, block) in blocks.iter().enumerate() {
+        let id = u16::from(*block as u8) | u16::from(nibble(&add, i)) << 8;
+        let key = (id, nibble(&data, i));
+        let state = states.entry(key).or_insert_with(|| {
+            legacy_ids::block_state(key.0, key.1)
+                .or_else(|| legacy_ids::block_state(key.0, 0))
+                .unwrap_or_else(BlockState::air)
+        });
+        container.set(i & 15, i >> 8, (i >> 4) & 15, state.clone());
+    }
+    let (palette, data) = container.to_packed(Packing::Spanning);
+    // sections before 1.18 always have their indices, even if all zero
+    let data = data.unwrap_or_else(|| palette::pack(&[0; 4096], 4, Packing::Spanning));
+    section.insert("Palette".to_owned(), coruscant_nbt::to_value(palette)?);
+    section.insert("BlockStates".to_owned(), Value::LongArray(data));
+    Ok(())
+}
+
+fn nibbles(value: Option<Value>) -> Result<Option<Vec<i8>>> {
+    match value {
+        Some(Value::ByteArray(nibbles)) if nibbles.len() == 2048 => Ok(Some(nibbles)),
+        Some(_) => Err(Error::CorruptChunk("nibble array is not 2048 bytes")),
+        None => Ok(None),
+    }
+}
+
+fn nibble(nibbles: &Option<Vec<i8>>, index: usize) -> u8 {
+    match nibbles {
+        Some(nibbles) => (nibbles[index / 2] as u8 >> (index % 2 * 4)) & 0xF,
+        None => 0,
+    }
+}
+
+/// Chunk statuses of 1.13 to those of 1.14.
+pub(super) fn rename_statuses(chunk: &mut Value) -> Result<()> {
+    if let Some(level) = level_mut(chunk) {
+        if let Some(Value::String(status)) = level.get_mut("Status") {
+            if let Some((_, new)) = STATUS_RENAMES.iter().find(|(old, _)| old == status) {
+                *status = (*new).to_owned();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Block states and heightmaps packed so that indices do not span longs, at
+/// 1.16.
+pub(super) fn align_packing(chunk: &mut Value) -> Result<()> {
+    let level = match level_mut(chunk) {
+        Some(level) => level,
+        None => return Ok(()),
+    };
+    if let Some(Value::List(sections)) = level.get_mut("Sections") {
+        for section in sections {
+            let section = match section {
+                Value::Compound(section) => section,
+                _ => continue,
+            };
+            let bits = match section.get("Palette") {
+                Some(Value::List(palette)) => palette::bits_for(palette.len(), 4),
+                _ => continue,
+            };
+            if let Some(Value::LongArray(data)) = section.get_mut("BlockStates") {
+                *data = realign(data, bits, 4096)?;
+            }
+        }
+    }
+    if let Some(Value::Compound(heightmaps)) = level.get_mut("Heightmaps") {
+        for (_, heightmap) in heightmaps.iter_mut() {
+            if let Value::LongArray(data) = heightmap {
+                *data = realign(data, 9, 256)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn realign(data: &[i64], bits: u32, len: usize) -> Result<Vec<i64>> {
+    let indices = palette::unpack(data, bits, len, Packing::Spanning)?;
+    Ok(palette::pack(&indices, bits, Packing::Aligned))
+}
+
+/// Chunk data moved out of the `Level` compound, with block states and
+/// biomes in sections, at 1.18.
+///
+/// Chunks of the overworld are extended with air down to Y -64 and up to
+/// Y 320. The generated chunks of a noise generator are marked for the game
+/// to generate the terrain below Y 0 and blend it with the old terrain.
+///
+/// Heightmaps are emptied and light marked as not calculated, for the game
+/// to compute them again.
+pub(super) fn remove_level(chunk: &mut Value) -> Result<()> {
+    let root = match chunk {
+        Value::Compound(root) if root.contains_key("Level") => root,
+        _ => return Ok(()),
+    };
+    let (overworld, noise) = context(root)?;
+    let mut level = match root.remove("Level") {
+        Some(Value::Compound(level)) => level,
+        _ => return Err(Error::CorruptChunk("Level is not a compound")),
+    };
+    let biomes = match level.remove("Biomes") {
+        Some(Value::IntArray(biomes)) => Some(biomes),
+        _ => None,
+    };
+    // 1.17 snapshots with the heights of 1.18 saved biomes for 24 sections
+    let extended = overworld && matches!(&biomes, Some(biomes) if biomes.len() == 1536);
+    let shift = overworld && !extended;
+    let (min_y, max_y) = if overworld { (-4, 20) } else { (0, 16) };
+    let mut old_sections = BTreeMap::new();
+    if let Some(Value::List(sections)) = level.remove("Sections") {
+        for section in sections {
+            if let Value::Compound(section) = section {
+                let y = match section.get("Y") {
+                    Some(Value::Byte(y)) => i32::from(*y),
+                    _ => return Err(Error::CorruptChunk("section without Y")),
+                };
+                old_sections.insert(y, section);
+            }
+        }
+    }
+    let mut bottom = None;
+    let mut sections = Vec::new();
+    for y in min_y..max_y {
+        let mut section = old_sections.remove(&y).unwrap_or_else(|| {
+            let mut section = Map::new();
+            section.insert("Y".to_owned(), Value::Byte(y as i8));
+            section
+        });
+        let blocks = upgrade_section(&mut section)?;
+        if y == 0 {
+            bottom = blocks;
+        }
+        let biome_y = if extended { y - min_y } else { y };
+        let biomes = (biomes.as_deref())
+            .and_then(|biomes| section_biomes(biomes, biome_y))
+            .unwrap_or_else(|| PalettedContainer::biomes(biome_name(1).to_owned()));
+        let biomes = coruscant_nbt::to_value(biomes.to_biomes())?;
+        section.insert("biomes".to_owned(), biomes);
+        sections.push(Value::Compound(section));
+    }
+    // sections outside the heights of the dimension only hold light
+    sections.extend(old_sections.into_values().map(Value::Compound));
+    level.insert("sections".to_owned(), Value::List(sections));
+    if shift {
+        for key in &["PostProcessing", "ToBeTicked", "LiquidsToBeTicked"] {
+            pad_sections(&mut level, key);
+        }
+        shift_upgrade_data(&mut level);
+    }
+    if let Some(Value::Compound(masks)) = level.get_mut("CarvingMasks") {
+        for (_, mask) in masks.iter_mut() {
+            if let Value::ByteArray(bytes) = mask {
+                *mask = Value::LongArray(carving_mask(bytes, shift));
+            }
+        }
+    }
+    for (old, new) in &LEVEL_RENAMES {
+        rename_field(&mut level, old, new);
+    }
+    match level.get_mut("structures") {
+        Some(Value::Compound(structures)) => {
+            rename_field(structures, "Starts", "starts");
+        }
+        _ => {
+            let mut structures = Map::new();
+            structures.insert("References".to_owned(), Value::Compound(Map::new()));
+            structures.insert("starts".to_owned(), Value::Compound(Map::new()));
+            level.insert("structures".to_owned(), Value::Compound(structures));
+        }
+    }
+    let status = match level.get("Status") {
+        Some(Value::String(status)) => status.clone(),
+        _ => "empty".to_owned(),
+    };
+    if shift && noise && status != "empty" {
+        let old_noise = STATUSES_AFTER_NOISE.contains(&status.as_str());
+        let mut blending = Map::new();
+        blending.insert("old_noise".to_owned(), Value::Byte(old_noise as i8));
+        level.insert("blending_data".to_owned(), Value::Compound(blending));
+        if let Some(retrogen) = bottom.and_then(|bottom| below_zero_retrogen(&bottom, &status)) {
+            level.insert("below_zero_retrogen".to_owned(), retrogen);
+            level.insert("Status".to_owned(), Value::String("empty".to_owned()));
+        }
+    }
+    level.insert("Heightmaps".to_owned(), Value::Compound(Map::new()));
+    level.insert("isLightOn".to_owned(), Value::Byte(0));
+    level.insert("yPos".to_owned(), Value::Int(min_y));
+    for (key, value) in level {
+        root.insert(key, value);
+    }
+    Ok(())
+}
+
+// Whether a chunk is in the overworld and made by a noise generator, from
+// the context added by `Upgrader::upgrade_chunk`.
+fn context(root: &Map<String, Value>) -> Result<(bool, bool)> {
+    let context = match root.get(CONTEXT) {
+        Some(Value::Compound(context)) => context,
+        _ => {
+            return Err(Error::UnsupportedUpgrade(
+                DATA_VERSION_NO_LEVEL,
+                "chunk without the context of its dimension",
+            ))
+        }
+    };
+    let is = |key, expected| matches!(context.get(key), Some(Value::String(s)) if s == expected);
+    Ok((
+        is("dimension", "minecraft:overworld"),
+        is("generator", "minecraft:noise"),
+    ))
+}
+
+// Move the palette of a section into `block_states`, filling sections
+// without one with air, and return its blocks if it had a palette.
+fn upgrade_section(
+    section: &mut Map<String, Value>,
+) -> Result<Option<PalettedContainer<BlockState>>> {
+    let data = match section.remove("BlockStates") {
+        Some(Value::LongArray(data)) => Some(data),
+        _ => None,
+    };
+    let blocks = match section.remove("Palette") {
+        Some(palette) => {
+            let palette: Vec<BlockState> = coruscant_nbt::from_value(palette)?;
+            let blocks =
+                PalettedContainer::from_packed(16, 4, palette, data.as_deref(), Packing::Aligned)?;
+            Some(blocks)
+        }
+        None => None,
+    };
+    let block_states = match &blocks {
+        Some(blocks) => blocks.to_block_states(),
+        None => PalettedContainer::blocks(BlockState::air()).to_block_states(),
+    };
+    let block_states = coruscant_nbt::to_value(block_states)?;
+    section.insert("block_states".to_owned(), block_states);
+    Ok(blocks)
+}
+
+// The columns of a chunk missing the bedrock floor at Y 0, for the game to
+// generate the terrain below, unless the chunk has no bedrock at all.
+fn below_zero_retrogen(bottom: &PalettedContainer<BlockState>, status: &str) -> Option<Value> {
+    // bedrock is placed after the noise status
+    let mut has_bedrock = status == "noise";
+    let mut missing = [0_i64; 4];
+    for z in 0..16 {
+        for x in 0..16 {
+            match bottom.get(x, 0, z).map(|b| b.name.as_str()) {
+                Some("minecraft:air") => missing[z / 4] |= 1 << (z % 4 * 16 + x),
+                Some("minecraft:bedrock") => has_bedrock = true,
+                _ => {}
+            }
+        }
+    }
+    if !has_bedrock || missing.iter().all(|&bits| bits == -1) {
+        return None;
+    }
+    // a bit set without its trailing zero longs, as written by the game
+    let len = missing
+        .iter()
+        .rposition(|&bits| bits != 0)
+        .map_or(0, |i| i + 1);
+    let target = if status == "full" {
+        "heightmaps"
+    } else {
+        status
+    };
+    let mut retrogen = Map::new();
+    retrogen.insert("target_status".to_owned(), Value::String(target.to_owned()));
+    let missing = Value::LongArray(missing[..len].to_vec());
+    retrogen.insert("missing_bedrock".to_owned(), missing);
+    Some(Value::Compound(retrogen))
+}
+
+// Pad a list of 16 lists, one for each section, with empty lists for the
+// 4 sections below and above.
+fn pad_sections(level: &mut Map<String, Value>, key: &str) {
+    if let Some(Value::List(lists)) = level.get_mut(key) {
+        let mut padded = vec![Value::List(Vec::new()); 4];
+        padded.append(lists);
+        padded.resize(24, Value::List(Vec::new()));
+        *lists = padded;
+    }
+}
+
+// Shift the section indices of the blocks to upgrade by 4 sections.
+fn shift_upgrade_data(level: &mut Map<String, Value>) {
+    let indices = match level.get_mut("UpgradeData") {
+        Some(Value::Compound(upgrade)) => match upgrade.get_mut("Indices") {
+            Some(Value::Compound(indices)) => indices,
+            _ => return,
+        },
+        _ => return,
+    };
+    for (key, value) in mem::replace(indices, Map::new()) {
+        let key = match key.parse::<i32>() {
+            Ok(index) => (index + 4).to_string(),
+            Err(_) => key,
+        };
+        indices.insert(key, value);
+    }
+}
+
+// A carving mask of 1.17, a byte array with a bit for each block, to a long
+// array, with 4 sections added below when `shift`.
+fn carving_mask(bytes: &[i8], shift: bool) -> Vec<i64> {
+    let mut longs: Vec<i64> = (bytes.chunks(8))
+        .map(|bytes| {
+            (bytes.iter().enumerate()).fold(0, |long, (i, byte)| {
+                long | i64::from(*byte as u8) << (i * 8)
+            })
+        })
+        .collect();
+    while longs.last() == Some(&0) {
+        longs.pop();
+    }
+    if shift && !longs.is_empty() {
+        longs.splice(0..0, vec![0; 4 * 4096 / 64]);
+    }
+    longs
+}
+
+// Biomes of a section from the biome IDs of a chunk: 256, one for each
+// column, before 1.15, and 4×4×4 cells from the bottom since.
+fn section_biomes(biomes: &[i32], y: i32) -> Option<PalettedContainer<String>> {
+    let cells_y = match biomes.len() {
+        256 => None,
+        len if len > 0 && len % 16 == 0 => Some(len / 16),
+        _ => return None,
+    };
+    let mut container = PalettedContainer::biomes(biome_name(1).to_owned());
+    for cell_y in 0..4 {
+        for z in 0..4 {
+            for x in 0..4 {
+                let id = match cells_y {
+                    Some(cells_y) => {
+                        let chunk_y = (y * 4 + cell_y as i32).clamp(0, cells_y as i32 - 1);
+                        biomes[chunk_y as usize * 16 + z * 4 + x]
+                    }
+                    None => biomes[z * 4 * 16 + x * 4],
+                };
+                container.set(x, cell_y, z, biome_name(id).to_owned());
+            }
+        }
+    }
+    Some(container)
+}
+
+// Name since 1.18 of a numeric biome ID of 1.17; plains for unknown IDs.
+fn biome_name(id: i32) -> &'static str {
+    match id {
+        0 => "minecraft:ocean",
+        2 | 17 | 130 => "minecraft:desert",
+        3 | 20 => "minecraft:windswept_hills",
+        4 | 18 => "minecraft:forest",
+        5 | 19 | 133 => "minecraft:taiga",
+        6 | 134 => "minecraft:swamp",
+        7 => "minecraft:river",
+        8 => "minecraft:nether_wastes",
+        9 => "minecraft:the_end",
+        10 => "minecraft:frozen_ocean",
+        11 => "minecraft:frozen_river",
+        12 | 13 => "minecraft:snowy_plains",
+        14 | 15 => "minecraft:mushroom_fields",
+        16 => "minecraft:beach",
+        21 | 22 | 149 => "minecraft:jungle",
+        23 | 151 => "minecraft:sparse_jungle",
+        24 => "minecraft:deep_ocean",
+        25 => "minecraft:stony_shore",
+        26 => "minecraft:snowy_beach",
+        27 | 28 => "minecraft:birch_forest",
+        29 | 157 => "minecraft:dark_forest",
+        30 | 31 | 158 => "minecraft:snowy_taiga",
+        32 | 33 => "minecraft:old_growth_pine_taiga",
+        34 => "minecraft:windswept_forest",
+        35 => "minecraft:savanna",
+        36 => "minecraft:savanna_plateau",
+        37 | 39 | 167 => "minecraft:badlands",
+        38 | 166 => "minecraft:wooded_badlands",
+        40 => "minecraft:small_end_islands",
+        41 => "minecraft:end_midlands",
+        42 => "minecraft:end_highlands",
+        43 => "minecraft:end_barrens",
+        44 | 47 => "minecraft:warm_ocean",
+        45 => "minecraft:lukewarm_ocean",
+        46 => "minecraft:cold_ocean",
+        48 => "minecraft:deep_lukewarm_ocean",
+        49 => "minecraft:deep_cold_ocean",
+        50 => "minecraft:deep_frozen_ocean",
+        127 => "minecraft:the_void",
+        129 => "minecraft:sunflower_plains",
+        131 | 162 => "minecraft:windswept_gravelly_hills",
+        132 => "minecraft:flower_forest",
+        140 => "minecraft:ice_spikes",
+        155 | 156 => "minecraft:old_growth_birch_forest",
+        160 | 161 => "minecraft:old_growth_spruce_taiga",
+        163 | 164 => "minecraft:windswept_savanna",
+        165 => "minecraft:eroded_badlands",
+        168 | 169 => "minecraft:bamboo_jungle",
+        170 => "minecraft:soul_sand_valley",
+        171 => "minecraft:crimson_forest",
+        172 => "minecraft:warped_forest",
+        173 => "minecraft:basalt_deltas",
+        174 => "minecraft:dripstone_caves",
+        175 => "minecraft:lush_caves",
+        _ => "minecraft:plains",
+    }
+}
+
+fn level_mut(chunk: &mut Value) -> Option<&mut Map<String, Value>> {
+    match chunk {
+        Value::Compound(root) => match root.get_mut("Level") {
+            Some(Value::Compound(level)) => Some(level),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,57 @@
+//! Parts of the Flattening (1.13) that are not implemented, so that data
+//! needing them is not marked as upgraded.
+use crate::chunk::DATA_VERSION_FLATTENING;
+use crate::error::{Error, Result};
+use coruscant_nbt::Value;
+
+// Block entities whose data became part of the block state, or that were
+// removed, by the Flattening.
+const MERGED_BLOCK_ENTITIES: [&str; 6] = [
+    "minecraft:banner",
+    "minecraft:bed",
+    "minecraft:flower_pot",
+    "minecraft:noteblock",
+    "minecraft:piston",
+    "minecraft:skull",
+];
+
+// Fields of entities holding a block by its ID before the Flattening.
+const ENTITY_BLOCKS: [&str; 4] = ["Block", "TileID", "carried", "DisplayTile"];
+
+/// Item IDs with a damage value, like `minecraft:wool` with its color,
+/// became separate item IDs.
+pub(super) fn item_stack(item: &mut Value) -> Result<()> {
+    match item {
+        Value::Compound(item) if item.contains_key("id") => {
+            Err(unsupported("item IDs are not flattened"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Some block entities were merged into the block state.
+pub(super) fn block_entity(block_entity: &mut Value) -> Result<()> {
+    match block_entity {
+        Value::Compound(block_entity) => match block_entity.get("id") {
+            Some(Value::String(id)) if MERGED_BLOCK_ENTITIES.contains(&id.as_str()) => {
+                Err(unsupported("block entity is not merged into its block"))
+            }
+            _ => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Blocks held by entities, like falling blocks, became block states.
+pub(super) fn entity(entity: &mut Value) -> Result<()> {
+    match entity {
+        Value::Compound(entity) if ENTITY_BLOCKS.iter().any(|k| entity.contains_key(*k)) => {
+            Err(unsupported("block of entity is not flattened"))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn unsupported(msg: &'static str) -> Error {
+    Error::UnsupportedUpgrade(DATA_VERSION_FLATTENING, msg)
+}
@@ -0,0 +1,228 @@
+//! Fixes of entity and block entity IDs, namespaced in 1.11.
+use crate::error::Result;
+use coruscant_nbt::{Map, Value};
+
+type Split = fn(&mut Value) -> Result<()>;
+
+/// Entities split by variant in the 1.11 snapshots, with the data version
+/// of each split.
+pub(super) const SPLITS: [(i32, Split); 4] = [
+    (700, elder_guardian),
+    (701, skeleton),
+    (702, zombie),
+    (703, horse),
+];
+
+// Block entity IDs before 1.11 and since.
+const BLOCK_ENTITY_IDS: [(&str, &str); 23] = [
+    ("Airportal", "minecraft:end_portal"),
+    ("Banner", "minecraft:banner"),
+    ("Beacon", "minecraft:beacon"),
+    ("Cauldron", "minecraft:brewing_stand"),
+    ("Chest", "minecraft:chest"),
+    ("Comparator", "minecraft:comparator"),
+    ("Control", "minecraft:command_block"),
+    ("DLDetector", "minecraft:daylight_detector"),
+    ("Dropper", "minecraft:dropper"),
+    ("EnchantTable", "minecraft:enchanting_table"),
+    ("EndGateway", "minecraft:end_gateway"),
+    ("EnderChest", "minecraft:ender_chest"),
+    ("FlowerPot", "minecraft:flower_pot"),
+    ("Furnace", "minecraft:furnace"),
+    ("Hopper", "minecraft:hopper"),
+    ("MobSpawner", "minecraft:mob_spawner"),
+    ("Music", "minecraft:noteblock"),
+    ("Piston", "minecraft:piston"),
+    ("RecordPlayer", "minecraft:jukebox"),
+    ("Sign", "minecraft:sign"),
+    ("Skull", "minecraft:skull"),
+    ("Structure", "minecraft:structure_block"),
+    ("Trap", "minecraft:dispenser"),
+];
+
+// Entity IDs before 1.11 and since.
+const ENTITY_IDS: [(&str, &str); 80] = [
+    ("AreaEffectCloud", "minecraft:area_effect_cloud"),
+    ("ArmorStand", "minecraft:armor_stand"),
+    ("Arrow", "minecraft:arrow"),
+    ("Bat", "minecraft:bat"),
+    ("Blaze", "minecraft:blaze"),
+    ("Boat", "minecraft:boat"),
+    ("CaveSpider", "minecraft:cave_spider"),
+    ("Chicken", "minecraft:chicken"),
+    ("Cow", "minecraft:cow"),
+    ("Creeper", "minecraft:creeper"),
+    ("Donkey", "minecraft:donkey"),
+    ("DragonFireball", "minecraft:dragon_fireball"),
+    ("ElderGuardian", "minecraft:elder_guardian"),
+    ("EnderCrystal", "minecraft:ender_crystal"),
+    ("EnderDragon", "minecraft:ender_dragon"),
+    ("Enderman", "minecraft:enderman"),
+    ("Endermite", "minecraft:endermite"),
+    ("EntityHorse", "minecraft:horse"),
+    ("EvocationFangs", "minecraft:evocation_fangs"),
+    ("EvocationIllager", "minecraft:evocation_illager"),
+    ("EyeOfEnderSignal", "minecraft:eye_of_ender_signal"),
+    ("FallingSand", "minecraft:falling_block"),
+    ("Fireball", "minecraft:fireball"),
+    ("FireworksRocketEntity", "minecraft:fireworks_rocket"),
+    ("Ghast", "minecraft:ghast"),
+    ("Giant", "minecraft:giant"),
+    ("Guardian", "minecraft:guardian"),
+    ("Husk", "minecraft:husk"),
+    ("Item", "minecraft:item"),
+    ("ItemFrame", "minecraft:item_frame"),
+    ("LavaSlime", "minecraft:magma_cube"),
+    ("LeashKnot", "minecraft:leash_knot"),
+    ("Llama", "minecraft:llama"),
+    ("LlamaSpit", "minecraft:llama_spit"),
+    ("MinecartChest", "minecraft:chest_minecart"),
+    ("MinecartCommandBlock", "minecraft:commandblock_minecart"),
+    ("MinecartFurnace", "minecraft:furnace_minecart"),
+    ("MinecartHopper", "minecraft:hopper_minecart"),
+    ("MinecartRideable", "minecraft:minecart"),
+    ("MinecartSpawner", "minecraft:spawner_minecart"),
+    ("MinecartTNT", "minecraft:tnt_minecart"),
+    ("Mule", "minecraft:mule"),
+    ("MushroomCow", "minecraft:mooshroom"),
+    ("Ozelot", "minecraft:ocelot"),
+    ("Painting", "minecraft:painting"),
+    ("Pig", "minecraft:pig"),
+    ("PigZombie", "minecraft:zombie_pigman"),
+    ("PolarBear", "minecraft:polar_bear"),
+    ("PrimedTnt", "minecraft:tnt"),
+    ("Rabbit", "minecraft:rabbit"),
+    ("Sheep", "minecraft:sheep"),
+    ("Shulker", "minecraft:shulker"),
+    ("ShulkerBullet", "minecraft:shulker_bullet"),
+    ("Silverfish", "minecraft:silverfish"),
+    ("Skeleton", "minecraft:skeleton"),
+    ("SkeletonHorse", "minecraft:skeleton_horse"),
+    ("Slime", "minecraft:slime"),
+    ("SmallFireball", "minecraft:small_fireball"),
+    ("SnowMan", "minecraft:snowman"),
+    ("Snowball", "minecraft:snowball"),
+    ("SpectralArrow", "minecraft:spectral_arrow"),
+    ("Spider", "minecraft:spider"),
+    ("Squid", "minecraft:squid"),
+    ("Stray", "minecraft:stray"),
+    ("ThrownEgg", "minecraft:egg"),
+    ("ThrownEnderpearl", "minecraft:ender_pearl"),
+    ("ThrownExpBottle", "minecraft:xp_bottle"),
+    ("ThrownPotion", "minecraft:potion"),
+    ("Villager", "minecraft:villager"),
+    ("VillagerGolem", "minecraft:villager_golem"),
+    ("VindicationIllager", "minecraft:vindication_illager"),
+    ("Witch", "minecraft:witch"),
+    ("WitherBoss", "minecraft:wither"),
+    ("WitherSkeleton", "minecraft:wither_skeleton"),
+    ("WitherSkull", "minecraft:wither_skull"),
+    ("Wolf", "minecraft:wolf"),
+    ("XPOrb", "minecraft:xp_orb"),
+    ("Zombie", "minecraft:zombie"),
+    ("ZombieHorse", "minecraft:zombie_horse"),
+    ("ZombieVillager", "minecraft:zombie_villager"),
+];
+
+/// Block entity IDs namespaced, at 1.11.
+pub(super) fn block_entity(block_entity: &mut Value) -> Result<()> {
+    rename_id(block_entity, &BLOCK_ENTITY_IDS);
+    Ok(())
+}
+
+/// Entity IDs namespaced, at 1.11.
+pub(super) fn entity(entity: &mut Value) -> Result<()> {
+    rename_id(entity, &ENTITY_IDS);
+    Ok(())
+}
+
+fn rename_id(value: &mut Value, ids: &[(&str, &str)]) {
+    if let Value::Compound(compound) = value {
+        if let Some(Value::String(id)) = compound.get_mut("id") {
+            if let Some((_, new)) = ids.iter().find(|(old, _)| old == id) {
+                *id = (*new).to_owned();
+            }
+        }
+    }
+}
+
+// Guardians with `Elder` became elder guardians.
+fn elder_guardian(entity: &mut Value) -> Result<()> {
+    if let Some(entity) = with_id(entity, "Guardian") {
+        if let Some(Value::Byte(1)) = entity.remove("Elder") {
+            set_id(entity, "ElderGuardian");
+        }
+    }
+    Ok(())
+}
+
+// Skeletons with a `SkeletonType` became wither skeletons and strays.
+fn skeleton(entity: &mut Value) -> Result<()> {
+    if let Some(entity) = with_id(entity, "Skeleton") {
+        match entity.remove("SkeletonType").and_then(|v| as_int(&v)) {
+            Some(1) => set_id(entity, "WitherSkeleton"),
+            Some(2) => set_id(entity, "Stray"),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Zombies with a `ZombieType`, or before 1.10 `IsVillager`, became zombie
+// villagers with their profession and husks.
+fn zombie(entity: &mut Value) -> Result<()> {
+    let entity = match with_id(entity, "Zombie") {
+        Some(entity) => entity,
+        None => return Ok(()),
+    };
+    let profession = entity.remove("VillagerProfession").and_then(|v| as_int(&v));
+    let zombie_type = match (entity.remove("ZombieType"), entity.remove("IsVillager")) {
+        (Some(zombie_type), _) => as_int(&zombie_type).unwrap_or(0),
+        (None, Some(Value::Byte(1))) => profession.unwrap_or(0).clamp(0, 4) + 1,
+        (None, _) => 0,
+    };
+    match zombie_type {
+        1..=5 => {
+            set_id(entity, "ZombieVillager");
+            entity.insert("Profession".to_owned(), Value::Int(zombie_type - 1));
+        }
+        6 => set_id(entity, "Husk"),
+        _ => {}
+    }
+    Ok(())
+}
+
+// Horses with a `Type` became donkeys, mules and undead horses.
+fn horse(entity: &mut Value) -> Result<()> {
+    if let Some(entity) = with_id(entity, "EntityHorse") {
+        match entity.remove("Type").and_then(|v| as_int(&v)) {
+            Some(1) => set_id(entity, "Donkey"),
+            Some(2) => set_id(entity, "Mule"),
+            Some(3) => set_id(entity, "ZombieHorse"),
+            Some(4) => set_id(entity, "SkeletonHorse"),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn with_id<'a>(entity: &'a mut Value, id: &str) -> Option<&'a mut Map<String, Value>> {
+    match entity {
+        Value::Compound(entity) if entity.get("id") == Some(&Value::String(id.to_owned())) => {
+            Some(entity)
+        }
+        _ => None,
+    }
+}
+
+fn set_id(entity: &mut Map<String, Value>, id: &str) {
+    entity.insert("id".to_owned(), Value::String(id.to_owned()));
+}
+
+fn as_int(value: &Value) -> Option<i32> {
+    match value {
+        Value::Byte(v) => Some(i32::from(*v)),
+        Value::Int(v) => Some(*v),
+        _ => None,
+    }
+}
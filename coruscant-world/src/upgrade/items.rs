@@ -0,0 +1,467 @@
+//! Fixes of item stacks.
+use super::rename_field;
+use crate::error::{Error, Result};
+use crate::item::DATA_VERSION_ITEM_COMPONENTS;
+use coruscant_nbt::{Map, Value};
+use std::convert::TryFrom;
+
+// Fields of `tag` that became components not implemented here. Other
+// fields unknown to the game are kept in `minecraft:custom_data`, like the
+// game does.
+const UNTRANSLATED: [&str; 16] = [
+    "BlockStateTag",
+    "BucketVariantTag",
+    "CanDestroy",
+    "CanPlaceOn",
+    "DebugProperty",
+    "Decorations",
+    "LodestoneDimension",
+    "LodestonePos",
+    "LodestoneTracked",
+    "Recipes",
+    "Trim",
+    "effects",
+    "instrument",
+    "map",
+    "map_scale_direction",
+    "map_to_lock",
+];
+
+// Bits of `HideFlags` and the components whose tooltip they hide.
+const HIDE_FLAGS: [(i32, &str); 5] = [
+    (1, "minecraft:enchantments"),
+    (2, "minecraft:attribute_modifiers"),
+    (4, "minecraft:unbreakable"),
+    (32, "minecraft:stored_enchantments"),
+    (64, "minecraft:dyed_color"),
+];
+
+// Firework explosion shapes by their numeric `Type`.
+const SHAPES: [&str; 5] = ["small_ball", "large_ball", "star", "creeper", "burst"];
+
+// Numeric effect IDs before 1.20.2, from 1.
+const EFFECTS: [&str; 33] = [
+    "speed",
+    "slowness",
+    "haste",
+    "mining_fatigue",
+    "strength",
+    "instant_health",
+    "instant_damage",
+    "jump_boost",
+    "nausea",
+    "regeneration",
+    "resistance",
+    "fire_resistance",
+    "water_breathing",
+    "invisibility",
+    "blindness",
+    "night_vision",
+    "hunger",
+    "weakness",
+    "poison",
+    "wither",
+    "health_boost",
+    "absorption",
+    "saturation",
+    "glowing",
+    "levitation",
+    "luck",
+    "unluck",
+    "slow_falling",
+    "conduit_power",
+    "dolphins_grace",
+    "bad_omen",
+    "hero_of_the_village",
+    "darkness",
+];
+
+/// The `tag` of item stacks split into components, at 1.20.5.
+///
+/// The fields of `tag` become their components; fields unknown to the game
+/// are kept in `minecraft:custom_data`. Items with fields whose component
+/// is not implemented are refused.
+pub(super) fn to_components(item: &mut Value) -> Result<()> {
+    let item = match item {
+        Value::Compound(item) if !item.contains_key("components") => item,
+        _ => return Ok(()),
+    };
+    if let Some(Value::Compound(tag)) = item.get("tag") {
+        if UNTRANSLATED.iter().any(|key| tag.contains_key(*key)) {
+            return Err(Error::UnsupportedUpgrade(
+                DATA_VERSION_ITEM_COMPONENTS,
+                "item tag field has no implemented component",
+            ));
+        }
+    }
+    if let Some(count) = item.remove("Count") {
+        let count = match count {
+            Value::Byte(count) => i32::from(count),
+            Value::Int(count) => count,
+            _ => 1,
+        };
+        item.insert("count".to_owned(), Value::Int(count));
+    }
+    let mut tag = match item.remove("tag") {
+        Some(Value::Compound(tag)) => tag,
+        _ => return Ok(()),
+    };
+    let mut components = Map::new();
+    for (key, component) in &[
+        ("Damage", "minecraft:damage"),
+        ("RepairCost", "minecraft:repair_cost"),
+    ] {
+        if let Some(value) = tag.remove(*key).and_then(|v| as_int(&v)) {
+            if value != 0 {
+                components.insert((*component).to_owned(), Value::Int(value));
+            }
+        }
+    }
+    if let Some(value) = tag.remove("CustomModelData").and_then(|v| as_int(&v)) {
+        components.insert("minecraft:custom_model_data".to_owned(), Value::Int(value));
+    }
+    if let Some(Value::Byte(1)) = tag.remove("Unbreakable") {
+        let unbreakable = Value::Compound(Map::new());
+        components.insert("minecraft:unbreakable".to_owned(), unbreakable);
+    }
+    if let Some(Value::Compound(mut display)) = tag.remove("display") {
+        if let Some(name @ Value::String(_)) = display.remove("Name") {
+            components.insert("minecraft:custom_name".to_owned(), name);
+        }
+        if let Some(lore @ Value::List(_)) = display.remove("Lore") {
+            components.insert("minecraft:lore".to_owned(), lore);
+        }
+        if let Some(rgb) = display.remove("color").and_then(|v| as_int(&v)) {
+            let mut color = Map::new();
+            color.insert("rgb".to_owned(), Value::Int(rgb));
+            components.insert("minecraft:dyed_color".to_owned(), Value::Compound(color));
+        }
+        if let Some(color) = display.remove("MapColor").and_then(|v| as_int(&v)) {
+            components.insert("minecraft:map_color".to_owned(), Value::Int(color));
+        }
+        if !display.is_empty() {
+            tag.insert("display".to_owned(), Value::Compound(display));
+        }
+    }
+    for (key, component) in &[
+        ("Enchantments", "minecraft:enchantments"),
+        ("StoredEnchantments", "minecraft:stored_enchantments"),
+    ] {
+        if let Some(Value::List(list)) = tag.remove(*key) {
+            components.insert((*component).to_owned(), enchantments(list));
+        }
+    }
+    if let Some(owner) = tag.remove("SkullOwner") {
+        components.insert("minecraft:profile".to_owned(), profile(owner));
+    }
+    if let Some(Value::Compound(mut block_entity)) = tag.remove("BlockEntityTag") {
+        if !block_entity.contains_key("id") {
+            if let Some(Value::String(id)) = item.get("id") {
+                let id = Value::String(block_entity_id(id));
+                block_entity.insert("id".to_owned(), id);
+            }
+        }
+        let block_entity = Value::Compound(block_entity);
+        components.insert("minecraft:block_entity_data".to_owned(), block_entity);
+    }
+    if let Some(entity @ Value::Compound(_)) = tag.remove("EntityTag") {
+        components.insert("minecraft:entity_data".to_owned(), entity);
+    }
+    if let Some(modifiers) = tag.remove("AttributeModifiers") {
+        let modifiers = attribute_modifiers(modifiers);
+        components.insert("minecraft:attribute_modifiers".to_owned(), modifiers);
+    }
+    if let Some(potion) = potion_contents(&mut tag) {
+        components.insert("minecraft:potion_contents".to_owned(), potion);
+    }
+    let written =
+        matches!(item.get("id"), Some(Value::String(id)) if id == "minecraft:written_book");
+    if let Some(book) = book_content(&mut tag, written) {
+        let component = if written {
+            "minecraft:written_book_content"
+        } else {
+            "minecraft:writable_book_content"
+        };
+        components.insert(component.to_owned(), book);
+    }
+    if let Some(Value::Compound(mut fireworks)) = tag.remove("Fireworks") {
+        let mut component = Map::new();
+        if let Some(flight) = fireworks.remove("Flight").and_then(|v| as_int(&v)) {
+            component.insert("flight_duration".to_owned(), Value::Byte(flight as i8));
+        }
+        if let Some(Value::List(explosions)) = fireworks.remove("Explosions") {
+            let explosions = explosions.into_iter().map(explosion).collect();
+            component.insert("explosions".to_owned(), Value::List(explosions));
+        }
+        components.insert("minecraft:fireworks".to_owned(), Value::Compound(component));
+    }
+    if let Some(star) = tag.remove("Explosion") {
+        components.insert("minecraft:firework_explosion".to_owned(), explosion(star));
+    }
+    if let Some(items @ Value::List(_)) = tag.remove("Items") {
+        components.insert("minecraft:bundle_contents".to_owned(), items);
+    }
+    tag.remove("Charged");
+    if let Some(items @ Value::List(_)) = tag.remove("ChargedProjectiles") {
+        components.insert("minecraft:charged_projectiles".to_owned(), items);
+    }
+    if let Some(flags) = tag.remove("HideFlags").and_then(|v| as_int(&v)) {
+        hide_tooltips(&mut components, flags);
+    }
+    if !tag.is_empty() {
+        components.insert("minecraft:custom_data".to_owned(), Value::Compound(tag));
+    }
+    if !components.is_empty() {
+        item.insert("components".to_owned(), Value::Compound(components));
+    }
+    Ok(())
+}
+
+// Hide the tooltips of components by the bits of `HideFlags`.
+fn hide_tooltips(components: &mut Map<String, Value>, flags: i32) {
+    for (bit, key) in &HIDE_FLAGS {
+        if flags & bit == 0 {
+            continue;
+        }
+        if key.ends_with("enchantments") && !components.contains_key(*key) {
+            components.insert((*key).to_owned(), enchantments(Vec::new()));
+        }
+        if let Some(Value::Compound(component)) = components.get_mut(*key) {
+            component.insert("show_in_tooltip".to_owned(), Value::Byte(0));
+        }
+    }
+    if flags & 32 != 0 {
+        let hidden = Value::Compound(Map::new());
+        components.insert("minecraft:hide_additional_tooltip".to_owned(), hidden);
+    }
+}
+
+// `AttributeModifiers` to a compound with the list of modifiers.
+fn attribute_modifiers(modifiers: Value) -> Value {
+    let modifiers = match modifiers {
+        Value::List(modifiers) => modifiers,
+        _ => Vec::new(),
+    };
+    let modifiers = (modifiers.into_iter())
+        .filter_map(|modifier| match modifier {
+            Value::Compound(mut modifier) => {
+                rename_field(&mut modifier, "AttributeName", "type");
+                rename_field(&mut modifier, "Name", "name");
+                rename_field(&mut modifier, "Amount", "amount");
+                rename_field(&mut modifier, "UUID", "uuid");
+                let slot = match modifier.remove("Slot") {
+                    Some(slot @ Value::String(_)) => slot,
+                    _ => Value::String("any".to_owned()),
+                };
+                modifier.insert("slot".to_owned(), slot);
+                let operation = match modifier.remove("Operation").and_then(|v| as_int(&v)) {
+                    Some(1) => "add_multiplied_base",
+                    Some(2) => "add_multiplied_total",
+                    _ => "add_value",
+                };
+                let operation = Value::String(operation.to_owned());
+                modifier.insert("operation".to_owned(), operation);
+                Some(Value::Compound(modifier))
+            }
+            _ => None,
+        })
+        .collect();
+    let mut component = Map::new();
+    component.insert("modifiers".to_owned(), Value::List(modifiers));
+    Value::Compound(component)
+}
+
+// `Potion`, `CustomPotionColor` and the custom effects to potion contents.
+fn potion_contents(tag: &mut Map<String, Value>) -> Option<Value> {
+    let mut contents = Map::new();
+    match tag.remove("Potion") {
+        Some(Value::String(potion)) if potion != "minecraft:empty" => {
+            contents.insert("potion".to_owned(), Value::String(potion));
+        }
+        _ => {}
+    }
+    if let Some(color) = tag.remove("CustomPotionColor").and_then(|v| as_int(&v)) {
+        contents.insert("custom_color".to_owned(), Value::Int(color));
+    }
+    let effects = match (
+        tag.remove("custom_potion_effects"),
+        tag.remove("CustomPotionEffects"),
+    ) {
+        (Some(Value::List(effects)), _) => Some(effects),
+        (None, Some(Value::List(effects))) => {
+            Some(effects.into_iter().map(legacy_effect).collect())
+        }
+        _ => None,
+    };
+    if let Some(effects) = effects {
+        contents.insert("custom_effects".to_owned(), Value::List(effects));
+    }
+    if contents.is_empty() {
+        None
+    } else {
+        Some(Value::Compound(contents))
+    }
+}
+
+// An effect saved before 1.20.2, with a numeric ID and capitalized fields,
+// to the layout since.
+fn legacy_effect(value: Value) -> Value {
+    let mut effect = match value {
+        Value::Compound(effect) => effect,
+        other => return other,
+    };
+    let id = effect.get("Id").and_then(as_int);
+    let name = id.and_then(|id| EFFECTS.get(usize::try_from(id - 1).ok()?));
+    if let Some(name) = name {
+        effect.remove("Id");
+        let id = Value::String(format!("minecraft:{}", name));
+        effect.insert("id".to_owned(), id);
+    }
+    for (old, new) in &[
+        ("Amplifier", "amplifier"),
+        ("Duration", "duration"),
+        ("Ambient", "ambient"),
+        ("ShowParticles", "show_particles"),
+        ("ShowIcon", "show_icon"),
+        ("FactorCalculationData", "factor_calculation_data"),
+    ] {
+        rename_field(&mut effect, old, new);
+    }
+    if let Some(hidden) = effect.remove("HiddenEffect") {
+        effect.insert("hidden_effect".to_owned(), legacy_effect(hidden));
+    }
+    Value::Compound(effect)
+}
+
+// The pages and, for written books, the title, author and generation of a
+// book to its content.
+fn book_content(tag: &mut Map<String, Value>, written: bool) -> Option<Value> {
+    let pages = match tag.remove("pages") {
+        Some(Value::List(pages)) => pages,
+        _ if written && tag.contains_key("title") => Vec::new(),
+        _ => return None,
+    };
+    let filtered = match tag.remove("filtered_pages") {
+        Some(Value::Compound(filtered)) => filtered,
+        _ => Map::new(),
+    };
+    let pages = (pages.into_iter().enumerate())
+        .map(|(i, page)| filterable(page, filtered.get(&i.to_string()).cloned()))
+        .collect();
+    let mut content = Map::new();
+    content.insert("pages".to_owned(), Value::List(pages));
+    if written {
+        if let Some(title) = tag.remove("title") {
+            let filtered = tag.remove("filtered_title");
+            content.insert("title".to_owned(), filterable(title, filtered));
+        }
+        for key in &["author", "generation", "resolved"] {
+            if let Some(value) = tag.remove(*key) {
+                content.insert((*key).to_owned(), value);
+            }
+        }
+    }
+    Some(Value::Compound(content))
+}
+
+// A text with its filtered version, if any.
+fn filterable(raw: Value, filtered: Option<Value>) -> Value {
+    let mut text = Map::new();
+    text.insert("raw".to_owned(), raw);
+    if let Some(filtered) = filtered {
+        text.insert("filtered".to_owned(), filtered);
+    }
+    Value::Compound(text)
+}
+
+// A firework explosion with a numeric `Type` to one with a `shape`.
+fn explosion(explosion: Value) -> Value {
+    let mut explosion = match explosion {
+        Value::Compound(explosion) => explosion,
+        other => return other,
+    };
+    let shape = explosion
+        .remove("Type")
+        .and_then(|v| as_int(&v))
+        .unwrap_or(0);
+    let shape = SHAPES.get(shape as usize).unwrap_or(&SHAPES[0]);
+    explosion.insert("shape".to_owned(), Value::String((*shape).to_owned()));
+    rename_field(&mut explosion, "Colors", "colors");
+    rename_field(&mut explosion, "FadeColors", "fade_colors");
+    rename_field(&mut explosion, "Trail", "has_trail");
+    rename_field(&mut explosion, "Flicker", "has_twinkle");
+    Value::Compound(explosion)
+}
+
+// A list of `id` and `lvl` compounds to a compound of levels by ID.
+fn enchantments(list: Vec<Value>) -> Value {
+    let mut levels = Map::new();
+    for enchantment in list {
+        if let Value::Compound(enchantment) = enchantment {
+            let level = enchantment.get("lvl").and_then(as_int).unwrap_or(0);
+            if let (Some(Value::String(id)), 1..=255) = (enchantment.get("id"), level) {
+                levels.insert(id.clone(), Value::Int(level));
+            }
+        }
+    }
+    let mut ans = Map::new();
+    ans.insert("levels".to_owned(), Value::Compound(levels));
+    Value::Compound(ans)
+}
+
+// `SkullOwner`, a name or a compound of `Name`, `Id` and `Properties`, to a
+// profile.
+fn profile(owner: Value) -> Value {
+    let mut owner = match owner {
+        Value::String(name) => {
+            let mut profile = Map::new();
+            profile.insert("name".to_owned(), Value::String(name));
+            return Value::Compound(profile);
+        }
+        Value::Compound(owner) => owner,
+        other => return other,
+    };
+    rename_field(&mut owner, "Name", "name");
+    rename_field(&mut owner, "Id", "id");
+    if let Some(Value::Compound(properties)) = owner.remove("Properties") {
+        let mut list = Vec::new();
+        for (name, values) in properties {
+            let values = match values {
+                Value::List(values) => values,
+                _ => continue,
+            };
+            for value in values {
+                if let Value::Compound(mut value) = value {
+                    rename_field(&mut value, "Value", "value");
+                    rename_field(&mut value, "Signature", "signature");
+                    value.insert("name".to_owned(), Value::String(name.clone()));
+                    list.push(Value::Compound(value));
+                }
+            }
+        }
+        owner.insert("properties".to_owned(), Value::List(list));
+    }
+    Value::Compound(owner)
+}
+
+// ID of the block entity of a block item, for the items whose block
+// entity is named differently.
+fn block_entity_id(item_id: &str) -> String {
+    let name = item_id.strip_prefix("minecraft:").unwrap_or(item_id);
+    let shared = ["shulker_box", "bed", "banner", "hanging_sign", "sign"];
+    let name = match shared.iter().find(|shared| name.ends_with(*shared)) {
+        Some(shared) => shared,
+        None if name.ends_with("_head") || name.ends_with("_skull") => "skull",
+        None => name,
+    };
+    format!("minecraft:{}", name)
+}
+
+fn as_int(value: &Value) -> Option<i32> {
+    match value {
+        Value::Byte(v) => Some(i32::from(*v)),
+        Value::Short(v) => Some(i32::from(*v)),
+        Value::Int(v) => Some(*v),
+        _ => None,
+    }
+}
@@ -0,0 +1,172 @@
+//! Fixes of UUIDs, stored as int arrays since 1.16 instead of two longs or a
+//! string.
+use crate::error::Result;
+use crate::uuid::Uuid;
+use coruscant_nbt::{Map, Value};
+
+/// UUIDs of entities, their owners, leashes and attribute modifiers.
+pub(super) fn entity(entity: &mut Value) -> Result<()> {
+    let entity = match entity {
+        Value::Compound(entity) => entity,
+        _ => return Ok(()),
+    };
+    halves_to_array(entity, "UUIDMost", "UUIDLeast", "UUID");
+    string_to_array(entity, "OwnerUUID", "Owner");
+    string_to_array(entity, "AngryAt", "AngryAt");
+    for key in &["Owner", "Thrower", "LoveCause"] {
+        compound_to_array(entity, key);
+    }
+    if let Some(Value::Compound(leash)) = entity.get_mut("Leash") {
+        halves_to_array(leash, "UUIDMost", "UUIDLeast", "UUID");
+    }
+    if let Some(Value::List(attributes)) = entity.get_mut("Attributes") {
+        for attribute in attributes {
+            if let Value::Compound(attribute) = attribute {
+                modifiers(attribute, "Modifiers");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The owners of skulls, now `SkullOwner`, and the targets of conduits.
+pub(super) fn block_entity(block_entity: &mut Value) -> Result<()> {
+    let block_entity = match block_entity {
+        Value::Compound(block_entity) => block_entity,
+        _ => return Ok(()),
+    };
+    let skull = Value::String("minecraft:skull".to_owned());
+    if block_entity.get("id") == Some(&skull) {
+        if let Some(mut owner) = block_entity.remove("Owner") {
+            if let Value::Compound(profile) = &mut owner {
+                string_to_array(profile, "Id", "Id");
+            }
+            block_entity.insert("SkullOwner".to_owned(), owner);
+        }
+    }
+    if let Some(Value::Compound(target)) = block_entity.remove("target_uuid") {
+        if let Some(uuid) = from_halves(&target, "M", "L") {
+            block_entity.insert("Target".to_owned(), uuid);
+        }
+    }
+    Ok(())
+}
+
+/// The wandering trader, the ender dragon and the players of boss bars in
+/// `level.dat`.
+pub(super) fn level(level: &mut Value) -> Result<()> {
+    let data = match level {
+        Value::Compound(root) => match root.get_mut("Data") {
+            Some(Value::Compound(data)) => data,
+            _ => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+    string_to_array(data, "WanderingTraderId", "WanderingTraderId");
+    // The dragon fight is in `DimensionData` until 1.16.
+    if let Some(Value::Compound(fight)) = data.get_mut("DragonFight") {
+        halves_to_array(fight, "DragonUUIDMost", "DragonUUIDLeast", "Dragon");
+    }
+    if let Some(Value::Compound(dimensions)) = data.get_mut("DimensionData") {
+        for (_, dimension) in dimensions.iter_mut() {
+            if let Value::Compound(dimension) = dimension {
+                if let Some(Value::Compound(fight)) = dimension.get_mut("DragonFight") {
+                    halves_to_array(fight, "DragonUUIDMost", "DragonUUIDLeast", "Dragon");
+                }
+            }
+        }
+    }
+    if let Some(Value::Compound(events)) = data.get_mut("CustomBossEvents") {
+        for (_, event) in events.iter_mut() {
+            if let Value::Compound(event) = event {
+                if let Some(Value::List(players)) = event.get_mut("Players") {
+                    for player in players {
+                        let uuid = match player {
+                            Value::Compound(halves) => from_halves(halves, "M", "L"),
+                            _ => None,
+                        };
+                        if let Some(uuid) = uuid {
+                            *player = uuid;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The owners of player heads and UUIDs of attribute modifiers in items.
+pub(super) fn item_stack(item: &mut Value) -> Result<()> {
+    let tag = match item {
+        Value::Compound(item) => match item.get_mut("tag") {
+            Some(Value::Compound(tag)) => tag,
+            _ => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+    if let Some(Value::Compound(owner)) = tag.get_mut("SkullOwner") {
+        string_to_array(owner, "Id", "Id");
+    }
+    modifiers(tag, "AttributeModifiers");
+    Ok(())
+}
+
+fn modifiers(compound: &mut Map<String, Value>, key: &str) {
+    if let Some(Value::List(modifiers)) = compound.get_mut(key) {
+        for modifier in modifiers {
+            if let Value::Compound(modifier) = modifier {
+                halves_to_array(modifier, "UUIDMost", "UUIDLeast", "UUID");
+            }
+        }
+    }
+}
+
+// Two long fields to one int array field.
+fn halves_to_array(compound: &mut Map<String, Value>, most: &str, least: &str, to: &str) {
+    if let Some(uuid) = from_halves(compound, most, least) {
+        compound.remove(most);
+        compound.remove(least);
+        compound.insert(to.to_owned(), uuid);
+    }
+}
+
+// A compound of `M` and `L` longs to an int array in place.
+fn compound_to_array(compound: &mut Map<String, Value>, key: &str) {
+    let uuid = match compound.get(key) {
+        Some(Value::Compound(halves)) => from_halves(halves, "M", "L"),
+        _ => None,
+    };
+    if let Some(uuid) = uuid {
+        compound.insert(key.to_owned(), uuid);
+    }
+}
+
+// A UUID string field to an int array field; empty strings are removed.
+fn string_to_array(compound: &mut Map<String, Value>, from: &str, to: &str) {
+    let uuid = match compound.get(from) {
+        Some(Value::String(uuid)) if uuid.is_empty() => None,
+        Some(Value::String(uuid)) => match uuid.parse::<Uuid>() {
+            Ok(uuid) => Some(int_array(uuid)),
+            Err(_) => return,
+        },
+        _ => return,
+    };
+    compound.remove(from);
+    if let Some(uuid) = uuid {
+        compound.insert(to.to_owned(), uuid);
+    }
+}
+
+fn from_halves(compound: &Map<String, Value>, most: &str, least: &str) -> Option<Value> {
+    match (compound.get(most), compound.get(least)) {
+        (Some(Value::Long(most)), Some(Value::Long(least))) => {
+            Some(int_array(Uuid::from_most_least(*most, *least)))
+        }
+        _ => None,
+    }
+}
+
+fn int_array(uuid: Uuid) -> Value {
+    Value::IntArray(uuid.to_ints().to_vec())
+}
@@ -0,0 +1,332 @@
+//! Universally unique identifiers of entities and players.
+//!
+//! Minecraft has stored UUIDs in several ways over the versions:
+//!
+//! - since 1.16, an int array of four ints, most significant first, usually
+//!   named `UUID`;
+//! - before 1.16, two longs named like `UUIDMost` and `UUIDLeast`, or a
+//!   compound of `M` and `L`;
+//! - in some places, a hyphenated string like
+//!   `f81d4fae-7dec-11d0-a765-00a0c91e6bf6`.
+//!
+//! `Uuid` is written as an int array and reads any of these. The modules in
+//! this module write the other encodings, for use with `#[serde(with)]`; each
+//! of them also reads any encoding.
+//!
+//! Ref: https://minecraft.gamepedia.com/Universally_unique_identifier
+use core::fmt;
+use core::str::FromStr;
+use serde::de::{self, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+/// A universally unique identifier.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::Uuid;
+///
+/// let uuid: Uuid = "f81d4fae-7dec-11d0-a765-00a0c91e6bf6".parse().unwrap();
+/// assert_eq!(uuid.to_ints(), [-132296786, 2112623056, -1486552928, -920753162]);
+/// assert_eq!(Uuid::from_most_least(uuid.most(), uuid.least()), uuid);
+/// assert_eq!(uuid.to_string(), "f81d4fae-7dec-11d0-a765-00a0c91e6bf6");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uuid(u128);
+
+impl Uuid {
+    /// The UUID with the given 128 bits.
+    pub const fn from_u128(value: u128) -> Uuid {
+        Uuid(value)
+    }
+
+    /// The 128 bits of the UUID.
+    pub const fn as_u128(self) -> u128 {
+        self.0
+    }
+
+    /// The UUID with the given most and least significant halves.
+    pub const fn from_most_least(most: i64, least: i64) -> Uuid {
+        Uuid(((most as u64 as u128) << 64) | least as u64 as u128)
+    }
+
+    /// The most significant 64 bits.
+    pub const fn most(self) -> i64 {
+        (self.0 >> 64) as i64
+    }
+
+    /// The least significant 64 bits.
+    pub const fn least(self) -> i64 {
+        self.0 as i64
+    }
+
+    /// The UUID with the given four ints, most significant first.
+    pub const fn from_ints(ints: [i32; 4]) -> Uuid {
+        Uuid(
+            (ints[0] as u32 as u128) << 96
+                | (ints[1] as u32 as u128) << 64
+                | (ints[2] as u32 as u128) << 32
+                | ints[3] as u32 as u128,
+        )
+    }
+
+    /// The four ints of the UUID, most significant first.
+    pub const fn to_ints(self) -> [i32; 4] {
+        [
+            (self.0 >> 96) as i32,
+            (self.0 >> 64) as i32,
+            (self.0 >> 32) as i32,
+            self.0 as i32,
+        ]
+    }
+}
+
+impl fmt::Display for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let v = self.0;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            v >> 96,
+            (v >> 80) & 0xffff,
+            (v >> 64) & 0xffff,
+            (v >> 48) & 0xffff,
+            v & 0xffff_ffff_ffff
+        )
+    }
+}
+
+/// An error parsing a string into a `Uuid`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseUuidError(());
+
+impl fmt::Display for ParseUuidError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid UUID string")
+    }
+}
+
+impl std::error::Error for ParseUuidError {}
+
+impl FromStr for Uuid {
+    type Err = ParseUuidError;
+
+    /// Parse a hyphenated UUID, or 32 hexadecimal digits without hyphens.
+    fn from_str(s: &str) -> Result<Uuid, ParseUuidError> {
+        let groups: Vec<&str> = s.split('-').collect();
+        let valid = match groups.len() {
+            1 => groups[0].len() == 32,
+            5 => groups
+                .iter()
+                .map(|g| g.len())
+                .eq([8, 4, 4, 4, 12].iter().copied()),
+            _ => false,
+        };
+        if !valid
+            || !groups
+                .iter()
+                .all(|g| g.bytes().all(|b| b.is_ascii_hexdigit()))
+        {
+            return Err(ParseUuidError(()));
+        }
+        u128::from_str_radix(&groups.concat(), 16)
+            .map(Uuid)
+            .map_err(|_| ParseUuidError(()))
+    }
+}
+
+impl Serialize for Uuid {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        int_array::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Uuid {
+    fn deserialize<D>(deserializer: D) -> Result<Uuid, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(UuidVisitor)
+    }
+}
+
+struct UuidVisitor;
+
+impl<'de> Visitor<'de> for UuidVisitor {
+    type Value = Uuid;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a UUID as four ints, a string, or most and least significant longs")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Uuid, E> {
+        value
+            .parse()
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Uuid, A::Error> {
+        let mut ints = [0; 4];
+        for (i, int) in ints.iter_mut().enumerate() {
+            *int = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        if seq.next_element::<IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(5, &self));
+        }
+        Ok(Uuid::from_ints(ints))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Uuid, A::Error> {
+        let (mut most, mut least) = (None, None);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "M" | "Most" | "UUIDMost" => most = Some(map.next_value()?),
+                "L" | "Least" | "UUIDLeast" => least = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        let most = most.ok_or_else(|| de::Error::missing_field("M"))?;
+        let least = least.ok_or_else(|| de::Error::missing_field("L"))?;
+        Ok(Uuid::from_most_least(most, least))
+    }
+}
+
+/// Write a UUID as an int array of four ints, as since 1.16.
+pub mod int_array {
+    use super::Uuid;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(uuid: &Uuid, serializer: S) -> Result<S::Ok, S::Error> {
+        coruscant_nbt::as_nbt_array(&&uuid.to_ints()[..], serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Uuid, D::Error> {
+        Uuid::deserialize(deserializer)
+    }
+}
+
+/// Write a UUID as a hyphenated string.
+pub mod string {
+    use super::Uuid;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(uuid: &Uuid, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(uuid)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Uuid, D::Error> {
+        Uuid::deserialize(deserializer)
+    }
+}
+
+/// Write a UUID as a compound of `M` and `L` longs, as in the player lists of
+/// boss bars before 1.16.
+pub mod compound {
+    use super::Uuid;
+    use serde::ser::{SerializeMap, Serializer};
+    use serde::{Deserialize, Deserializer};
+
+    pub fn serialize<S: Serializer>(uuid: &Uuid, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("M", &uuid.most())?;
+        map.serialize_entry("L", &uuid.least())?;
+        map.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Uuid, D::Error> {
+        Uuid::deserialize(deserializer)
+    }
+}
+
+/// Write a UUID as the two longs `UUIDMost` and `UUIDLeast` of the containing
+/// compound, as in entities before 1.16. Use with `#[serde(flatten)]`.
+///
+/// Reads either the two longs or an int array named `UUID`.
+///
+/// # Example
+///
+/// ```
+/// use coruscant_world::Uuid;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Entity {
+///     #[serde(flatten, with = "coruscant_world::uuid::most_least")]
+///     uuid: Uuid,
+/// }
+///
+/// let entity = Entity { uuid: Uuid::from_most_least(1, 2) };
+/// let snbt = coruscant_nbt::to_string_snbt(&entity).unwrap();
+/// assert_eq!(snbt, "{UUIDMost:1L,UUIDLeast:2L}");
+///
+/// let modern: Entity = coruscant_nbt::from_str_snbt("{UUID:[I;0,1,0,2]}").unwrap();
+/// assert_eq!(modern.uuid, entity.uuid);
+/// ```
+pub mod most_least {
+    use super::Uuid;
+    use serde::ser::{SerializeMap, Serializer};
+    use serde::{de, Deserializer};
+
+    const FIELDS: &[&str] = &["UUIDMost", "UUIDLeast", "UUID"];
+
+    pub fn serialize<S: Serializer>(uuid: &Uuid, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry(FIELDS[0], &uuid.most())?;
+        map.serialize_entry(FIELDS[1], &uuid.least())?;
+        map.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Uuid, D::Error> {
+        super::deserialize_halves(FIELDS, deserializer)?
+            .ok_or_else(|| de::Error::missing_field(FIELDS[0]))
+    }
+}
+
+// Read a UUID from two longs named by `fields[0]` and `fields[1]`, or from
+// any encoding named by `fields[2]`; `None` if neither is present.
+pub(crate) fn deserialize_halves<'de, D: Deserializer<'de>>(
+    fields: &'static [&'static str],
+    deserializer: D,
+) -> Result<Option<Uuid>, D::Error> {
+    struct HalvesVisitor(&'static [&'static str]);
+
+    impl<'de> Visitor<'de> for HalvesVisitor {
+        type Value = Option<Uuid>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{} and {}, or {}", self.0[0], self.0[1], self.0[2])
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Option<Uuid>, A::Error> {
+            let (mut most, mut least, mut whole) = (None, None, None);
+            while let Some(key) = map.next_key::<String>()? {
+                if key == self.0[0] {
+                    most = Some(map.next_value()?);
+                } else if key == self.0[1] {
+                    least = Some(map.next_value()?);
+                } else if key == self.0[2] {
+                    whole = Some(map.next_value()?);
+                } else {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+            match (most, least, whole) {
+                (_, _, Some(uuid)) => Ok(Some(uuid)),
+                (Some(most), Some(least), None) => Ok(Some(Uuid::from_most_least(most, least))),
+                (None, None, None) => Ok(None),
+                (None, _, None) => Err(de::Error::missing_field(self.0[0])),
+                (_, None, None) => Err(de::Error::missing_field(self.0[1])),
+            }
+        }
+    }
+
+    deserializer.deserialize_struct("Uuid", fields, HalvesVisitor(fields))
+}
@@ -0,0 +1,870 @@
+//! A world folder and its dimensions.
+//!
+//! A world is laid out as follows, each dimension with its own `region/`,
+//! `entities/` and `poi/` folders of region files:
+//!
+//! - `level.dat`, global information about the world;
+//! - `session.lock`, locked by the program that has the world open;
+//! - `playerdata/<uuid>.dat`, the data of each player;
+//! - `data/`, saved data like maps, raids and the scoreboard;
+//! - the overworld at the root of the world folder;
+//! - the Nether in `DIM-1/` and the End in `DIM1/`;
+//! - datapack dimensions in `dimensions/<namespace>/<name>/`.
+//!
+//! Ref: https://minecraft.gamepedia.com/Java_Edition_level_format
+use crate::block_entity::BlockEntity;
+use crate::chunk::BlockState;
+use crate::entity::EntityChunk;
+use crate::error::{Error, Result};
+use crate::level_dat::LevelDat;
+use crate::map_data::MapData;
+use crate::player::Player;
+use crate::poi::{fits_block, OrphanedPoi, PoiChunk};
+use crate::prune::{self, PruneOptions, PruneStats};
+use crate::region::{self, RegionFile};
+use crate::saved_data::SavedData;
+use crate::upgrade::DATA_VERSION_UNVERSIONED;
+use crate::uuid::Uuid;
+use coruscant_nbt::Value;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+mod loaded;
+
+use loaded::LoadedChunk;
+
+/// Name of the overworld.
+pub const OVERWORLD: &str = "minecraft:overworld";
+/// Name of the Nether.
+pub const THE_NETHER: &str = "minecraft:the_nether";
+/// Name of the End.
+pub const THE_END: &str = "minecraft:the_end";
+
+/// Data version of 21w37a, the first snapshot where the overworld reaches
+/// from Y -64 to 319 instead of 0 to 255.
+pub const DATA_VERSION_EXTENDED_HEIGHT: i32 = 2834;
+
+/// Position of a chunk, in chunk coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChunkPos {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkPos {
+    pub fn new(x: i32, z: i32) -> ChunkPos {
+        ChunkPos { x, z }
+    }
+
+    /// The chunk containing the given block coordinates.
+    pub fn from_block(block_x: i32, block_z: i32) -> ChunkPos {
+        ChunkPos::new(block_x >> 4, block_z >> 4)
+    }
+
+    /// Coordinates of the region containing this chunk.
+    pub fn region(self) -> (i32, i32) {
+        (self.x >> 5, self.z >> 5)
+    }
+}
+
+/// Position of a block, in block coordinates.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockPos {
+    #[serde(rename = "x")]
+    pub x: i32,
+    #[serde(rename = "y")]
+    pub y: i32,
+    #[serde(rename = "z")]
+    pub z: i32,
+}
+
+impl BlockPos {
+    pub fn new(x: i32, y: i32, z: i32) -> BlockPos {
+        BlockPos { x, y, z }
+    }
+
+    /// The chunk containing this block.
+    pub fn chunk(self) -> ChunkPos {
+        ChunkPos::from_block(self.x, self.z)
+    }
+}
+
+/// A dimension of a world and the folder it is stored in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dimension {
+    name: String,
+    path: PathBuf,
+    y_range: Option<Range<i32>>,
+}
+
+impl Dimension {
+    /// Resource location of the dimension, e.g. `minecraft:overworld`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Folder of the dimension.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Heights blocks can be at, from `min_y` of the dimension type up to
+    /// `min_y + height`, e.g. `-64..320` in the overworld since 1.18.
+    ///
+    /// `None` for dimension types of datapacks, which are not read.
+    pub fn y_range(&self) -> Option<Range<i32>> {
+        self.y_range.clone()
+    }
+
+    /// Folder of the region files holding terrain.
+    pub fn region_dir(&self) -> PathBuf {
+        self.path.join("region")
+    }
+
+    /// Folder of the saved data of the dimension, like raids.
+    pub fn data_dir(&self) -> PathBuf {
+        self.path.join("data")
+    }
+
+    /// Folder of the region files holding entities, since 1.17.
+    pub fn entities_dir(&self) -> PathBuf {
+        self.path.join("entities")
+    }
+
+    /// Folder of the region files holding points of interest, since 1.14.
+    pub fn poi_dir(&self) -> PathBuf {
+        self.path.join("poi")
+    }
+
+    /// Region coordinates and paths of the terrain region files.
+    pub fn region_files(&self) -> Result<Vec<((i32, i32), PathBuf)>> {
+        list_region_files(&self.region_dir())
+    }
+
+    /// Region coordinates and paths of the entity region files.
+    pub fn entity_files(&self) -> Result<Vec<((i32, i32), PathBuf)>> {
+        list_region_files(&self.entities_dir())
+    }
+
+    /// Region coordinates and paths of the point of interest region files.
+    pub fn poi_files(&self) -> Result<Vec<((i32, i32), PathBuf)>> {
+        list_region_files(&self.poi_dir())
+    }
+}
+
+/// An open world.
+///
+/// The world holds `session.lock` until it is dropped, so that the game and
+/// other tools do not write it at the same time. Chunks are read through
+/// region files that are kept open, and kept in memory once read. Changed
+/// chunks are only written back on `flush`.
+///
+/// Block access supports chunks since 1.13.
+///
+/// # Example
+///
+/// ```no_run
+/// use coruscant_world::{BlockPos, BlockState, ChunkPos, World};
+///
+/// let mut world = World::open("saves/New World").expect("open world");
+/// for dimension in world.dimensions() {
+///     println!("{} in {}", dimension.name(), dimension.path().display());
+/// }
+/// let chunk = world.chunk("minecraft:overworld", ChunkPos::new(0, 0)).unwrap();
+/// println!("{:?}", chunk.is_some());
+///
+/// let pos = BlockPos::new(0, 64, 0);
+/// let stairs = BlockState::new("minecraft:oak_stairs".to_string())
+///     .with_property("facing", "north");
+/// world.set_block("minecraft:overworld", pos, stairs).unwrap();
+/// world.flush().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct World {
+    path: PathBuf,
+    level_dat: LevelDat,
+    dimensions: Vec<Dimension>,
+    regions: HashMap<(usize, i32, i32), RegionFile>,
+    chunks: HashMap<(usize, ChunkPos), LoadedChunk>,
+    _session_lock: File,
+}
+
+impl World {
+    /// Open the world in the given folder.
+    ///
+    /// Fails with `Error::SessionLocked` if another program has the world
+    /// open.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<World> {
+        let path = path.as_ref().to_owned();
+        let session_lock = lock_session(&path)?;
+        let level_dat: LevelDat =
+            coruscant_nbt::from_gzip_reader(File::open(path.join("level.dat"))?)?;
+        let mut dimensions = discover_dimensions(&path)?;
+        for dimension in &mut dimensions {
+            dimension.y_range = dimension_y_range(&level_dat, &dimension.name);
+        }
+        Ok(World {
+            path,
+            level_dat,
+            dimensions,
+            regions: HashMap::new(),
+            chunks: HashMap::new(),
+            _session_lock: session_lock,
+        })
+    }
+
+    /// Folder of the world.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Contents of `level.dat`.
+    pub fn level_dat(&self) -> &LevelDat {
+        &self.level_dat
+    }
+
+    /// Dimensions found in the world folder, the overworld first.
+    pub fn dimensions(&self) -> &[Dimension] {
+        &self.dimensions
+    }
+
+    /// The dimension with the given name.
+    pub fn dimension(&self, name: &str) -> Option<&Dimension> {
+        self.dimensions.iter().find(|d| d.name == name)
+    }
+
+    /// The chunk at the given position, as NBT, or `None` if it is not
+    /// generated.
+    pub fn chunk(&mut self, dimension: &str, pos: ChunkPos) -> Result<Option<&Value>> {
+        let dimension = self.dimension_index(dimension)?;
+        match self.loaded_chunk(dimension, pos)? {
+            Some(chunk) => {
+                chunk.sync()?;
+                Ok(Some(chunk.nbt()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The block state at the given position, or `None` if its chunk is not
+    /// generated. Blocks in sections that are not stored are air.
+    ///
+    /// Fails with `Error::BlockOutOfWorld` if the position is outside the
+    /// heights of the dimension, see `Dimension::y_range`.
+    pub fn get_block(&mut self, dimension: &str, pos: BlockPos) -> Result<Option<BlockState>> {
+        let dimension = self.dimension_index(dimension)?;
+        match self.loaded_chunk(dimension, pos.chunk())? {
+            Some(chunk) => chunk.get_block(pos.x, pos.y, pos.z).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Set the block state at the given position, returning the old one.
+    /// If the state changes, the block entity at the position is removed.
+    ///
+    /// The heightmaps and light of the chunk are dropped when it is written,
+    /// for the game to compute them again. Sections created in chunks since
+    /// 1.18 get plains biomes. Fails with `Error::BlockOutOfWorld` if the
+    /// position is outside the heights of the dimension.
+    pub fn set_block(
+        &mut self,
+        dimension: &str,
+        pos: BlockPos,
+        state: BlockState,
+    ) -> Result<BlockState> {
+        let dimension = self.dimension_index(dimension)?;
+        match self.loaded_chunk(dimension, pos.chunk())? {
+            Some(chunk) => chunk.set_block(pos.x, pos.y, pos.z, state),
+            None => Err(Error::ChunkNotGenerated(pos.chunk())),
+        }
+    }
+
+    /// The block entity at the given position, or `None` if there is none or
+    /// its chunk is not generated.
+    pub fn block_entity(&mut self, dimension: &str, pos: BlockPos) -> Result<Option<BlockEntity>> {
+        let dimension = self.dimension_index(dimension)?;
+        match self.loaded_chunk(dimension, pos.chunk())? {
+            Some(chunk) => chunk.block_entity(pos.x, pos.y, pos.z),
+            None => Ok(None),
+        }
+    }
+
+    /// Set or remove the block entity at the given position, returning the
+    /// old one. The coordinates of the new block entity are set to the
+    /// position.
+    pub fn set_block_entity(
+        &mut self,
+        dimension: &str,
+        pos: BlockPos,
+        block_entity: Option<BlockEntity>,
+    ) -> Result<Option<BlockEntity>> {
+        let dimension = self.dimension_index(dimension)?;
+        match self.loaded_chunk(dimension, pos.chunk())? {
+            Some(chunk) => chunk.set_block_entity(pos.x, pos.y, pos.z, block_entity),
+            None => Err(Error::ChunkNotGenerated(pos.chunk())),
+        }
+    }
+
+    /// Write all changed chunks back into their region files.
+    pub fn flush(&mut self) -> Result<()> {
+        let mut dirty: Vec<_> = self
+            .chunks
+            .iter()
+            .filter(|(_, chunk)| chunk.is_dirty())
+            .map(|(key, _)| *key)
+            .collect();
+        dirty.sort();
+        for (dimension, pos) in dirty {
+            let chunk = self
+                .chunks
+                .get_mut(&(dimension, pos))
+                .expect("listed above");
+            chunk.sync()?;
+            let region = match self
+                .regions
+                .get_mut(&(dimension, pos.region().0, pos.region().1))
+            {
+                Some(region) => region,
+                None => unreachable!("chunks are loaded through their region"),
+            };
+            region.write_chunk(pos.x, pos.z, chunk.nbt())?;
+            chunk.mark_saved();
+        }
+        Ok(())
+    }
+
+    /// Forget all chunks read so far, and close the region files. Changes
+    /// that are not flushed are lost.
+    pub fn clear_cache(&mut self) {
+        self.chunks.clear();
+        self.regions.clear();
+    }
+
+    /// Read the entities of the chunk at the given position from `entities/`,
+    /// or `None` if the chunk has no entity data. Worlds before 1.17 store
+    /// entities in the chunk itself.
+    pub fn read_entities(&self, dimension: &str, pos: ChunkPos) -> Result<Option<EntityChunk>> {
+        let (x, z) = pos.region();
+        let path = self
+            .dimension_by_name(dimension)?
+            .entities_dir()
+            .join(region::region_file_name(x, z));
+        match RegionFile::open_read_only(path) {
+            Ok(region) => region.read_chunk(pos.x, pos.z),
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write the entities of a chunk into `entities/`, at the position stored
+    /// in the entity chunk.
+    pub fn write_entities(&self, dimension: &str, chunk: &EntityChunk) -> Result<()> {
+        let pos = chunk.position();
+        let (x, z) = pos.region();
+        let dir = self.dimension_by_name(dimension)?.entities_dir();
+        fs::create_dir_all(&dir)?;
+        let mut region = RegionFile::create(dir.join(region::region_file_name(x, z)))?;
+        region.write_chunk(pos.x, pos.z, chunk)
+    }
+
+    /// Read the points of interest of the chunk at the given position from
+    /// `poi/`, or `None` if the chunk has none stored.
+    pub fn read_poi(&self, dimension: &str, pos: ChunkPos) -> Result<Option<PoiChunk>> {
+        let (x, z) = pos.region();
+        let path = self
+            .dimension_by_name(dimension)?
+            .poi_dir()
+            .join(region::region_file_name(x, z));
+        match RegionFile::open_read_only(path) {
+            Ok(region) => region.read_chunk(pos.x, pos.z),
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write the points of interest of the chunk at the given position into
+    /// `poi/`.
+    pub fn write_poi(&self, dimension: &str, pos: ChunkPos, chunk: &PoiChunk) -> Result<()> {
+        let (x, z) = pos.region();
+        let dir = self.dimension_by_name(dimension)?.poi_dir();
+        fs::create_dir_all(&dir)?;
+        let mut region = RegionFile::create(dir.join(region::region_file_name(x, z)))?;
+        region.write_chunk(pos.x, pos.z, chunk)
+    }
+
+    /// Points of interest of a dimension whose block does not fit their
+    /// type, or whose chunk is not generated.
+    ///
+    /// Records of types that are not vanilla are not checked. The chunks
+    /// read to look at the blocks stay cached.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use coruscant_world::World;
+    ///
+    /// let mut world = World::open("saves/New World")?;
+    /// for orphan in world.check_poi("minecraft:overworld")? {
+    ///     let pos = orphan.record.block_pos();
+    ///     println!("{} at {:?}: {:?}", orphan.record.poi_type, pos, orphan.block);
+    /// }
+    /// # Ok::<(), coruscant_world::Error>(())
+    /// ```
+    pub fn check_poi(&mut self, dimension: &str) -> Result<Vec<OrphanedPoi>> {
+        self.orphaned_poi(dimension, false)
+    }
+
+    /// Remove the points of interest `check_poi` reports from `poi/`,
+    /// returning them.
+    pub fn purge_poi(&mut self, dimension: &str) -> Result<Vec<OrphanedPoi>> {
+        self.orphaned_poi(dimension, true)
+    }
+
+    /// Remove the chunks of the dimension that `options` select, along with
+    /// their entities and points of interest, and compact its region files.
+    /// Region files left without chunks are deleted.
+    ///
+    /// Changed chunks are flushed and the cache is cleared first.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use coruscant_world::prune::{ChunkArea, PruneOptions};
+    /// use coruscant_world::{ChunkPos, World};
+    ///
+    /// let mut world = World::open("saves/New World")?;
+    /// let mut options = PruneOptions::new();
+    /// options.min_inhabited_time = Some(20 * 60);
+    /// options.keep_areas.push(ChunkArea::around(ChunkPos::new(0, 0), 32));
+    /// let stats = world.prune("minecraft:overworld", &options)?;
+    /// println!("{} chunks removed", stats.chunks_removed);
+    /// # Ok::<(), coruscant_world::Error>(())
+    /// ```
+    pub fn prune(&mut self, dimension: &str, options: &PruneOptions) -> Result<PruneStats> {
+        self.flush()?;
+        self.clear_cache();
+        prune::prune_dimension(self.dimension_by_name(dimension)?, options)
+    }
+
+    /// Rewrite the region files of the dimension without free sectors, and
+    /// delete the empty ones.
+    pub fn compact(&mut self, dimension: &str) -> Result<PruneStats> {
+        self.prune(dimension, &PruneOptions::new())
+    }
+
+    /// UUIDs of the players with a file in `playerdata`, sorted.
+    pub fn player_uuids(&self) -> Result<Vec<Uuid>> {
+        let dir = self.path.join("playerdata");
+        let mut ans = Vec::new();
+        if !dir.is_dir() {
+            return Ok(ans);
+        }
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("dat") {
+                continue;
+            }
+            let uuid = path.file_stem().and_then(|s| s.to_str()?.parse().ok());
+            if let Some(uuid) = uuid {
+                ans.push(uuid);
+            }
+        }
+        ans.sort();
+        Ok(ans)
+    }
+
+    /// Read the data of the player with the given UUID from `playerdata`, or
+    /// `None` if the player has no file.
+    ///
+    /// The singleplayer player is also stored in `level.dat`, which the game
+    /// prefers over the player file.
+    pub fn read_player(&self, uuid: Uuid) -> Result<Option<Player>> {
+        match Player::read(self.player_path(uuid)) {
+            Ok(player) => Ok(Some(player)),
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write the data of the player with the given UUID into `playerdata`.
+    pub fn write_player(&self, uuid: Uuid, player: &Player) -> Result<()> {
+        fs::create_dir_all(self.path.join("playerdata"))?;
+        player.write(self.player_path(uuid))
+    }
+
+    /// Read the saved data file `data/<name>.dat` of a dimension, or `None`
+    /// if there is no such file.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use coruscant_world::scoreboard::Scoreboard;
+    /// use coruscant_world::World;
+    ///
+    /// let world = World::open("saves/New World")?;
+    /// if let Some(saved) = world.read_data::<Scoreboard>("minecraft:overworld", "scoreboard")? {
+    ///     for score in &saved.data.player_scores {
+    ///         println!("{} {}: {}", score.name, score.objective, score.score);
+    ///     }
+    /// }
+    /// # Ok::<(), coruscant_world::Error>(())
+    /// ```
+    pub fn read_data<T: DeserializeOwned>(
+        &self,
+        dimension: &str,
+        name: &str,
+    ) -> Result<Option<SavedData<T>>> {
+        let path = self.data_path(dimension, name)?;
+        match SavedData::read(path) {
+            Ok(data) => Ok(Some(data)),
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write the saved data file `data/<name>.dat` of a dimension.
+    pub fn write_data<T: Serialize>(
+        &self,
+        dimension: &str,
+        name: &str,
+        data: &SavedData<T>,
+    ) -> Result<()> {
+        let path = self.data_path(dimension, name)?;
+        fs::create_dir_all(self.dimension_by_name(dimension)?.data_dir())?;
+        data.write(path)
+    }
+
+    /// IDs of the maps with a file in `data`, sorted.
+    pub fn map_ids(&self) -> Result<Vec<i32>> {
+        let dir = self.path.join("data");
+        let mut ans = Vec::new();
+        if !dir.is_dir() {
+            return Ok(ans);
+        }
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("dat") {
+                continue;
+            }
+            let id = path.file_stem().and_then(|s| {
+                let id = s.to_str()?.strip_prefix("map_")?;
+                id.parse().ok()
+            });
+            if let Some(id) = id {
+                ans.push(id);
+            }
+        }
+        ans.sort_unstable();
+        Ok(ans)
+    }
+
+    /// Read the data of the map with the given ID from `data`, or `None` if
+    /// the map has no file.
+    pub fn read_map(&self, id: i32) -> Result<Option<SavedData<MapData>>> {
+        match SavedData::read(self.map_path(id)) {
+            Ok(map) => Ok(Some(map)),
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write the data of the map with the given ID into `data`.
+    pub fn write_map(&self, id: i32, map: &SavedData<MapData>) -> Result<()> {
+        fs::create_dir_all(self.path.join("data"))?;
+        map.write(self.map_path(id))
+    }
+
+    fn orphaned_poi(&mut self, dimension: &str, purge: bool) -> Result<Vec<OrphanedPoi>> {
+        let mut orphans = Vec::new();
+        for ((region_x, region_z), path) in self.dimension_by_name(dimension)?.poi_files()? {
+            let mut region = match purge {
+                true => RegionFile::open(path)?,
+                false => RegionFile::open_read_only(path)?,
+            };
+            let chunks: Vec<_> = region.chunks().collect();
+            for (x, z) in chunks {
+                let mut chunk: PoiChunk = match region.read_chunk(x, z)? {
+                    Some(chunk) => chunk,
+                    None => continue,
+                };
+                let mut found = Vec::new();
+                for record in chunk.records() {
+                    let block = self.get_block(dimension, record.block_pos())?;
+                    let fits = match &block {
+                        Some(block) => fits_block(&record.poi_type, block),
+                        None => Some(false),
+                    };
+                    if fits == Some(false) {
+                        found.push(OrphanedPoi {
+                            record: record.clone(),
+                            block,
+                        });
+                    }
+                }
+                if purge && !found.is_empty() {
+                    chunk.retain(|r| found.iter().all(|o| o.record.pos != r.pos));
+                    region.write_chunk(region_x * 32 + x, region_z * 32 + z, &chunk)?;
+                }
+                orphans.extend(found);
+            }
+        }
+        Ok(orphans)
+    }
+
+    fn data_path(&self, dimension: &str, name: &str) -> Result<PathBuf> {
+        let dir = self.dimension_by_name(dimension)?.data_dir();
+        Ok(dir.join(format!("{}.dat", name)))
+    }
+
+    fn map_path(&self, id: i32) -> PathBuf {
+        self.path.join("data").join(format!("map_{}.dat", id))
+    }
+
+    fn player_path(&self, uuid: Uuid) -> PathBuf {
+        self.path.join("playerdata").join(format!("{}.dat", uuid))
+    }
+
+    fn dimension_by_name(&self, name: &str) -> Result<&Dimension> {
+        self.dimension(name)
+            .ok_or_else(|| Error::UnknownDimension(name.to_owned()))
+    }
+
+    fn dimension_index(&self, name: &str) -> Result<usize> {
+        self.dimensions
+            .iter()
+            .position(|d| d.name == name)
+            .ok_or_else(|| Error::UnknownDimension(name.to_owned()))
+    }
+
+    // Read the chunk into the cache, if it exists and is not cached yet.
+    fn loaded_chunk(
+        &mut self,
+        dimension: usize,
+        pos: ChunkPos,
+    ) -> Result<Option<&mut LoadedChunk>> {
+        let key = (dimension, pos);
+        if !self.chunks.contains_key(&key) {
+            let region = match self.region(dimension, pos.region())? {
+                Some(region) => region,
+                None => return Ok(None),
+            };
+            match region.read_chunk(pos.x, pos.z)? {
+                Some(nbt) => {
+                    let y_range = self.dimensions[dimension].y_range();
+                    self.chunks.insert(key, LoadedChunk::new(nbt, y_range))
+                }
+                None => return Ok(None),
+            };
+        }
+        Ok(self.chunks.get_mut(&key))
+    }
+
+    fn region(&mut self, dimension: usize, (x, z): (i32, i32)) -> Result<Option<&mut RegionFile>> {
+        let key = (dimension, x, z);
+        if !self.regions.contains_key(&key) {
+            let path = self.dimensions[dimension]
+                .region_dir()
+                .join(region::region_file_name(x, z));
+            let region = match RegionFile::open(&path) {
+                Ok(region) => region,
+                // worlds on read-only mounts can still be read
+                Err(Error::Io(e)) if e.kind() == io::ErrorKind::PermissionDenied => {
+                    RegionFile::open_read_only(path)?
+                }
+                Err(Error::Io(e)) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e),
+            };
+            self.regions.insert(key, region);
+        }
+        Ok(self.regions.get_mut(&key))
+    }
+}
+
+// Vanilla writes a snowman into the lock file and locks it while the world
+// is open.
+fn lock_session(path: &Path) -> Result<File> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path.join("session.lock"))?;
+    if !try_lock(&file)? {
+        return Err(Error::SessionLocked);
+    }
+    file.set_len(0)?;
+    file.write_all("☃".as_bytes())?;
+    Ok(file)
+}
+
+// Java locks files with `fcntl` on Unix, which does not see `flock` locks
+// like the ones of `File::try_lock`. Open file description locks are used
+// where there are, as they conflict with those of Java and, unlike process
+// locks, with other locks of this process too.
+#[cfg(unix)]
+fn try_lock(file: &File) -> Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const SET_LOCK: libc::c_int = libc::F_OFD_SETLK;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    const SET_LOCK: libc::c_int = libc::F_SETLK;
+
+    // the whole file, like `FileChannel::tryLock`
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as _;
+    lock.l_whence = libc::SEEK_SET as _;
+    // SAFETY: the descriptor is open for the lifetime of `file`, and `lock`
+    // is a valid `flock` struct.
+    if unsafe { libc::fcntl(file.as_raw_fd(), SET_LOCK, &lock) } == 0 {
+        return Ok(true);
+    }
+    let e = io::Error::last_os_error();
+    match e.raw_os_error() {
+        Some(libc::EAGAIN) | Some(libc::EACCES) => Ok(false),
+        _ => Err(e.into()),
+    }
+}
+
+// Java locks files with `LockFileEx` on Windows, like `File::try_lock`.
+#[cfg(not(unix))]
+fn try_lock(file: &File) -> Result<bool> {
+    match file.try_lock() {
+        Ok(()) => Ok(true),
+        Err(fs::TryLockError::WouldBlock) => Ok(false),
+        Err(fs::TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+// The heights of a dimension from its type in the world generation settings,
+// either inline or the ID of a vanilla type. Worlds before 1.16 have no
+// settings, and only the vanilla dimensions with types of the same name.
+fn dimension_y_range(level_dat: &LevelDat, name: &str) -> Option<Range<i32>> {
+    let data = &level_dat.data;
+    let dimension_type = data
+        .world_gen_settings
+        .as_ref()
+        .and_then(|settings| settings.dimensions.get(name))
+        .map(|dimension| &dimension.dimension_type);
+    let type_id = match dimension_type {
+        Some(Value::Compound(inline)) => {
+            return match (inline.get("min_y"), inline.get("height")) {
+                (Some(Value::Int(min_y)), Some(Value::Int(height))) => Some(*min_y..min_y + height),
+                // inline types before 1.17 have no heights
+                _ => Some(0..256),
+            };
+        }
+        Some(Value::String(id)) => id.as_str(),
+        Some(_) => return None,
+        None => name,
+    };
+    match type_id {
+        "minecraft:overworld" | "minecraft:overworld_caves"
+            if data.data_version.unwrap_or(DATA_VERSION_UNVERSIONED)
+                >= DATA_VERSION_EXTENDED_HEIGHT =>
+        {
+            Some(-64..320)
+        }
+        "minecraft:overworld"
+        | "minecraft:overworld_caves"
+        | "minecraft:the_nether"
+        | "minecraft:the_end" => Some(0..256),
+        _ => None,
+    }
+}
+
+fn discover_dimensions(path: &Path) -> Result<Vec<Dimension>> {
+    let mut ans = vec![Dimension {
+        name: OVERWORLD.to_owned(),
+        path: path.to_owned(),
+        y_range: None,
+    }];
+    for (name, dir) in [(THE_NETHER, "DIM-1"), (THE_END, "DIM1")] {
+        let dir = path.join(dir);
+        if dir.is_dir() {
+            ans.push(Dimension {
+                name: name.to_owned(),
+                path: dir,
+                y_range: None,
+            });
+        }
+    }
+    let mut custom = Vec::new();
+    for namespace in read_dir_if_exists(&path.join("dimensions"))? {
+        let namespace = namespace?;
+        if !namespace.file_type()?.is_dir() {
+            continue;
+        }
+        let namespace_name = namespace.file_name().to_string_lossy().into_owned();
+        find_dimensions(&namespace.path(), &namespace_name, "", &mut custom)?;
+    }
+    // vanilla dimensions may be stored here as well by some tools
+    custom.retain(|c| ans.iter().all(|d| d.name != c.name));
+    custom.sort_by(|a, b| a.name.cmp(&b.name));
+    ans.extend(custom);
+    Ok(ans)
+}
+
+// Dimensions of datapacks may have paths in their names, like `ns:a/b`,
+// stored in `dimensions/ns/a/b`. A directory is a dimension if it has one
+// of the directories of dimensions; others are searched for nested
+// dimensions, and taken as an empty dimension if there are none.
+fn find_dimensions(
+    dir: &Path,
+    namespace: &str,
+    prefix: &str,
+    ans: &mut Vec<Dimension>,
+) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        let path = entry.path();
+        let found = ans.len();
+        let is_dimension = ["region", "entities", "poi", "data"]
+            .iter()
+            .any(|dir| path.join(dir).is_dir());
+        if !is_dimension {
+            find_dimensions(&path, namespace, &format!("{}/", name), ans)?;
+        }
+        if ans.len() == found {
+            ans.push(Dimension {
+                name: format!("{}:{}", namespace, name),
+                path,
+                y_range: None,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn list_region_files(dir: &Path) -> Result<Vec<((i32, i32), PathBuf)>> {
+    let mut ans = Vec::new();
+    for entry in read_dir_if_exists(dir)? {
+        let entry = entry?;
+        let position = entry
+            .file_name()
+            .to_str()
+            .and_then(region::parse_region_file_name);
+        if let Some(position) = position {
+            ans.push((position, entry.path()));
+        }
+    }
+    ans.sort();
+    Ok(ans)
+}
+
+fn read_dir_if_exists(dir: &Path) -> io::Result<Vec<io::Result<fs::DirEntry>>> {
+    match fs::read_dir(dir) {
+        Ok(entries) => Ok(entries.collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,371 @@
+//! A chunk held in memory by `World`, with the sections decoded so far.
+use crate::block_entity::BlockEntity;
+use crate::chunk::{BlockState, BlockStates, ChunkFormat};
+use crate::error::{Error, Result};
+use crate::palette::{self, Packing, PalettedContainer};
+use crate::world::BlockPos;
+use coruscant_nbt::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryFrom;
+use std::ops::Range;
+
+#[derive(Debug)]
+pub(super) struct LoadedChunk {
+    nbt: Value,
+    format: ChunkFormat,
+    packing: Packing,
+    y_range: Option<Range<i32>>,
+    sections: HashMap<i8, PalettedContainer<BlockState>>,
+    changed: BTreeSet<i8>,
+    dirty: bool,
+}
+
+impl LoadedChunk {
+    /// A chunk of a dimension with the given heights, if known.
+    pub(super) fn new(nbt: Value, y_range: Option<Range<i32>>) -> LoadedChunk {
+        let data_version = match &nbt {
+            Value::Compound(root) => match root.get("DataVersion") {
+                Some(Value::Int(v)) => Some(*v),
+                _ => None,
+            },
+            _ => None,
+        };
+        LoadedChunk {
+            nbt,
+            format: ChunkFormat::from_data_version(data_version),
+            packing: Packing::from_data_version(data_version.unwrap_or(0)),
+            y_range,
+            sections: HashMap::new(),
+            changed: BTreeSet::new(),
+            dirty: false,
+        }
+    }
+
+    /// The chunk NBT, without block changes since the last `sync`.
+    pub(super) fn nbt(&self) -> &Value {
+        &self.nbt
+    }
+
+    /// Whether the chunk changed since it was last saved.
+    pub(super) fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub(super) fn mark_saved(&mut self) {
+        self.dirty = false;
+    }
+
+    pub(super) fn get_block(&mut self, x: i32, y: i32, z: i32) -> Result<BlockState> {
+        self.check_height(x, y, z)?;
+        let container = self.section(y)?;
+        let state = container.get(local(x), local(y), local(z));
+        Ok(state.expect("inside the section").clone())
+    }
+
+    pub(super) fn set_block(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        state: BlockState,
+    ) -> Result<BlockState> {
+        self.check_height(x, y, z)?;
+        let container = self.section(y)?;
+        let old = container
+            .set(local(x), local(y), local(z), state.clone())
+            .expect("a section has fewer blocks than a palette can hold");
+        let replaced = old != state;
+        self.changed.insert(section_y(y)?);
+        self.dirty = true;
+        if replaced {
+            self.remove_block_entity(x, y, z)?;
+        }
+        Ok(old)
+    }
+
+    // The block entity of a replaced block does not belong to the new one.
+    fn remove_block_entity(&mut self, x: i32, y: i32, z: i32) -> Result<()> {
+        let layout = self.layout()?;
+        let level = level_mut(&mut self.nbt, layout)?;
+        if let Some(Value::List(list)) = level.get_mut(layout.block_entities) {
+            list.retain(|b| !is_at(b, x, y, z));
+        }
+        Ok(())
+    }
+
+    pub(super) fn block_entity(&self, x: i32, y: i32, z: i32) -> Result<Option<BlockEntity>> {
+        let layout = self.layout()?;
+        let found = match self
+            .level(layout)
+            .and_then(|l| l.get(layout.block_entities))
+        {
+            Some(Value::List(list)) => list.iter().find(|b| is_at(b, x, y, z)),
+            _ => None,
+        };
+        Ok(found.map(|value| BlockEntity::from_value(value.clone())))
+    }
+
+    /// Replace or remove the block entity at the given position, setting
+    /// the coordinates of the new one to the position.
+    pub(super) fn set_block_entity(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        block_entity: Option<BlockEntity>,
+    ) -> Result<Option<BlockEntity>> {
+        let layout = self.layout()?;
+        let new = match block_entity {
+            Some(block_entity) => match block_entity.to_value()? {
+                Value::Compound(mut compound) => {
+                    compound.insert("x".to_owned(), Value::Int(x));
+                    compound.insert("y".to_owned(), Value::Int(y));
+                    compound.insert("z".to_owned(), Value::Int(z));
+                    Some(Value::Compound(compound))
+                }
+                _ => return Err(Error::CorruptChunk("block entity is not a compound")),
+            },
+            None => None,
+        };
+        let level = level_mut(&mut self.nbt, layout)?;
+        if !matches!(level.get(layout.block_entities), Some(Value::List(_))) {
+            level.insert(layout.block_entities.to_owned(), Value::List(Vec::new()));
+        }
+        let list = match level.get_mut(layout.block_entities) {
+            Some(Value::List(list)) => list,
+            _ => unreachable!("inserted above"),
+        };
+        let old = list
+            .iter()
+            .position(|b| is_at(b, x, y, z))
+            .map(|index| list.remove(index));
+        if let Some(new) = new {
+            list.push(new);
+        }
+        self.dirty = true;
+        Ok(old.map(BlockEntity::from_value))
+    }
+
+    /// Write changed sections back into the chunk NBT.
+    ///
+    /// Heightmaps are emptied and the light of the chunk marked as not
+    /// calculated, so that the game computes them again on load.
+    pub(super) fn sync(&mut self) -> Result<()> {
+        if self.changed.is_empty() {
+            return Ok(());
+        }
+        let layout = self.layout()?;
+        let packing = self.packing;
+        let mut encoded = Vec::new();
+        for y in &self.changed {
+            let container = &self.sections[y];
+            encoded.push((*y, encode(container, layout, packing)?));
+        }
+        let level = level_mut(&mut self.nbt, layout)?;
+        let sections = match level.get_mut(layout.sections) {
+            Some(Value::List(sections)) => sections,
+            _ => {
+                level.insert(layout.sections.to_owned(), Value::List(Vec::new()));
+                match level.get_mut(layout.sections) {
+                    Some(Value::List(sections)) => sections,
+                    _ => unreachable!(),
+                }
+            }
+        };
+        for (y, fields) in encoded {
+            let index = match sections.iter().position(|s| section_y_of(s) == Some(y)) {
+                Some(index) => index,
+                None => {
+                    let mut section = Map::new();
+                    section.insert("Y".to_owned(), Value::Byte(y));
+                    sections.push(Value::Compound(section));
+                    sections.len() - 1
+                }
+            };
+            if let Value::Compound(section) = &mut sections[index] {
+                for (key, value) in fields {
+                    section.insert(key.to_owned(), value);
+                }
+                // sections since 1.18 have biomes, which the game requires
+                if layout.level.is_none() && !section.contains_key("biomes") {
+                    section.insert("biomes".to_owned(), default_biomes());
+                }
+                section.remove("BlockLight");
+                section.remove("SkyLight");
+            }
+        }
+        sections.sort_by_key(|s| section_y_of(s).unwrap_or(i8::MIN));
+        level.insert("Heightmaps".to_owned(), Value::Compound(Map::new()));
+        level.insert("isLightOn".to_owned(), Value::Byte(0));
+        self.changed.clear();
+        Ok(())
+    }
+
+    // Blocks have to be within the heights of the dimension, or of the
+    // sections the chunk can hold if those are not known.
+    fn check_height(&self, x: i32, y: i32, z: i32) -> Result<()> {
+        let inside = match &self.y_range {
+            Some(range) => range.contains(&y),
+            None => section_y(y).is_ok(),
+        };
+        match inside {
+            true => Ok(()),
+            false => Err(Error::BlockOutOfWorld(BlockPos::new(x, y, z))),
+        }
+    }
+
+    fn layout(&self) -> Result<&'static Layout> {
+        match self.format {
+            ChunkFormat::Modern => Ok(&MODERN),
+            ChunkFormat::Paletted => Ok(&PALETTED),
+            format => Err(Error::UnsupportedChunkFormat(format)),
+        }
+    }
+
+    // The compound holding the chunk data in the given layout.
+    fn level(&self, layout: &Layout) -> Option<&Map<String, Value>> {
+        match (&self.nbt, layout.level) {
+            (Value::Compound(root), None) => Some(root),
+            (Value::Compound(root), Some(key)) => match root.get(key) {
+                Some(Value::Compound(level)) => Some(level),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // Decoded blocks of the section containing block height `y`.
+    fn section(&mut self, y: i32) -> Result<&mut PalettedContainer<BlockState>> {
+        let y = section_y(y)?;
+        if !self.sections.contains_key(&y) {
+            let layout = self.layout()?;
+            let level = self.level(layout);
+            let section = match level.and_then(|level| level.get(layout.sections)) {
+                Some(Value::List(sections)) => sections.iter().find(|s| section_y_of(s) == Some(y)),
+                _ => None,
+            };
+            let container = match section {
+                Some(Value::Compound(section)) => decode(section, layout, self.packing)?,
+                _ => PalettedContainer::blocks(BlockState::air()),
+            };
+            self.sections.insert(y, container);
+        }
+        Ok(self.sections.get_mut(&y).expect("inserted above"))
+    }
+}
+
+// Where block data is kept in a chunk format.
+#[derive(Debug)]
+struct Layout {
+    level: Option<&'static str>,
+    sections: &'static str,
+    block_entities: &'static str,
+}
+
+const MODERN: Layout = Layout {
+    level: None,
+    sections: "sections",
+    block_entities: "block_entities",
+};
+
+const PALETTED: Layout = Layout {
+    level: Some("Level"),
+    sections: "Sections",
+    block_entities: "TileEntities",
+};
+
+fn decode(
+    section: &Map<String, Value>,
+    layout: &Layout,
+    packing: Packing,
+) -> Result<PalettedContainer<BlockState>> {
+    if layout.level.is_none() {
+        return match section.get("block_states") {
+            Some(value) => {
+                let block_states: BlockStates = coruscant_nbt::from_value(value.clone())?;
+                PalettedContainer::from_block_states(&block_states)
+            }
+            None => Ok(PalettedContainer::blocks(BlockState::air())),
+        };
+    }
+    let palette: Vec<BlockState> = match section.get("Palette") {
+        Some(value) => coruscant_nbt::from_value(value.clone())?,
+        None => return Ok(PalettedContainer::blocks(BlockState::air())),
+    };
+    let data = match section.get("BlockStates") {
+        Some(Value::LongArray(data)) => Some(&data[..]),
+        _ => None,
+    };
+    PalettedContainer::from_packed(16, 4, palette, data, packing)
+}
+
+fn encode(
+    container: &PalettedContainer<BlockState>,
+    layout: &Layout,
+    packing: Packing,
+) -> Result<Vec<(&'static str, Value)>> {
+    if layout.level.is_none() {
+        let block_states = coruscant_nbt::to_value(container.to_block_states())?;
+        return Ok(vec![("block_states", block_states)]);
+    }
+    let (palette, data) = container.to_packed(packing);
+    // sections before 1.18 always have their indices, even if all zero
+    let data = data.unwrap_or_else(|| palette::pack(&[0; 4096], 4, packing));
+    Ok(vec![
+        ("Palette", coruscant_nbt::to_value(palette)?),
+        ("BlockStates", Value::LongArray(data)),
+    ])
+}
+
+// Biomes of a section without any, all plains like the sections the game
+// adds to chunks upgraded to 1.18.
+fn default_biomes() -> Value {
+    let mut biomes = Map::new();
+    let palette = vec![Value::String("minecraft:plains".to_owned())];
+    biomes.insert("palette".to_owned(), Value::List(palette));
+    Value::Compound(biomes)
+}
+
+fn level_mut<'a>(nbt: &'a mut Value, layout: &Layout) -> Result<&'a mut Map<String, Value>> {
+    let root = match nbt {
+        Value::Compound(root) => root,
+        _ => return Err(Error::CorruptChunk("chunk is not a compound")),
+    };
+    match layout.level {
+        None => Ok(root),
+        Some(key) => match root.get_mut(key) {
+            Some(Value::Compound(level)) => Ok(level),
+            _ => Err(Error::CorruptChunk("missing Level compound")),
+        },
+    }
+}
+
+fn is_at(block_entity: &Value, x: i32, y: i32, z: i32) -> bool {
+    match block_entity {
+        Value::Compound(b) => {
+            b.get("x") == Some(&Value::Int(x))
+                && b.get("y") == Some(&Value::Int(y))
+                && b.get("z") == Some(&Value::Int(z))
+        }
+        _ => false,
+    }
+}
+
+fn section_y_of(section: &Value) -> Option<i8> {
+    match section {
+        Value::Compound(section) => match section.get("Y") {
+            Some(Value::Byte(y)) => Some(*y),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn section_y(y: i32) -> Result<i8> {
+    i8::try_from(y >> 4).map_err(|_| Error::CorruptChunk("block height out of range"))
+}
+
+#[inline]
+fn local(coord: i32) -> usize {
+    (coord & 15) as usize
+}
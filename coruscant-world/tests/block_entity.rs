@@ -0,0 +1,133 @@
+use coruscant_nbt::Value;
+use coruscant_world::block_entity::BlockEntity;
+use coruscant_world::world::BlockPos;
+
+fn snbt(snbt: &str) -> Value {
+    coruscant_nbt::snbt::parse(snbt).unwrap()
+}
+
+// A 1.20.5 chest with the item components of its block, a field of the
+// block entity that is not typed and a field some mod added.
+const CHEST: &str = r#"{
+    id: "minecraft:chest", x: 10, y: 64, z: -3, keepPacked: 0b,
+    Items: [{Slot: 0b, id: "minecraft:diamond", count: 3}],
+    LootTable: "minecraft:chests/simple_dungeon", LootTableSeed: 7L,
+    components: {"minecraft:custom_name": '"Loot"'},
+    ForgeCaps: {"mod:data": [I; 1, 2, 3]}
+}"#;
+
+#[test]
+fn typed_block_entities_keep_their_other_fields() {
+    let value = snbt(CHEST);
+    let block_entity = BlockEntity::from_value(value.clone());
+    let chest = match &block_entity {
+        BlockEntity::Container(chest) => chest,
+        other => panic!("read as {:?}", other),
+    };
+    assert_eq!(chest.pos(), BlockPos::new(10, 64, -3));
+    assert_eq!(chest.data.items[0].count, 3);
+    for key in &["keepPacked", "components", "ForgeCaps"] {
+        assert!(chest.extra.contains_key(*key), "{} is not in extra", key);
+    }
+    for key in &["id", "x", "Items", "LootTable"] {
+        assert!(!chest.extra.contains_key(*key), "{} is in extra", key);
+    }
+
+    assert_eq!(block_entity.to_value().unwrap(), value);
+    let bytes = coruscant_nbt::to_vec(&block_entity).unwrap();
+    let read: Value = coruscant_nbt::from_slice(&bytes).unwrap();
+    assert_eq!(read, value);
+    let read: BlockEntity = coruscant_nbt::from_slice(&bytes).unwrap();
+    assert_eq!(read, block_entity);
+}
+
+#[test]
+fn ids_are_matched_with_or_without_namespace() {
+    let cases = [
+        (r#"{id: "hopper", x: 0, y: 0, z: 0}"#, "container"),
+        (
+            r#"{id: "minecraft:smoker", x: 0, y: 0, z: 0, BurnTime: 5s}"#,
+            "furnace",
+        ),
+        (
+            r#"{id: "minecraft:hanging_sign", x: 0, y: 0, z: 0}"#,
+            "sign",
+        ),
+        (
+            r#"{id: "mob_spawner", x: 0, y: 0, z: 0, Delay: 20s}"#,
+            "spawner",
+        ),
+        (r#"{id: "minecraft:banner", x: 0, y: 0, z: 0}"#, "banner"),
+    ];
+    for (case, kind) in &cases {
+        let read = match BlockEntity::from_value(snbt(case)) {
+            BlockEntity::Container(_) => "container",
+            BlockEntity::Furnace(_) => "furnace",
+            BlockEntity::Sign(_) => "sign",
+            BlockEntity::Spawner(_) => "spawner",
+            BlockEntity::Banner(_) => "banner",
+            other => panic!("{} read as {:?}", case, other),
+        };
+        assert_eq!(read, *kind, "{}", case);
+    }
+}
+
+#[test]
+fn other_block_entities_are_unknown() {
+    let cases = [
+        // a type with no typed data
+        r#"{id: "minecraft:beehive", x: 1, y: 2, z: 3, bees: []}"#,
+        // a known type in another layout: block entities before 1.11
+        r#"{id: "Chest", x: 1, y: 2, z: 3}"#,
+        // a known type with a field of the wrong type
+        r#"{id: "minecraft:sign", x: 1, y: 2, z: 3, Text1: 5}"#,
+        // a known type without coordinates
+        r#"{id: "minecraft:barrel", Items: []}"#,
+        // no ID, or an ID that is not a string
+        r#"{x: 1, y: 2, z: 3}"#,
+        r#"{id: 54, x: 1, y: 2, z: 3}"#,
+    ];
+    for case in &cases {
+        let value = snbt(case);
+        let block_entity = BlockEntity::from_value(value.clone());
+        assert_eq!(
+            block_entity,
+            BlockEntity::Unknown(value.clone()),
+            "{}",
+            case
+        );
+        assert_eq!(block_entity.items(), None);
+        assert_eq!(block_entity.to_value().unwrap(), value);
+    }
+
+    let beehive = BlockEntity::from_value(snbt(cases[0]));
+    assert_eq!(beehive.id(), Some("minecraft:beehive"));
+    assert_eq!(beehive.pos(), Some(BlockPos::new(1, 2, 3)));
+    let barrel = BlockEntity::from_value(snbt(cases[3]));
+    assert_eq!(barrel.id(), Some("minecraft:barrel"));
+    assert_eq!(barrel.pos(), None);
+    assert_eq!(BlockEntity::from_value(snbt(cases[5])).id(), None);
+    assert_eq!(
+        BlockEntity::from_value(Value::Int(1)),
+        BlockEntity::Unknown(Value::Int(1))
+    );
+}
+
+// A sign of before 1.20, with its lines in `Text1` to `Text4`.
+#[test]
+fn legacy_signs_round_trip() {
+    let value = snbt(
+        r#"{id: "minecraft:sign", x: 0, y: 70, z: 0, Text1: '{"text":"a"}', Text2: '""',
+            Text3: '""', Text4: '""', Color: "red"}"#,
+    );
+    let block_entity = BlockEntity::from_value(value.clone());
+    match &block_entity {
+        BlockEntity::Sign(sign) => {
+            let lines = sign.data.front_lines();
+            assert_eq!(lines[0], Value::String(r#"{"text":"a"}"#.to_owned()));
+            assert_eq!(sign.data.color.as_deref(), Some("red"));
+        }
+        other => panic!("read as {:?}", other),
+    }
+    assert_eq!(block_entity.to_value().unwrap(), value);
+}
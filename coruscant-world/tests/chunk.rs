@@ -0,0 +1,120 @@
+use coruscant_nbt::Value;
+use coruscant_world::chunk::legacy::{NumericChunk, PalettedChunk};
+use coruscant_world::chunk::Chunk;
+
+// A 1.20 chunk with fields `Chunk` has no typed field for.
+const CHUNK: &str = r#"{
+    DataVersion: 3465,
+    xPos: 2, yPos: -4, zPos: -7,
+    Status: "minecraft:full",
+    LastUpdate: 1200L,
+    InhabitedTime: 300L,
+    isLightOn: 1b,
+    sections: [{
+        Y: -4b,
+        block_states: {palette: [{Name: "minecraft:bedrock"}]},
+        biomes: {palette: ["minecraft:plains"]},
+        SkyLight: [B; 1b, 2b, 3b]
+    }],
+    block_entities: [],
+    Heightmaps: {WORLD_SURFACE: [L; 1L, 2L]},
+    PostProcessing: [[], [1s, 2s]],
+    structures: {References: {}, starts: {}},
+    block_ticks: [],
+    fluid_ticks: [],
+    blending_data: {min_section: -4, max_section: 20, heights: [1.0d, 2.0d]},
+    CarvingMasks: {AIR: [L; 5L, 6L]},
+    below_zero_retrogen: {target_status: "spawn", missing_bedrock: [L; 7L]},
+    ForgeCaps: {"mod:data": [I; 1, 2, 3], "mod:name": "x"}
+}"#;
+
+#[test]
+fn unknown_fields_round_trip() {
+    let value = coruscant_nbt::snbt::parse(CHUNK).unwrap();
+    let chunk: Chunk = coruscant_nbt::from_value(value.clone()).unwrap();
+    assert!(chunk.extra.contains_key("blending_data"));
+    assert!(chunk.extra.contains_key("ForgeCaps"));
+    assert!(!chunk.extra.contains_key("sections"));
+
+    let bytes = coruscant_nbt::to_vec(&chunk).unwrap();
+    let read: Value = coruscant_nbt::from_slice(&bytes).unwrap();
+    assert_eq!(read, value);
+    let read: Chunk = coruscant_nbt::from_slice(&bytes).unwrap();
+    assert_eq!(read, chunk);
+}
+
+// A 1.16 chunk with fields `PalettedLevel` and `PalettedSection` have no
+// typed field for.
+const PALETTED_CHUNK: &str = r#"{
+    DataVersion: 2586,
+    Level: {
+        xPos: 2, zPos: -7,
+        LastUpdate: 1200L,
+        InhabitedTime: 300L,
+        Status: "full",
+        Biomes: [I; 1, 1, 1],
+        Heightmaps: {WORLD_SURFACE: [L; 1L, 2L]},
+        Sections: [{
+            Y: 0b,
+            Palette: [{Name: "minecraft:bedrock"}],
+            BlockStates: [L; 0L],
+            SkyLight: [B; 1b, 2b, 3b],
+            "mod:section": [B; 4b]
+        }],
+        Entities: [],
+        TileEntities: [],
+        TileTicks: [],
+        LiquidTicks: [],
+        PostProcessing: [[], [1s, 2s]],
+        Structures: {References: {}, Starts: {}},
+        isLightOn: 1b,
+        CarvingMasks: {AIR: [B; 5b, 6b], LIQUID: [B; 7b]},
+        ToBeTicked: [[], [3s]],
+        LiquidsToBeTicked: [[4s], []],
+        Lights: [[], [5s]],
+        ForgeCaps: {"mod:data": [I; 1, 2, 3], "mod:name": "x"}
+    }
+}"#;
+
+// A 1.12 chunk with fields `NumericLevel` has no typed field for.
+const NUMERIC_CHUNK: &str = r#"{
+    DataVersion: 1343,
+    Level: {
+        xPos: 2, zPos: -7,
+        LastUpdate: 1200L,
+        InhabitedTime: 300L,
+        TerrainPopulated: 1b,
+        LightPopulated: 1b,
+        V: 1b,
+        HeightMap: [I; 1, 2],
+        Sections: [],
+        Entities: [],
+        TileEntities: [],
+        TileTicks: [],
+        ForgeCaps: {"mod:data": [I; 1, 2, 3], "mod:name": "x"}
+    }
+}"#;
+
+#[test]
+fn unknown_legacy_fields_round_trip() {
+    let value = coruscant_nbt::snbt::parse(PALETTED_CHUNK).unwrap();
+    let chunk: PalettedChunk = coruscant_nbt::from_value(value.clone()).unwrap();
+    for key in ["CarvingMasks", "ToBeTicked", "LiquidsToBeTicked", "Lights"] {
+        assert!(chunk.level.extra.contains_key(key), "{}", key);
+    }
+    assert!(!chunk.level.extra.contains_key("Sections"));
+    assert!(chunk.level.sections[0].extra.contains_key("mod:section"));
+
+    let bytes = coruscant_nbt::to_vec(&chunk).unwrap();
+    let read: Value = coruscant_nbt::from_slice(&bytes).unwrap();
+    assert_eq!(read, value);
+    let read: PalettedChunk = coruscant_nbt::from_slice(&bytes).unwrap();
+    assert_eq!(read, chunk);
+
+    let value = coruscant_nbt::snbt::parse(NUMERIC_CHUNK).unwrap();
+    let chunk: NumericChunk = coruscant_nbt::from_value(value.clone()).unwrap();
+    assert!(chunk.level.extra.contains_key("ForgeCaps"));
+    let bytes = coruscant_nbt::to_vec(&chunk).unwrap();
+    let read: Value = coruscant_nbt::from_slice(&bytes).unwrap();
+    assert_eq!(read, value);
+}
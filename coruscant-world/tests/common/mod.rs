@@ -0,0 +1,88 @@
+#![allow(dead_code)]
+
+use coruscant_nbt::Compression;
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+// The fields of `level.dat` that are not optional, as written by 1.20.4.
+const LEVEL_DAT: &str = r#"{Data: {
+    CustomBossEvents: {},
+    DataPacks: {Disabled: [], Enabled: ["vanilla"]},
+    DataVersion: 3700, version: 19133, initialized: 1b, LevelName: "Test",
+    LastPlayed: 0L, SizeOnDisk: 0L, allowCommands: 0b, hardcore: 0b,
+    Difficulty: 2b, DifficultyLocked: 0b, Time: 0L, DayTime: 0L, GameType: 0,
+    SpawnX: 0, SpawnY: 64, SpawnZ: 0,
+    BorderCenterX: 0.0d, BorderCenterZ: 0.0d, BorderSize: 59999968.0d,
+    BorderSafeZone: 5.0d, BorderWarningBlocks: 5.0d, BorderWarningTime: 15.0d,
+    BorderSizeLerpTarget: 59999968.0d, BorderSizeLerpTime: 0L,
+    BorderDamagePerBlock: 0.2d,
+    raining: 0b, rainTime: 0, thundering: 0b, thunderTime: 0, clearWeatherTime: 0,
+    WanderingTraderSpawnChance: 25, WanderingTraderSpawnDelay: 24000,
+    Version: {Id: 3700, Name: "1.20.4", Series: "main", Snapshot: 0b}
+}}"#;
+
+// A `level.dat` as written by 1.12.2, before data packs and boss bars.
+pub const LEVEL_DAT_1_12: &str = r#"{Data: {
+    DataVersion: 1343, version: 19133, initialized: 1b, LevelName: "Legacy",
+    generatorName: "default", generatorVersion: 1, generatorOptions: "",
+    RandomSeed: 42L, MapFeatures: 1b,
+    LastPlayed: 0L, SizeOnDisk: 0L, allowCommands: 0b, hardcore: 0b,
+    Difficulty: 2b, DifficultyLocked: 0b, Time: 0L, DayTime: 0L, GameType: 0,
+    SpawnX: 0, SpawnY: 64, SpawnZ: 0,
+    BorderCenterX: 0.0d, BorderCenterZ: 0.0d, BorderSize: 60000000.0d,
+    BorderSafeZone: 5.0d, BorderWarningBlocks: 5.0d, BorderWarningTime: 15.0d,
+    BorderSizeLerpTarget: 60000000.0d, BorderSizeLerpTime: 0L,
+    BorderDamagePerBlock: 0.2d,
+    raining: 0b, rainTime: 0, thundering: 0b, thunderTime: 0, clearWeatherTime: 0,
+    GameRules: {doDaylightCycle: "true", keepInventory: "false"},
+    DimensionData: {"1": {DragonFight: {
+        Gateways: [0, 1], DragonKilled: 0b, PreviouslyKilled: 1b,
+        DragonUUIDMost: 1L, DragonUUIDLeast: 2L
+    }}},
+    Version: {Id: 1343, Name: "1.12.2", Snapshot: 0b}
+}}"#;
+
+// A `level.dat` as written by 1.13.2, before the wandering trader.
+pub const LEVEL_DAT_1_13: &str = r#"{Data: {
+    CustomBossEvents: {},
+    DataPacks: {Disabled: [], Enabled: ["vanilla"]},
+    DataVersion: 1631, version: 19133, initialized: 1b, LevelName: "Legacy",
+    generatorName: "default", generatorVersion: 1, generatorOptions: {},
+    RandomSeed: 42L, MapFeatures: 1b,
+    LastPlayed: 0L, SizeOnDisk: 0L, allowCommands: 0b, hardcore: 0b,
+    Difficulty: 2b, DifficultyLocked: 0b, Time: 0L, DayTime: 0L, GameType: 0,
+    SpawnX: 0, SpawnY: 64, SpawnZ: 0,
+    BorderCenterX: 0.0d, BorderCenterZ: 0.0d, BorderSize: 60000000.0d,
+    BorderSafeZone: 5.0d, BorderWarningBlocks: 5.0d, BorderWarningTime: 15.0d,
+    BorderSizeLerpTarget: 60000000.0d, BorderSizeLerpTime: 0L,
+    BorderDamagePerBlock: 0.2d,
+    raining: 0b, rainTime: 0, thundering: 0b, thunderTime: 0, clearWeatherTime: 0,
+    GameRules: {doDaylightCycle: "true", keepInventory: "false"},
+    DimensionData: {"1": {DragonFight: {
+        Gateways: [0, 1], DragonKilled: 0b, PreviouslyKilled: 1b,
+        DragonUUIDMost: 1L, DragonUUIDLeast: 2L
+    }}},
+    Version: {Id: 1631, Name: "1.13.2", Snapshot: 0b}
+}}"#;
+
+/// An empty directory for the test of the given name.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("coruscant-world-test-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A world with only a `level.dat`, for the test of the given name.
+pub fn temp_world(name: &str) -> PathBuf {
+    temp_world_with(name, LEVEL_DAT)
+}
+
+/// A world with only the given `level.dat`, for the test of the given name.
+pub fn temp_world_with(name: &str, level_dat: &str) -> PathBuf {
+    let dir = temp_dir(name);
+    let level_dat = coruscant_nbt::snbt::parse(level_dat).unwrap();
+    let file = File::create(dir.join("level.dat")).unwrap();
+    coruscant_nbt::to_gzip_writer(file, &level_dat, Compression::default()).unwrap();
+    dir
+}
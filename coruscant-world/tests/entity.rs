@@ -0,0 +1,125 @@
+use coruscant_nbt::Value;
+use coruscant_world::entity::{Entity, EntityChunk};
+use coruscant_world::world::ChunkPos;
+
+fn snbt(snbt: &str) -> Value {
+    coruscant_nbt::snbt::parse(snbt).unwrap()
+}
+
+// A 1.15 sheep with every field its parts write, the UUID of before 1.16,
+// an equipment compound of 1.21.5 and a field some mod added.
+const SHEEP: &str = r#"{
+    id: "minecraft:sheep",
+    Pos: [1.5d, 64.0d, 2.5d], Motion: [0.0d, -0.08d, 0.0d], Rotation: [90.0f, 0.0f],
+    FallDistance: 0.0f, Fire: -1s, Air: 300s, OnGround: 1b, Invulnerable: 0b,
+    PortalCooldown: 0, UUIDMost: 1L, UUIDLeast: 2L,
+    Health: 8.0f, AbsorptionAmount: 0.0f, HurtTime: 0s, HurtByTimestamp: 0,
+    DeathTime: 0s, FallFlying: 0b, CanPickUpLoot: 0b, PersistenceRequired: 0b,
+    LeftHanded: 0b,
+    Color: 14b, Sheared: 1b,
+    equipment: {head: {id: "minecraft:carved_pumpkin", count: 1}},
+    ForgeCaps: {"mod:data": [I; 1, 2, 3]}
+}"#;
+
+#[test]
+fn typed_entities_keep_their_other_fields() {
+    let value = snbt(SHEEP);
+    let entity = Entity::from_value(value.clone());
+    let sheep = match &entity {
+        Entity::Sheep(sheep) => sheep,
+        other => panic!("read as {:?}", other),
+    };
+    assert_eq!(sheep.id, "minecraft:sheep");
+    assert_eq!(sheep.data.color, 14);
+    assert_eq!(sheep.mob.health, 8.0);
+    for key in &["UUIDMost", "UUIDLeast", "equipment", "ForgeCaps"] {
+        assert!(sheep.extra.contains_key(*key), "{} is not in extra", key);
+    }
+    for key in &["id", "Pos", "Health", "Color"] {
+        assert!(!sheep.extra.contains_key(*key), "{} is in extra", key);
+    }
+
+    assert_eq!(entity.to_value().unwrap(), value);
+    let bytes = coruscant_nbt::to_vec(&entity).unwrap();
+    let read: Value = coruscant_nbt::from_slice(&bytes).unwrap();
+    assert_eq!(read, value);
+    let read: Entity = coruscant_nbt::from_slice(&bytes).unwrap();
+    assert_eq!(read, entity);
+}
+
+#[test]
+fn ids_are_matched_with_or_without_namespace() {
+    let zombie = r#"{id: "husk", Pos: [0.0d, 0.0d, 0.0d], Health: 20.0f}"#;
+    let entity = Entity::from_value(snbt(zombie));
+    assert!(matches!(entity, Entity::Zombie(_)));
+    assert_eq!(entity.id(), Some("husk"));
+
+    let frame = r#"{id: "minecraft:glow_item_frame", Pos: [0.0d, 0.0d, 0.0d],
+        TileX: 0, TileY: 0, TileZ: 0, Facing: 1b}"#;
+    assert!(matches!(
+        Entity::from_value(snbt(frame)),
+        Entity::ItemFrame(_)
+    ));
+}
+
+#[test]
+fn other_entities_are_unknown() {
+    let cases = [
+        // a type with no typed data
+        r#"{id: "minecraft:pig", Pos: [0.0d, 0.0d, 0.0d], Saddle: 1b}"#,
+        // a known type in another layout: falling blocks before 1.13
+        r#"{id: "minecraft:falling_block", Pos: [0.0d, 0.0d, 0.0d], Block: "minecraft:sand",
+            Data: 0b}"#,
+        // a known type with a field of the wrong type
+        r#"{id: "minecraft:creeper", Pos: [0.0d, 0.0d, 0.0d], Health: "full"}"#,
+        // no ID, or an ID that is not a string
+        r#"{Pos: [0.0d, 0.0d, 0.0d]}"#,
+        r#"{id: 90, Pos: [0.0d, 0.0d, 0.0d]}"#,
+    ];
+    for case in &cases {
+        let value = snbt(case);
+        let entity = Entity::from_value(value.clone());
+        assert_eq!(entity, Entity::Unknown(value.clone()), "{}", case);
+        assert_eq!(entity.entity(), None);
+        assert_eq!(entity.to_value().unwrap(), value);
+    }
+    assert_eq!(
+        Entity::from_value(snbt(cases[0])).id(),
+        Some("minecraft:pig")
+    );
+    assert_eq!(Entity::from_value(snbt(cases[3])).id(), None);
+    assert_eq!(Entity::from_value(snbt(cases[4])).id(), None);
+
+    let entity = Entity::from_value(Value::Int(1));
+    assert_eq!(entity, Entity::Unknown(Value::Int(1)));
+}
+
+#[test]
+fn passengers_are_dispatched() {
+    let value = snbt(
+        r#"{id: "minecraft:pig", Pos: [0.0d, 0.0d, 0.0d], Passengers: [
+            {id: "minecraft:zombie", Pos: [0.0d, 1.0d, 0.0d], Health: 20.0f, IsBaby: 1b}]}"#,
+    );
+    let chunk = EntityChunk::new(
+        2730,
+        ChunkPos::new(0, 0),
+        vec![Entity::from_value(value.clone())],
+    );
+    let bytes = coruscant_nbt::to_vec(&chunk).unwrap();
+    let read: EntityChunk = coruscant_nbt::from_slice(&bytes).unwrap();
+    // the pig is unknown, so its passengers are kept as NBT
+    assert_eq!(read.entities, vec![Entity::Unknown(value)]);
+
+    let value = snbt(
+        r#"{id: "minecraft:sheep", Pos: [0.0d, 0.0d, 0.0d], Health: 8.0f, Passengers: [
+            {id: "minecraft:zombie", Pos: [0.0d, 1.0d, 0.0d], Health: 20.0f, IsBaby: 1b},
+            {id: "minecraft:bat", Pos: [0.0d, 2.0d, 0.0d]}]}"#,
+    );
+    let entity = Entity::from_value(value);
+    let passengers = &entity.entity().unwrap().passengers;
+    match &passengers[0] {
+        Entity::Zombie(zombie) => assert!(zombie.data.is_baby),
+        other => panic!("read as {:?}", other),
+    }
+    assert!(matches!(passengers[1], Entity::Unknown(_)));
+}
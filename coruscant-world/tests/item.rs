@@ -0,0 +1,44 @@
+use coruscant_nbt::Value;
+use coruscant_world::item::{ItemFormat, ItemStack};
+
+// A 1.12 item with the top-level `Damage` short of items before 1.13, and a
+// field some mod added.
+const LEGACY_ITEM: &str = r#"{
+    Slot: 3b,
+    id: "minecraft:wool",
+    Count: 5b,
+    Damage: 14s,
+    tag: {display: {Name: "Red"}},
+    ForgeCaps: {"mod:data": [I; 1, 2, 3]}
+}"#;
+
+#[test]
+fn unknown_fields_round_trip() {
+    let value = coruscant_nbt::snbt::parse(LEGACY_ITEM).unwrap();
+    let item: ItemStack = coruscant_nbt::from_value(value.clone()).unwrap();
+    assert_eq!(item.format(), ItemFormat::Tag);
+    assert_eq!(item.count, 5);
+    assert_eq!(item.extra.get("Damage"), Some(&Value::Short(14)));
+    assert!(item.extra.contains_key("ForgeCaps"));
+    assert!(!item.extra.contains_key("tag"));
+
+    let bytes = coruscant_nbt::to_vec(&item).unwrap();
+    let read: Value = coruscant_nbt::from_slice(&bytes).unwrap();
+    assert_eq!(read, value);
+    let read: ItemStack = coruscant_nbt::from_slice(&bytes).unwrap();
+    assert_eq!(read, item);
+}
+
+#[test]
+fn components_keep_unknown_fields() {
+    let value =
+        coruscant_nbt::snbt::parse(r#"{id: "minecraft:stone", count: 2, "mod:origin": "quarry"}"#)
+            .unwrap();
+    let item: ItemStack = coruscant_nbt::from_value(value.clone()).unwrap();
+    assert_eq!(item.format(), ItemFormat::Components);
+    assert_eq!(
+        item.extra.get("mod:origin"),
+        Some(&Value::String("quarry".to_owned()))
+    );
+    assert_eq!(coruscant_nbt::to_value(&item).unwrap(), value);
+}
@@ -0,0 +1,54 @@
+use coruscant_world::chunk::{Chunk, Section};
+use coruscant_world::lighting::{heightmap_values, recompute_heightmaps, BlockTable};
+use coruscant_world::palette::PalettedContainer;
+use coruscant_world::BlockState;
+
+fn section(y: i8, stone_at: Option<usize>) -> Section {
+    let mut blocks = PalettedContainer::blocks(BlockState::air());
+    if let Some(local_y) = stone_at {
+        blocks.set(
+            0,
+            local_y,
+            0,
+            BlockState::new("minecraft:stone".to_string()),
+        );
+    }
+    let mut section = Section::new(y);
+    section.block_states = Some(blocks.to_block_states());
+    section
+}
+
+// Only the lowest section of the overworld has blocks, as in chunks written
+// by tools that leave out empty sections.
+#[test]
+fn heightmaps_use_the_dimension_height() {
+    let mut sparse = Chunk::new(3700, 0, -4, 0, "minecraft:full".to_string());
+    sparse.sections.push(section(-4, Some(5)));
+    recompute_heightmaps(&mut sparse, &BlockTable::vanilla(), 384).unwrap();
+
+    // 9 bits for heights up to 384, 7 values in each long
+    let world_surface = sparse.heightmaps.world_surface.clone().unwrap();
+    assert_eq!(world_surface.len(), 37);
+    let values = heightmap_values(&world_surface, 384);
+    assert_eq!(values[0], 6);
+    assert_eq!(values[1], 0);
+
+    // the same as with all sections of the world present
+    let mut full = Chunk::new(3700, 0, -4, 0, "minecraft:full".to_string());
+    full.sections.push(section(-4, Some(5)));
+    for y in -3..20 {
+        full.sections.push(section(y, None));
+    }
+    recompute_heightmaps(&mut full, &BlockTable::vanilla(), 384).unwrap();
+    assert_eq!(full.heightmaps, sparse.heightmaps);
+}
+
+#[test]
+fn heightmaps_leave_out_blocks_above_the_dimension() {
+    let mut chunk = Chunk::new(3700, 0, 0, 0, "minecraft:full".to_string());
+    chunk.sections.push(section(0, Some(0)));
+    chunk.sections.push(section(16, Some(0)));
+    recompute_heightmaps(&mut chunk, &BlockTable::vanilla(), 256).unwrap();
+    let world_surface = chunk.heightmaps.world_surface.unwrap();
+    assert_eq!(heightmap_values(&world_surface, 256)[0], 1);
+}
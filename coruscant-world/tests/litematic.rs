@@ -0,0 +1,120 @@
+use coruscant_world::litematic::{Litematic, Region};
+use coruscant_world::palette::PalettedContainer;
+use coruscant_world::{BlockPos, BlockState};
+
+mod common;
+
+use common::temp_dir;
+
+const BLOCKS: [&str; 6] = ["stone", "dirt", "oak_planks", "glass", "sand", "gravel"];
+
+fn block(name: &str) -> BlockState {
+    BlockState::new(format!("minecraft:{}", name))
+}
+
+// The blocks of a container, ordered by Y, then Z, then X.
+fn states(blocks: &PalettedContainer<BlockState>) -> Vec<BlockState> {
+    let [size_x, size_y, size_z] = blocks.size();
+    let mut ans = Vec::new();
+    for y in 0..size_y {
+        for z in 0..size_z {
+            for x in 0..size_x {
+                ans.push(blocks.get(x, y, z).unwrap().clone());
+            }
+        }
+    }
+    ans
+}
+
+// Indices packed back to back into longs, spanning longs, as Litematica
+// packs them.
+fn pack_spanning(indices: &[u64], bits: usize) -> Vec<i64> {
+    let mut data = vec![0u64; (indices.len() * bits).div_ceil(64)];
+    for (i, &index) in indices.iter().enumerate() {
+        let (word, offset) = (i * bits / 64, i * bits % 64);
+        data[word] |= index << offset;
+        if offset + bits > 64 {
+            data[word + 1] |= index >> (64 - offset);
+        }
+    }
+    data.into_iter().map(|word| word as i64).collect()
+}
+
+// The blocks of `BLOCKS` in turn from `shift`, with air every seventh
+// block, ordered by Y, then Z, then X.
+fn stripes(len: usize, shift: usize) -> Vec<BlockState> {
+    (shift..len + shift)
+        .map(|i| match i % 7 {
+            0 => BlockState::air(),
+            _ => block(BLOCKS[i % BLOCKS.len()]),
+        })
+        .collect()
+}
+
+fn striped_region(position: BlockPos, size: BlockPos, shift: usize) -> Region {
+    let mut region = Region::new(position, size);
+    let mut blocks = region.blocks().unwrap();
+    let [size_x, size_y, size_z] = blocks.size();
+    let mut stripes = stripes(size_x * size_y * size_z, shift).into_iter();
+    for y in 0..size_y {
+        for z in 0..size_z {
+            for x in 0..size_x {
+                blocks.set(x, y, z, stripes.next().unwrap());
+            }
+        }
+    }
+    region.set_blocks(&blocks);
+    region
+}
+
+#[test]
+fn spanning_block_states_are_packed() {
+    // 3 bits per block, so the 22nd block spans the first two longs
+    let mut region = Region::new(BlockPos::new(0, 0, 0), BlockPos::new(30, 1, 1));
+    let mut blocks = region.blocks().unwrap();
+    for x in 0..30 {
+        blocks.set(x, 0, 0, block(BLOCKS[x % 5]));
+    }
+    region.set_blocks(&blocks);
+    let indices: Vec<u64> = (0..30)
+        .map(|x| {
+            let state = block(BLOCKS[x % 5]);
+            region.palette.iter().position(|s| *s == state).unwrap() as u64
+        })
+        .collect();
+    assert_eq!(region.palette.len(), 5);
+    assert_eq!(region.block_states, pack_spanning(&indices, 3));
+    assert_eq!(states(&region.blocks().unwrap()), states(&blocks));
+}
+
+#[test]
+fn regions_round_trip() {
+    let mut litematic = Litematic::new(3465, "Two rooms".to_string());
+    // sizes of 5 by 3 by 7 and 9 by 2 by 4 blocks, with 3 bits per block
+    // that do not divide a long, and sizes towards negative coordinates
+    let east = striped_region(BlockPos::new(0, 0, 0), BlockPos::new(5, 3, 7), 0);
+    let west = striped_region(BlockPos::new(-1, 4, 2), BlockPos::new(-9, 2, -4), 3);
+    litematic.regions.insert("east".to_string(), east.clone());
+    litematic.regions.insert("west".to_string(), west.clone());
+    litematic.update_metadata().unwrap();
+    assert_eq!(litematic.metadata.region_count, 2);
+    assert_eq!(litematic.metadata.total_volume, 105 + 72);
+    let air = BlockState::air();
+    let solid = |len, shift| stripes(len, shift).iter().filter(|s| **s != air).count();
+    let total_blocks = solid(105, 0) + solid(72, 3);
+    assert_eq!(litematic.metadata.total_blocks, total_blocks as i32);
+    // from -9 to 5 along X, 0 to 6 along Y and -1 to 7 along Z
+    assert_eq!(litematic.metadata.enclosing_size, BlockPos::new(14, 6, 8));
+    assert_eq!(west.min_corner(), BlockPos::new(-9, 4, -1));
+
+    let dir = temp_dir("litematic-regions");
+    let path = dir.join("rooms.litematic");
+    litematic.write(&path).unwrap();
+    let read = Litematic::read(&path).unwrap();
+    assert_eq!(read, litematic);
+    for (name, size, shift) in &[("east", [5, 3, 7], 0), ("west", [9, 2, 4], 3)] {
+        let blocks = read.regions[*name].blocks().unwrap();
+        assert_eq!(blocks.size(), *size);
+        assert_eq!(states(&blocks), stripes(size.iter().product(), *shift));
+    }
+}
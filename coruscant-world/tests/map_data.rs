@@ -0,0 +1,112 @@
+use coruscant_nbt::{Compression, Value};
+use coruscant_world::map_data::{MapData, MapDimension, MAP_SIZE};
+use coruscant_world::{Error, World};
+use std::fs::{self, File};
+
+mod common;
+
+use common::{temp_world_with, LEVEL_DAT_1_12};
+
+#[test]
+fn short_colors_are_an_error() {
+    let mut map = MapData::new(0, 0, "minecraft:overworld".to_string());
+    map.colors.truncate(MAP_SIZE * MAP_SIZE - 1);
+    assert!(matches!(map.to_rgba(), Err(Error::CorruptMap(_))));
+    assert!(matches!(map.render(), Err(Error::CorruptMap(_))));
+    assert_eq!(map.pixel(0, 0), Some([0; 4]));
+    assert_eq!(map.pixel(MAP_SIZE - 1, MAP_SIZE - 1), None);
+
+    map.colors.extend([0; 2]);
+    assert!(matches!(map.render(), Err(Error::CorruptMap(_))));
+}
+
+#[test]
+fn render_map() {
+    let mut map = MapData::new(0, 0, "minecraft:overworld".to_string());
+    // water in its brightest shade
+    map.colors[MAP_SIZE + 2] = 12 * 4 + 2;
+    let image = map.render().unwrap();
+    assert_eq!(image.dimensions(), (MAP_SIZE as u32, MAP_SIZE as u32));
+    assert_eq!(image.get_pixel(2, 1).0, [0x40, 0x40, 0xFF, 0xFF]);
+    assert_eq!(map.pixel(2, 1), Some([0x40, 0x40, 0xFF, 0xFF]));
+    assert_eq!(map.pixel(MAP_SIZE, 0), None);
+}
+
+// A map of the Nether as written by 1.12, with a byte dimension and the size
+// of its image.
+const LEGACY_MAP: &str = r#"{data: {
+    scale: 3b, dimension: -1b, trackingPosition: 1b, unlimitedTracking: 0b,
+    width: 128s, height: 128s, xCenter: 64, zCenter: -64
+}}"#;
+
+fn field<'a>(value: &'a Value, key: &str) -> &'a Value {
+    match value {
+        Value::Compound(compound) => &compound[key],
+        other => panic!("{:?} is not a compound", other),
+    }
+}
+
+#[test]
+fn legacy_maps_keep_their_dimension_tag() {
+    let path = temp_world_with("map-data-legacy", LEVEL_DAT_1_12);
+    let mut value = coruscant_nbt::snbt::parse(LEGACY_MAP).unwrap();
+    let colors: Vec<i8> = (0..MAP_SIZE * MAP_SIZE).map(|i| (i % 200) as i8).collect();
+    if let Value::Compound(root) = &mut value {
+        if let Some(Value::Compound(data)) = root.get_mut("data") {
+            data.insert("colors".to_owned(), Value::ByteArray(colors.clone()));
+        }
+    }
+    fs::create_dir_all(path.join("data")).unwrap();
+    let file = File::create(path.join("data/map_0.dat")).unwrap();
+    coruscant_nbt::to_gzip_writer(file, &value, Compression::default()).unwrap();
+
+    let world = World::open(&path).unwrap();
+    let map = world.read_map(0).unwrap().unwrap();
+    assert_eq!(map.data_version, None);
+    assert_eq!(map.data.dimension, MapDimension::LegacyId(-1));
+    assert_eq!(map.data.dimension.name(), "minecraft:the_nether");
+    assert_eq!(map.data.width, Some(128));
+    assert_eq!(map.data.colors, colors);
+
+    world.write_map(0, &map).unwrap();
+    let file = File::open(path.join("data/map_0.dat")).unwrap();
+    let written: Value = coruscant_nbt::from_gzip_reader(file).unwrap();
+    let data = field(&written, "data");
+    for key in &["scale", "dimension", "width", "height", "xCenter", "colors"] {
+        assert_eq!(
+            field(data, key),
+            field(field(&value, "data"), key),
+            "{}",
+            key
+        );
+    }
+}
+
+#[test]
+fn dimensions_keep_their_tag() {
+    for (dimension, expected, name) in [
+        ("1b", MapDimension::LegacyId(1), "minecraft:the_end"),
+        ("-1", MapDimension::Id(-1), "minecraft:the_nether"),
+        ("0", MapDimension::Id(0), "minecraft:overworld"),
+        (
+            r#""minecraft:overworld""#,
+            MapDimension::Name("minecraft:overworld".to_string()),
+            "minecraft:overworld",
+        ),
+    ] {
+        let snbt = format!(
+            "{{scale: 0b, dimension: {}, xCenter: 0, zCenter: 0, colors: [B;]}}",
+            dimension
+        );
+        let value = coruscant_nbt::snbt::parse(&snbt).unwrap();
+        let map: MapData = coruscant_nbt::from_value(value.clone()).unwrap();
+        assert_eq!(map.dimension, expected);
+        assert_eq!(map.dimension.name(), name);
+        let written = coruscant_nbt::to_value(&map).unwrap();
+        assert_eq!(field(&written, "dimension"), field(&value, "dimension"));
+    }
+
+    let snbt = "{scale: 0b, dimension: 0L, xCenter: 0, zCenter: 0, colors: [B;]}";
+    let value = coruscant_nbt::snbt::parse(snbt).unwrap();
+    assert!(coruscant_nbt::from_value::<MapData>(value).is_err());
+}
@@ -0,0 +1,55 @@
+use coruscant_world::chunk::BlockState;
+use coruscant_world::palette::{self, Packing, PalettedContainer, MAX_PALETTE_LEN};
+
+fn block(name: &str) -> BlockState {
+    BlockState::new(format!("minecraft:{}", name))
+}
+
+#[test]
+fn positions_outside_the_container_are_refused() {
+    let mut blocks = PalettedContainer::with_size([2, 3, 4], 2, BlockState::air());
+    assert_eq!(blocks.get(1, 2, 3), Some(&BlockState::air()));
+    for &(x, y, z) in &[(2, 0, 0), (0, 3, 0), (0, 0, 4)] {
+        assert_eq!(blocks.get(x, y, z), None);
+        assert_eq!(blocks.set(x, y, z, block("stone")), None);
+    }
+    assert_eq!(blocks.palette(), &[BlockState::air()]);
+
+    assert_eq!(blocks.set(1, 2, 3, block("stone")), Some(BlockState::air()));
+    assert_eq!(blocks.set(1, 2, 3, block("dirt")), Some(block("stone")));
+    assert_eq!(blocks.get(1, 2, 3), Some(&block("dirt")));
+}
+
+#[test]
+fn palettes_are_bounded() {
+    // a row of MAX_PALETTE_LEN + 1 positions, holding every value but the last
+    let len = MAX_PALETTE_LEN + 1;
+    let indices: Vec<u16> = (0..len).map(|x| (x % MAX_PALETTE_LEN) as u16).collect();
+    let data = palette::pack(&indices, 16, Packing::Aligned);
+    let values = (0..MAX_PALETTE_LEN as u32).collect();
+    let size = [len, 1, 1];
+    let mut ids =
+        PalettedContainer::from_packed_with_size(size, 1, values, Some(&data), Packing::Aligned)
+            .unwrap();
+    assert_eq!(ids.palette().len(), MAX_PALETTE_LEN);
+    assert_eq!(ids.bits(), 16);
+    assert_eq!(ids.set(MAX_PALETTE_LEN, 0, 0, MAX_PALETTE_LEN as u32), None);
+    assert_eq!(ids.get(MAX_PALETTE_LEN, 0, 0), Some(&0));
+    // values already in the palette can still be set
+    assert_eq!(ids.set(MAX_PALETTE_LEN, 0, 0, 1), Some(0));
+
+    // unused values are dropped to make room
+    assert_eq!(ids.set(2, 0, 0, 0), Some(2));
+    assert_eq!(ids.set(0, 0, 0, u32::MAX), Some(0));
+    assert_eq!(ids.get(0, 0, 0), Some(&u32::MAX));
+    assert_eq!(ids.get(2, 0, 0), Some(&0));
+    assert_eq!(ids.get(3, 0, 0), Some(&3));
+    assert_eq!(ids.palette().len(), MAX_PALETTE_LEN);
+
+    let (values, data) = ids.to_packed(Packing::Aligned);
+    let data = data.as_deref();
+    let read =
+        PalettedContainer::from_packed_with_size(size, 1, values, data, Packing::Aligned).unwrap();
+    assert_eq!(read.get(0, 0, 0), Some(&u32::MAX));
+    assert_eq!(read.get(MAX_PALETTE_LEN, 0, 0), Some(&1));
+}
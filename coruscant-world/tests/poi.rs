@@ -0,0 +1,138 @@
+use coruscant_world::chunk::{BlockStates, Chunk, Section};
+use coruscant_world::poi::{PoiChunk, PoiRecord};
+use coruscant_world::region::RegionFile;
+use coruscant_world::world::{ChunkPos, OVERWORLD};
+use coruscant_world::{BlockPos, BlockState, World};
+use std::fs;
+
+mod common;
+
+use common::temp_world;
+
+fn block(name: &str, properties: &[(&str, &str)]) -> BlockState {
+    let mut state = BlockState::new(format!("minecraft:{}", name));
+    for (name, value) in properties {
+        state = state.with_property(name, value);
+    }
+    state
+}
+
+fn record(poi_type: &str, pos: BlockPos) -> PoiRecord {
+    PoiRecord::new(format!("minecraft:{}", poi_type), pos, 1)
+}
+
+// A 1.20.4 world with a chunk at 0, 0 holding a lectern and a bed, and
+// points of interest for them and for blocks that are gone: in that chunk,
+// in a chunk of the same region that is not generated, and in a chunk of
+// another region.
+fn world_with_poi(name: &str) -> std::path::PathBuf {
+    let path = temp_world(name);
+    let mut chunk = Chunk::new(3700, 0, -4, 0, "minecraft:full".to_string());
+    let mut section = Section::new(-4);
+    let bedrock = block("bedrock", &[]);
+    section.block_states = Some(BlockStates::new(vec![bedrock], None));
+    chunk.sections.push(section);
+    fs::create_dir_all(path.join("region")).unwrap();
+    let mut region = RegionFile::create(path.join("region/r.0.0.mca")).unwrap();
+    region.write_chunk(0, 0, &chunk).unwrap();
+
+    let mut world = World::open(&path).unwrap();
+    let blocks = [
+        (
+            BlockPos::new(1, -60, 1),
+            block("lectern", &[("facing", "north")]),
+        ),
+        (
+            BlockPos::new(2, -60, 2),
+            block("red_bed", &[("part", "head")]),
+        ),
+        (
+            BlockPos::new(2, -60, 3),
+            block("red_bed", &[("part", "foot")]),
+        ),
+        (BlockPos::new(4, -60, 4), block("stone", &[])),
+    ];
+    for (pos, state) in blocks.iter() {
+        world.set_block(OVERWORLD, *pos, state.clone()).unwrap();
+    }
+    world.flush().unwrap();
+
+    let mut poi = PoiChunk::new(3700);
+    poi.insert(record("librarian", BlockPos::new(1, -60, 1)));
+    poi.insert(record("home", BlockPos::new(2, -60, 2)));
+    // the foot of a bed, and a bell that was mined
+    poi.insert(record("home", BlockPos::new(2, -60, 3)));
+    poi.insert(record("meeting", BlockPos::new(4, -60, 4)));
+    // types that are not vanilla are left alone
+    poi.insert(PoiRecord::new(
+        "mymod:altar".to_string(),
+        BlockPos::new(5, 10, 5),
+        1,
+    ));
+    world
+        .write_poi(OVERWORLD, ChunkPos::new(0, 0), &poi)
+        .unwrap();
+
+    let mut poi = PoiChunk::new(3700);
+    poi.insert(record("bee_nest", BlockPos::new(80, 70, 90)));
+    world
+        .write_poi(OVERWORLD, ChunkPos::new(5, 5), &poi)
+        .unwrap();
+    let mut poi = PoiChunk::new(3700);
+    poi.insert(record("nether_portal", BlockPos::new(-3, 64, -20)));
+    world
+        .write_poi(OVERWORLD, ChunkPos::new(-1, -2), &poi)
+        .unwrap();
+    path
+}
+
+fn positions(chunk: Option<PoiChunk>) -> Vec<[i32; 3]> {
+    let mut ans: Vec<_> = chunk
+        .iter()
+        .flat_map(|c| c.records())
+        .map(|r| r.pos)
+        .collect();
+    ans.sort();
+    ans
+}
+
+#[test]
+fn orphaned_poi_are_found_and_purged() {
+    let path = world_with_poi("poi-orphans");
+    let mut world = World::open(&path).unwrap();
+    let mut orphans = world.check_poi(OVERWORLD).unwrap();
+    orphans.sort_by_key(|o| o.record.pos);
+    let found: Vec<_> = orphans
+        .iter()
+        .map(|o| (o.record.pos, o.block.clone()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ([-3, 64, -20], None),
+            ([2, -60, 3], Some(block("red_bed", &[("part", "foot")]))),
+            ([4, -60, 4], Some(block("stone", &[]))),
+            ([80, 70, 90], None),
+        ]
+    );
+    // checking changes nothing
+    let kept = positions(world.read_poi(OVERWORLD, ChunkPos::new(0, 0)).unwrap());
+    assert_eq!(kept.len(), 5);
+
+    let mut purged = world.purge_poi(OVERWORLD).unwrap();
+    purged.sort_by_key(|o| o.record.pos);
+    assert_eq!(purged, orphans);
+    assert!(world.check_poi(OVERWORLD).unwrap().is_empty());
+    drop(world);
+
+    let world = World::open(&path).unwrap();
+    assert_eq!(
+        positions(world.read_poi(OVERWORLD, ChunkPos::new(0, 0)).unwrap()),
+        [[1, -60, 1], [2, -60, 2], [5, 10, 5]]
+    );
+    for pos in &[ChunkPos::new(5, 5), ChunkPos::new(-1, -2)] {
+        let chunk = world.read_poi(OVERWORLD, *pos).unwrap();
+        assert!(chunk.is_some(), "{:?} was removed", pos);
+        assert!(positions(chunk).is_empty(), "{:?} was not purged", pos);
+    }
+}
@@ -0,0 +1,130 @@
+use coruscant_nbt::Value;
+use coruscant_world::region::{CompressionScheme, RegionFile};
+use coruscant_world::Error;
+use std::sync::Arc;
+use std::{fs, thread};
+
+mod common;
+
+use common::temp_dir;
+
+fn chunk(x: i32, z: i32) -> Value {
+    let filler = "stone,".repeat(((x + z) as usize % 7) * 500);
+    let snbt = format!(
+        "{{xPos:{},zPos:{},Status:\"full\",Filler:\"{}\"}}",
+        x, z, filler
+    );
+    coruscant_nbt::snbt::parse(&snbt).unwrap()
+}
+
+#[test]
+fn reads_from_many_threads() {
+    let dir = temp_dir("region-threads");
+    let path = dir.join("r.0.0.mca");
+    let mut region = RegionFile::create(&path).unwrap();
+    for x in 0..32 {
+        for z in 0..4 {
+            region.write_chunk(x, z, &chunk(x, z)).unwrap();
+        }
+    }
+    let region = Arc::new(RegionFile::open_read_only(&path).unwrap());
+    let threads: Vec<_> = (0..8)
+        .map(|n| {
+            let region = Arc::clone(&region);
+            thread::spawn(move || {
+                for _ in 0..20 {
+                    for x in 0..32 {
+                        let z = (x + n) % 4;
+                        let read: Option<Value> = region.read_chunk(x, z).unwrap();
+                        assert_eq!(read, Some(chunk(x, z)));
+                    }
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+#[test]
+fn read_only_region_refuses_writes() {
+    let dir = temp_dir("region-read-only");
+    let path = dir.join("r.0.0.mca");
+    RegionFile::create(&path)
+        .unwrap()
+        .write_chunk(1, 2, &chunk(1, 2))
+        .unwrap();
+    let before = fs::read(&path).unwrap();
+
+    let mut region = RegionFile::open_read_only(&path).unwrap();
+    assert_eq!(region.read_chunk::<Value>(1, 2).unwrap(), Some(chunk(1, 2)));
+    assert!(matches!(
+        region.write_chunk(3, 3, &chunk(3, 3)),
+        Err(Error::ReadOnlyRegion)
+    ));
+    assert!(matches!(
+        region.remove_chunk(1, 2),
+        Err(Error::ReadOnlyRegion)
+    ));
+    assert!(matches!(
+        region.retain_chunks(|_, _| false),
+        Err(Error::ReadOnlyRegion)
+    ));
+    assert_eq!(fs::read(&path).unwrap(), before);
+}
+
+#[test]
+fn read_only_open_leaves_empty_file_alone() {
+    let dir = temp_dir("region-read-only-empty");
+    let path = dir.join("r.0.0.mca");
+    fs::write(&path, b"").unwrap();
+    let region = RegionFile::open_read_only(&path).unwrap();
+    assert!(region.is_empty());
+    assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+}
+
+#[test]
+fn failed_rewrite_removes_temporary_file() {
+    let dir = temp_dir("region-failed-rewrite");
+    let path = dir.join("r.0.0.mca");
+    let mut region = RegionFile::create(&path).unwrap();
+    region.write_chunk(0, 0, &chunk(0, 0)).unwrap();
+    region.write_chunk(5, 0, &chunk(5, 0)).unwrap();
+    drop(region);
+    // point chunk 5, 0 far past the end of the file
+    let mut bytes = fs::read(&path).unwrap();
+    bytes[20..24].copy_from_slice(&[0, 0x10, 0, 1]);
+    fs::write(&path, &bytes).unwrap();
+
+    let mut region = RegionFile::open(&path).unwrap();
+    assert!(matches!(region.compact(), Err(Error::CorruptRegion(_))));
+    assert!(!dir.join("r.0.0.mca.tmp").exists());
+    assert_eq!(fs::read(&path).unwrap(), bytes);
+    assert_eq!(region.read_chunk::<Value>(0, 0).unwrap(), Some(chunk(0, 0)));
+}
+
+#[test]
+fn external_chunks_are_replaced() {
+    let dir = temp_dir("region-external");
+    let mut region = RegionFile::create(dir.join("r.-1.2.mca")).unwrap();
+    let external = dir.join("c.-31.64.mcc");
+    // more than 255 sectors of 4 KiB do not fit in the region file
+    let large: Vec<u8> = (0..256 * 4096).map(|i| (i * 7 % 251) as u8).collect();
+    region
+        .write_chunk_raw(1, 0, CompressionScheme::Uncompressed, &large)
+        .unwrap();
+    assert_eq!(fs::read(&external).unwrap(), large);
+    let read = region.read_chunk_raw(1, 0).unwrap();
+    assert_eq!(read, Some((CompressionScheme::Uncompressed, large)));
+
+    region
+        .write_chunk_raw(1, 0, CompressionScheme::Uncompressed, b"small")
+        .unwrap();
+    assert!(!external.exists());
+    let read = region.read_chunk_raw(1, 0).unwrap();
+    assert_eq!(
+        read,
+        Some((CompressionScheme::Uncompressed, b"small".to_vec()))
+    );
+}
@@ -0,0 +1,201 @@
+use coruscant_nbt::{Compression, Value};
+use coruscant_world::raids::{self, Raids, DATA_VERSION_RAIDS_CODEC};
+use coruscant_world::saved_data::{ForcedChunks, SavedData};
+use coruscant_world::scoreboard::{text_component, DisplaySlot, Scoreboard, Team};
+use coruscant_world::uuid::Uuid;
+use coruscant_world::world::{OVERWORLD, THE_NETHER};
+use coruscant_world::{ChunkPos, World};
+use serde::de::DeserializeOwned;
+use std::fs::{self, File};
+use std::path::Path;
+
+mod common;
+
+use common::temp_world;
+
+// Write a saved data file from SNBT, read it with `World::read_data`, write
+// it back with `World::write_data` and check that nothing changed.
+fn round_trip<T>(path: &Path, dimension: &str, name: &str, snbt: &str) -> SavedData<T>
+where
+    T: DeserializeOwned + serde::Serialize,
+{
+    let value = coruscant_nbt::snbt::parse(snbt).unwrap();
+    let world = World::open(path).unwrap();
+    let dir = world.dimension(dimension).unwrap().data_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let file = File::create(dir.join(format!("{}.dat", name))).unwrap();
+    coruscant_nbt::to_gzip_writer(file, &value, Compression::default()).unwrap();
+
+    let data: SavedData<T> = world.read_data(dimension, name).unwrap().unwrap();
+    world.write_data(dimension, name, &data).unwrap();
+    let file = File::open(dir.join(format!("{}.dat", name))).unwrap();
+    let written: Value = coruscant_nbt::from_gzip_reader(file).unwrap();
+    assert_eq!(written, value);
+    data
+}
+
+// Raids of the Nether as written by 1.21.4.
+const LEGACY_RAIDS: &str = r#"{DataVersion: 4189, data: {
+    Raids: [{
+        Id: 1, Started: 1b, Active: 1b, TicksActive: 300L, BadOmenLevel: 2,
+        GroupsSpawned: 1, PreRaidTicks: 0, PostRaidTicks: 0, TotalHealth: 80.0f,
+        NumGroups: 3, Status: "ongoing", CX: 10, CY: 64, CZ: -5,
+        HeroesOfTheVillage: [[I; 1, 2, 3, 4]]
+    }],
+    NextAvailableID: 2, Tick: 1200
+}}"#;
+
+// The same raids as written by 1.21.5.
+const CURRENT_RAIDS: &str = r#"{DataVersion: 4325, data: {
+    raids: [{
+        id: 1, started: 1b, active: 1b, ticks_active: 300L, raid_omen_level: 2,
+        groups_spawned: 1, cooldown_ticks: 0, post_raid_ticks: 0, total_health: 80.0f,
+        group_count: 3, status: "ongoing", heroes_of_the_village: [[I; 1, 2, 3, 4]],
+        center: [I; 10, 64, -5]
+    }],
+    next_id: 2, tick: 1200
+}}"#;
+
+#[test]
+fn raids_round_trip_in_both_layouts() {
+    let path = temp_world("saved-data-raids");
+    fs::create_dir_all(path.join("DIM-1/region")).unwrap();
+    let name = raids::file_name(THE_NETHER);
+    assert_eq!(name, "raids_nether");
+
+    let legacy: SavedData<Raids> = round_trip(&path, THE_NETHER, name, LEGACY_RAIDS);
+    assert!(path.join("DIM-1/data/raids_nether.dat").exists());
+    let hero = Uuid::from_ints([1, 2, 3, 4]);
+    match &legacy.data {
+        Raids::Legacy(raids) => {
+            assert_eq!(raids.raids[0].bad_omen_level, 2);
+            assert_eq!(raids.raids[0].heroes_of_the_village, [hero]);
+        }
+        other => panic!("read as {:?}", other),
+    }
+    let current: SavedData<Raids> = round_trip(&path, THE_NETHER, name, CURRENT_RAIDS);
+    match &current.data {
+        Raids::Current(raids) => {
+            assert_eq!(raids.raids[0].raid_omen_level, 2);
+            assert_eq!(raids.raids[0].heroes_of_the_village, [hero]);
+        }
+        other => panic!("read as {:?}", other),
+    }
+    for raids in &[&legacy.data, &current.data] {
+        assert_eq!(raids.len(), 1);
+        assert_eq!(raids.next_id(), 2);
+        assert_eq!(raids.centers(), [[10, 64, -5]]);
+    }
+
+    let mut cleared = current.data.clone();
+    cleared.clear();
+    assert!(cleared.is_empty());
+    assert!(matches!(cleared, Raids::Current(_)));
+    // files without raids are read in the layout of their field names
+    let read = |snbt| -> Raids {
+        coruscant_nbt::from_value(coruscant_nbt::snbt::parse(snbt).unwrap()).unwrap()
+    };
+    let empty = Raids::new(DATA_VERSION_RAIDS_CODEC);
+    assert_eq!(read("{next_id: 0, tick: 0}"), empty);
+    let empty = Raids::new(DATA_VERSION_RAIDS_CODEC - 1);
+    assert_eq!(read("{Raids: [], NextAvailableID: 0, Tick: 0}"), empty);
+}
+
+// A scoreboard as written by 1.20.1, with JSON text components and
+// numbered display slots.
+const LEGACY_SCOREBOARD: &str = r#"{DataVersion: 3465, data: {
+    Objectives: [{Name: "kills", CriteriaName: "playerKillCount",
+        DisplayName: '{"text":"Kills"}', RenderType: "integer"}],
+    PlayerScores: [{Name: "Steve", Objective: "kills", Score: 3, Locked: 0b}],
+    Teams: [{Name: "red", DisplayName: '{"text":"Red"}', TeamColor: "red",
+        AllowFriendlyFire: 1b, SeeFriendlyInvisibles: 1b, NameTagVisibility: "always",
+        DeathMessageVisibility: "always", CollisionRule: "always",
+        MemberNamePrefix: '{"text":"[R] "}', MemberNameSuffix: '""', Players: ["Steve"]}],
+    DisplaySlots: {slot_1: "kills", slot_15: "kills"}
+}}"#;
+
+// A scoreboard as written by 1.21.5, with text components as NBT and named
+// display slots.
+const CURRENT_SCOREBOARD: &str = r#"{DataVersion: 4325, data: {
+    Objectives: [{Name: "kills", CriteriaName: "playerKillCount",
+        DisplayName: {text: "Kills"}, RenderType: "integer", display_auto_update: 0b,
+        format: {type: "minecraft:blank"}}],
+    PlayerScores: [{Name: "Steve", Objective: "kills", Score: 3, Locked: 1b,
+        display: {text: "Steve", color: "gold"}}],
+    Teams: [{Name: "red", DisplayName: {text: "Red"}, TeamColor: "red",
+        AllowFriendlyFire: 0b, SeeFriendlyInvisibles: 1b, NameTagVisibility: "never",
+        DeathMessageVisibility: "always", CollisionRule: "pushOwnTeam",
+        MemberNamePrefix: {text: "[R] "}, MemberNameSuffix: "", Players: ["Steve"]}],
+    DisplaySlots: {sidebar: "kills", "sidebar.team.red": "kills"}
+}}"#;
+
+#[test]
+fn scoreboards_round_trip_in_both_layouts() {
+    let path = temp_world("saved-data-scoreboard");
+    let legacy: SavedData<Scoreboard> =
+        round_trip(&path, OVERWORLD, "scoreboard", LEGACY_SCOREBOARD);
+    let current: SavedData<Scoreboard> =
+        round_trip(&path, OVERWORLD, "scoreboard", CURRENT_SCOREBOARD);
+
+    for (scoreboard, version) in &[(&legacy, 3465), (&current, 4325)] {
+        assert_eq!(scoreboard.data_version, Some(*version));
+        let scoreboard = &scoreboard.data;
+        assert_eq!(scoreboard.score("Steve", "kills"), Some(3));
+        assert_eq!(
+            scoreboard.objectives[0].display_name,
+            text_component("Kills", *version)
+        );
+        let mut slots: Vec<_> = (scoreboard.display_slots.keys())
+            .map(|key| DisplaySlot::from_key(key).unwrap())
+            .collect();
+        slots.sort_by_key(|slot| DisplaySlot::key(*slot, *version));
+        assert_eq!(slots, [DisplaySlot::Sidebar, DisplaySlot::SidebarTeam(12)]);
+        for slot in slots {
+            assert!(scoreboard.display_slots.contains_key(&slot.key(*version)));
+        }
+    }
+    let team = &current.data.teams[0];
+    assert!(!team.allow_friendly_fire);
+    assert_eq!(team.collision_rule, "pushOwnTeam");
+    assert_eq!(team.member_name_suffix, Some(Value::String(String::new())));
+    assert!(current.data.player_scores[0].locked);
+
+    // new teams and objectives get the text components of their version
+    let team = Team::new("blue", "Blue \"team\"", 3465);
+    assert_eq!(
+        team.display_name,
+        Value::String(r#"{"text":"Blue \"team\""}"#.to_owned())
+    );
+    let team = Team::new("blue", "Blue", 4325);
+    assert_eq!(team.display_name, text_component("Blue", 4325));
+    assert!(matches!(team.display_name, Value::Compound(_)));
+
+    let mut scoreboard = legacy.data;
+    scoreboard.remove_objective("kills");
+    assert!(scoreboard.objectives.is_empty());
+    assert!(scoreboard.player_scores.is_empty());
+    assert!(scoreboard.display_slots.is_empty());
+}
+
+#[test]
+fn forced_chunks_round_trip_in_both_layouts() {
+    let path = temp_world("saved-data-forced-chunks");
+    // -1, 2 packed into a long, as written by 1.21.4
+    let legacy = "{DataVersion: 4189, data: {Forced: [L; 12884901887L]}}";
+    let legacy: SavedData<ForcedChunks> = round_trip(&path, OVERWORLD, "chunks", legacy);
+    assert_eq!(legacy.data.forced_chunks(), [ChunkPos::new(-1, 2)]);
+
+    // the same chunk among other tickets, as written by 1.21.5
+    let current = r#"{DataVersion: 4325, data: {tickets: [
+        {type: "minecraft:portal", chunk_pos: 0L, level: 30, ticks_left: 300L},
+        {type: "minecraft:forced", chunk_pos: 12884901887L, level: 31}
+    ]}}"#;
+    let current: SavedData<ForcedChunks> = round_trip(&path, OVERWORLD, "chunks", current);
+    assert_eq!(current.data.forced_chunks(), [ChunkPos::new(-1, 2)]);
+
+    let mut forced = current.data;
+    forced.set_forced(vec![ChunkPos::new(3, -4)]);
+    assert_eq!(forced.forced_chunks(), [ChunkPos::new(3, -4)]);
+    assert_eq!(forced.tickets.as_ref().map(Vec::len), Some(2));
+    assert_eq!(forced.forced, None);
+}
@@ -0,0 +1,80 @@
+use coruscant_world::schematic::Schematic;
+use coruscant_world::structure::Structure;
+use coruscant_world::{BlockState, Error};
+
+#[test]
+fn set_block_outside_changes_nothing() {
+    let mut schematic = Schematic::new(3465, [2, 2, 2]);
+    let before = schematic.clone();
+    let stone = BlockState::new("minecraft:stone".to_string());
+    for pos in [[2, 0, 0], [0, -1, 0], [0, 0, 5]] {
+        assert_eq!(schematic.set_block(pos, stone.clone()), None);
+    }
+    assert_eq!(schematic, before);
+    assert_eq!(
+        schematic.set_block([1, 1, 1], stone.clone()),
+        Some(BlockState::air())
+    );
+    assert_eq!(schematic.set_block([1, 1, 1], stone.clone()), Some(stone));
+}
+
+#[test]
+fn set_block_in_inconsistent_schematic_changes_nothing() {
+    let mut schematic = Schematic::new(3465, [2, 2, 2]);
+    schematic.blocks.truncate(3);
+    schematic.blocks[0] = 7;
+    let before = schematic.clone();
+    let stone = BlockState::new("minecraft:stone".to_string());
+    assert_eq!(schematic.set_block([1, 1, 1], stone.clone()), None);
+    assert_eq!(schematic.set_block([0, 0, 0], stone), None);
+    assert_eq!(schematic, before);
+    assert!(matches!(
+        schematic.to_structure(),
+        Err(Error::CorruptSchematic(_))
+    ));
+}
+
+#[test]
+fn structure_round_trip() {
+    let mut schematic = Schematic::new(3465, [3, 2, 1]);
+    let stone = BlockState::new("minecraft:stone".to_string());
+    schematic.set_block([2, 1, 0], stone.clone()).unwrap();
+    let structure: Structure = schematic.to_structure().unwrap();
+    let read = Schematic::from_structure(&structure);
+    assert_eq!(read.block([2, 1, 0]), Some(&stone));
+    assert_eq!(read.block([0, 0, 0]), Some(&BlockState::air()));
+}
+
+#[test]
+fn hostile_sizes_are_refused_before_allocating() {
+    let headers = [
+        // Sponge 2 with far fewer blocks than its size
+        r#"{Version: 2, Width: -1s, Height: -1s, Length: -1s,
+            Palette: {"minecraft:air": 0}, BlockData: [B; 0b, 0b]}"#,
+        // Sponge 3 of only air, too large to fit in memory
+        r#"{Schematic: {Version: 3, Width: -1s, Height: -1s, Length: -1s}}"#,
+        // MCEdit with mismatched block arrays
+        r#"{Materials: "Alpha", Width: -1s, Height: -1s, Length: -1s,
+            Blocks: [B; 1b], Data: [B; 0b]}"#,
+    ];
+    for header in &headers {
+        let value = coruscant_nbt::from_str_snbt(header).unwrap();
+        assert!(matches!(
+            Schematic::from_value(&value),
+            Err(Error::CorruptSchematic(_))
+        ));
+    }
+}
+
+#[test]
+fn palette_indices_past_the_entries_are_refused() {
+    let value = coruscant_nbt::from_str_snbt(
+        r#"{Version: 2, Width: 1s, Height: 1s, Length: 1s,
+            Palette: {"minecraft:stone": 2000000000}, BlockData: [B; 0b]}"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        Schematic::from_value(&value),
+        Err(Error::CorruptSchematic("palette index out of range"))
+    ));
+}
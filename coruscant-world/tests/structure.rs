@@ -0,0 +1,305 @@
+use coruscant_nbt::Value;
+use coruscant_world::chunk::{BlockStates, Chunk, Section};
+use coruscant_world::entity::Entity;
+use coruscant_world::region::RegionFile;
+use coruscant_world::structure::{
+    transform_block_state, Mirror, Rotation, Structure, StructureEntity,
+};
+use coruscant_world::world::{ChunkPos, OVERWORLD};
+use coruscant_world::{BlockPos, BlockState, World};
+use std::fs;
+
+mod common;
+
+use common::temp_world;
+
+const ROTATIONS: [Rotation; 4] = [
+    Rotation::None,
+    Rotation::Clockwise90,
+    Rotation::Clockwise180,
+    Rotation::Counterclockwise90,
+];
+
+fn snbt(snbt: &str) -> Value {
+    coruscant_nbt::snbt::parse(snbt).unwrap()
+}
+
+fn block(name: &str, properties: &[(&str, &str)]) -> BlockState {
+    let mut state = BlockState::new(format!("minecraft:{}", name));
+    for (name, value) in properties {
+        state = state.with_property(name, value);
+    }
+    state
+}
+
+fn transform(state: &BlockState, mirror: Mirror, rotation: Rotation) -> BlockState {
+    transform_block_state(state, mirror, rotation)
+}
+
+#[test]
+fn block_states_are_turned() {
+    let stairs = block(
+        "oak_stairs",
+        &[("facing", "north"), ("shape", "outer_left")],
+    );
+    assert_eq!(
+        transform(&stairs, Mirror::None, Rotation::Clockwise90),
+        block("oak_stairs", &[("facing", "east"), ("shape", "outer_left")])
+    );
+    // the shape flips when mirrored along the facing, and only then
+    assert_eq!(
+        transform(&stairs, Mirror::LeftRight, Rotation::None),
+        block(
+            "oak_stairs",
+            &[("facing", "south"), ("shape", "outer_right")]
+        )
+    );
+    assert_eq!(
+        transform(&stairs, Mirror::FrontBack, Rotation::None),
+        stairs
+    );
+
+    let rail = block("rail", &[("shape", "north_east")]);
+    assert_eq!(
+        transform(&rail, Mirror::None, Rotation::Clockwise90),
+        block("rail", &[("shape", "south_east")])
+    );
+    let rail = block("rail", &[("shape", "ascending_west")]);
+    assert_eq!(
+        transform(&rail, Mirror::FrontBack, Rotation::Clockwise180),
+        block("rail", &[("shape", "ascending_west")])
+    );
+
+    // 0 faces south and 4 west
+    let sign = block("oak_sign", &[("rotation", "0")]);
+    assert_eq!(
+        transform(&sign, Mirror::None, Rotation::Clockwise90),
+        block("oak_sign", &[("rotation", "4")])
+    );
+    let sign = block("oak_sign", &[("rotation", "3")]);
+    assert_eq!(
+        transform(&sign, Mirror::LeftRight, Rotation::None),
+        block("oak_sign", &[("rotation", "5")])
+    );
+
+    let log = block("oak_log", &[("axis", "x")]);
+    assert_eq!(
+        transform(&log, Mirror::None, Rotation::Counterclockwise90),
+        block("oak_log", &[("axis", "z")])
+    );
+    assert_eq!(
+        transform(&log, Mirror::FrontBack, Rotation::Clockwise180),
+        log
+    );
+
+    let fence = block("oak_fence", &[("north", "true"), ("east", "false")]);
+    assert_eq!(
+        transform(&fence, Mirror::None, Rotation::Clockwise90),
+        block("oak_fence", &[("east", "true"), ("south", "false")])
+    );
+
+    let door = block("oak_door", &[("facing", "east"), ("hinge", "left")]);
+    assert_eq!(
+        transform(&door, Mirror::LeftRight, Rotation::None),
+        block("oak_door", &[("facing", "east"), ("hinge", "right")])
+    );
+    let chest = block("chest", &[("facing", "west"), ("type", "left")]);
+    assert_eq!(
+        transform(&chest, Mirror::FrontBack, Rotation::None),
+        block("chest", &[("facing", "east"), ("type", "right")])
+    );
+
+    let crafter = block("crafter", &[("orientation", "up_north")]);
+    assert_eq!(
+        transform(&crafter, Mirror::None, Rotation::Clockwise180),
+        block("crafter", &[("orientation", "up_south")])
+    );
+}
+
+// A structure of 3 by 1 by 2 blocks with stairs in one corner, a torch in
+// the opposite one, and a sheep facing south.
+fn corner_structure() -> Structure {
+    let mut structure = Structure::new(3700, [3, 1, 2]);
+    structure.set_block([0, 0, 0], block("oak_stairs", &[("facing", "north")]), None);
+    structure.set_block([2, 0, 1], block("wall_torch", &[("facing", "east")]), None);
+    let sheep = snbt(
+        r#"{id: "minecraft:sheep", Pos: [0.5d, 0.0d, 0.5d], Rotation: [0.0f, 0.0f],
+            Health: 8.0f}"#,
+    );
+    let sheep = StructureEntity::new([0.5, 0.0, 0.5], Entity::from_value(sheep));
+    structure.entities.push(sheep);
+    structure
+}
+
+fn blocks(structure: &Structure) -> Vec<([i32; 3], BlockState)> {
+    let mut ans: Vec<_> = structure
+        .blocks()
+        .map(|(pos, state, _)| (pos, state.clone()))
+        .collect();
+    ans.sort_by_key(|(pos, _)| *pos);
+    ans
+}
+
+fn yaw(entity: &StructureEntity) -> f32 {
+    entity.nbt.entity().unwrap().rotation[0]
+}
+
+#[test]
+fn structures_are_turned() {
+    let structure = corner_structure();
+
+    let rotated = structure.transformed(Mirror::None, Rotation::Clockwise90);
+    assert_eq!(rotated.size, [2, 1, 3]);
+    assert_eq!(
+        blocks(&rotated),
+        [
+            ([0, 0, 2], block("wall_torch", &[("facing", "south")])),
+            ([1, 0, 0], block("oak_stairs", &[("facing", "east")])),
+        ]
+    );
+    // south turns west, at 90 degrees
+    assert_eq!(rotated.entities[0].pos, [1.5, 0.0, 0.5]);
+    assert_eq!(rotated.entities[0].block_pos, [1, 0, 0]);
+    assert_eq!(yaw(&rotated.entities[0]), 90.0);
+
+    let mirrored = structure.transformed(Mirror::LeftRight, Rotation::None);
+    assert_eq!(mirrored.size, [3, 1, 2]);
+    assert_eq!(
+        blocks(&mirrored),
+        [
+            ([0, 0, 1], block("oak_stairs", &[("facing", "south")])),
+            ([2, 0, 0], block("wall_torch", &[("facing", "east")])),
+        ]
+    );
+    assert_eq!(mirrored.entities[0].pos, [0.5, 0.0, 1.5]);
+    assert_eq!(yaw(&mirrored.entities[0]), 180.0);
+
+    // flipping east and west and turning halfway flips north and south
+    let turned = structure.transformed(Mirror::FrontBack, Rotation::Clockwise180);
+    assert_eq!(blocks(&turned), blocks(&mirrored));
+    assert_eq!(turned.entities, mirrored.entities);
+}
+
+#[test]
+fn transforms_undo_each_other() {
+    let structure = corner_structure();
+    for &rotation in &ROTATIONS {
+        let mut turned = structure.clone();
+        for _ in 0..4 {
+            turned = turned.transformed(Mirror::None, rotation);
+        }
+        assert_eq!(turned, structure, "{:?}", rotation);
+    }
+    for &mirror in &[Mirror::LeftRight, Mirror::FrontBack] {
+        let twice = structure
+            .transformed(mirror, Rotation::None)
+            .transformed(mirror, Rotation::None);
+        assert_eq!(twice, structure, "{:?}", mirror);
+    }
+}
+
+#[test]
+fn unknown_entities_are_turned() {
+    let mut structure = Structure::new(3700, [1, 1, 1]);
+    let bat = snbt(r#"{id: "minecraft:bat", Pos: [0.5d, 0.0d, 0.5d], Rotation: [45.0f, 0.0f]}"#);
+    let bat = StructureEntity::new([0.5, 0.5, 0.5], Entity::from_value(bat));
+    structure.entities.push(bat);
+    let rotated = structure.transformed(Mirror::None, Rotation::Counterclockwise90);
+    let expected =
+        snbt(r#"{id: "minecraft:bat", Pos: [0.5d, 0.0d, 0.5d], Rotation: [315.0f, 0.0f]}"#);
+    assert_eq!(rotated.entities[0].nbt, Entity::Unknown(expected));
+}
+
+// A 1.20.4 world with a chunk at 0, 0 in the overworld with a bedrock
+// floor.
+fn world_with_chunk(name: &str) -> std::path::PathBuf {
+    let path = temp_world(name);
+    let mut chunk = Chunk::new(3700, 0, -4, 0, "minecraft:full".to_string());
+    let mut section = Section::new(-4);
+    let bedrock = block("bedrock", &[]);
+    section.block_states = Some(BlockStates::new(vec![bedrock], None));
+    chunk.sections.push(section);
+    fs::create_dir_all(path.join("region")).unwrap();
+    let mut region = RegionFile::create(path.join("region/r.0.0.mca")).unwrap();
+    region.write_chunk(0, 0, &chunk).unwrap();
+    path
+}
+
+fn entity_uuid(entity: &Entity) -> u128 {
+    entity.entity().unwrap().uuid.unwrap().as_u128()
+}
+
+#[test]
+fn structures_are_placed_and_saved() {
+    let path = world_with_chunk("structure-place");
+    let mut structure = Structure::new(3700, [2, 1, 1]);
+    structure.set_block([0, 0, 0], block("stone", &[]), None);
+    let items =
+        snbt(r#"{id: "minecraft:chest", Items: [{Slot: 0b, id: "minecraft:apple", count: 1}]}"#);
+    structure.set_block(
+        [1, 0, 0],
+        block("chest", &[("facing", "north")]),
+        Some(items.clone()),
+    );
+    let sheep =
+        snbt(r#"{id: "minecraft:sheep", Pos: [9.0d, 9.0d, 9.0d], Health: 8.0f, Color: 3b}"#);
+    structure.entities.push(StructureEntity::new(
+        [1.5, 0.0, 0.5],
+        Entity::from_value(sheep),
+    ));
+
+    let origin = BlockPos::new(4, -60, 7);
+    let mut world = World::open(&path).unwrap();
+    structure.place(&mut world, OVERWORLD, origin).unwrap();
+    structure.place(&mut world, OVERWORLD, origin).unwrap();
+    world.flush().unwrap();
+    drop(world);
+
+    let mut world = World::open(&path).unwrap();
+    assert_eq!(
+        world
+            .get_block(OVERWORLD, BlockPos::new(4, -60, 7))
+            .unwrap(),
+        Some(block("stone", &[]))
+    );
+    let chest = world
+        .block_entity(OVERWORLD, BlockPos::new(5, -60, 7))
+        .unwrap()
+        .unwrap();
+    assert_eq!(chest.pos(), Some(BlockPos::new(5, -60, 7)));
+    assert_eq!(chest.items().map(|items| items.len()), Some(1));
+
+    // both placements added a sheep, moved to the origin and with new UUIDs
+    let entities = world
+        .read_entities(OVERWORLD, ChunkPos::new(0, 0))
+        .unwrap()
+        .unwrap()
+        .entities;
+    assert_eq!(entities.len(), 2);
+    for entity in &entities {
+        assert_eq!(entity.id(), Some("minecraft:sheep"));
+        assert_eq!(entity.entity().unwrap().pos, [5.5, -60.0, 7.5]);
+        // a version 4 UUID of the variant of RFC 4122
+        let uuid = entity_uuid(entity);
+        assert_eq!(uuid >> 76 & 0xf, 4);
+        assert_eq!(uuid >> 62 & 0x3, 2);
+    }
+    assert_ne!(entity_uuid(&entities[0]), entity_uuid(&entities[1]));
+
+    let saved = Structure::from_world(&mut world, OVERWORLD, origin, [2, 1, 1]).unwrap();
+    assert_eq!(saved.data_version, 3700);
+    assert_eq!(blocks(&saved), blocks(&structure));
+    let nbt: Vec<_> = saved.blocks().map(|(_, _, nbt)| nbt.cloned()).collect();
+    assert_eq!(nbt, [None, Some(items)]);
+    assert_eq!(saved.entities.len(), 2);
+    for entity in &saved.entities {
+        assert_eq!(entity.pos, [1.5, 0.0, 0.5]);
+        assert_eq!(entity.block_pos, [1, 0, 0]);
+        assert_eq!(entity.nbt.entity().unwrap().uuid, None);
+    }
+
+    // entities outside the box are left out
+    let saved = Structure::from_world(&mut world, OVERWORLD, origin, [1, 1, 1]).unwrap();
+    assert_eq!(saved.blocks.len(), 1);
+    assert!(saved.entities.is_empty());
+}
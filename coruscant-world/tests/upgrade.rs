@@ -0,0 +1,718 @@
+use coruscant_nbt::{from_str_snbt, Value};
+use coruscant_world::chunk::{Biomes, BlockStates};
+use coruscant_world::palette::{self, Packing, PalettedContainer};
+use coruscant_world::region::RegionFile;
+use coruscant_world::upgrade::{ChunkContext, DataType, Upgrader};
+use coruscant_world::{BlockState, Error};
+
+mod common;
+
+use common::temp_dir;
+
+fn snbt(snbt: &str) -> Value {
+    from_str_snbt(snbt).unwrap()
+}
+
+// The value at a path of compound keys and list indices, like
+// `sections.0.Y`.
+fn field<'a>(value: &'a Value, path: &str) -> &'a Value {
+    path.split('.').fold(value, |value, key| match value {
+        Value::Compound(compound) => compound
+            .get(key)
+            .unwrap_or_else(|| panic!("no {} in {}", key, path)),
+        Value::List(list) => &list[key.parse::<usize>().unwrap()],
+        _ => panic!("{} is not a compound or list", path),
+    })
+}
+
+fn has(value: &Value, key: &str) -> bool {
+    match value {
+        Value::Compound(compound) => compound.contains_key(key),
+        _ => false,
+    }
+}
+
+fn list(value: &Value) -> &Vec<Value> {
+    match value {
+        Value::List(list) => list,
+        _ => panic!("not a list: {:?}", value),
+    }
+}
+
+fn context(dimension: &str, generator: &str) -> ChunkContext {
+    ChunkContext::new(dimension.to_owned(), generator.to_owned())
+}
+
+fn block(name: &str) -> BlockState {
+    BlockState::new(format!("minecraft:{}", name))
+}
+
+fn blocks(section: &Value) -> PalettedContainer<BlockState> {
+    let states: BlockStates =
+        coruscant_nbt::from_value(field(section, "block_states").clone()).unwrap();
+    PalettedContainer::from_block_states(&states).unwrap()
+}
+
+fn biomes(section: &Value) -> PalettedContainer<String> {
+    let biomes: Biomes = coruscant_nbt::from_value(field(section, "biomes").clone()).unwrap();
+    PalettedContainer::from_biomes(&biomes).unwrap()
+}
+
+// A section of 1.17 with its blocks packed into aligned longs.
+fn section_1_17(y: i8, blocks: &PalettedContainer<BlockState>) -> Value {
+    let (palette, data) = blocks.to_packed(Packing::Aligned);
+    let mut section = snbt(&format!("{{Y: {}b}}", y));
+    if let Value::Compound(section) = &mut section {
+        section.insert(
+            "Palette".to_owned(),
+            coruscant_nbt::to_value(palette).unwrap(),
+        );
+        let data = data.unwrap_or_else(|| palette::pack(&[0; 4096], 4, Packing::Aligned));
+        section.insert("BlockStates".to_owned(), Value::LongArray(data));
+    }
+    section
+}
+
+// A chunk of 1.17.1 with a floor of bedrock at Y 0, missing in columns at
+// X 0, and the given status.
+fn chunk_1_17(status: &str) -> Value {
+    let mut floor = PalettedContainer::blocks(block("stone"));
+    for z in 0..16 {
+        for x in 0..16 {
+            let state = if x == 0 {
+                BlockState::air()
+            } else {
+                block("bedrock")
+            };
+            floor.set(x, 0, z, state);
+        }
+    }
+    let mut chunk = snbt(&format!(
+        r#"{{DataVersion: 2730, Level: {{
+            xPos: 3, zPos: -2, Status: "{}", Biomes: [I; {}],
+            PostProcessing: [{}], TileEntities: [], Entities: [],
+            UpgradeData: {{Indices: {{"0": [I; 5], "15": [I; 7]}}}},
+            CarvingMasks: {{AIR: [B; 1b, 0b, 0b, 0b, 0b, 0b, 0b, 0b, 2b]}},
+            Sections: [{{Y: -1b, SkyLight: [B; 1b]}}]
+        }}}}"#,
+        status,
+        vec!["5"; 1024].join(", "),
+        vec!["[]"; 16].join(", "),
+    ));
+    if let Value::Compound(root) = &mut chunk {
+        if let Some(Value::Compound(level)) = root.get_mut("Level") {
+            if let Some(Value::List(sections)) = level.get_mut("Sections") {
+                sections.push(section_1_17(0, &floor));
+            }
+        }
+    }
+    chunk
+}
+
+#[test]
+fn flattening_turns_block_ids_into_a_palette() {
+    // stone with granite at 1, 0, 0, on an old chunk that was populated
+    let mut blocks = vec!["1b"; 4096];
+    blocks[1] = "1b";
+    let mut data = vec!["0b"; 2048];
+    data[0] = "16b";
+    let mut chunk = snbt(&format!(
+        r#"{{DataVersion: 1343, Level: {{xPos: 0, zPos: 0, TerrainPopulated: 1b,
+            LightPopulated: 1b, HeightMap: [I; 0], Biomes: [B; {}],
+            Sections: [{{Y: 0b, Blocks: [B; {}], Data: [B; {}]}}]}}}}"#,
+        vec!["-127b"; 256].join(", "),
+        blocks.join(", "),
+        data.join(", "),
+    ));
+    let upgrader = Upgrader::vanilla();
+    assert!(upgrader
+        .upgrade_root(DataType::Chunk, &mut chunk, 1631)
+        .unwrap());
+
+    assert_eq!(field(&chunk, "DataVersion"), &Value::Int(1631));
+    let level = field(&chunk, "Level");
+    assert_eq!(
+        field(level, "Status"),
+        &Value::String("postprocessed".into())
+    );
+    assert!(!has(level, "HeightMap") && !has(level, "LightPopulated"));
+    assert_eq!(field(level, "Biomes"), &Value::IntArray(vec![129; 256]));
+    let section = field(level, "Sections.0");
+    assert!(!has(section, "Blocks") && !has(section, "Data"));
+    let palette: Vec<BlockState> =
+        coruscant_nbt::from_value(field(section, "Palette").clone()).unwrap();
+    let data = match field(section, "BlockStates") {
+        Value::LongArray(data) => data.clone(),
+        other => panic!("BlockStates is {:?}", other),
+    };
+    let blocks =
+        PalettedContainer::from_packed(16, 4, palette, Some(&data), Packing::Spanning).unwrap();
+    assert_eq!(blocks.get(0, 0, 0), Some(&block("stone")));
+    assert_eq!(blocks.get(1, 0, 0), Some(&block("granite")));
+    assert_eq!(blocks.get(15, 15, 15), Some(&block("stone")));
+}
+
+#[test]
+fn chunk_statuses_are_renamed() {
+    let mut chunk = snbt(r#"{DataVersion: 1631, Level: {Status: "decorated"}}"#);
+    let upgrader = Upgrader::vanilla();
+    assert!(upgrader
+        .upgrade_root(DataType::Chunk, &mut chunk, 1976)
+        .unwrap());
+    assert_eq!(
+        field(&chunk, "Level.Status"),
+        &Value::String("features".into())
+    );
+}
+
+#[test]
+fn uuids_become_int_arrays() {
+    let mut chunk = snbt(
+        r#"{DataVersion: 2230, Level: {
+            Entities: [{id: "minecraft:cat", UUIDMost: 1L, UUIDLeast: -1L,
+                OwnerUUID: "00000002-0000-0003-0000-000400000005"}],
+            TileEntities: [{id: "minecraft:chest", Items: [{id: "minecraft:player_head",
+                Count: 1b, tag: {SkullOwner: {Id: "00000000-0000-0001-0000-000000000002"}}}]},
+                {id: "minecraft:conduit", target_uuid: {M: 0L, L: 7L}}]
+        }}"#,
+    );
+    let upgrader = Upgrader::vanilla();
+    assert!(upgrader
+        .upgrade_root(DataType::Chunk, &mut chunk, 2566)
+        .unwrap());
+
+    let cat = field(&chunk, "Level.Entities.0");
+    assert_eq!(field(cat, "UUID"), &Value::IntArray(vec![0, 1, -1, -1]));
+    assert_eq!(field(cat, "Owner"), &Value::IntArray(vec![2, 3, 4, 5]));
+    assert!(!has(cat, "UUIDMost") && !has(cat, "OwnerUUID"));
+    let head = field(&chunk, "Level.TileEntities.0.Items.0");
+    let id = field(head, "tag.SkullOwner.Id");
+    assert_eq!(id, &Value::IntArray(vec![0, 1, 0, 2]));
+    let conduit = field(&chunk, "Level.TileEntities.1");
+    assert_eq!(field(conduit, "Target"), &Value::IntArray(vec![0, 0, 0, 7]));
+}
+
+#[test]
+fn packed_indices_are_aligned() {
+    let mut blocks = PalettedContainer::blocks(BlockState::air());
+    for i in 0..20 {
+        blocks.set(i % 16, i / 16, 0, block(&format!("block_{}", i)));
+    }
+    let (palette, data) = blocks.to_packed(Packing::Spanning);
+    let mut chunk = snbt(r#"{DataVersion: 2230, Level: {Sections: [{Y: 0b}]}}"#);
+    if let Value::Compound(section) = &mut chunk {
+        let section = match section.get_mut("Level") {
+            Some(Value::Compound(level)) => match level.get_mut("Sections") {
+                Some(Value::List(sections)) => match &mut sections[0] {
+                    Value::Compound(section) => section,
+                    _ => unreachable!(),
+                },
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        section.insert(
+            "Palette".to_owned(),
+            coruscant_nbt::to_value(&palette).unwrap(),
+        );
+        section.insert("BlockStates".to_owned(), Value::LongArray(data.unwrap()));
+    }
+    let upgrader = Upgrader::vanilla();
+    assert!(upgrader
+        .upgrade_root(DataType::Chunk, &mut chunk, 2586)
+        .unwrap());
+
+    let data = match field(&chunk, "Level.Sections.0.BlockStates") {
+        Value::LongArray(data) => data.clone(),
+        other => panic!("BlockStates is {:?}", other),
+    };
+    // 5 bits for each of 4096 indices, 12 to a long
+    assert_eq!(data.len(), 342);
+    let aligned =
+        PalettedContainer::from_packed(16, 4, palette, Some(&data), Packing::Aligned).unwrap();
+    assert_eq!(aligned, blocks);
+}
+
+#[test]
+fn nether_chunks_keep_their_heights() {
+    let mut chunk = chunk_1_17("full");
+    let nether = context("minecraft:the_nether", "minecraft:noise");
+    assert!(Upgrader::vanilla()
+        .upgrade_chunk(&mut chunk, &nether, 2860)
+        .unwrap());
+
+    assert!(!has(&chunk, "Level") && !has(&chunk, "__context"));
+    assert_eq!(field(&chunk, "yPos"), &Value::Int(0));
+    assert_eq!(field(&chunk, "xPos"), &Value::Int(3));
+    assert_eq!(field(&chunk, "Status"), &Value::String("full".into()));
+    assert!(!has(&chunk, "below_zero_retrogen") && !has(&chunk, "blending_data"));
+    assert_eq!(list(field(&chunk, "PostProcessing")).len(), 16);
+    assert_eq!(
+        field(&chunk, "UpgradeData.Indices.0"),
+        &Value::IntArray(vec![5])
+    );
+    let air = field(&chunk, "CarvingMasks.AIR");
+    assert_eq!(air, &Value::LongArray(vec![1, 2]));
+
+    // every section of the dimension, then the one below holding light
+    let sections = list(field(&chunk, "sections"));
+    assert_eq!(sections.len(), 17);
+    for (y, section) in sections[..16].iter().enumerate() {
+        assert_eq!(field(section, "Y"), &Value::Byte(y as i8));
+        assert_eq!(biomes(section).get(0, 0, 0).unwrap(), "minecraft:taiga");
+    }
+    assert_eq!(blocks(&sections[0]).get(0, 0, 0), Some(&BlockState::air()));
+    assert_eq!(blocks(&sections[0]).get(1, 0, 0), Some(&block("bedrock")));
+    assert_eq!(blocks(&sections[0]).get(1, 1, 0), Some(&block("stone")));
+    assert_eq!(blocks(&sections[15]).palette(), &[BlockState::air()]);
+    assert_eq!(field(&sections[16], "Y"), &Value::Byte(-1));
+    assert!(!has(&sections[16], "block_states"));
+}
+
+#[test]
+fn overworld_chunks_are_extended_below_zero() {
+    let mut chunk = chunk_1_17("full");
+    let overworld = context("minecraft:overworld", "minecraft:noise");
+    assert!(Upgrader::vanilla()
+        .upgrade_chunk(&mut chunk, &overworld, 2860)
+        .unwrap());
+
+    assert_eq!(field(&chunk, "DataVersion"), &Value::Int(2860));
+    assert_eq!(field(&chunk, "yPos"), &Value::Int(-4));
+    assert_eq!(field(&chunk, "isLightOn"), &Value::Byte(0));
+    let sections = list(field(&chunk, "sections"));
+    assert_eq!(sections.len(), 24);
+    for (i, section) in sections.iter().enumerate() {
+        assert_eq!(field(section, "Y"), &Value::Byte(i as i8 - 4));
+        assert_eq!(biomes(section).get(3, 3, 3).unwrap(), "minecraft:taiga");
+    }
+    // the section that held light below the old floor is now filled with air
+    assert!(has(&sections[3], "SkyLight"));
+    assert_eq!(blocks(&sections[3]).palette(), &[BlockState::air()]);
+    assert_eq!(blocks(&sections[4]).get(1, 0, 0), Some(&block("bedrock")));
+
+    // the game generates the terrain below the bedrock floor
+    assert_eq!(field(&chunk, "Status"), &Value::String("empty".into()));
+    let retrogen = field(&chunk, "below_zero_retrogen");
+    let target = field(retrogen, "target_status");
+    assert_eq!(target, &Value::String("heightmaps".into()));
+    let missing = 1 | 1 << 16 | 1 << 32 | 1 << 48;
+    let expected = Value::LongArray(vec![missing; 4]);
+    assert_eq!(field(retrogen, "missing_bedrock"), &expected);
+    assert_eq!(field(&chunk, "blending_data.old_noise"), &Value::Byte(1));
+
+    // lists by section, carving masks and upgrade data gain 4 sections below
+    let post_processing = list(field(&chunk, "PostProcessing"));
+    assert_eq!(post_processing.len(), 24);
+    let indices = field(&chunk, "UpgradeData.Indices");
+    assert_eq!(field(indices, "4"), &Value::IntArray(vec![5]));
+    assert_eq!(field(indices, "19"), &Value::IntArray(vec![7]));
+    assert!(!has(indices, "0"));
+    let mut air = vec![0; 256];
+    air.extend([1, 2]);
+    assert_eq!(field(&chunk, "CarvingMasks.AIR"), &Value::LongArray(air));
+}
+
+#[test]
+fn chunks_without_bedrock_or_of_flat_worlds_are_not_retrogenerated() {
+    let upgrader = Upgrader::vanilla();
+    let flat = context("minecraft:overworld", "minecraft:flat");
+    let mut chunk = chunk_1_17("full");
+    assert!(upgrader.upgrade_chunk(&mut chunk, &flat, 2860).unwrap());
+    assert_eq!(field(&chunk, "yPos"), &Value::Int(-4));
+    assert_eq!(field(&chunk, "Status"), &Value::String("full".into()));
+    assert!(!has(&chunk, "below_zero_retrogen") && !has(&chunk, "blending_data"));
+
+    // before the noise status, there are no blocks to extend yet
+    let overworld = context("minecraft:overworld", "minecraft:noise");
+    let mut chunk = snbt(r#"{DataVersion: 2730, Level: {Status: "biomes", Sections: []}}"#);
+    assert!(upgrader
+        .upgrade_chunk(&mut chunk, &overworld, 2860)
+        .unwrap());
+    assert_eq!(field(&chunk, "Status"), &Value::String("biomes".into()));
+    assert_eq!(field(&chunk, "blending_data.old_noise"), &Value::Byte(0));
+    assert!(!has(&chunk, "below_zero_retrogen"));
+    let sections = list(field(&chunk, "sections"));
+    assert_eq!(sections.len(), 24);
+    assert_eq!(
+        biomes(&sections[0]).palette(),
+        &["minecraft:plains".to_owned()]
+    );
+}
+
+#[test]
+fn item_tags_become_components() {
+    let mut entity = snbt(
+        r#"{DataVersion: 3700, id: "minecraft:zombie",
+            HandItems: [{id: "minecraft:iron_sword", Count: 1b,
+                tag: {Damage: 5, display: {Name: '"Edge"'}, Custom: 1b}}, {}]}"#,
+    );
+    let upgrader = Upgrader::vanilla();
+    assert!(upgrader
+        .upgrade_root(DataType::Entity, &mut entity, 3953)
+        .unwrap());
+    let expected = snbt(
+        r#"{id: "minecraft:iron_sword", count: 1, components: {
+            "minecraft:damage": 5, "minecraft:custom_name": '"Edge"',
+            "minecraft:custom_data": {Custom: 1b}}}"#,
+    );
+    assert_eq!(field(&entity, "HandItems.0"), &expected);
+}
+
+#[test]
+fn version_is_stamped_only_on_upgraded_data() {
+    let upgrader = Upgrader::vanilla();
+    let overworld = context("minecraft:overworld", "minecraft:noise");
+
+    // item IDs of the Flattening are not implemented
+    let chest = r#"{DataVersion: 1343, Level: {Status: "full", TileEntities: [
+        {id: "minecraft:chest", Items: [{id: "minecraft:wool", Damage: 14s, Count: 1b}]}]}}"#;
+    let mut chunk = snbt(chest);
+    let err = upgrader
+        .upgrade_chunk(&mut chunk, &overworld, 3953)
+        .unwrap_err();
+    assert!(matches!(err, Error::UnsupportedUpgrade(1451, _)));
+    assert_eq!(chunk, snbt(chest));
+
+    // nor are block entities merged into block states
+    let bed = r#"{DataVersion: 1343, Level: {TileEntities: [{id: "minecraft:bed", color: 14}]}}"#;
+    let mut chunk = snbt(bed);
+    let err = upgrader
+        .upgrade_chunk(&mut chunk, &overworld, 1631)
+        .unwrap_err();
+    assert!(matches!(err, Error::UnsupportedUpgrade(1451, _)));
+    assert_eq!(chunk, snbt(bed));
+
+    // nor are blocks held by entities
+    let sand = r#"{DataVersion: 1343, id: "minecraft:falling_block", Block: "minecraft:sand"}"#;
+    let mut entity = snbt(sand);
+    let err = upgrader
+        .upgrade_root(DataType::Entity, &mut entity, 1631)
+        .unwrap_err();
+    assert!(matches!(err, Error::UnsupportedUpgrade(1451, _)));
+    assert_eq!(entity, snbt(sand));
+
+    // items already flattened upgrade past 1.13
+    let mut chunk = snbt(
+        r#"{DataVersion: 1631, Level: {Status: "full", TileEntities: [
+            {id: "minecraft:chest", Items: [{id: "minecraft:red_wool", Count: 1b}]}]}}"#,
+    );
+    assert!(upgrader
+        .upgrade_chunk(&mut chunk, &overworld, 3953)
+        .unwrap());
+    assert_eq!(field(&chunk, "DataVersion"), &Value::Int(3953));
+    let item = field(&chunk, "block_entities.0.Items.0");
+    assert_eq!(item, &snbt(r#"{id: "minecraft:red_wool", count: 1}"#));
+
+    // chunks need their dimension to be upgraded past 1.18, not before
+    let old = r#"{DataVersion: 2730, Level: {Status: "full", Sections: []}}"#;
+    let mut chunk = snbt(old);
+    let err = upgrader
+        .upgrade_root(DataType::Chunk, &mut chunk, 2860)
+        .unwrap_err();
+    assert!(matches!(err, Error::UnsupportedUpgrade(2844, _)));
+    assert_eq!(chunk, snbt(old));
+    assert!(upgrader
+        .upgrade_root(DataType::Chunk, &mut chunk, 2800)
+        .unwrap());
+    assert_eq!(field(&chunk, "DataVersion"), &Value::Int(2800));
+
+    // data at the version or newer is left as is
+    let mut chunk = snbt(r#"{DataVersion: 3953, Status: "minecraft:full"}"#);
+    assert!(!upgrader
+        .upgrade_chunk(&mut chunk, &overworld, 3700)
+        .unwrap());
+    assert_eq!(
+        chunk,
+        snbt(r#"{DataVersion: 3953, Status: "minecraft:full"}"#)
+    );
+}
+
+#[test]
+fn region_upgrade_stops_at_unsupported_chunks() {
+    let dir = temp_dir("upgrade-region");
+    let path = dir.join("r.0.0.mca");
+    let mut region = RegionFile::create(&path).unwrap();
+    region.write_chunk(0, 0, &chunk_1_17("full")).unwrap();
+    region.write_chunk(1, 0, &chunk_1_17("full")).unwrap();
+    let upgrader = Upgrader::vanilla();
+
+    let err = upgrader
+        .upgrade_region(DataType::Chunk, &mut region, None, 2860)
+        .unwrap_err();
+    assert!(matches!(err, Error::UnsupportedUpgrade(2844, _)));
+    assert_eq!(
+        region.read_chunk::<Value>(0, 0).unwrap(),
+        Some(chunk_1_17("full"))
+    );
+
+    let overworld = context("minecraft:overworld", "minecraft:noise");
+    let upgraded = upgrader
+        .upgrade_region(DataType::Chunk, &mut region, Some(&overworld), 2860)
+        .unwrap();
+    assert_eq!(upgraded, 2);
+    for x in 0..2 {
+        let chunk: Value = region.read_chunk(x, 0).unwrap().unwrap();
+        assert_eq!(field(&chunk, "yPos"), &Value::Int(-4));
+        assert!(has(&chunk, "below_zero_retrogen") && !has(&chunk, "__context"));
+    }
+    let again = upgrader
+        .upgrade_region(DataType::Chunk, &mut region, Some(&overworld), 2860)
+        .unwrap();
+    assert_eq!(again, 0);
+}
+
+#[test]
+fn chunks_of_snapshots_with_new_heights_are_not_shifted() {
+    let mut ids = vec!["0"; 16];
+    ids.extend(vec!["5"; 1520]);
+    let mut chunk = snbt(&format!(
+        r#"{{DataVersion: 2730, Level: {{Status: "full", Biomes: [I; {}],
+            PostProcessing: [{}], UpgradeData: {{Indices: {{"0": [I; 5]}}}},
+            Sections: [{{Y: -4b}}]}}}}"#,
+        ids.join(", "),
+        vec!["[]"; 24].join(", "),
+    ));
+    let overworld = context("minecraft:overworld", "minecraft:noise");
+    assert!(Upgrader::vanilla()
+        .upgrade_chunk(&mut chunk, &overworld, 2860)
+        .unwrap());
+
+    assert_eq!(field(&chunk, "yPos"), &Value::Int(-4));
+    assert!(!has(&chunk, "below_zero_retrogen") && !has(&chunk, "blending_data"));
+    assert_eq!(list(field(&chunk, "PostProcessing")).len(), 24);
+    let indices = field(&chunk, "UpgradeData.Indices");
+    assert_eq!(field(indices, "0"), &Value::IntArray(vec![5]));
+    let sections = list(field(&chunk, "sections"));
+    assert_eq!(sections.len(), 24);
+    assert_eq!(
+        biomes(&sections[0]).get(0, 0, 0).unwrap(),
+        "minecraft:ocean"
+    );
+    assert_eq!(
+        biomes(&sections[0]).get(0, 1, 0).unwrap(),
+        "minecraft:taiga"
+    );
+    assert_eq!(
+        biomes(&sections[1]).get(0, 0, 0).unwrap(),
+        "minecraft:taiga"
+    );
+}
+
+// The components of an item of 1.20.4 with the given ID and tag.
+fn components(id: &str, tag: &str) -> Value {
+    let mut item = snbt(&format!(
+        r#"{{id: "minecraft:{}", Count: 1b, tag: {}}}"#,
+        id, tag
+    ));
+    let upgrader = Upgrader::vanilla();
+    upgrader
+        .upgrade(DataType::ItemStack, &mut item, 3700, 3953)
+        .unwrap();
+    assert!(!has(&item, "tag"));
+    field(&item, "components").clone()
+}
+
+#[test]
+fn potions_become_potion_contents() {
+    let potion = components(
+        "potion",
+        r#"{Potion: "minecraft:swiftness", CustomPotionColor: 255,
+            CustomPotionEffects: [{Id: 1b, Amplifier: 1b, Duration: 200, ShowIcon: 1b}]}"#,
+    );
+    let expected = snbt(
+        r#"{"minecraft:potion_contents": {potion: "minecraft:swiftness", custom_color: 255,
+            custom_effects: [{id: "minecraft:speed", amplifier: 1b, duration: 200,
+                show_icon: 1b}]}}"#,
+    );
+    assert_eq!(potion, expected);
+
+    // effects of 1.20.2 and newer are kept as they are
+    let arrow = components(
+        "tipped_arrow",
+        r#"{custom_potion_effects: [{id: "minecraft:darkness", duration: 20}]}"#,
+    );
+    let effect = field(&arrow, "minecraft:potion_contents.custom_effects.0");
+    assert_eq!(effect, &snbt(r#"{id: "minecraft:darkness", duration: 20}"#));
+}
+
+#[test]
+fn book_pages_become_book_content() {
+    let writable = components(
+        "writable_book",
+        r#"{pages: ["one", "two"], filtered_pages: {"1": "t*o"}}"#,
+    );
+    let expected = snbt(
+        r#"{"minecraft:writable_book_content": {pages: [{raw: "one"},
+            {raw: "two", filtered: "t*o"}]}}"#,
+    );
+    assert_eq!(writable, expected);
+
+    let written = components(
+        "written_book",
+        r#"{pages: ['"one"'], title: "Title", author: "Alex", generation: 1, resolved: 1b}"#,
+    );
+    let expected = snbt(
+        r#"{"minecraft:written_book_content": {pages: [{raw: '"one"'}],
+            title: {raw: "Title"}, author: "Alex", generation: 1, resolved: 1b}}"#,
+    );
+    assert_eq!(written, expected);
+}
+
+#[test]
+fn fireworks_become_components() {
+    let rocket = components(
+        "firework_rocket",
+        r#"{Fireworks: {Flight: 2b, Explosions: [{Type: 1b, Colors: [I; 255],
+            FadeColors: [I; 0], Trail: 1b, Flicker: 0b}]}}"#,
+    );
+    let expected = snbt(
+        r#"{"minecraft:fireworks": {flight_duration: 2b, explosions: [{shape: "large_ball",
+            colors: [I; 255], fade_colors: [I; 0], has_trail: 1b, has_twinkle: 0b}]}}"#,
+    );
+    assert_eq!(rocket, expected);
+
+    let star = components("firework_star", r#"{Explosion: {Type: 3b}}"#);
+    let expected = snbt(r#"{"minecraft:firework_explosion": {shape: "creeper"}}"#);
+    assert_eq!(star, expected);
+}
+
+#[test]
+fn attribute_modifiers_and_hidden_tooltips_become_components() {
+    let sword = components(
+        "diamond_sword",
+        r#"{AttributeModifiers: [{AttributeName: "minecraft:generic.attack_damage",
+            Name: "Sharp", Amount: 3.0d, Operation: 1, UUID: [I; 1, 2, 3, 4],
+            Slot: "mainhand"}, {AttributeName: "minecraft:generic.luck", Name: "Luck",
+            Amount: 1.0d, Operation: 0, UUID: [I; 5, 6, 7, 8]}],
+            Unbreakable: 1b, HideFlags: 39}"#,
+    );
+    let expected = snbt(
+        r#"{"minecraft:attribute_modifiers": {show_in_tooltip: 0b, modifiers: [
+            {type: "minecraft:generic.attack_damage", name: "Sharp", amount: 3.0d,
+                operation: "add_multiplied_base", uuid: [I; 1, 2, 3, 4], slot: "mainhand"},
+            {type: "minecraft:generic.luck", name: "Luck", amount: 1.0d,
+                operation: "add_value", uuid: [I; 5, 6, 7, 8], slot: "any"}]},
+            "minecraft:unbreakable": {show_in_tooltip: 0b},
+            "minecraft:enchantments": {levels: {}, show_in_tooltip: 0b},
+            "minecraft:stored_enchantments": {levels: {}, show_in_tooltip: 0b},
+            "minecraft:hide_additional_tooltip": {}}"#,
+    );
+    assert_eq!(sword, expected);
+}
+
+#[test]
+fn bundle_contents_are_upgraded_as_items() {
+    let mut player = snbt(
+        r#"{DataVersion: 2230, id: "minecraft:player", Inventory: [{id: "minecraft:bundle",
+            Count: 1b, tag: {Items: [{id: "minecraft:diamond_sword", Count: 1b,
+                tag: {AttributeModifiers: [{AttributeName: "generic.luck", Amount: 1.0d,
+                    UUIDMost: 0L, UUIDLeast: 9L}], RepairCost: 1}}]}}]}"#,
+    );
+    let upgrader = Upgrader::vanilla();
+    assert!(upgrader
+        .upgrade_root(DataType::Entity, &mut player, 3953)
+        .unwrap());
+    let sword = field(
+        &player,
+        "Inventory.0.components.minecraft:bundle_contents.0",
+    );
+    assert_eq!(field(sword, "count"), &Value::Int(1));
+    let modifier = field(
+        sword,
+        "components.minecraft:attribute_modifiers.modifiers.0",
+    );
+    assert_eq!(field(modifier, "uuid"), &Value::IntArray(vec![0, 0, 0, 9]));
+    let repair_cost = field(sword, "components.minecraft:repair_cost");
+    assert_eq!(repair_cost, &Value::Int(1));
+}
+
+#[test]
+fn items_with_untranslated_tag_fields_are_refused() {
+    let upgrader = Upgrader::vanilla();
+    for tag in &[
+        r#"{CanDestroy: ["minecraft:stone"]}"#,
+        r#"{Trim: {material: "minecraft:gold", pattern: "minecraft:eye"}}"#,
+        r#"{LodestonePos: {X: 0, Y: 64, Z: 0}}"#,
+    ] {
+        let before = snbt(&format!(
+            r#"{{DataVersion: 3700, id: "minecraft:item", Item: {{id: "minecraft:compass",
+                Count: 1b, tag: {}}}}}"#,
+            tag
+        ));
+        let mut entity = before.clone();
+        let err = upgrader
+            .upgrade_root(DataType::Entity, &mut entity, 3953)
+            .unwrap_err();
+        assert!(matches!(err, Error::UnsupportedUpgrade(3819, _)));
+        assert_eq!(entity, before);
+    }
+
+    // fields the game does not know are kept as custom data
+    let custom = components("stick", r#"{MyPlugin: {level: 3}}"#);
+    let expected = snbt(r#"{"minecraft:custom_data": {MyPlugin: {level: 3}}}"#);
+    assert_eq!(custom, expected);
+}
+
+#[test]
+fn entity_and_block_entity_ids_are_namespaced() {
+    let mut chunk = snbt(
+        r#"{DataVersion: 512, Level: {
+            Entities: [{id: "Zombie", IsVillager: 1b, VillagerProfession: 2},
+                {id: "Zombie", ZombieType: 6}, {id: "Skeleton", SkeletonType: 1b},
+                {id: "EntityHorse", Type: 2}, {id: "Guardian", Elder: 1b},
+                {id: "PigZombie"}],
+            TileEntities: [{id: "Sign"}, {id: "MobSpawner", SpawnData: {id: "Creeper"},
+                SpawnPotentials: [{Weight: 1, Entity: {id: "Ozelot"}}]}]
+        }}"#,
+    );
+    let upgrader = Upgrader::vanilla();
+    assert!(upgrader
+        .upgrade_root(DataType::Chunk, &mut chunk, 1343)
+        .unwrap());
+
+    let entities = list(field(&chunk, "Level.Entities"));
+    let expected = snbt(
+        r#"[{id: "minecraft:zombie_villager", Profession: 2}, {id: "minecraft:husk"},
+            {id: "minecraft:wither_skeleton"}, {id: "minecraft:mule"},
+            {id: "minecraft:elder_guardian"}, {id: "minecraft:zombie_pigman"}]"#,
+    );
+    assert_eq!(entities, list(&expected));
+    let sign = field(&chunk, "Level.TileEntities.0.id");
+    assert_eq!(sign, &Value::String("minecraft:sign".into()));
+    let spawner = field(&chunk, "Level.TileEntities.1");
+    assert_eq!(
+        field(spawner, "id"),
+        &Value::String("minecraft:mob_spawner".into())
+    );
+    assert_eq!(
+        field(spawner, "SpawnData.id"),
+        &Value::String("minecraft:creeper".into())
+    );
+    assert_eq!(
+        field(spawner, "SpawnPotentials.0.Entity.id"),
+        &Value::String("minecraft:ocelot".into())
+    );
+}
+
+#[test]
+fn level_uuids_become_int_arrays() {
+    let mut level = snbt(
+        r#"{Data: {DataVersion: 2230,
+            WanderingTraderId: "00000001-0000-0002-0000-000300000004",
+            DragonFight: {DragonUUIDMost: 5L, DragonUUIDLeast: 6L, DragonKilled: 1b},
+            DimensionData: {"1": {DragonFight: {DragonUUIDMost: 9L, DragonUUIDLeast: 10L}}},
+            CustomBossEvents: {"custom:bar": {Players: [{M: 7L, L: 8L}]}}}}"#,
+    );
+    let upgrader = Upgrader::vanilla();
+    assert!(upgrader
+        .upgrade_root(DataType::Level, &mut level, 2586)
+        .unwrap());
+    let expected = snbt(
+        r#"{Data: {DataVersion: 2586, WanderingTraderId: [I; 1, 2, 3, 4],
+            DragonFight: {Dragon: [I; 0, 5, 0, 6], DragonKilled: 1b},
+            DimensionData: {"1": {DragonFight: {Dragon: [I; 0, 9, 0, 10]}}},
+            CustomBossEvents: {"custom:bar": {Players: [[I; 0, 7, 0, 8]]}}}}"#,
+    );
+    assert_eq!(level, expected);
+}
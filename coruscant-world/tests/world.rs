@@ -0,0 +1,295 @@
+use coruscant_nbt::Value;
+use coruscant_world::block_entity::BlockEntity;
+use coruscant_world::chunk::legacy::{PalettedChunk, PalettedLevel, PalettedSection};
+use coruscant_world::chunk::{Biomes, BlockStates, Chunk, Section};
+use coruscant_world::region::RegionFile;
+use coruscant_world::uuid::Uuid;
+use coruscant_world::world::{OVERWORLD, THE_NETHER};
+use coruscant_world::{BlockPos, BlockState, Error, World};
+use std::fs;
+
+mod common;
+
+use common::{temp_world, temp_world_with, LEVEL_DAT_1_12, LEVEL_DAT_1_13};
+
+#[test]
+fn world_is_locked_while_open() {
+    let path = temp_world("world-locked");
+    let world = World::open(&path).unwrap();
+    assert!(matches!(World::open(&path), Err(Error::SessionLocked)));
+    drop(world);
+    World::open(&path).unwrap();
+}
+
+// Java locks `session.lock` with `fcntl`, not `flock`.
+#[cfg(unix)]
+#[test]
+fn world_is_locked_by_fcntl_lock() {
+    use std::fs::OpenOptions;
+    use std::os::unix::io::AsRawFd;
+
+    let path = temp_world("world-fcntl-locked");
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path.join("session.lock"))
+        .unwrap();
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as _;
+    lock.l_whence = libc::SEEK_SET as _;
+    // SAFETY: the descriptor is open and `lock` is a valid `flock` struct.
+    assert_eq!(
+        unsafe { libc::fcntl(file.as_raw_fd(), libc::F_SETLK, &lock) },
+        0
+    );
+    assert!(matches!(World::open(&path), Err(Error::SessionLocked)));
+    drop(file);
+    World::open(&path).unwrap();
+}
+
+#[test]
+fn nested_datapack_dimensions_are_found() {
+    let path = temp_world("world-nested-dimensions");
+    for dir in [
+        "DIM-1/region",
+        "dimensions/ns/a/b/region",
+        "dimensions/ns/a/b/entities",
+        "dimensions/ns/a/c/poi",
+        "dimensions/ns/d/region",
+        "dimensions/ns/e",
+        "dimensions/minecraft/the_nether/region",
+    ] {
+        fs::create_dir_all(path.join(dir)).unwrap();
+    }
+    let world = World::open(&path).unwrap();
+    let names: Vec<_> = world.dimensions().iter().map(|d| d.name()).collect();
+    assert_eq!(
+        names,
+        [
+            "minecraft:overworld",
+            "minecraft:the_nether",
+            "ns:a/b",
+            "ns:a/c",
+            "ns:d",
+            "ns:e",
+        ]
+    );
+    let nested = &world.dimensions()[2];
+    assert_eq!(nested.path(), path.join("dimensions/ns/a/b"));
+}
+
+#[test]
+fn legacy_level_dats_are_read() {
+    for (name, level_dat, version) in [
+        ("world-level-dat-1-12", LEVEL_DAT_1_12, 1343),
+        ("world-level-dat-1-13", LEVEL_DAT_1_13, 1631),
+    ] {
+        let path = temp_world_with(name, level_dat);
+        let world = World::open(&path).unwrap();
+        let data = &world.level_dat().data;
+        assert_eq!(data.data_version, Some(version));
+        assert_eq!(data.version.as_ref().map(|v| v.id), Some(version));
+        assert_eq!(data.data_packs.is_some(), version >= 1451);
+        assert_eq!(data.wandering_trader_spawn_chance, None);
+        assert_eq!(data.seed(), Some(42));
+        let the_end = &data.dimension_data.as_ref().unwrap().the_end;
+        let fight = the_end.dragon_flight.as_ref().unwrap();
+        assert_eq!(fight.gateways, [0, 1]);
+        assert_eq!(fight.dragon_uuid, Some(Uuid::from_most_least(1, 2)));
+        // nothing is added or lost when written back
+        let value = coruscant_nbt::snbt::parse(level_dat).unwrap();
+        assert_eq!(coruscant_nbt::to_value(world.level_dat()).unwrap(), value);
+    }
+}
+
+#[test]
+fn dragon_uuid_is_written_as_of_the_data_version() {
+    let path = temp_world_with("world-dragon-uuid", LEVEL_DAT_1_13);
+    let world = World::open(&path).unwrap();
+    let mut level_dat = world.level_dat().clone();
+    level_dat.data.data_version = Some(2514);
+    let mut value = coruscant_nbt::to_value(&level_dat).unwrap();
+    for key in ["Data", "DimensionData", "1", "DragonFight"] {
+        value = match value {
+            Value::Compound(mut compound) => compound.remove(key).unwrap(),
+            _ => panic!("{} is not in a compound", key),
+        };
+    }
+    let fight = match value {
+        Value::Compound(fight) => fight,
+        _ => panic!("DragonFight is not a compound"),
+    };
+    assert_eq!(
+        fight.get("Dragon"),
+        Some(&Value::IntArray(vec![0, 1, 0, 2]))
+    );
+    assert_eq!(fight.get("DragonUUIDMost"), None);
+    assert_eq!(fight.get("DragonUUIDLeast"), None);
+}
+
+// A world with a 1.20.4 chunk at 0, 0 in the overworld and the Nether,
+// each with a bedrock floor.
+fn world_with_chunks(name: &str) -> std::path::PathBuf {
+    let path = temp_world(name);
+    for (dir, y_pos) in [("region", -4), ("DIM-1/region", 0)] {
+        let mut chunk = Chunk::new(3700, 0, y_pos, 0, "minecraft:full".to_string());
+        let mut section = Section::new(y_pos as i8);
+        let bedrock = BlockState::new("minecraft:bedrock".to_string());
+        section.block_states = Some(BlockStates::new(vec![bedrock], None));
+        chunk.sections.push(section);
+        fs::create_dir_all(path.join(dir)).unwrap();
+        let mut region = RegionFile::create(path.join(dir).join("r.0.0.mca")).unwrap();
+        region.write_chunk(0, 0, &chunk).unwrap();
+    }
+    path
+}
+
+#[test]
+fn blocks_outside_the_dimension_are_refused() {
+    let path = world_with_chunks("world-out-of-world");
+    let mut world = World::open(&path).unwrap();
+    assert_eq!(world.dimensions()[0].y_range(), Some(-64..320));
+    assert_eq!(world.dimensions()[1].y_range(), Some(0..256));
+
+    let stone = BlockState::new("minecraft:stone".to_string());
+    for (dimension, y) in [(OVERWORLD, -65), (OVERWORLD, 320), (THE_NETHER, -1)] {
+        let pos = BlockPos::new(1, y, 1);
+        assert!(matches!(
+            world.set_block(dimension, pos, stone.clone()),
+            Err(Error::BlockOutOfWorld(p)) if p == pos
+        ));
+        assert!(matches!(
+            world.get_block(dimension, pos),
+            Err(Error::BlockOutOfWorld(_))
+        ));
+    }
+    for (dimension, y) in [(OVERWORLD, -64), (OVERWORLD, 319), (THE_NETHER, 255)] {
+        let pos = BlockPos::new(1, y, 1);
+        world.set_block(dimension, pos, stone.clone()).unwrap();
+        assert_eq!(
+            world.get_block(dimension, pos).unwrap(),
+            Some(stone.clone())
+        );
+    }
+}
+
+#[test]
+fn new_sections_get_biomes() {
+    let path = world_with_chunks("world-new-section-biomes");
+    let mut world = World::open(&path).unwrap();
+    let stone = BlockState::new("minecraft:stone".to_string());
+    world
+        .set_block(OVERWORLD, BlockPos::new(0, 100, 0), stone.clone())
+        .unwrap();
+    world
+        .set_block(OVERWORLD, BlockPos::new(0, -64, 0), stone)
+        .unwrap();
+    world.flush().unwrap();
+    drop(world);
+
+    let region = RegionFile::open(path.join("region/r.0.0.mca")).unwrap();
+    let chunk: Chunk = region.read_chunk(0, 0).unwrap().unwrap();
+    let plains = Biomes::new(vec!["minecraft:plains".to_string()], None);
+    let created = chunk.sections.iter().find(|s| s.y == 6).unwrap();
+    assert_eq!(created.biomes, Some(plains.clone()));
+    // the floor section of the fixture has no biomes either
+    let floor = chunk.sections.iter().find(|s| s.y == -4).unwrap();
+    assert_eq!(floor.biomes, Some(plains));
+    let untouched = chunk.sections.iter().filter(|s| s.y != 6 && s.y != -4);
+    assert_eq!(untouched.count(), 0);
+}
+
+#[test]
+fn replaced_blocks_lose_their_block_entity() {
+    let path = world_with_chunks("world-replaced-block-entity");
+    let mut world = World::open(&path).unwrap();
+    let chest = BlockState::new("minecraft:chest".to_string());
+    let pos = BlockPos::new(1, 0, 1);
+    world.set_block(OVERWORLD, pos, chest.clone()).unwrap();
+    let nbt = coruscant_nbt::snbt::parse(r#"{id: "minecraft:chest", Items: []}"#).unwrap();
+    world
+        .set_block_entity(OVERWORLD, pos, Some(BlockEntity::from_value(nbt)))
+        .unwrap();
+
+    // setting the same state keeps it
+    world.set_block(OVERWORLD, pos, chest).unwrap();
+    assert!(world.block_entity(OVERWORLD, pos).unwrap().is_some());
+
+    let stone = BlockState::new("minecraft:stone".to_string());
+    world.set_block(OVERWORLD, pos, stone).unwrap();
+    assert!(world.block_entity(OVERWORLD, pos).unwrap().is_none());
+    world.flush().unwrap();
+    drop(world);
+
+    let mut world = World::open(&path).unwrap();
+    assert!(world.block_entity(OVERWORLD, pos).unwrap().is_none());
+}
+
+// A 1.13 world with a chunk at 0, 0 in the overworld with a bedrock floor,
+// in the paletted format of 1.13 to 1.17.
+fn legacy_world_with_chunk(name: &str) -> std::path::PathBuf {
+    let path = temp_world_with(name, LEVEL_DAT_1_13);
+    let mut level = PalettedLevel::new(0, 0, "full".to_string());
+    let mut section = PalettedSection::new(0);
+    let bedrock = BlockState::new("minecraft:bedrock".to_string());
+    section.palette = Some(vec![BlockState::air(), bedrock]);
+    // 4 bits for each entry: the 256 blocks at Y 0 fill the first 16 longs
+    let mut block_states = vec![0; 256];
+    block_states[..16].fill(0x1111_1111_1111_1111);
+    section.block_states = Some(block_states);
+    level.sections.push(section);
+    fs::create_dir_all(path.join("region")).unwrap();
+    let mut region = RegionFile::create(path.join("region/r.0.0.mca")).unwrap();
+    region
+        .write_chunk(0, 0, &PalettedChunk::new(1631, level))
+        .unwrap();
+    path
+}
+
+#[test]
+fn legacy_world_blocks_are_read_and_written() {
+    let path = legacy_world_with_chunk("world-legacy-blocks");
+    let mut world = World::open(&path).unwrap();
+    assert_eq!(world.dimensions()[0].y_range(), Some(0..256));
+    let bedrock = BlockState::new("minecraft:bedrock".to_string());
+    let stone = BlockState::new("minecraft:stone".to_string());
+    assert_eq!(
+        world.get_block(OVERWORLD, BlockPos::new(3, 0, 7)).unwrap(),
+        Some(bedrock.clone())
+    );
+    assert_eq!(
+        world.get_block(OVERWORLD, BlockPos::new(3, 1, 7)).unwrap(),
+        Some(BlockState::air())
+    );
+    assert!(matches!(
+        world.get_block(OVERWORLD, BlockPos::new(3, -1, 7)),
+        Err(Error::BlockOutOfWorld(_))
+    ));
+
+    let old = world
+        .set_block(OVERWORLD, BlockPos::new(3, 0, 7), stone.clone())
+        .unwrap();
+    assert_eq!(old, bedrock);
+    world
+        .set_block(OVERWORLD, BlockPos::new(3, 100, 7), stone.clone())
+        .unwrap();
+    world.flush().unwrap();
+    drop(world);
+
+    let region = RegionFile::open(path.join("region/r.0.0.mca")).unwrap();
+    let chunk: PalettedChunk = region.read_chunk(0, 0).unwrap().unwrap();
+    assert_eq!(chunk.data_version, 1631);
+    let ys: Vec<_> = chunk.level.sections.iter().map(|s| s.y).collect();
+    assert_eq!(ys, [0, 6]);
+
+    let mut world = World::open(&path).unwrap();
+    for (pos, state) in [
+        (BlockPos::new(3, 0, 7), stone.clone()),
+        (BlockPos::new(4, 0, 7), bedrock),
+        (BlockPos::new(3, 100, 7), stone),
+        (BlockPos::new(3, 101, 7), BlockState::air()),
+    ] {
+        assert_eq!(world.get_block(OVERWORLD, pos).unwrap(), Some(state));
+    }
+}